@@ -17,10 +17,12 @@
 
 use std::env;
 use std::path::Path;
+use std::sync::Arc;
 
 use ballista::prelude::{BallistaConfig, BallistaContext, Result};
 use ballista_cli::{
-    exec, print_format::PrintFormat, print_options::PrintOptions, BALLISTA_CLI_VERSION,
+    exec, print_format::PrintFormat, print_options::PrintOptions, progress::ProgressBar,
+    BALLISTA_CLI_VERSION,
 };
 use clap::Parser;
 use mimalloc::MiMalloc;
@@ -130,6 +132,10 @@ pub async fn main() -> Result<()> {
         }
     };
 
+    if !args.quiet {
+        ctx.register_job_progress_listener(Arc::new(ProgressBar::default()));
+    }
+
     let mut print_options = PrintOptions {
         format: args.format,
         quiet: args.quiet,
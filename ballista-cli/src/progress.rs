@@ -0,0 +1,55 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Progress bar of the running jobs
+
+use std::io::Write;
+
+use ballista::prelude::{JobProgress, JobProgressListener};
+
+const BAR_WIDTH: usize = 30;
+
+/// Renders the progress of a running job on a single line of stderr
+#[derive(Default)]
+pub struct ProgressBar {}
+
+impl JobProgressListener for ProgressBar {
+    fn on_progress(&self, job_id: &str, progress: &JobProgress) {
+        let filled = ((progress.percent_complete / 100.0 * BAR_WIDTH as f64) as usize)
+            .min(BAR_WIDTH);
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:>3.0}% Job {}: {}/{} tasks, {} rows",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            progress.percent_complete,
+            job_id,
+            progress.completed_tasks,
+            progress.total_tasks,
+            progress.output_rows
+        );
+        let _ = stderr.flush();
+    }
+
+    fn on_finished(&self, _job_id: &str) {
+        // clear the line of the progress bar before the results are printed
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K");
+        let _ = stderr.flush();
+    }
+}
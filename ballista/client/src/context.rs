@@ -27,10 +27,12 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use ballista_core::config::BallistaConfig;
+use ballista_core::execution_plans::{JobProgressListener, JobProgressListeners};
 use ballista_core::serde::protobuf::scheduler_grpc_client::SchedulerGrpcClient;
 use ballista_core::serde::protobuf::{CreateSessionParams, KeyValuePair};
 use ballista_core::utils::{
     create_df_ctx_with_ballista_query_planner, create_grpc_client_connection,
+    GrpcTlsConfig,
 };
 use datafusion_proto::protobuf::LogicalPlanNode;
 
@@ -88,6 +90,26 @@ impl BallistaContext {
         host: &str,
         port: u16,
         config: &BallistaConfig,
+    ) -> ballista_core::error::Result<Self> {
+        Self::connect(host, port, config, None).await
+    }
+
+    /// Create a context for executing queries against a remote Ballista scheduler instance,
+    /// connecting to the scheduler and executors with TLS
+    pub async fn remote_with_tls(
+        host: &str,
+        port: u16,
+        config: &BallistaConfig,
+        tls: GrpcTlsConfig,
+    ) -> ballista_core::error::Result<Self> {
+        Self::connect(host, port, config, Some(tls)).await
+    }
+
+    async fn connect(
+        host: &str,
+        port: u16,
+        config: &BallistaConfig,
+        tls: Option<GrpcTlsConfig>,
     ) -> ballista_core::error::Result<Self> {
         let state = BallistaContextState::new(host.to_owned(), port, config);
 
@@ -97,9 +119,10 @@ impl BallistaContext {
             "Connecting to Ballista scheduler at {}",
             scheduler_url.clone()
         );
-        let connection = create_grpc_client_connection(scheduler_url.clone())
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
+        let connection =
+            create_grpc_client_connection(scheduler_url.clone(), tls.as_ref())
+                .await
+                .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
         let mut scheduler = SchedulerGrpcClient::new(connection);
 
         let remote_session_id = scheduler
@@ -128,6 +151,7 @@ impl BallistaContext {
                 scheduler_url,
                 remote_session_id,
                 state.config(),
+                tls,
             )
         };
 
@@ -185,6 +209,7 @@ impl BallistaContext {
                 scheduler_url,
                 remote_session_id,
                 config,
+                None,
             )
         };
 
@@ -207,6 +232,19 @@ impl BallistaContext {
         })
     }
 
+    /// Register a listener which is notified of the progress of the jobs run by this
+    /// context, e.g. to render a progress bar
+    pub fn register_job_progress_listener(&self, listener: Arc<dyn JobProgressListener>) {
+        if let Some(listeners) = self
+            .context
+            .state()
+            .config()
+            .get_extension::<JobProgressListeners>()
+        {
+            listeners.register(listener);
+        }
+    }
+
     /// Create a DataFrame representing an Json table scan
     /// TODO fetch schema from scheduler instead of resolving locally
     pub async fn read_json<P: DataFilePaths>(
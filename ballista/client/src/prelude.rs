@@ -26,6 +26,9 @@ pub use ballista_core::{
         BALLISTA_WITH_INFORMATION_SCHEMA,
     },
     error::{BallistaError, Result},
+    execution_plans::JobProgressListener,
+    serde::protobuf::JobProgress,
+    utils::GrpcTlsConfig,
 };
 
 pub use futures::StreamExt;
@@ -25,6 +25,7 @@ use std::{
     task::{Context, Poll},
 };
 
+use crate::config::ShuffleCompression;
 use crate::error::{BallistaError, Result};
 use crate::serde::scheduler::{Action, PartitionId};
 
@@ -40,7 +41,7 @@ use datafusion::arrow::{
 use datafusion::error::DataFusionError;
 
 use crate::serde::protobuf;
-use crate::utils::create_grpc_client_connection;
+use crate::utils::{create_grpc_client_connection, GrpcTlsConfig};
 use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
 use futures::{Stream, StreamExt};
 use log::{debug, warn};
@@ -59,25 +60,31 @@ const IO_RETRY_WAIT_TIME_MS: u64 = 3000;
 
 impl BallistaClient {
     /// Create a new BallistaClient to connect to the executor listening on the specified
-    /// host and port
-    pub async fn try_new(host: &str, port: u16) -> Result<Self> {
+    /// host and port, using TLS if `tls` is set
+    pub async fn try_new(
+        host: &str,
+        port: u16,
+        tls: Option<&GrpcTlsConfig>,
+    ) -> Result<Self> {
         let addr = format!("http://{host}:{port}");
         debug!("BallistaClient connecting to {}", addr);
-        let connection =
-            create_grpc_client_connection(addr.clone())
-                .await
-                .map_err(|e| {
-                    BallistaError::GrpcConnectionError(format!(
+        let connection = create_grpc_client_connection(addr.clone(), tls)
+            .await
+            .map_err(|e| {
+                BallistaError::GrpcConnectionError(format!(
                     "Error connecting to Ballista scheduler or executor at {addr}: {e:?}"
                 ))
-                })?;
+            })?;
         let flight_client = FlightServiceClient::new(connection);
         debug!("BallistaClient connected OK");
 
         Ok(Self { flight_client })
     }
 
-    /// Fetch a partition from an executor
+    /// Fetch a partition from an executor, which sends it compressed with the codec
+    /// of its shuffle file. The partition is looked up in the index of the shuffle
+    /// file if there is one.
+    #[allow(clippy::too_many_arguments)]
     pub async fn fetch_partition(
         &mut self,
         executor_id: &str,
@@ -85,6 +92,8 @@ impl BallistaClient {
         path: &str,
         host: &str,
         port: u16,
+        compression: ShuffleCompression,
+        index_path: Option<&str>,
     ) -> Result<SendableRecordBatchStream> {
         let action = Action::FetchPartition {
             job_id: partition_id.job_id.clone(),
@@ -93,6 +102,8 @@ impl BallistaClient {
             path: path.to_owned(),
             host: host.to_owned(),
             port,
+            compression,
+            index_path: index_path.map(str::to_owned),
         };
         self.execute_action(&action)
             .await
@@ -26,17 +26,59 @@ use std::result;
 use crate::error::{BallistaError, Result};
 
 use datafusion::arrow::datatypes::DataType;
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::ipc::writer::IpcWriteOptions;
+use datafusion::arrow::ipc::CompressionType;
 
 pub const BALLISTA_JOB_NAME: &str = "ballista.job.name";
+/// Priority of the jobs of a session, jobs with a higher priority are scheduled first
+pub const BALLISTA_JOB_PRIORITY: &str = "ballista.job.priority";
+/// Pool of the jobs of a session, the scheduler shares executor slots fairly across pools
+pub const BALLISTA_JOB_POOL: &str = "ballista.job.pool";
+/// Memory in bytes reserved on an executor by each task of a job, which the task is
+/// limited to. Zero means tasks reserve no memory and are not limited.
+pub const BALLISTA_TASK_MEMORY: &str = "ballista.task.memory";
+/// Number of CPUs reserved on an executor by each task of a job
+pub const BALLISTA_TASK_CPUS: &str = "ballista.task.cpus";
+/// Labels of the executors which may run the tasks of a job, as a comma separated list
+/// of key=value
+pub const BALLISTA_JOB_EXECUTOR_SELECTOR: &str = "ballista.job.executor_selector";
+/// Time in seconds after which a running job is aborted. Zero means no timeout.
+pub const BALLISTA_JOB_TIMEOUT_SECONDS: &str = "ballista.job.timeout_seconds";
+/// Time in seconds after which a task of a job fails on the executor running it. Zero
+/// means no timeout.
+pub const BALLISTA_TASK_TIMEOUT_SECONDS: &str = "ballista.task.timeout_seconds";
+/// Reuse the results of an earlier job running the same plan over unchanged files
+pub const BALLISTA_JOB_RESULT_CACHE: &str = "ballista.job.result_cache";
 pub const BALLISTA_DEFAULT_SHUFFLE_PARTITIONS: &str = "ballista.shuffle.partitions";
+/// Codec used to compress the shuffle files, one of none, lz4 or zstd
+pub const BALLISTA_SHUFFLE_COMPRESSION: &str = "ballista.shuffle.compression";
+/// Write one data file and one index file per map task instead of one file per shuffle partition
+pub const BALLISTA_SHUFFLE_SORT_BASED: &str = "ballista.shuffle.sort_based";
 pub const BALLISTA_HASH_JOIN_SINGLE_PARTITION_THRESHOLD: &str =
     "ballista.optimizer.hash_join_single_partition_threshold";
 pub const BALLISTA_DEFAULT_BATCH_SIZE: &str = "ballista.batch.size";
 pub const BALLISTA_REPARTITION_JOINS: &str = "ballista.repartition.joins";
 pub const BALLISTA_REPARTITION_AGGREGATIONS: &str = "ballista.repartition.aggregations";
 pub const BALLISTA_REPARTITION_WINDOWS: &str = "ballista.repartition.windows";
+/// Sort the result of a query in parallel by range partitioning it on the sort keys
+pub const BALLISTA_REPARTITION_SORTS: &str = "ballista.repartition.sorts";
+/// Number of rows sampled from each input partition of a range partitioned sort
+pub const BALLISTA_REPARTITION_SORTS_SAMPLE_SIZE: &str =
+    "ballista.repartition.sorts.sample_size";
 pub const BALLISTA_PARQUET_PRUNING: &str = "ballista.parquet.pruning";
 pub const BALLISTA_COLLECT_STATISTICS: &str = "ballista.collect_statistics";
+/// Re-plan stages with the statistics of their shuffle inputs when they are resolved
+pub const BALLISTA_ADAPTIVE_ENABLED: &str = "ballista.adaptive.enabled";
+/// Target size of the shuffle partitions read by a task, small partitions are coalesced
+/// up to this size and skewed partitions are split into chunks of about this size
+pub const BALLISTA_ADAPTIVE_TARGET_PARTITION_BYTES: &str =
+    "ballista.adaptive.target_partition_bytes";
+/// A shuffle partition is skewed if it is this many times larger than the median one
+pub const BALLISTA_ADAPTIVE_SKEW_FACTOR: &str = "ballista.adaptive.skew_factor";
+/// Shuffle partitions smaller than this are never considered skewed
+pub const BALLISTA_ADAPTIVE_SKEW_THRESHOLD_BYTES: &str =
+    "ballista.adaptive.skew_threshold_bytes";
 /// Indicate whether to enable to data cache for a task
 pub const BALLISTA_DATA_CACHE_ENABLED: &str = "ballista.data_cache.enabled";
 
@@ -134,6 +176,13 @@ impl BallistaConfig {
             }
         }
 
+        if let Some(v) = settings.get(BALLISTA_SHUFFLE_COMPRESSION) {
+            v.parse::<ShuffleCompression>().map_err(|e| BallistaError::General(format!("Failed to parse user-supplied value '{BALLISTA_SHUFFLE_COMPRESSION}' for configuration setting '{v}': {e}")))?;
+        }
+        if let Some(v) = settings.get(BALLISTA_JOB_EXECUTOR_SELECTOR) {
+            parse_labels(v).map_err(|e| BallistaError::General(format!("Failed to parse user-supplied value '{BALLISTA_JOB_EXECUTOR_SELECTOR}' for configuration setting '{v}': {e}")))?;
+        }
+
         Ok(Self { settings })
     }
 
@@ -176,9 +225,39 @@ impl BallistaConfig {
             ConfigEntry::new(BALLISTA_JOB_NAME.to_string(),
                              "Sets the job name that will appear in the web user interface for any submitted jobs".to_string(),
                              DataType::Utf8, None),
+            ConfigEntry::new(BALLISTA_JOB_PRIORITY.to_string(),
+                             "Sets the priority of submitted jobs, jobs with a higher priority are scheduled first".to_string(),
+                             DataType::UInt32, Some("0".to_string())),
+            ConfigEntry::new(BALLISTA_JOB_POOL.to_string(),
+                             "Sets the scheduling pool of submitted jobs, used by the fair job scheduling policy".to_string(),
+                             DataType::Utf8, Some("default".to_string())),
+            ConfigEntry::new(BALLISTA_JOB_EXECUTOR_SELECTOR.to_string(),
+                             "Sets the labels of the executors which may run the tasks of submitted jobs, as a comma separated list of key=value".to_string(),
+                             DataType::Utf8, Some("".to_string())),
+            ConfigEntry::new(BALLISTA_JOB_TIMEOUT_SECONDS.to_string(),
+                             "Sets the time in seconds after which running jobs are aborted, zero means no timeout".to_string(),
+                             DataType::UInt64, Some("0".to_string())),
+            ConfigEntry::new(BALLISTA_TASK_TIMEOUT_SECONDS.to_string(),
+                             "Sets the time in seconds after which the tasks of submitted jobs fail, zero means no timeout".to_string(),
+                             DataType::UInt64, Some("0".to_string())),
+            ConfigEntry::new(BALLISTA_JOB_RESULT_CACHE.to_string(),
+                             "Sets whether submitted jobs reuse the results of an earlier job running the same plan over unchanged files".to_string(),
+                             DataType::Boolean, Some("false".to_string())),
+            ConfigEntry::new(BALLISTA_TASK_MEMORY.to_string(),
+                             "Sets the memory in bytes reserved by each task of submitted jobs and which the tasks are limited to, zero means no limit".to_string(),
+                             DataType::UInt64, Some("0".to_string())),
+            ConfigEntry::new(BALLISTA_TASK_CPUS.to_string(),
+                             "Sets the number of CPUs reserved by each task of submitted jobs, zero means no CPUs are reserved".to_string(),
+                             DataType::UInt32, Some("0".to_string())),
             ConfigEntry::new(BALLISTA_DEFAULT_SHUFFLE_PARTITIONS.to_string(),
                              "Sets the default number of partitions to create when repartitioning query stages".to_string(),
                              DataType::UInt16, Some("16".to_string())),
+            ConfigEntry::new(BALLISTA_SHUFFLE_COMPRESSION.to_string(),
+                             "Sets the codec used to compress the shuffle files, one of none, lz4 or zstd".to_string(),
+                             DataType::Utf8, Some("none".to_string())),
+            ConfigEntry::new(BALLISTA_SHUFFLE_SORT_BASED.to_string(),
+                             "Sets whether each map task writes its shuffle partitions to a single data file with an index".to_string(),
+                             DataType::Boolean, Some("false".to_string())),
             ConfigEntry::new(BALLISTA_DEFAULT_BATCH_SIZE.to_string(),
                              "Sets the default batch size".to_string(),
                              DataType::UInt16, Some("8192".to_string())),
@@ -191,6 +270,12 @@ impl BallistaConfig {
             ConfigEntry::new(BALLISTA_REPARTITION_WINDOWS.to_string(),
                              "Configuration for repartition windows".to_string(),
                              DataType::Boolean, Some("true".to_string())),
+            ConfigEntry::new(BALLISTA_REPARTITION_SORTS.to_string(),
+                             "Sets whether to sort query results in parallel by range partitioning them on the sort keys".to_string(),
+                             DataType::Boolean, Some("false".to_string())),
+            ConfigEntry::new(BALLISTA_REPARTITION_SORTS_SAMPLE_SIZE.to_string(),
+                             "Sets the number of rows sampled from each input partition of a range partitioned sort to compute the boundaries of the ranges".to_string(),
+                             DataType::UInt64, Some("100".to_string())),
             ConfigEntry::new(BALLISTA_PARQUET_PRUNING.to_string(),
                              "Configuration for parquet prune".to_string(),
                              DataType::Boolean, Some("true".to_string())),
@@ -200,6 +285,18 @@ impl BallistaConfig {
             ConfigEntry::new(BALLISTA_HASH_JOIN_SINGLE_PARTITION_THRESHOLD.to_string(),
                 "Sets threshold in bytes for collecting the smaller side of the hash join in memory".to_string(),
                 DataType::UInt64, Some((1024 * 1024).to_string())),
+            ConfigEntry::new(BALLISTA_ADAPTIVE_ENABLED.to_string(),
+                "Sets whether to re-plan stages with the statistics of their shuffle inputs".to_string(),
+                DataType::Boolean, Some("false".to_string())),
+            ConfigEntry::new(BALLISTA_ADAPTIVE_TARGET_PARTITION_BYTES.to_string(),
+                "Sets the target size in bytes of the shuffle partitions read by a task when re-planning stages".to_string(),
+                DataType::UInt64, Some((64 * 1024 * 1024).to_string())),
+            ConfigEntry::new(BALLISTA_ADAPTIVE_SKEW_FACTOR.to_string(),
+                "Sets how many times larger than the median a shuffle partition must be to be split as skewed".to_string(),
+                DataType::UInt64, Some("5".to_string())),
+            ConfigEntry::new(BALLISTA_ADAPTIVE_SKEW_THRESHOLD_BYTES.to_string(),
+                "Sets the size in bytes below which shuffle partitions are never split as skewed".to_string(),
+                DataType::UInt64, Some((256 * 1024 * 1024).to_string())),
             ConfigEntry::new(BALLISTA_COLLECT_STATISTICS.to_string(),
                 "Configuration for collecting statistics during scan".to_string(),
                 DataType::Boolean, Some("false".to_string())
@@ -222,6 +319,50 @@ impl BallistaConfig {
         self.get_usize_setting(BALLISTA_DEFAULT_SHUFFLE_PARTITIONS)
     }
 
+    pub fn shuffle_compression(&self) -> ShuffleCompression {
+        // infallible because we validate all configs in the constructor
+        self.get_string_setting(BALLISTA_SHUFFLE_COMPRESSION)
+            .parse()
+            .unwrap()
+    }
+
+    pub fn shuffle_sort_based(&self) -> bool {
+        self.get_bool_setting(BALLISTA_SHUFFLE_SORT_BASED)
+    }
+
+    pub fn job_priority(&self) -> u32 {
+        self.get_usize_setting(BALLISTA_JOB_PRIORITY) as u32
+    }
+
+    pub fn job_pool(&self) -> String {
+        self.get_string_setting(BALLISTA_JOB_POOL)
+    }
+
+    pub fn executor_selector(&self) -> HashMap<String, String> {
+        // infallible because we validate all configs in the constructor
+        parse_labels(&self.get_string_setting(BALLISTA_JOB_EXECUTOR_SELECTOR)).unwrap()
+    }
+
+    pub fn job_timeout_seconds(&self) -> u64 {
+        self.get_usize_setting(BALLISTA_JOB_TIMEOUT_SECONDS) as u64
+    }
+
+    pub fn task_timeout_seconds(&self) -> u64 {
+        self.get_usize_setting(BALLISTA_TASK_TIMEOUT_SECONDS) as u64
+    }
+
+    pub fn job_result_cache(&self) -> bool {
+        self.get_bool_setting(BALLISTA_JOB_RESULT_CACHE)
+    }
+
+    pub fn task_memory(&self) -> u64 {
+        self.get_usize_setting(BALLISTA_TASK_MEMORY) as u64
+    }
+
+    pub fn task_cpus(&self) -> u32 {
+        self.get_usize_setting(BALLISTA_TASK_CPUS) as u32
+    }
+
     pub fn default_plugin_dir(&self) -> String {
         self.get_string_setting(BALLISTA_PLUGIN_DIR)
     }
@@ -234,6 +375,22 @@ impl BallistaConfig {
         self.get_usize_setting(BALLISTA_HASH_JOIN_SINGLE_PARTITION_THRESHOLD)
     }
 
+    pub fn adaptive_enabled(&self) -> bool {
+        self.get_bool_setting(BALLISTA_ADAPTIVE_ENABLED)
+    }
+
+    pub fn adaptive_target_partition_bytes(&self) -> usize {
+        self.get_usize_setting(BALLISTA_ADAPTIVE_TARGET_PARTITION_BYTES)
+    }
+
+    pub fn adaptive_skew_factor(&self) -> usize {
+        self.get_usize_setting(BALLISTA_ADAPTIVE_SKEW_FACTOR)
+    }
+
+    pub fn adaptive_skew_threshold_bytes(&self) -> usize {
+        self.get_usize_setting(BALLISTA_ADAPTIVE_SKEW_THRESHOLD_BYTES)
+    }
+
     pub fn repartition_joins(&self) -> bool {
         self.get_bool_setting(BALLISTA_REPARTITION_JOINS)
     }
@@ -246,6 +403,14 @@ impl BallistaConfig {
         self.get_bool_setting(BALLISTA_REPARTITION_WINDOWS)
     }
 
+    pub fn repartition_sorts(&self) -> bool {
+        self.get_bool_setting(BALLISTA_REPARTITION_SORTS)
+    }
+
+    pub fn repartition_sorts_sample_size(&self) -> usize {
+        self.get_usize_setting(BALLISTA_REPARTITION_SORTS_SAMPLE_SIZE)
+    }
+
     pub fn parquet_pruning(&self) -> bool {
         self.get_bool_setting(BALLISTA_PARQUET_PRUNING)
     }
@@ -361,6 +526,69 @@ impl parse_arg::ParseArgFromStr for DataCachePolicy {
     }
 }
 
+/// Codec used to compress the record batches of the shuffle files, which are also sent
+/// compressed to the executors fetching them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShuffleCompression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl ShuffleCompression {
+    /// Options to write Arrow IPC data compressed with this codec
+    pub fn ipc_write_options(&self) -> result::Result<IpcWriteOptions, ArrowError> {
+        let compression = match self {
+            ShuffleCompression::None => None,
+            ShuffleCompression::Lz4 => Some(CompressionType::LZ4_FRAME),
+            ShuffleCompression::Zstd => Some(CompressionType::ZSTD),
+        };
+        IpcWriteOptions::default().try_with_compression(compression)
+    }
+}
+
+impl std::str::FromStr for ShuffleCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            // shuffle partitions written before compression was supported have no codec
+            "" | "none" => Ok(ShuffleCompression::None),
+            "lz4" => Ok(ShuffleCompression::Lz4),
+            "zstd" => Ok(ShuffleCompression::Zstd),
+            _ => Err(format!(
+                "unknown shuffle compression '{s}', expected one of none, lz4 or zstd"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ShuffleCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShuffleCompression::None => write!(f, "none"),
+            ShuffleCompression::Lz4 => write!(f, "lz4"),
+            ShuffleCompression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Parse executor labels given as a comma separated list of `key=value`
+pub fn parse_labels(labels: &str) -> result::Result<HashMap<String, String>, String> {
+    labels
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(format!("Invalid label {s}, expected key=value")),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -371,6 +599,20 @@ mod tests {
         assert_eq!(16, config.default_shuffle_partitions());
         assert!(!config.default_with_information_schema());
         assert_eq!("", config.default_plugin_dir().as_str());
+        assert_eq!(0, config.job_priority());
+        assert_eq!("default", config.job_pool());
+        assert_eq!(0, config.task_memory());
+        assert_eq!(0, config.task_cpus());
+        assert!(config.executor_selector().is_empty());
+        assert_eq!(0, config.job_timeout_seconds());
+        assert_eq!(0, config.task_timeout_seconds());
+        assert!(!config.job_result_cache());
+        assert!(!config.adaptive_enabled());
+        assert_eq!(64 * 1024 * 1024, config.adaptive_target_partition_bytes());
+        assert_eq!(ShuffleCompression::None, config.shuffle_compression());
+        assert!(!config.shuffle_sort_based());
+        assert!(!config.repartition_sorts());
+        assert_eq!(100, config.repartition_sorts_sample_size());
         Ok(())
     }
 
@@ -379,9 +621,43 @@ mod tests {
         let config = BallistaConfig::builder()
             .set(BALLISTA_DEFAULT_SHUFFLE_PARTITIONS, "123")
             .set(BALLISTA_WITH_INFORMATION_SCHEMA, "true")
+            .set(BALLISTA_JOB_PRIORITY, "10")
+            .set(BALLISTA_JOB_POOL, "etl")
+            .set(BALLISTA_TASK_MEMORY, "1073741824")
+            .set(BALLISTA_TASK_CPUS, "2")
+            .set(BALLISTA_JOB_EXECUTOR_SELECTOR, "team=etl, disk = ssd")
+            .set(BALLISTA_JOB_TIMEOUT_SECONDS, "3600")
+            .set(BALLISTA_TASK_TIMEOUT_SECONDS, "600")
+            .set(BALLISTA_JOB_RESULT_CACHE, "true")
+            .set(BALLISTA_ADAPTIVE_ENABLED, "true")
+            .set(BALLISTA_ADAPTIVE_SKEW_FACTOR, "10")
+            .set(BALLISTA_SHUFFLE_COMPRESSION, "zstd")
+            .set(BALLISTA_SHUFFLE_SORT_BASED, "true")
+            .set(BALLISTA_REPARTITION_SORTS, "true")
+            .set(BALLISTA_REPARTITION_SORTS_SAMPLE_SIZE, "1000")
             .build()?;
         assert_eq!(123, config.default_shuffle_partitions());
         assert!(config.default_with_information_schema());
+        assert_eq!(10, config.job_priority());
+        assert_eq!("etl", config.job_pool());
+        assert_eq!(1073741824, config.task_memory());
+        assert_eq!(2, config.task_cpus());
+        assert_eq!(
+            HashMap::from([
+                ("team".to_string(), "etl".to_string()),
+                ("disk".to_string(), "ssd".to_string())
+            ]),
+            config.executor_selector()
+        );
+        assert_eq!(3600, config.job_timeout_seconds());
+        assert_eq!(600, config.task_timeout_seconds());
+        assert!(config.job_result_cache());
+        assert!(config.adaptive_enabled());
+        assert_eq!(10, config.adaptive_skew_factor());
+        assert_eq!(ShuffleCompression::Zstd, config.shuffle_compression());
+        assert!(config.shuffle_sort_based());
+        assert!(config.repartition_sorts());
+        assert_eq!(1000, config.repartition_sorts_sample_size());
         Ok(())
     }
 
@@ -399,6 +675,16 @@ mod tests {
             .build();
         assert!(config.is_err());
         assert_eq!("General(\"Failed to parse user-supplied value 'ballista.with_information_schema' for configuration setting '123': ParseBoolError\")", format!("{:?}", config.unwrap_err()));
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_SHUFFLE_COMPRESSION, "gzip")
+            .build();
+        assert!(config.is_err());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_JOB_EXECUTOR_SELECTOR, "team")
+            .build();
+        assert!(config.is_err());
         Ok(())
     }
 }
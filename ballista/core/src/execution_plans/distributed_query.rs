@@ -21,9 +21,10 @@ use crate::serde::protobuf::execute_query_params::OptionalSessionId;
 use crate::serde::protobuf::{
     execute_query_params::Query, execute_query_result, job_status,
     scheduler_grpc_client::SchedulerGrpcClient, ExecuteQueryParams, GetJobStatusParams,
-    GetJobStatusResult, PartitionLocation,
+    JobProgress, JobStatus, KeyValuePair, PartitionLocation, WatchJobParams,
 };
-use crate::utils::create_grpc_client_connection;
+use crate::telemetry;
+use crate::utils::{create_grpc_client_connection, GrpcTlsConfig};
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::arrow::error::ArrowError;
 use datafusion::arrow::record_batch::RecordBatch;
@@ -39,13 +40,52 @@ use datafusion::physical_plan::{
 use datafusion_proto::logical_plan::{
     AsLogicalPlan, DefaultLogicalExtensionCodec, LogicalExtensionCodec,
 };
+use futures::stream::BoxStream;
 use futures::{Stream, StreamExt, TryFutureExt, TryStreamExt};
-use log::{error, info};
+use log::{error, info, warn};
+use parking_lot::RwLock;
 use std::any::Any;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::Duration;
+use tonic::transport::Channel;
+use tonic::Code;
+use tracing::Span;
+
+/// Receives the progress of the jobs executed by [DistributedQueryExec]
+pub trait JobProgressListener: Send + Sync {
+    /// Called each time the scheduler reports the progress of a running job
+    fn on_progress(&self, job_id: &str, progress: &JobProgress);
+
+    /// Called once a job is no longer running, whether it succeeded or failed
+    fn on_finished(&self, _job_id: &str) {}
+}
+
+/// The [JobProgressListener]s of a client session, registered as an extension of its
+/// `SessionConfig`
+#[derive(Default)]
+pub struct JobProgressListeners {
+    listeners: RwLock<Vec<Arc<dyn JobProgressListener>>>,
+}
+
+impl JobProgressListeners {
+    pub fn register(&self, listener: Arc<dyn JobProgressListener>) {
+        self.listeners.write().push(listener);
+    }
+
+    fn on_progress(&self, job_id: &str, progress: &JobProgress) {
+        for listener in self.listeners.read().iter() {
+            listener.on_progress(job_id, progress);
+        }
+    }
+
+    fn on_finished(&self, job_id: &str) {
+        for listener in self.listeners.read().iter() {
+            listener.on_finished(job_id);
+        }
+    }
+}
 
 /// This operator sends a logical plan to a Ballista scheduler for execution and
 /// polls the scheduler until the query is complete and then fetches the resulting
@@ -176,7 +216,7 @@ impl<T: 'static + AsLogicalPlan> ExecutionPlan for DistributedQueryExec<T> {
     fn execute(
         &self,
         partition: usize,
-        _context: Arc<TaskContext>,
+        context: Arc<TaskContext>,
     ) -> Result<SendableRecordBatchStream> {
         assert_eq!(0, partition);
 
@@ -198,11 +238,23 @@ impl<T: 'static + AsLogicalPlan> ExecutionPlan for DistributedQueryExec<T> {
             optional_session_id: Some(OptionalSessionId::SessionId(
                 self.session_id.clone(),
             )),
+            trace_context: telemetry::trace_context(&Span::current())
+                .into_iter()
+                .map(|(key, value)| KeyValuePair { key, value })
+                .collect(),
         };
 
         let stream = futures::stream::once(
-            execute_query(self.scheduler_url.clone(), self.session_id.clone(), query)
-                .map_err(|e| ArrowError::ExternalError(Box::new(e))),
+            execute_query(
+                self.scheduler_url.clone(),
+                self.session_id.clone(),
+                query,
+                context.session_config().get_extension::<GrpcTlsConfig>(),
+                context
+                    .session_config()
+                    .get_extension::<JobProgressListeners>(),
+            )
+            .map_err(|e| ArrowError::ExternalError(Box::new(e))),
         )
         .try_flatten();
 
@@ -222,10 +274,12 @@ async fn execute_query(
     scheduler_url: String,
     session_id: String,
     query: ExecuteQueryParams,
+    tls: Option<Arc<GrpcTlsConfig>>,
+    progress_listeners: Option<Arc<JobProgressListeners>>,
 ) -> Result<impl Stream<Item = Result<RecordBatch>> + Send> {
     info!("Connecting to Ballista scheduler at {}", scheduler_url);
     // TODO reuse the scheduler to avoid connecting to the Ballista scheduler again and again
-    let connection = create_grpc_client_connection(scheduler_url)
+    let connection = create_grpc_client_connection(scheduler_url, tls.as_deref())
         .await
         .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
 
@@ -252,49 +306,62 @@ async fn execute_query(
     );
 
     let job_id = query_result.job_id;
+    let mut statuses = watch_job(scheduler, job_id.clone()).await?;
     let mut prev_status: Option<job_status::Status> = None;
 
     loop {
-        let GetJobStatusResult { status } = scheduler
-            .get_job_status(GetJobStatusParams {
-                job_id: job_id.clone(),
-            })
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?
-            .into_inner();
+        let status = statuses.next().await.ok_or_else(|| {
+            DataFusionError::Execution(format!(
+                "Stream of the status of job {job_id} ended before the job finished"
+            ))
+        })??;
         let status = status.and_then(|s| s.status);
-        let wait_future = tokio::time::sleep(Duration::from_millis(100));
-        let has_status_change = prev_status != status;
+        // the progress of a running job changes without its status changing
+        let has_status_change = prev_status.as_ref().map(std::mem::discriminant)
+            != status.as_ref().map(std::mem::discriminant);
         match status {
             None => {
                 if has_status_change {
                     info!("Job {} still in initialization ...", job_id);
                 }
-                wait_future.await;
                 prev_status = status;
             }
-            Some(job_status::Status::Queued(_)) => {
-                if has_status_change {
+            Some(job_status::Status::Queued(ref queued)) => {
+                if queued.position > 0 {
+                    info!(
+                        "Job {} waiting for running jobs to finish, at position {}",
+                        job_id, queued.position
+                    );
+                } else if has_status_change {
                     info!("Job {} still queued...", job_id);
                 }
-                wait_future.await;
                 prev_status = status;
             }
-            Some(job_status::Status::Running(_)) => {
+            Some(job_status::Status::Running(ref running)) => {
                 if has_status_change {
                     info!("Job {} is running...", job_id);
                 }
-                wait_future.await;
+                if let (Some(listeners), Some(progress)) =
+                    (&progress_listeners, &running.progress)
+                {
+                    listeners.on_progress(&job_id, progress);
+                }
                 prev_status = status;
             }
             Some(job_status::Status::Failed(err)) => {
+                if let Some(listeners) = &progress_listeners {
+                    listeners.on_finished(&job_id);
+                }
                 let msg = format!("Job {} failed: {}", job_id, err.error);
                 error!("{}", msg);
                 break Err(DataFusionError::Execution(msg));
             }
             Some(job_status::Status::Successful(successful)) => {
-                let streams = successful.partition_location.into_iter().map(|p| {
-                    let f = fetch_partition(p)
+                if let Some(listeners) = &progress_listeners {
+                    listeners.on_finished(&job_id);
+                }
+                let streams = successful.partition_location.into_iter().map(move |p| {
+                    let f = fetch_partition(p, tls.clone())
                         .map_err(|e| ArrowError::ExternalError(Box::new(e)));
 
                     futures::stream::once(f).try_flatten()
@@ -306,8 +373,51 @@ async fn execute_query(
     }
 }
 
+/// Stream the status of a job. Schedulers that do not implement the `WatchJob` RPC
+/// are polled with `GetJobStatus` instead.
+async fn watch_job(
+    mut scheduler: SchedulerGrpcClient<Channel>,
+    job_id: String,
+) -> Result<BoxStream<'static, Result<Option<JobStatus>>>> {
+    let params = WatchJobParams {
+        job_id: job_id.clone(),
+    };
+    match scheduler.watch_job(params).await {
+        Ok(response) => Ok(response
+            .into_inner()
+            .map_ok(|result| result.status)
+            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))
+            .boxed()),
+        Err(e) if e.code() == Code::Unimplemented => {
+            warn!(
+                "Scheduler does not support watching jobs, polling the status of job {}",
+                job_id
+            );
+            let statuses = futures::stream::unfold(
+                (scheduler, job_id, true),
+                |(mut scheduler, job_id, first)| async move {
+                    if !first {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                    let status = scheduler
+                        .get_job_status(GetJobStatusParams {
+                            job_id: job_id.clone(),
+                        })
+                        .await
+                        .map(|response| response.into_inner().status)
+                        .map_err(|e| DataFusionError::Execution(format!("{e:?}")));
+                    Some((status, (scheduler, job_id, false)))
+                },
+            );
+            Ok(statuses.boxed())
+        }
+        Err(e) => Err(DataFusionError::Execution(format!("{e:?}"))),
+    }
+}
+
 async fn fetch_partition(
     location: PartitionLocation,
+    tls: Option<Arc<GrpcTlsConfig>>,
 ) -> Result<SendableRecordBatchStream> {
     let metadata = location.executor_meta.ok_or_else(|| {
         DataFusionError::Internal("Received empty executor metadata".to_owned())
@@ -315,11 +425,16 @@ async fn fetch_partition(
     let partition_id = location.partition_id.ok_or_else(|| {
         DataFusionError::Internal("Received empty partition id".to_owned())
     })?;
+    let compression = location
+        .compression
+        .parse()
+        .map_err(DataFusionError::Execution)?;
     let host = metadata.host.as_str();
     let port = metadata.port as u16;
-    let mut ballista_client = BallistaClient::try_new(host, port)
-        .await
-        .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
+    let mut ballista_client =
+        BallistaClient::try_new(host, port, tls.as_deref())
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
     ballista_client
         .fetch_partition(
             &metadata.id,
@@ -327,6 +442,8 @@ async fn fetch_partition(
             &location.path,
             host,
             port,
+            compression,
+            Some(location.index_path.as_str()).filter(|p| !p.is_empty()),
         )
         .await
         .map_err(|e| DataFusionError::External(Box::new(e)))
@@ -19,11 +19,19 @@
 //! several Ballista executors.
 
 mod distributed_query;
+mod range_partitioner;
+mod range_sample;
 mod shuffle_reader;
 mod shuffle_writer;
+mod sort_shuffle;
 mod unresolved_shuffle;
 
-pub use distributed_query::DistributedQueryExec;
+pub use distributed_query::{
+    DistributedQueryExec, JobProgressListener, JobProgressListeners,
+};
+pub use range_partitioner::RangePartitioning;
+pub use range_sample::RangeSampleExec;
 pub use shuffle_reader::ShuffleReaderExec;
 pub use shuffle_writer::ShuffleWriterExec;
+pub use sort_shuffle::read_sort_shuffle_partition;
 pub use unresolved_shuffle::UnresolvedShuffleExec;
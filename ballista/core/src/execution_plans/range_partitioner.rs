@@ -0,0 +1,210 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Range partitioning of the output of a shuffle, which splits the rows into ranges of
+//! their sort keys so that the sorted ranges can be concatenated into a total order.
+
+use std::fmt;
+use std::sync::Arc;
+
+use datafusion::arrow::array::{ArrayRef, UInt32Array};
+use datafusion::arrow::compute::take;
+use datafusion::arrow::datatypes::Schema;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::row::{OwnedRow, RowConverter, SortField};
+use datafusion::error::Result;
+use datafusion::physical_plan::expressions::PhysicalSortExpr;
+use datafusion::physical_plan::metrics;
+use datafusion::physical_plan::ExecutionPlan;
+
+/// Range partitioning of the output of a [ShuffleWriterExec](super::ShuffleWriterExec).
+///
+/// The boundaries of the ranges are only known once the input has been sampled, so the
+/// partitioning holds the plan reading the sample instead.
+#[derive(Debug, Clone)]
+pub struct RangePartitioning {
+    /// Sort order of the ranges
+    pub sort_exprs: Vec<PhysicalSortExpr>,
+    /// Number of ranges
+    pub partition_count: usize,
+    /// Plan reading a sample of the whole input of the shuffle as a single partition
+    pub sample: Arc<dyn ExecutionPlan>,
+}
+
+impl fmt::Display for RangePartitioning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sort_exprs = self
+            .sort_exprs
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "Range([{}], {})", sort_exprs, self.partition_count)
+    }
+}
+
+/// Splits record batches into the ranges of a [RangePartitioning]
+pub(crate) struct RangePartitioner {
+    sort_exprs: Vec<PhysicalSortExpr>,
+    converter: RowConverter,
+    /// Lower bounds of all the ranges but the first one, in ascending order
+    bounds: Vec<OwnedRow>,
+    partition_count: usize,
+    timer: metrics::Time,
+}
+
+impl RangePartitioner {
+    /// Create a partitioner whose boundaries split the given sample into ranges of
+    /// about the same number of rows
+    pub(crate) fn try_new(
+        sort_exprs: Vec<PhysicalSortExpr>,
+        partition_count: usize,
+        schema: &Schema,
+        sample: &[RecordBatch],
+        timer: metrics::Time,
+    ) -> Result<Self> {
+        let fields = sort_exprs
+            .iter()
+            .map(|e| {
+                Ok(SortField::new_with_options(
+                    e.expr.data_type(schema)?,
+                    e.options,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut converter = RowConverter::new(fields)?;
+
+        let mut keys = vec![];
+        for batch in sample {
+            let rows = converter.convert_columns(&sort_keys(&sort_exprs, batch)?)?;
+            keys.extend(rows.iter().map(|row| row.owned()));
+        }
+        keys.sort();
+
+        let bounds = (1..partition_count)
+            .filter_map(|i| keys.get(i * keys.len() / partition_count).cloned())
+            .collect();
+
+        Ok(Self {
+            sort_exprs,
+            converter,
+            bounds,
+            partition_count,
+            timer,
+        })
+    }
+
+    pub(crate) fn partition_count(&self) -> usize {
+        self.partition_count
+    }
+
+    /// Split a batch into the ranges of its rows, calling `f` with every non-empty range
+    pub(crate) fn partition<F>(&mut self, batch: RecordBatch, mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, RecordBatch) -> Result<()>,
+    {
+        let timer = self.timer.timer();
+        let rows = self
+            .converter
+            .convert_columns(&sort_keys(&self.sort_exprs, &batch)?)?;
+        let mut indices = vec![vec![]; self.partition_count];
+        for (index, row) in rows.iter().enumerate() {
+            let partition = self.bounds.partition_point(|bound| bound.row() <= row);
+            indices[partition].push(index as u32);
+        }
+        timer.done();
+
+        for (partition, indices) in indices.into_iter().enumerate() {
+            if indices.is_empty() {
+                continue;
+            }
+            let timer = self.timer.timer();
+            let indices = UInt32Array::from(indices);
+            let columns = batch
+                .columns()
+                .iter()
+                .map(|c| take(c.as_ref(), &indices, None))
+                .collect::<Result<Vec<_>, _>>()?;
+            let output = RecordBatch::try_new(batch.schema(), columns)?;
+            timer.done();
+            f(partition, output)?;
+        }
+        Ok(())
+    }
+}
+
+fn sort_keys(
+    sort_exprs: &[PhysicalSortExpr],
+    batch: &RecordBatch,
+) -> Result<Vec<ArrayRef>> {
+    sort_exprs
+        .iter()
+        .map(|e| Ok(e.expr.evaluate(batch)?.into_array(batch.num_rows())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::compute::SortOptions;
+    use datafusion::arrow::datatypes::{DataType, Field};
+    use datafusion::physical_plan::expressions::col;
+
+    fn batch(schema: &Arc<Schema>, values: Vec<i32>) -> RecordBatch {
+        RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_partition() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: col("a", &schema)?,
+            options: SortOptions {
+                descending: true,
+                nulls_first: false,
+            },
+        }];
+        let sample = vec![
+            batch(&schema, vec![1, 7, 3]),
+            batch(&schema, vec![9, 5, 11]),
+        ];
+        let mut partitioner = RangePartitioner::try_new(
+            sort_exprs,
+            3,
+            &schema,
+            &sample,
+            metrics::Time::new(),
+        )?;
+
+        let mut output = vec![vec![]; 3];
+        partitioner.partition(
+            batch(&schema, vec![0, 12, 6, 8, 4, 2, 10]),
+            |partition, batch| {
+                let array = batch.column(0);
+                let array = array.as_any().downcast_ref::<Int32Array>().unwrap();
+                output[partition].extend(array.values().iter().copied());
+                Ok(())
+            },
+        )?;
+
+        // the sample [11, 9, 7, 5, 3, 1] is split into ranges starting at 7 and 3
+        assert_eq!(vec![vec![12, 8, 10], vec![6, 4], vec![0, 2]], output);
+        Ok(())
+    }
+}
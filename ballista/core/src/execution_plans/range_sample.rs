@@ -0,0 +1,248 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use datafusion::arrow::compute::interleave;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_plan::expressions::PhysicalSortExpr;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+    Statistics,
+};
+use futures::{stream, StreamExt};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// RangeSampleExec draws a uniform random sample of up to `sample_size` rows from each
+/// partition of its input, which is used to compute the boundaries of a range
+/// partitioned shuffle.
+#[derive(Debug, Clone)]
+pub struct RangeSampleExec {
+    input: Arc<dyn ExecutionPlan>,
+    sample_size: usize,
+}
+
+impl RangeSampleExec {
+    /// Create a new RangeSampleExec
+    pub fn new(input: Arc<dyn ExecutionPlan>, sample_size: usize) -> Self {
+        Self { input, sample_size }
+    }
+
+    /// Get the maximum number of rows sampled from each input partition
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+}
+
+impl DisplayAs for RangeSampleExec {
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "RangeSampleExec: sample_size={}", self.sample_size)
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for RangeSampleExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(
+            self.input.output_partitioning().partition_count(),
+        )
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if children.len() == 1 {
+            Ok(Arc::new(RangeSampleExec::new(
+                children[0].clone(),
+                self.sample_size,
+            )))
+        } else {
+            Err(DataFusionError::Plan(
+                "RangeSampleExec wrong number of children".to_string(),
+            ))
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let schema = self.schema();
+        let sample_size = self.sample_size;
+        let mut input = self.input.execute(partition, context)?;
+
+        let sample = async move {
+            // seeding by partition keeps the sample, and so the ranges, the same when a
+            // task is retried
+            let mut reservoir = ReservoirSampler::new(
+                RecordBatch::new_empty(input.schema()),
+                sample_size,
+                partition as u64,
+            );
+            while let Some(batch) = input.next().await {
+                reservoir.insert(&batch?)?;
+            }
+            Ok::<_, DataFusionError>(reservoir.sample)
+        };
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream::once(sample),
+        )))
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// Reservoir sampling of the rows of a stream of record batches
+struct ReservoirSampler {
+    sample: RecordBatch,
+    sample_size: usize,
+    num_rows_seen: usize,
+    rng: StdRng,
+}
+
+impl ReservoirSampler {
+    fn new(empty: RecordBatch, sample_size: usize, seed: u64) -> Self {
+        Self {
+            sample: empty,
+            sample_size,
+            num_rows_seen: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn insert(&mut self, batch: &RecordBatch) -> Result<()> {
+        // every slot of the new sample refers to a row of either the current sample (0)
+        // or the batch (1)
+        let mut slots = (0..self.sample.num_rows())
+            .map(|row| (0, row))
+            .collect::<Vec<_>>();
+        let mut changed = false;
+        for row in 0..batch.num_rows() {
+            self.num_rows_seen += 1;
+            if slots.len() < self.sample_size {
+                slots.push((1, row));
+                changed = true;
+            } else {
+                let slot = self.rng.gen_range(0..self.num_rows_seen);
+                if slot < self.sample_size {
+                    slots[slot] = (1, row);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+
+        let columns = self
+            .sample
+            .columns()
+            .iter()
+            .zip(batch.columns())
+            .map(|(sample, batch)| interleave(&[sample.as_ref(), batch.as_ref()], &slots))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.sample = RecordBatch::try_new(self.sample.schema(), columns)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::UInt32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::prelude::SessionContext;
+
+    #[tokio::test]
+    async fn test_sample_partitions() -> Result<()> {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("a", DataType::UInt32, false)]));
+        let batches = (0..10)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(UInt32Array::from_iter_values(i * 10..i * 10 + 10))],
+                )
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[batches, vec![]],
+            schema.clone(),
+            None,
+        )?);
+
+        let sample = Arc::new(RangeSampleExec::new(input, 20));
+        assert_eq!(2, sample.output_partitioning().partition_count());
+
+        let task_ctx = SessionContext::new().task_ctx();
+        let mut stream = sample.execute(0, task_ctx.clone())?;
+        let batch = stream.next().await.unwrap()?;
+        assert!(stream.next().await.is_none());
+        assert_eq!(20, batch.num_rows());
+        let values = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        let mut values = values.values().to_vec();
+        values.sort_unstable();
+        values.dedup();
+        assert_eq!(20, values.len());
+        assert!(values.iter().all(|v| *v < 100));
+
+        // the sample of an empty partition is empty
+        let mut stream = sample.execute(1, task_ctx)?;
+        let batch = stream.next().await.unwrap()?;
+        assert_eq!(0, batch.num_rows());
+        Ok(())
+    }
+}
@@ -20,22 +20,27 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fs::File;
+use std::io::Cursor;
 use std::pin::Pin;
 use std::result;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use crate::client::BallistaClient;
+use crate::execution_plans::read_sort_shuffle_partition;
 use crate::serde::scheduler::{PartitionLocation, PartitionStats};
+use crate::utils::{self, GrpcTlsConfig};
 
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::arrow::error::ArrowError;
 use datafusion::arrow::ipc::reader::FileReader;
 use datafusion::arrow::record_batch::RecordBatch;
 
-use datafusion::error::Result;
+use datafusion::error::{DataFusionError, Result};
 use datafusion::physical_plan::expressions::PhysicalSortExpr;
-use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::metrics::{
+    self, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
     SendableRecordBatchStream, Statistics,
@@ -43,7 +48,9 @@ use datafusion::physical_plan::{
 use futures::{Stream, StreamExt, TryStreamExt};
 
 use crate::error::BallistaError;
+use bytes::Bytes;
 use datafusion::execution::context::TaskContext;
+use datafusion::execution::runtime_env::RuntimeEnv;
 use datafusion::physical_plan::common::AbortOnDropMany;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use itertools::Itertools;
@@ -53,6 +60,7 @@ use rand::thread_rng;
 use tokio::sync::{mpsc, Semaphore};
 use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info_span, Instrument, Span};
 
 /// ShuffleReaderExec reads partitions that have already been materialized by a ShuffleWriterExec
 /// being executed by an executor
@@ -63,6 +71,9 @@ pub struct ShuffleReaderExec {
     pub(crate) schema: SchemaRef,
     /// Each partition of a shuffle can read data from multiple locations
     pub partition: Vec<Vec<PartitionLocation>>,
+    /// Whether all the partitions are read as a single partition, e.g. to send the build
+    /// side of a join to every task of a stage
+    pub broadcast: bool,
     /// Execution metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -78,9 +89,22 @@ impl ShuffleReaderExec {
             stage_id,
             schema,
             partition,
+            broadcast: false,
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
+
+    /// Create a new ShuffleReaderExec which reads all the partitions as a single partition
+    pub fn try_new_broadcast(
+        stage_id: usize,
+        partition: Vec<Vec<PartitionLocation>>,
+        schema: SchemaRef,
+    ) -> Result<Self> {
+        Ok(Self {
+            broadcast: true,
+            ..Self::try_new(stage_id, partition, schema)?
+        })
+    }
 }
 
 impl DisplayAs for ShuffleReaderExec {
@@ -91,7 +115,15 @@ impl DisplayAs for ShuffleReaderExec {
     ) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default | DisplayFormatType::Verbose => {
-                write!(f, "ShuffleReaderExec: partitions={}", self.partition.len())
+                if self.broadcast {
+                    write!(
+                        f,
+                        "ShuffleReaderExec: broadcast partitions={}",
+                        self.partition.len()
+                    )
+                } else {
+                    write!(f, "ShuffleReaderExec: partitions={}", self.partition.len())
+                }
             }
         }
     }
@@ -109,7 +141,11 @@ impl ExecutionPlan for ShuffleReaderExec {
     fn output_partitioning(&self) -> Partitioning {
         // TODO partitioning may be known and could be populated here
         // see https://github.com/apache/arrow-datafusion/issues/758
-        Partitioning::UnknownPartitioning(self.partition.len())
+        if self.broadcast {
+            Partitioning::UnknownPartitioning(1)
+        } else {
+            Partitioning::UnknownPartitioning(self.partition.len())
+        }
     }
 
     fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
@@ -124,11 +160,10 @@ impl ExecutionPlan for ShuffleReaderExec {
         self: Arc<Self>,
         _children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(ShuffleReaderExec::try_new(
-            self.stage_id,
-            self.partition.clone(),
-            self.schema.clone(),
-        )?))
+        Ok(Arc::new(ShuffleReaderExec {
+            metrics: ExecutionPlanMetricsSet::new(),
+            ..(*self).clone()
+        }))
     }
 
     fn execute(
@@ -142,7 +177,12 @@ impl ExecutionPlan for ShuffleReaderExec {
         // TODO make the maximum size configurable, or make it depends on global memory control
         let max_request_num = 50usize;
         let mut partition_locations = HashMap::new();
-        for p in &self.partition[partition] {
+        let locations = if self.broadcast {
+            self.partition.iter().flatten().collect::<Vec<_>>()
+        } else {
+            self.partition[partition].iter().collect()
+        };
+        for p in locations {
             partition_locations
                 .entry(p.executor_meta.id.clone())
                 .or_insert_with(Vec::new)
@@ -158,8 +198,15 @@ impl ExecutionPlan for ShuffleReaderExec {
         // Shuffle partitions for evenly send fetching partition requests to avoid hot executors within multiple tasks
         partition_locations.shuffle(&mut thread_rng());
 
-        let response_receiver =
-            send_fetch_partitions(partition_locations, max_request_num);
+        let fetch_time =
+            MetricBuilder::new(&self.metrics).subset_time("fetch_time", partition);
+        let response_receiver = send_fetch_partitions(
+            partition_locations,
+            max_request_num,
+            context.runtime_env(),
+            context.session_config().get_extension::<GrpcTlsConfig>(),
+            fetch_time,
+        );
 
         let result = RecordBatchStreamAdapter::new(
             Arc::new(self.schema.as_ref().clone()),
@@ -275,39 +322,82 @@ impl Stream for AbortableReceiverStream {
 fn send_fetch_partitions(
     partition_locations: Vec<PartitionLocation>,
     max_request_num: usize,
+    runtime: Arc<RuntimeEnv>,
+    tls: Option<Arc<GrpcTlsConfig>>,
+    fetch_time: metrics::Time,
 ) -> AbortableReceiverStream {
     let (response_sender, response_receiver) = mpsc::channel(max_request_num);
     let semaphore = Arc::new(Semaphore::new(max_request_num));
     let mut join_handles = vec![];
-    let (local_locations, remote_locations): (Vec<_>, Vec<_>) = partition_locations
+    let (object_store_locations, file_locations): (Vec<_>, Vec<_>) = partition_locations
+        .into_iter()
+        .partition(|location| utils::is_object_store_location(&location.path));
+    let (local_locations, remote_locations): (Vec<_>, Vec<_>) = file_locations
         .into_iter()
         .partition(check_is_local_location);
 
     info!(
-        "local shuffle file counts:{}, remote shuffle file count:{}.",
+        "local shuffle file counts:{}, remote shuffle file count:{}, object store shuffle file count:{}.",
         local_locations.len(),
-        remote_locations.len()
+        remote_locations.len(),
+        object_store_locations.len()
     );
 
     // keep local shuffle files reading in serial order for memory control.
     let response_sender_c = response_sender.clone();
-    let join_handle = tokio::spawn(async move {
-        for p in local_locations {
-            let r = PartitionReaderEnum::Local.fetch_partition(&p).await;
-            if let Err(e) = response_sender_c.send(r).await {
-                error!("Fail to send response event to the channel due to {}", e);
+    let fetch_time_c = fetch_time.clone();
+    let join_handle = tokio::spawn(
+        async move {
+            for p in local_locations {
+                let timer = fetch_time_c.timer();
+                let r = PartitionReaderEnum::Local
+                    .fetch_partition(&p)
+                    .instrument(fetch_span(&p))
+                    .await;
+                timer.done();
+                if let Err(e) = response_sender_c.send(r).await {
+                    error!("Fail to send response event to the channel due to {}", e);
+                }
             }
         }
-    });
+        .in_current_span(),
+    );
     join_handles.push(join_handle);
 
     for p in remote_locations.into_iter() {
         let semaphore = semaphore.clone();
         let response_sender = response_sender.clone();
+        let reader = PartitionReaderEnum::FlightRemote(tls.clone());
+        let fetch_time = fetch_time.clone();
+        let span = fetch_span(&p);
+        let join_handle = tokio::spawn(async move {
+            // Block if exceeds max request number
+            let permit = semaphore.acquire_owned().await.unwrap();
+            let timer = fetch_time.timer();
+            let r = reader.fetch_partition(&p).instrument(span).await;
+            timer.done();
+            // Block if the channel buffer is ful
+            if let Err(e) = response_sender.send(r).await {
+                error!("Fail to send response event to the channel due to {}", e);
+            }
+            // Increase semaphore by dropping existing permits.
+            drop(permit);
+        });
+        join_handles.push(join_handle);
+    }
+
+    for p in object_store_locations.into_iter() {
+        let semaphore = semaphore.clone();
+        let response_sender = response_sender.clone();
+        let reader = PartitionReaderEnum::ObjectStoreRemote(runtime.clone());
+        let fetch_time = fetch_time.clone();
+        let span = fetch_span(&p);
         let join_handle = tokio::spawn(async move {
             // Block if exceeds max request number
             let permit = semaphore.acquire_owned().await.unwrap();
-            let r = PartitionReaderEnum::FlightRemote.fetch_partition(&p).await;
+            let timer = fetch_time.timer();
+            let r = reader.fetch_partition(&p).instrument(span).await;
+            timer.done();
             // Block if the channel buffer is ful
             if let Err(e) = response_sender.send(r).await {
                 error!("Fail to send response event to the channel due to {}", e);
@@ -321,6 +411,19 @@ fn send_fetch_partitions(
     AbortableReceiverStream::create(response_receiver, join_handles)
 }
 
+/// Span of the fetch of a shuffle partition, the child of the span of the task reading
+/// the partition
+fn fetch_span(location: &PartitionLocation) -> Span {
+    info_span!(
+        "shuffle_fetch",
+        job_id = %location.partition_id.job_id,
+        stage_id = location.partition_id.stage_id,
+        partition_id = location.partition_id.partition_id,
+        map_partition_id = location.map_partition_id,
+        executor_id = %location.executor_meta.id,
+    )
+}
+
 fn check_is_local_location(location: &PartitionLocation) -> bool {
     std::path::Path::new(location.path.as_str()).exists()
 }
@@ -338,9 +441,8 @@ trait PartitionReader: Send + Sync + Clone {
 #[derive(Clone)]
 enum PartitionReaderEnum {
     Local,
-    FlightRemote,
-    #[allow(dead_code)]
-    ObjectStoreRemote,
+    FlightRemote(Option<Arc<GrpcTlsConfig>>),
+    ObjectStoreRemote(Arc<RuntimeEnv>),
 }
 
 #[async_trait]
@@ -351,10 +453,12 @@ impl PartitionReader for PartitionReaderEnum {
         location: &PartitionLocation,
     ) -> result::Result<SendableRecordBatchStream, BallistaError> {
         match self {
-            PartitionReaderEnum::FlightRemote => fetch_partition_remote(location).await,
+            PartitionReaderEnum::FlightRemote(tls) => {
+                fetch_partition_remote(location, tls.as_deref()).await
+            }
             PartitionReaderEnum::Local => fetch_partition_local(location).await,
-            PartitionReaderEnum::ObjectStoreRemote => {
-                fetch_partition_object_store(location).await
+            PartitionReaderEnum::ObjectStoreRemote(runtime) => {
+                fetch_partition_object_store(location, runtime).await
             }
         }
     }
@@ -362,6 +466,7 @@ impl PartitionReader for PartitionReaderEnum {
 
 async fn fetch_partition_remote(
     location: &PartitionLocation,
+    tls: Option<&GrpcTlsConfig>,
 ) -> result::Result<SendableRecordBatchStream, BallistaError> {
     let metadata = &location.executor_meta;
     let partition_id = &location.partition_id;
@@ -370,7 +475,7 @@ async fn fetch_partition_remote(
     let host = metadata.host.as_str();
     let port = metadata.port;
     let mut ballista_client =
-        BallistaClient::try_new(host, port)
+        BallistaClient::try_new(host, port, tls)
             .await
             .map_err(|error| match error {
                 // map grpc connection error to partition fetch error.
@@ -384,7 +489,15 @@ async fn fetch_partition_remote(
             })?;
 
     ballista_client
-        .fetch_partition(&metadata.id, partition_id, &location.path, host, port)
+        .fetch_partition(
+            &metadata.id,
+            partition_id,
+            &location.path,
+            host,
+            port,
+            location.compression,
+            location.index_path.as_deref(),
+        )
         .await
 }
 
@@ -394,16 +507,26 @@ async fn fetch_partition_local(
     let path = &location.path;
     let metadata = &location.executor_meta;
     let partition_id = &location.partition_id;
-
-    let reader = fetch_partition_local_inner(path).map_err(|e| {
-        // return BallistaError::FetchFailed may let scheduler retry this task.
+    // return BallistaError::FetchFailed may let scheduler retry this task.
+    let fetch_failed = |e: BallistaError| {
         BallistaError::FetchFailed(
             metadata.id.clone(),
             partition_id.stage_id,
             partition_id.partition_id,
             e.to_string(),
         )
-    })?;
+    };
+
+    if let Some(index_path) = &location.index_path {
+        let reader =
+            read_sort_shuffle_partition(path, index_path, partition_id.partition_id)
+                .map_err(fetch_failed)?;
+        let schema = reader.schema();
+        let stream = futures::stream::iter(reader).map_err(DataFusionError::from);
+        return Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)));
+    }
+
+    let reader = fetch_partition_local_inner(path).map_err(fetch_failed)?;
     Ok(Box::pin(LocalShuffleStream::new(reader)))
 }
 
@@ -419,11 +542,48 @@ fn fetch_partition_local_inner(
 }
 
 async fn fetch_partition_object_store(
-    _location: &PartitionLocation,
+    location: &PartitionLocation,
+    runtime: &RuntimeEnv,
 ) -> result::Result<SendableRecordBatchStream, BallistaError> {
-    Err(BallistaError::NotImplemented(
-        "Should not use ObjectStorePartitionReader".to_string(),
-    ))
+    let path = &location.path;
+    let metadata = &location.executor_meta;
+    let partition_id = &location.partition_id;
+
+    let reader = fetch_partition_object_store_inner(path, runtime)
+        .await
+        .map_err(|e| {
+            // return BallistaError::FetchFailed may let scheduler retry this task.
+            BallistaError::FetchFailed(
+                metadata.id.clone(),
+                partition_id.stage_id,
+                partition_id.partition_id,
+                e.to_string(),
+            )
+        })?;
+    let schema = reader.schema();
+    let stream = futures::stream::iter(reader).map_err(DataFusionError::from);
+    Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+}
+
+async fn fetch_partition_object_store_inner(
+    path: &str,
+    runtime: &RuntimeEnv,
+) -> result::Result<FileReader<Cursor<Bytes>>, BallistaError> {
+    let (store_url, location) =
+        utils::parse_object_store_location(path).ok_or_else(|| {
+            BallistaError::General(format!("Invalid object store location {path}"))
+        })?;
+    let object_store = runtime.object_store(&store_url)?;
+    let data = match object_store.get(&location).await {
+        Ok(result) => result.bytes().await,
+        Err(e) => Err(e),
+    }
+    .map_err(|e| {
+        BallistaError::General(format!("Failed to get partition file at {path}: {e:?}"))
+    })?;
+    FileReader::try_new(Cursor::new(data), None).map_err(|e| {
+        BallistaError::General(format!("Failed to new arrow FileReader at {path}: {e:?}"))
+    })
 }
 
 #[cfg(test)]
@@ -431,7 +591,6 @@ mod tests {
     use super::*;
     use crate::execution_plans::ShuffleWriterExec;
     use crate::serde::scheduler::{ExecutorMetadata, ExecutorSpecification, PartitionId};
-    use crate::utils;
     use datafusion::arrow::array::{Int32Array, StringArray, UInt32Array};
     use datafusion::arrow::datatypes::{DataType, Field, Schema};
     use datafusion::arrow::ipc::writer::FileWriter;
@@ -441,6 +600,7 @@ mod tests {
     use datafusion::physical_plan::common;
     use datafusion::physical_plan::memory::MemoryExec;
     use datafusion::prelude::SessionContext;
+    use object_store::memory::InMemory;
     use tempfile::{tempdir, TempDir};
 
     #[tokio::test]
@@ -537,10 +697,13 @@ mod tests {
                     host: "executor_1".to_string(),
                     port: 7070,
                     grpc_port: 8080,
-                    specification: ExecutorSpecification { task_slots: 1 },
+                    specification: ExecutorSpecification::new(1),
+                    labels: Default::default(),
                 },
                 partition_stats: Default::default(),
                 path: "test_path".to_string(),
+                compression: Default::default(),
+                index_path: None,
             })
         }
 
@@ -618,6 +781,115 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    // number of rows in each partition is a function of the hash output
+    #[cfg(not(feature = "force_hash_collisions"))]
+    async fn test_read_sort_based_shuffle() {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let work_dir = TempDir::new().unwrap();
+        let input = ShuffleWriterExec::try_new(
+            "sort_based".to_owned(),
+            1,
+            create_test_data_plan().unwrap(),
+            work_dir.into_path().to_str().unwrap().to_owned(),
+            Some(Partitioning::Hash(
+                vec![Arc::new(Column::new("number", 0))],
+                2,
+            )),
+        )
+        .unwrap()
+        .with_sort_based(true);
+
+        let shuffle_partitions = input
+            .execute_shuffle_write(0, task_ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(2, shuffle_partitions.len());
+        // both shuffle partitions are in the same data file
+        assert_eq!(shuffle_partitions[0].path, shuffle_partitions[1].path);
+
+        let partition_locations = shuffle_partitions
+            .into_iter()
+            .map(|p| {
+                let mut location =
+                    get_test_partition_locations(p.partition_id as usize + 1, p.path)
+                        .pop()
+                        .unwrap();
+                location.index_path = Some(p.index_path);
+                vec![location]
+            })
+            .collect();
+
+        let shuffle_reader_exec =
+            ShuffleReaderExec::try_new(1, partition_locations, create_test_schema())
+                .unwrap();
+        let mut num_rows = 0;
+        for partition in 0..2 {
+            let mut stream = shuffle_reader_exec
+                .execute(partition, task_ctx.clone())
+                .unwrap();
+            let result = utils::collect_stream(&mut stream)
+                .await
+                .map_err(|e| DataFusionError::Execution(format!("{e:?}")))
+                .unwrap();
+            num_rows += result.iter().map(|b| b.num_rows()).sum::<usize>();
+        }
+        assert_eq!(6, num_rows);
+    }
+
+    #[tokio::test]
+    async fn test_read_object_store_shuffle() {
+        let session_ctx = SessionContext::new();
+        session_ctx.runtime_env().register_object_store(
+            &url::Url::parse("memory://").unwrap(),
+            Arc::new(InMemory::new()),
+        );
+        let task_ctx = session_ctx.task_ctx();
+        let input = ShuffleWriterExec::try_new(
+            "object_store".to_owned(),
+            1,
+            create_test_data_plan().unwrap(),
+            "memory:///shuffle".to_owned(),
+            Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 1)),
+        )
+        .unwrap();
+
+        let mut stream = input.execute(0, task_ctx.clone()).unwrap();
+
+        let batches = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))
+            .unwrap();
+
+        let path = batches[0].columns()[1]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+
+        let file_path = path.value(0);
+        assert!(utils::is_object_store_location(file_path));
+        // the shuffle file is not bound to the executor which wrote it
+        let partition_locations = get_test_partition_locations(1, file_path.to_owned());
+
+        let shuffle_reader_exec = ShuffleReaderExec::try_new(
+            1,
+            vec![partition_locations],
+            create_test_schema(),
+        )
+        .unwrap();
+        let mut stream = shuffle_reader_exec.execute(0, task_ctx).unwrap();
+        let result = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))
+            .unwrap();
+
+        assert_eq!(result.len(), 2);
+        for b in result {
+            assert_eq!(b, create_test_batch())
+        }
+    }
+
     async fn test_send_fetch_partitions(max_request_num: usize, partition_num: usize) {
         let schema = get_test_partition_schema();
         let data_array = Int32Array::from(vec![1]);
@@ -636,8 +908,14 @@ mod tests {
             file_path.to_str().unwrap().to_string(),
         );
 
-        let response_receiver =
-            send_fetch_partitions(partition_locations, max_request_num);
+        let fetch_time = metrics::Time::new();
+        let response_receiver = send_fetch_partitions(
+            partition_locations,
+            max_request_num,
+            Arc::new(RuntimeEnv::default()),
+            None,
+            fetch_time.clone(),
+        );
 
         let stream = RecordBatchStreamAdapter::new(
             Arc::new(schema),
@@ -646,6 +924,7 @@ mod tests {
 
         let result = common::collect(Box::pin(stream)).await.unwrap();
         assert_eq!(partition_num, result.len());
+        assert!(fetch_time.value() > 0);
     }
 
     fn get_test_partition_locations(n: usize, path: String) -> Vec<PartitionLocation> {
@@ -662,10 +941,13 @@ mod tests {
                     host: "localhost".to_string(),
                     port: 50051,
                     grpc_port: 50052,
-                    specification: ExecutorSpecification { task_slots: 12 },
+                    specification: ExecutorSpecification::new(12),
+                    labels: Default::default(),
                 },
                 partition_stats: Default::default(),
                 path: path.clone(),
+                compression: Default::default(),
+                index_path: None,
             })
             .collect()
     }
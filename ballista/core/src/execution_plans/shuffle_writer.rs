@@ -29,6 +29,11 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 
+use crate::config::ShuffleCompression;
+use crate::execution_plans::range_partitioner::{RangePartitioner, RangePartitioning};
+use crate::execution_plans::sort_shuffle::{
+    SortShuffleWriter, SORT_SHUFFLE_SPILL_THRESHOLD,
+};
 use crate::utils;
 
 use crate::serde::protobuf::ShuffleWritePartition;
@@ -38,9 +43,11 @@ use datafusion::arrow::array::{
 };
 use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 
+use datafusion::arrow::ipc::writer::FileWriter;
 use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::object_store::ObjectStoreUrl;
 use datafusion::error::{DataFusionError, Result};
-use datafusion::physical_plan::common::IPCWriter;
+use datafusion::physical_plan::common::{self, IPCWriter};
 use datafusion::physical_plan::memory::MemoryStream;
 use datafusion::physical_plan::metrics::{
     self, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
@@ -56,7 +63,12 @@ use datafusion::arrow::error::ArrowError;
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::repartition::BatchPartitioner;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
-use log::{debug, info};
+use log::{debug, info, warn};
+use object_store::path::Path;
+use object_store::{MultipartId, ObjectStore};
+use parking_lot::Mutex;
+use std::io::Write;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 /// ShuffleWriterExec represents a section of a query plan that has consistent partitioning and
 /// can be executed as one unit with each partition being executed in parallel. The output of each
@@ -70,11 +82,20 @@ pub struct ShuffleWriterExec {
     stage_id: usize,
     /// Physical execution plan for this query stage
     plan: Arc<dyn ExecutionPlan>,
-    /// Path to write output streams to
+    /// Path to write output streams to. This is either a directory on the local file
+    /// system or the URL of an object store, e.g. `s3://bucket/shuffle`
     work_dir: String,
     /// Optional shuffle output partitioning.
     /// If it's none, it means there's no need to do repartitioning.
     shuffle_output_partitioning: Option<Partitioning>,
+    /// Codec used to compress the shuffle files
+    compression: ShuffleCompression,
+    /// Whether to write a single data file and index file per input partition when
+    /// repartitioning to a local work dir, instead of a file per output partition
+    sort_based: bool,
+    /// Range partitioning of the shuffle output, in which case the shuffle output
+    /// partitioning only holds the number of ranges
+    range_partitioning: Option<RangePartitioning>,
     /// Execution metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -122,10 +143,41 @@ impl ShuffleWriterExec {
             plan,
             work_dir,
             shuffle_output_partitioning,
+            compression: ShuffleCompression::None,
+            sort_based: false,
+            range_partitioning: None,
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
 
+    /// Create a new shuffle writer which splits its output into ranges of the sort keys
+    pub fn try_new_range(
+        job_id: String,
+        stage_id: usize,
+        plan: Arc<dyn ExecutionPlan>,
+        work_dir: String,
+        range_partitioning: RangePartitioning,
+    ) -> Result<Self> {
+        let partitioning =
+            Partitioning::UnknownPartitioning(range_partitioning.partition_count);
+        Ok(Self {
+            range_partitioning: Some(range_partitioning),
+            ..Self::try_new(job_id, stage_id, plan, work_dir, Some(partitioning))?
+        })
+    }
+
+    /// Compress the shuffle files with the given codec
+    pub fn with_compression(mut self, compression: ShuffleCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Write a single data file with an index per input partition when repartitioning
+    pub fn with_sort_based(mut self, sort_based: bool) -> Self {
+        self.sort_based = sort_based;
+        self
+    }
+
     /// Get the Job ID for this query stage
     pub fn job_id(&self) -> &str {
         &self.job_id
@@ -146,6 +198,21 @@ impl ShuffleWriterExec {
         self.shuffle_output_partitioning.as_ref()
     }
 
+    /// Get the codec of the shuffle files
+    pub fn compression(&self) -> ShuffleCompression {
+        self.compression
+    }
+
+    /// Whether the shuffle is sort-based
+    pub fn sort_based(&self) -> bool {
+        self.sort_based
+    }
+
+    /// Get the range partitioning of the shuffle output, if any
+    pub fn range_partitioning(&self) -> Option<&RangePartitioning> {
+        self.range_partitioning.as_ref()
+    }
+
     pub fn execute_shuffle_write(
         &self,
         input_partition: usize,
@@ -155,15 +222,62 @@ impl ShuffleWriterExec {
         path.push(&self.job_id);
         path.push(&format!("{}", self.stage_id));
 
+        let object_store_location = utils::parse_object_store_location(&self.work_dir)
+            .map(|(store_url, path)| {
+                let path = path
+                    .child(self.job_id.as_str())
+                    .child(self.stage_id.to_string());
+                (store_url, path)
+            });
+
         let write_metrics = ShuffleWriteMetrics::new(input_partition, &self.metrics);
         let output_partitioning = self.shuffle_output_partitioning.clone();
+        let range_partitioning = self.range_partitioning.clone();
         let plan = self.plan.clone();
+        let compression = self.compression;
+        let sort_based = self.sort_based;
 
         async move {
             let now = Instant::now();
+
+            let partitioner = create_partitioner(
+                output_partitioning,
+                range_partitioning,
+                plan.schema().as_ref(),
+                context.clone(),
+                &write_metrics,
+            )
+            .await?;
+
+            if let Some((store_url, stage_path)) = object_store_location {
+                let object_store = context.runtime_env().object_store(&store_url)?;
+                let stream = plan.execute(input_partition, context)?;
+                let part_locs = write_stream_to_object_store(
+                    stream,
+                    object_store,
+                    &store_url,
+                    &stage_path,
+                    input_partition,
+                    partitioner,
+                    compression,
+                    &write_metrics,
+                )
+                .await?;
+                info!(
+                    "Executed partition {} in {} seconds. Wrote {} shuffle partitions to {}",
+                    input_partition,
+                    now.elapsed().as_secs(),
+                    part_locs.len(),
+                    utils::object_store_location(&store_url, &stage_path)
+                );
+                return Ok(part_locs);
+            }
+
+            let memory_pool = context.memory_pool().clone();
             let mut stream = plan.execute(input_partition, context)?;
+            let write_options = compression.ipc_write_options()?;
 
-            match output_partitioning {
+            match partitioner {
                 None => {
                     let timer = write_metrics.write_time.timer();
                     path.push(&format!("{input_partition}"));
@@ -176,6 +290,7 @@ impl ShuffleWriterExec {
                     let stats = utils::write_stream_to_disk(
                         &mut stream,
                         path,
+                        write_options,
                         &write_metrics.write_time,
                     )
                     .await
@@ -202,22 +317,53 @@ impl ShuffleWriterExec {
                         num_batches: stats.num_batches.unwrap_or(0),
                         num_rows: stats.num_rows.unwrap_or(0),
                         num_bytes: stats.num_bytes.unwrap_or(0),
+                        compression: compression.to_string(),
+                        index_path: String::default(),
                     }])
                 }
 
-                Some(Partitioning::Hash(exprs, num_output_partitions)) => {
+                Some(mut partitioner) if sort_based => {
+                    let mut writer = SortShuffleWriter::try_new(
+                        path,
+                        input_partition,
+                        partitioner.partition_count(),
+                        stream.schema(),
+                        compression,
+                        SORT_SHUFFLE_SPILL_THRESHOLD,
+                        &memory_pool,
+                    )?;
+
+                    while let Some(result) = stream.next().await {
+                        let input_batch = result?;
+
+                        write_metrics.input_rows.add(input_batch.num_rows());
+
+                        partitioner.partition(
+                            input_batch,
+                            |output_partition, output_batch| {
+                                let timer = write_metrics.write_time.timer();
+                                write_metrics.output_rows.add(output_batch.num_rows());
+                                writer.write(output_partition, output_batch)?;
+                                timer.done();
+                                Ok(())
+                            },
+                        )?;
+                    }
+
+                    let timer = write_metrics.write_time.timer();
+                    let part_locs = writer.finish()?;
+                    timer.done();
+                    Ok(part_locs)
+                }
+
+                Some(mut partitioner) => {
                     // we won't necessary produce output for every possible partition, so we
                     // create writers on demand
                     let mut writers: Vec<Option<IPCWriter>> = vec![];
-                    for _ in 0..num_output_partitions {
+                    for _ in 0..partitioner.partition_count() {
                         writers.push(None);
                     }
 
-                    let mut partitioner = BatchPartitioner::try_new(
-                        Partitioning::Hash(exprs, num_output_partitions),
-                        write_metrics.repart_time.clone(),
-                    )?;
-
                     while let Some(result) = stream.next().await {
                         let input_batch = result?;
 
@@ -242,9 +388,10 @@ impl ShuffleWriterExec {
                                         ));
                                         debug!("Writing results to {:?}", path);
 
-                                        let mut writer = IPCWriter::new(
+                                        let mut writer = IPCWriter::new_with_options(
                                             &path,
                                             stream.schema().as_ref(),
+                                            write_options.clone(),
                                         )?;
 
                                         writer.write(&output_batch)?;
@@ -279,6 +426,8 @@ impl ShuffleWriterExec {
                                     num_batches: w.num_batches,
                                     num_rows: w.num_rows,
                                     num_bytes: w.num_bytes,
+                                    compression: compression.to_string(),
+                                    index_path: String::default(),
                                 });
                             }
                             None => {}
@@ -286,10 +435,360 @@ impl ShuffleWriterExec {
                     }
                     Ok(part_locs)
                 }
+            }
+        }
+    }
+}
+
+/// Splits the output of a shuffle into its output partitions
+enum ShufflePartitioner {
+    Hash(BatchPartitioner, usize),
+    Range(RangePartitioner),
+}
+
+impl ShufflePartitioner {
+    fn partition_count(&self) -> usize {
+        match self {
+            ShufflePartitioner::Hash(_, partition_count) => *partition_count,
+            ShufflePartitioner::Range(partitioner) => partitioner.partition_count(),
+        }
+    }
+
+    fn partition<F>(&mut self, batch: RecordBatch, f: F) -> Result<()>
+    where
+        F: FnMut(usize, RecordBatch) -> Result<()>,
+    {
+        match self {
+            ShufflePartitioner::Hash(partitioner, _) => partitioner.partition(batch, f),
+            ShufflePartitioner::Range(partitioner) => partitioner.partition(batch, f),
+        }
+    }
+}
+
+/// Create the partitioner of the shuffle output, reading the sample of the input to
+/// compute the boundaries of a range partitioning
+async fn create_partitioner(
+    output_partitioning: Option<Partitioning>,
+    range_partitioning: Option<RangePartitioning>,
+    schema: &Schema,
+    context: Arc<TaskContext>,
+    write_metrics: &ShuffleWriteMetrics,
+) -> Result<Option<ShufflePartitioner>> {
+    if let Some(range) = range_partitioning {
+        let sample = common::collect(range.sample.execute(0, context)?).await?;
+        return Ok(Some(ShufflePartitioner::Range(RangePartitioner::try_new(
+            range.sort_exprs,
+            range.partition_count,
+            schema,
+            &sample,
+            write_metrics.repart_time.clone(),
+        )?)));
+    }
+
+    match output_partitioning {
+        None => Ok(None),
+        Some(Partitioning::Hash(exprs, partition_count)) => {
+            let partitioner = BatchPartitioner::try_new(
+                Partitioning::Hash(exprs, partition_count),
+                write_metrics.repart_time.clone(),
+            )?;
+            Ok(Some(ShufflePartitioner::Hash(partitioner, partition_count)))
+        }
+        _ => Err(DataFusionError::Execution(
+            "Invalid shuffle partitioning scheme".to_owned(),
+        )),
+    }
+}
+
+/// Write the output of one input partition to an object store. Each shuffle partition
+/// is streamed as an Arrow IPC file through a multipart upload, so at most
+/// [`UPLOAD_PART_SIZE`] bytes per shuffle partition are buffered in memory.
+#[allow(clippy::too_many_arguments)]
+async fn write_stream_to_object_store(
+    mut stream: SendableRecordBatchStream,
+    object_store: Arc<dyn ObjectStore>,
+    store_url: &ObjectStoreUrl,
+    stage_path: &Path,
+    input_partition: usize,
+    partitioner: Option<ShufflePartitioner>,
+    compression: ShuffleCompression,
+    write_metrics: &ShuffleWriteMetrics,
+) -> Result<Vec<ShuffleWritePartition>> {
+    match partitioner {
+        None => {
+            let path = stage_path
+                .child(input_partition.to_string())
+                .child("data.arrow");
+            debug!("Writing results to {}", path);
+            let mut writer = ObjectStoreIPCWriter::try_new(
+                path,
+                &stream.schema(),
+                compression,
+                UPLOAD_PART_SIZE,
+            )?;
+
+            let result: Result<()> = async {
+                while let Some(result) = stream.next().await {
+                    let batch = result?;
+                    write_metrics.input_rows.add(batch.num_rows());
+
+                    let timer = write_metrics.write_time.timer();
+                    writer.write(&batch)?;
+                    writer.flush(object_store.as_ref()).await?;
+                    write_metrics.output_rows.add(batch.num_rows());
+                    timer.done();
+                }
+                Ok(())
+            }
+            .await;
+            if let Err(e) = result {
+                writer.abort(object_store.as_ref()).await;
+                return Err(e);
+            }
 
-                _ => Err(DataFusionError::Execution(
-                    "Invalid shuffle partitioning scheme".to_owned(),
-                )),
+            let timer = write_metrics.write_time.timer();
+            let part_loc = writer
+                .finish(input_partition, object_store.as_ref(), store_url)
+                .await?;
+            timer.done();
+            Ok(vec![part_loc])
+        }
+
+        Some(mut partitioner) => {
+            // we won't necessary produce output for every possible partition, so we
+            // create writers on demand
+            let mut writers: Vec<Option<ObjectStoreIPCWriter>> = vec![];
+            for _ in 0..partitioner.partition_count() {
+                writers.push(None);
+            }
+
+            let result: Result<()> = async {
+                while let Some(result) = stream.next().await {
+                    let input_batch = result?;
+
+                    write_metrics.input_rows.add(input_batch.num_rows());
+
+                    partitioner.partition(
+                        input_batch,
+                        |output_partition, output_batch| {
+                            let timer = write_metrics.write_time.timer();
+                            match &mut writers[output_partition] {
+                                Some(w) => {
+                                    w.write(&output_batch)?;
+                                }
+                                None => {
+                                    let path = stage_path
+                                        .child(output_partition.to_string())
+                                        .child(format!("data-{input_partition}.arrow"));
+                                    debug!("Writing results to {}", path);
+
+                                    let mut writer = ObjectStoreIPCWriter::try_new(
+                                        path,
+                                        &stream.schema(),
+                                        compression,
+                                        UPLOAD_PART_SIZE,
+                                    )?;
+                                    writer.write(&output_batch)?;
+                                    writers[output_partition] = Some(writer);
+                                }
+                            }
+                            write_metrics.output_rows.add(output_batch.num_rows());
+                            timer.done();
+                            Ok(())
+                        },
+                    )?;
+
+                    // upload the shuffle partitions that have buffered a full part
+                    let timer = write_metrics.write_time.timer();
+                    for w in writers.iter_mut().flatten() {
+                        w.flush(object_store.as_ref()).await?;
+                    }
+                    timer.done();
+                }
+                Ok(())
+            }
+            .await;
+
+            let mut writers = writers
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, w)| w.map(|w| (i, w)));
+            if let Err(e) = result {
+                for (_, w) in writers {
+                    w.abort(object_store.as_ref()).await;
+                }
+                return Err(e);
+            }
+
+            let mut part_locs = vec![];
+            while let Some((i, w)) = writers.next() {
+                let timer = write_metrics.write_time.timer();
+                match w.finish(i, object_store.as_ref(), store_url).await {
+                    Ok(part_loc) => part_locs.push(part_loc),
+                    Err(e) => {
+                        for (_, w) in writers {
+                            w.abort(object_store.as_ref()).await;
+                        }
+                        return Err(e);
+                    }
+                }
+                timer.done();
+            }
+            Ok(part_locs)
+        }
+    }
+}
+
+/// Size of the parts uploaded to an object store by [`ObjectStoreIPCWriter`]
+const UPLOAD_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// An in-memory buffer that can be drained while an IPC writer is writing to it
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn len(&self) -> usize {
+        self.0.lock().len()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes one shuffle partition in Arrow IPC format to an object store. The encoded
+/// batches are buffered until at least `part_size` bytes are available and then
+/// streamed through a multipart upload, which is started on the first flush.
+struct ObjectStoreIPCWriter {
+    path: Path,
+    writer: FileWriter<SharedBuffer>,
+    buffer: SharedBuffer,
+    upload: Option<(MultipartId, Box<dyn AsyncWrite + Send + Unpin>)>,
+    part_size: usize,
+    compression: ShuffleCompression,
+    num_parts: usize,
+    num_batches: u64,
+    num_rows: u64,
+    num_bytes: u64,
+}
+
+impl ObjectStoreIPCWriter {
+    fn try_new(
+        path: Path,
+        schema: &Schema,
+        compression: ShuffleCompression,
+        part_size: usize,
+    ) -> Result<Self> {
+        let write_options = compression.ipc_write_options()?;
+        let buffer = SharedBuffer::default();
+        Ok(Self {
+            path,
+            writer: FileWriter::try_new_with_options(
+                buffer.clone(),
+                schema,
+                write_options,
+            )?,
+            buffer,
+            upload: None,
+            part_size,
+            compression,
+            num_parts: 0,
+            num_batches: 0,
+            num_rows: 0,
+            num_bytes: 0,
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.writer.write(batch)?;
+        self.num_batches += 1;
+        self.num_rows += batch.num_rows() as u64;
+        self.num_bytes += batch.get_array_memory_size() as u64;
+        Ok(())
+    }
+
+    /// Upload the buffered bytes as the next part once a full part is buffered
+    async fn flush(&mut self, object_store: &dyn ObjectStore) -> Result<()> {
+        if self.buffer.len() < self.part_size {
+            return Ok(());
+        }
+        self.upload_part(object_store).await
+    }
+
+    async fn upload_part(&mut self, object_store: &dyn ObjectStore) -> Result<()> {
+        let data = self.buffer.take();
+        if data.is_empty() {
+            return Ok(());
+        }
+        if self.upload.is_none() {
+            self.upload = Some(object_store.put_multipart(&self.path).await?);
+        }
+        let (_, upload) = self.upload.as_mut().unwrap();
+        upload.write_all(&data).await?;
+        self.num_parts += 1;
+        Ok(())
+    }
+
+    async fn finish(
+        mut self,
+        partition_id: usize,
+        object_store: &dyn ObjectStore,
+        store_url: &ObjectStoreUrl,
+    ) -> Result<ShuffleWritePartition> {
+        let result: Result<()> = async {
+            self.writer.finish()?;
+            self.upload_part(object_store).await?;
+            let (_, upload) = self.upload.as_mut().unwrap();
+            upload.shutdown().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = result {
+            self.abort(object_store).await;
+            return Err(e);
+        }
+
+        let path = utils::object_store_location(store_url, &self.path);
+        debug!(
+            "Finished writing shuffle partition {} at {}. Parts: {}. Batches: {}. Rows: {}. Bytes: {}.",
+            partition_id,
+            path,
+            self.num_parts,
+            self.num_batches,
+            self.num_rows,
+            self.num_bytes
+        );
+
+        Ok(ShuffleWritePartition {
+            partition_id: partition_id as u64,
+            path,
+            num_batches: self.num_batches,
+            num_rows: self.num_rows,
+            num_bytes: self.num_bytes,
+            compression: self.compression.to_string(),
+            index_path: String::default(),
+        })
+    }
+
+    /// Abort the multipart upload, if one was started, so no parts are left behind
+    async fn abort(self, object_store: &dyn ObjectStore) {
+        if let Some((multipart_id, _)) = self.upload {
+            if let Err(e) = object_store
+                .abort_multipart(&self.path, &multipart_id)
+                .await
+            {
+                warn!("Failed to abort upload of {}: {:?}", self.path, e);
             }
         }
     }
@@ -303,11 +802,15 @@ impl DisplayAs for ShuffleWriterExec {
     ) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default | DisplayFormatType::Verbose => {
-                write!(
-                    f,
-                    "ShuffleWriterExec: {:?}",
-                    self.shuffle_output_partitioning
-                )
+                if let Some(range) = &self.range_partitioning {
+                    write!(f, "ShuffleWriterExec: {range}")
+                } else {
+                    write!(
+                        f,
+                        "ShuffleWriterExec: {:?}",
+                        self.shuffle_output_partitioning
+                    )
+                }
             }
         }
     }
@@ -334,21 +837,43 @@ impl ExecutionPlan for ShuffleWriterExec {
         None
     }
 
+    /// The sample of a range partitioning is a child too, so that the stage it is read
+    /// from is resolved like any other input of the stage
     fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
-        vec![self.plan.clone()]
+        match &self.range_partitioning {
+            Some(range) => vec![self.plan.clone(), range.sample.clone()],
+            None => vec![self.plan.clone()],
+        }
     }
 
     fn with_new_children(
         self: Arc<Self>,
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(ShuffleWriterExec::try_new(
-            self.job_id.clone(),
-            self.stage_id,
-            children[0].clone(),
-            self.work_dir.clone(),
-            self.shuffle_output_partitioning.clone(),
-        )?))
+        let writer = match &self.range_partitioning {
+            Some(range) => ShuffleWriterExec::try_new_range(
+                self.job_id.clone(),
+                self.stage_id,
+                children[0].clone(),
+                self.work_dir.clone(),
+                RangePartitioning {
+                    sample: children[1].clone(),
+                    ..range.clone()
+                },
+            )?,
+            None => ShuffleWriterExec::try_new(
+                self.job_id.clone(),
+                self.stage_id,
+                children[0].clone(),
+                self.work_dir.clone(),
+                self.shuffle_output_partitioning.clone(),
+            )?,
+        };
+        Ok(Arc::new(
+            writer
+                .with_compression(self.compression)
+                .with_sort_based(self.sort_based),
+        ))
     }
 
     fn execute(
@@ -441,7 +966,9 @@ mod tests {
 
     use datafusion::physical_plan::memory::MemoryExec;
     use datafusion::prelude::SessionContext;
+    use object_store::memory::InMemory;
     use tempfile::TempDir;
+    use url::Url;
 
     #[tokio::test]
     // number of rows in each partition is a function of the hash output, so don't test here
@@ -540,6 +1067,189 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    // number of rows in each partition is a function of the hash output, so don't test here
+    #[cfg(not(feature = "force_hash_collisions"))]
+    async fn test_object_store() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let object_store = Arc::new(InMemory::new());
+        session_ctx.runtime_env().register_object_store(
+            &Url::parse("memory://").unwrap(),
+            object_store.clone(),
+        );
+        let task_ctx = session_ctx.task_ctx();
+
+        let input_plan = Arc::new(CoalescePartitionsExec::new(create_input_plan()?));
+        let query_stage = ShuffleWriterExec::try_new(
+            "jobOne".to_owned(),
+            1,
+            input_plan,
+            "memory:///shuffle".to_owned(),
+            Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 2)),
+        )?;
+        let mut stream = query_stage.execute(0, task_ctx)?;
+        let batches = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(2, batch.num_rows());
+        let path = batch.columns()[1]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!("memory:///shuffle/jobOne/1/0/data-0.arrow", path.value(0));
+        assert_eq!("memory:///shuffle/jobOne/1/1/data-0.arrow", path.value(1));
+
+        for i in 0..2 {
+            let (_, location) = utils::parse_object_store_location(path.value(i))
+                .expect("object store location");
+            let data = object_store.get(&location).await?.bytes().await?;
+            let reader = datafusion::arrow::ipc::reader::FileReader::try_new(
+                std::io::Cursor::new(data),
+                None,
+            )?;
+            let num_rows: usize = reader
+                .map(|batch| batch.map(|batch| batch.num_rows()))
+                .sum::<std::result::Result<_, _>>()?;
+            assert_eq!(4, num_rows);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_object_store_multipart() -> Result<()> {
+        let object_store = InMemory::new();
+        let store_url = ObjectStoreUrl::parse("memory://")?;
+        let input_plan = create_input_plan()?;
+        let batch = RecordBatch::try_new(
+            input_plan.schema(),
+            vec![
+                Arc::new(UInt32Array::from_iter_values(0..1024)),
+                Arc::new(StringArray::from_iter_values(
+                    (0..1024).map(|i| i.to_string()),
+                )),
+            ],
+        )?;
+
+        let path = Path::from("shuffle/jobOne/1/0/data.arrow");
+        let mut writer = ObjectStoreIPCWriter::try_new(
+            path.clone(),
+            &input_plan.schema(),
+            ShuffleCompression::None,
+            1024,
+        )?;
+        for _ in 0..4 {
+            writer.write(&batch)?;
+            writer.flush(&object_store).await?;
+        }
+        assert_eq!(4, writer.num_parts);
+        let part_loc = writer.finish(0, &object_store, &store_url).await?;
+        assert_eq!("memory:///shuffle/jobOne/1/0/data.arrow", part_loc.path);
+        assert_eq!(4, part_loc.num_batches);
+        assert_eq!(4096, part_loc.num_rows);
+
+        let data = object_store.get(&path).await?.bytes().await?;
+        let reader = datafusion::arrow::ipc::reader::FileReader::try_new(
+            std::io::Cursor::new(data),
+            None,
+        )?;
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(
+            vec![batch.clone(), batch.clone(), batch.clone(), batch],
+            batches
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    // number of rows in each partition is a function of the hash output, so don't test here
+    #[cfg(not(feature = "force_hash_collisions"))]
+    async fn test_compressed() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        let input_plan = Arc::new(CoalescePartitionsExec::new(create_input_plan()?));
+        let work_dir = TempDir::new()?;
+        let query_stage = ShuffleWriterExec::try_new(
+            "jobOne".to_owned(),
+            1,
+            input_plan,
+            work_dir.into_path().to_str().unwrap().to_owned(),
+            Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 2)),
+        )?
+        .with_compression(ShuffleCompression::Zstd);
+        let part_locs = query_stage.execute_shuffle_write(0, task_ctx).await?;
+        assert_eq!(2, part_locs.len());
+
+        for part_loc in part_locs {
+            assert_eq!("zstd", part_loc.compression);
+            let reader = datafusion::arrow::ipc::reader::FileReader::try_new(
+                std::fs::File::open(&part_loc.path)?,
+                None,
+            )?;
+            let num_rows: usize = reader
+                .map(|batch| batch.map(|batch| batch.num_rows()))
+                .sum::<std::result::Result<_, _>>()?;
+            assert_eq!(4, num_rows);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_partitioned() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        let input_plan = create_input_plan()?;
+        let schema = input_plan.schema();
+        let sample = common::collect(input_plan.execute(0, task_ctx.clone())?).await?;
+        let sample = Arc::new(MemoryExec::try_new(&[sample], schema, None)?);
+        let work_dir = TempDir::new()?;
+        let query_stage = ShuffleWriterExec::try_new_range(
+            "jobOne".to_owned(),
+            1,
+            input_plan,
+            work_dir.into_path().to_str().unwrap().to_owned(),
+            RangePartitioning {
+                sort_exprs: vec![PhysicalSortExpr {
+                    expr: Arc::new(Column::new("a", 0)),
+                    options: Default::default(),
+                }],
+                partition_count: 2,
+                sample,
+            },
+        )?;
+        assert_eq!(2, query_stage.children().len());
+        assert_eq!(2, query_stage.output_partitioning().partition_count());
+
+        let part_locs = query_stage.execute_shuffle_write(0, task_ctx).await?;
+        assert_eq!(2, part_locs.len());
+
+        // the sample [1, 2] puts the rows with a = 1 in the first range
+        for (part_loc, expected) in part_locs.iter().zip([1, 2]) {
+            let reader = datafusion::arrow::ipc::reader::FileReader::try_new(
+                std::fs::File::open(&part_loc.path)?,
+                None,
+            )?;
+            for batch in reader {
+                let batch = batch?;
+                let a = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<UInt32Array>()
+                    .unwrap();
+                assert!(a.values().iter().all(|v| *v == expected));
+            }
+            assert_eq!(2, part_loc.num_rows);
+        }
+
+        Ok(())
+    }
+
     fn create_input_plan() -> Result<Arc<dyn ExecutionPlan>> {
         let schema = Arc::new(Schema::new(vec![
             Field::new("a", DataType::UInt32, true),
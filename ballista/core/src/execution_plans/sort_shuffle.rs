@@ -0,0 +1,454 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Sort-based shuffle, where a map task writes all of its shuffle partitions to a single
+//! data file ordered by partition, along with an index file holding the byte range of
+//! every partition. Each byte range is a complete Arrow IPC stream, so a shuffle
+//! partition can be read without reading the rest of the data file.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Take, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::ipc::writer::{IpcWriteOptions, StreamWriter};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation};
+use log::{debug, warn};
+
+use crate::config::ShuffleCompression;
+use crate::error::BallistaError;
+use crate::serde::protobuf::ShuffleWritePartition;
+
+/// Maximum size in bytes of the batches a map task buffers in memory before spilling them
+/// to disk, when the memory pool of the task has more memory available
+pub(crate) const SORT_SHUFFLE_SPILL_THRESHOLD: usize = 64 * 1024 * 1024;
+
+/// Number of bytes of each offset in the index file
+const INDEX_ENTRY_SIZE: u64 = 8;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct WriteStats {
+    num_batches: u64,
+    num_rows: u64,
+    num_bytes: u64,
+}
+
+/// Writes the shuffle partitions of one map task to a data file and an index file.
+///
+/// Batches are buffered in memory per shuffle partition and spilled to disk, in the same
+/// layout as the data file, whenever the buffer grows beyond the spill threshold or its
+/// memory can't be reserved from the memory pool of the task. When the writer is
+/// finished the spills and the buffered batches are merged into the data file.
+pub(crate) struct SortShuffleWriter {
+    input_partition: usize,
+    stage_dir: PathBuf,
+    schema: SchemaRef,
+    compression: ShuffleCompression,
+    write_options: IpcWriteOptions,
+    spill_threshold: usize,
+    /// Batches buffered in memory for each shuffle partition
+    buffered: Vec<Vec<RecordBatch>>,
+    buffered_bytes: usize,
+    /// Reservation of the memory of the buffered batches
+    reservation: MemoryReservation,
+    /// Files the buffered batches were spilled to, with the offsets of their partitions
+    spills: Vec<(PathBuf, Vec<u64>)>,
+    stats: Vec<WriteStats>,
+}
+
+impl SortShuffleWriter {
+    pub(crate) fn try_new(
+        stage_dir: PathBuf,
+        input_partition: usize,
+        num_partitions: usize,
+        schema: SchemaRef,
+        compression: ShuffleCompression,
+        spill_threshold: usize,
+        memory_pool: &Arc<dyn MemoryPool>,
+    ) -> Result<Self> {
+        fs::create_dir_all(&stage_dir)?;
+        let reservation =
+            MemoryConsumer::new(format!("SortShuffleWriter[{input_partition}]"))
+                .with_can_spill(true)
+                .register(memory_pool);
+        Ok(Self {
+            input_partition,
+            stage_dir,
+            schema,
+            compression,
+            write_options: compression.ipc_write_options()?,
+            spill_threshold,
+            buffered: vec![vec![]; num_partitions],
+            buffered_bytes: 0,
+            reservation,
+            spills: vec![],
+            stats: vec![WriteStats::default(); num_partitions],
+        })
+    }
+
+    pub(crate) fn write(&mut self, partition: usize, batch: RecordBatch) -> Result<()> {
+        let num_bytes = batch.get_array_memory_size();
+        let stats = &mut self.stats[partition];
+        stats.num_batches += 1;
+        stats.num_rows += batch.num_rows() as u64;
+        stats.num_bytes += num_bytes as u64;
+
+        let reserved = self.reservation.try_grow(num_bytes).is_ok();
+        self.buffered[partition].push(batch);
+        self.buffered_bytes += num_bytes;
+        if !reserved || self.buffered_bytes >= self.spill_threshold {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        let path = self.stage_dir.join(format!(
+            "data-{}.spill-{}",
+            self.input_partition,
+            self.spills.len()
+        ));
+        debug!(
+            "Spilling {} bytes of shuffle data to {:?}",
+            self.buffered_bytes, path
+        );
+
+        let buffered = &mut self.buffered;
+        let offsets = write_partitions(
+            &path,
+            &self.schema,
+            &self.write_options,
+            buffered.len(),
+            |partition| Ok(std::mem::take(&mut buffered[partition]).into_iter().map(Ok)),
+        )
+        .map_err(|e| {
+            let _ = fs::remove_file(&path);
+            e
+        })?;
+        self.spills.push((path, offsets));
+        self.buffered_bytes = 0;
+        self.reservation.free();
+        Ok(())
+    }
+
+    /// Write the data and index files, returning the shuffle partitions that have data
+    pub(crate) fn finish(mut self) -> Result<Vec<ShuffleWritePartition>> {
+        let data_path = self
+            .stage_dir
+            .join(format!("data-{}.arrow", self.input_partition));
+        let index_path = self
+            .stage_dir
+            .join(format!("data-{}.index", self.input_partition));
+
+        // the spill files are removed when the writer is dropped
+        if let Err(e) = self.write_data(&data_path, &index_path) {
+            for path in [&data_path, &index_path] {
+                let _ = fs::remove_file(path);
+            }
+            return Err(e);
+        }
+
+        let num_spills = self.spills.len();
+        for (path, _) in std::mem::take(&mut self.spills) {
+            fs::remove_file(path)?;
+        }
+
+        let data_path = data_path.to_string_lossy().to_string();
+        let index_path = index_path.to_string_lossy().to_string();
+        debug!(
+            "Finished writing sort-based shuffle file {} with {} spills",
+            data_path, num_spills
+        );
+
+        Ok(self
+            .stats
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.num_batches > 0)
+            .map(|(partition, stats)| ShuffleWritePartition {
+                partition_id: partition as u64,
+                path: data_path.clone(),
+                num_batches: stats.num_batches,
+                num_rows: stats.num_rows,
+                num_bytes: stats.num_bytes,
+                compression: self.compression.to_string(),
+                index_path: index_path.clone(),
+            })
+            .collect())
+    }
+
+    /// Merge the spills and the buffered batches into the data file and write the
+    /// offsets of the shuffle partitions to the index file
+    fn write_data(&mut self, data_path: &Path, index_path: &Path) -> Result<()> {
+        let spills = &self.spills;
+        let buffered = &mut self.buffered;
+        let offsets = write_partitions(
+            data_path,
+            &self.schema,
+            &self.write_options,
+            buffered.len(),
+            |partition| {
+                let mut batches: Vec<Box<dyn Iterator<Item = Result<RecordBatch>>>> =
+                    vec![];
+                for (path, offsets) in spills {
+                    let reader =
+                        open_range(path, offsets[partition], offsets[partition + 1])
+                            .map_err(|e| DataFusionError::Execution(format!("{e:?}")))?;
+                    batches
+                        .push(Box::new(reader.map(|b| b.map_err(DataFusionError::from))));
+                }
+                batches.push(Box::new(
+                    std::mem::take(&mut buffered[partition]).into_iter().map(Ok),
+                ));
+                Ok(batches.into_iter().flatten())
+            },
+        )?;
+
+        let mut index = File::create(index_path)?;
+        for offset in offsets {
+            index.write_all(&offset.to_le_bytes())?;
+        }
+        index.sync_all()?;
+        Ok(())
+    }
+}
+
+// Remove the spill files of a writer that was not finished, e.g. because the input of
+// the map task failed
+impl Drop for SortShuffleWriter {
+    fn drop(&mut self) {
+        for (path, _) in &self.spills {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Failed to remove shuffle spill file {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+/// Write the batches of every partition to a file as consecutive Arrow IPC streams,
+/// returning the offsets at which the streams start followed by the file length
+fn write_partitions<F, I>(
+    path: &Path,
+    schema: &SchemaRef,
+    write_options: &IpcWriteOptions,
+    num_partitions: usize,
+    mut partition_batches: F,
+) -> Result<Vec<u64>>
+where
+    F: FnMut(usize) -> Result<I>,
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    let mut file = File::create(path)?;
+    let mut offsets = vec![0];
+    for partition in 0..num_partitions {
+        // empty partitions are written too, so that every byte range is a valid stream
+        let mut writer =
+            StreamWriter::try_new_with_options(file, schema, write_options.clone())?;
+        for batch in partition_batches(partition)? {
+            writer.write(&batch?)?;
+        }
+        writer.finish()?;
+        file = writer.into_inner()?;
+        offsets.push(file.stream_position()?);
+    }
+    file.sync_all()?;
+    Ok(offsets)
+}
+
+fn open_range(
+    path: &Path,
+    start: u64,
+    end: u64,
+) -> result::Result<StreamReader<Take<File>>, BallistaError> {
+    let mut file = File::open(path).map_err(|e| {
+        BallistaError::General(format!("Failed to open shuffle file at {path:?}: {e:?}"))
+    })?;
+    file.seek(SeekFrom::Start(start))?;
+    Ok(StreamReader::try_new(file.take(end - start), None)?)
+}
+
+/// Open the byte range of one shuffle partition in a data file written by a sort-based
+/// shuffle, looking the range up in the index file
+pub fn read_sort_shuffle_partition(
+    path: &str,
+    index_path: &str,
+    partition: usize,
+) -> result::Result<StreamReader<Take<File>>, BallistaError> {
+    let mut index = File::open(index_path).map_err(|e| {
+        BallistaError::General(format!(
+            "Failed to open shuffle index file at {index_path}: {e:?}"
+        ))
+    })?;
+    index.seek(SeekFrom::Start(partition as u64 * INDEX_ENTRY_SIZE))?;
+    let mut buf = [0; 2 * INDEX_ENTRY_SIZE as usize];
+    index.read_exact(&mut buf).map_err(|e| {
+        BallistaError::General(format!(
+            "Failed to read partition {partition} from shuffle index file at {index_path}: {e:?}"
+        ))
+    })?;
+    let start = u64::from_le_bytes(buf[..8].try_into().unwrap());
+    let end = u64::from_le_bytes(buf[8..].try_into().unwrap());
+    open_range(Path::new(path), start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::UInt32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::execution::memory_pool::{GreedyMemoryPool, UnboundedMemoryPool};
+    use tempfile::TempDir;
+
+    fn memory_pool() -> Arc<dyn MemoryPool> {
+        Arc::new(UnboundedMemoryPool::default())
+    }
+
+    fn batch(schema: &SchemaRef, values: Vec<u32>) -> RecordBatch {
+        RecordBatch::try_new(schema.clone(), vec![Arc::new(UInt32Array::from(values))])
+            .unwrap()
+    }
+
+    fn read_values(path: &str, index_path: &str, partition: usize) -> Vec<u32> {
+        read_sort_shuffle_partition(path, index_path, partition)
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let array = batch.column(0);
+                let array = array.as_any().downcast_ref::<UInt32Array>().unwrap();
+                array.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_write_and_read_with_spills() -> Result<()> {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("a", DataType::UInt32, false)]));
+        let work_dir = TempDir::new()?;
+        let stage_dir = work_dir.path().join("job").join("1");
+
+        // spill after every batch
+        let mut writer = SortShuffleWriter::try_new(
+            stage_dir.clone(),
+            0,
+            3,
+            schema.clone(),
+            ShuffleCompression::Lz4,
+            1,
+            &memory_pool(),
+        )?;
+        writer.write(2, batch(&schema, vec![1, 2]))?;
+        writer.write(0, batch(&schema, vec![3]))?;
+        writer.write(2, batch(&schema, vec![4, 5, 6]))?;
+        assert_eq!(3, writer.spills.len());
+
+        let partitions = writer.finish()?;
+        assert_eq!(
+            vec![0, 2],
+            partitions
+                .iter()
+                .map(|p| p.partition_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, partitions[0].num_rows);
+        assert_eq!(5, partitions[1].num_rows);
+        assert_eq!(2, partitions[1].num_batches);
+        assert_eq!("lz4", partitions[1].compression);
+
+        let path = &partitions[0].path;
+        let index_path = &partitions[0].index_path;
+        assert!(path.ends_with("data-0.arrow"));
+        assert!(index_path.ends_with("data-0.index"));
+        assert_eq!(vec![3], read_values(path, index_path, 0));
+        assert!(read_values(path, index_path, 1).is_empty());
+        assert_eq!(vec![1, 2, 4, 5, 6], read_values(path, index_path, 2));
+
+        // only the data and index files are left behind
+        assert_eq!(2, fs::read_dir(&stage_dir)?.count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spill_when_memory_is_not_reserved() -> Result<()> {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("a", DataType::UInt32, false)]));
+        let work_dir = TempDir::new()?;
+        let stage_dir = work_dir.path().join("job").join("1");
+
+        // the memory pool only fits one batch
+        let batch_size = batch(&schema, vec![1, 2]).get_array_memory_size();
+        let memory_pool: Arc<dyn MemoryPool> =
+            Arc::new(GreedyMemoryPool::new(batch_size));
+        let mut writer = SortShuffleWriter::try_new(
+            stage_dir,
+            0,
+            2,
+            schema.clone(),
+            ShuffleCompression::None,
+            SORT_SHUFFLE_SPILL_THRESHOLD,
+            &memory_pool,
+        )?;
+        writer.write(0, batch(&schema, vec![1, 2]))?;
+        assert!(writer.spills.is_empty());
+        assert_eq!(batch_size, memory_pool.reserved());
+
+        writer.write(1, batch(&schema, vec![3, 4]))?;
+        assert_eq!(1, writer.spills.len());
+        assert_eq!(0, memory_pool.reserved());
+
+        writer.write(1, batch(&schema, vec![5, 6]))?;
+        assert_eq!(1, writer.spills.len());
+        assert_eq!(batch_size, memory_pool.reserved());
+
+        let partitions = writer.finish()?;
+        let (path, index_path) = (&partitions[0].path, &partitions[0].index_path);
+        assert_eq!(vec![1, 2], read_values(path, index_path, 0));
+        assert_eq!(vec![3, 4, 5, 6], read_values(path, index_path, 1));
+        assert_eq!(0, memory_pool.reserved());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_removes_spills() -> Result<()> {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("a", DataType::UInt32, false)]));
+        let work_dir = TempDir::new()?;
+        let stage_dir = work_dir.path().join("job").join("1");
+
+        let mut writer = SortShuffleWriter::try_new(
+            stage_dir.clone(),
+            0,
+            2,
+            schema.clone(),
+            ShuffleCompression::None,
+            1,
+            &memory_pool(),
+        )?;
+        writer.write(0, batch(&schema, vec![1, 2]))?;
+        writer.write(1, batch(&schema, vec![3]))?;
+        assert_eq!(2, fs::read_dir(&stage_dir)?.count());
+
+        drop(writer);
+        assert_eq!(0, fs::read_dir(&stage_dir)?.count());
+        Ok(())
+    }
+}
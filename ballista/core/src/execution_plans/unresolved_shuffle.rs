@@ -39,8 +39,11 @@ pub struct UnresolvedShuffleExec {
     // The schema this node will have once it is replaced with a ShuffleReaderExec
     pub schema: SchemaRef,
 
-    // The partition count this node will have once it is replaced with a ShuffleReaderExec
+    // The partition count of the shuffle this node will read once it is replaced with a ShuffleReaderExec
     pub output_partition_count: usize,
+
+    // Whether all the partitions of the shuffle will be read as a single partition
+    pub broadcast: bool,
 }
 
 impl UnresolvedShuffleExec {
@@ -54,6 +57,20 @@ impl UnresolvedShuffleExec {
             stage_id,
             schema,
             output_partition_count,
+            broadcast: false,
+        }
+    }
+
+    /// Create a new UnresolvedShuffleExec which will read all the partitions of the
+    /// shuffle as a single partition, in every task of the stage
+    pub fn new_broadcast(
+        stage_id: usize,
+        schema: SchemaRef,
+        output_partition_count: usize,
+    ) -> Self {
+        Self {
+            broadcast: true,
+            ..Self::new(stage_id, schema, output_partition_count)
         }
     }
 }
@@ -66,7 +83,11 @@ impl DisplayAs for UnresolvedShuffleExec {
     ) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default | DisplayFormatType::Verbose => {
-                write!(f, "UnresolvedShuffleExec")
+                if self.broadcast {
+                    write!(f, "UnresolvedShuffleExec: broadcast")
+                } else {
+                    write!(f, "UnresolvedShuffleExec")
+                }
             }
         }
     }
@@ -84,7 +105,11 @@ impl ExecutionPlan for UnresolvedShuffleExec {
     fn output_partitioning(&self) -> Partitioning {
         // TODO the output partition is known and should be populated here!
         // see https://github.com/apache/arrow-datafusion/issues/758
-        Partitioning::UnknownPartitioning(self.output_partition_count)
+        if self.broadcast {
+            Partitioning::UnknownPartitioning(1)
+        } else {
+            Partitioning::UnknownPartitioning(self.output_partition_count)
+        }
     }
 
     fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
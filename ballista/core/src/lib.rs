@@ -33,6 +33,7 @@ pub mod execution_plans;
 pub mod object_store_registry;
 /// some plugins
 pub mod plugin;
+pub mod telemetry;
 pub mod utils;
 
 #[macro_use]
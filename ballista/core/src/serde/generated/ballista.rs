@@ -4,7 +4,10 @@
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BallistaPhysicalPlanNode {
-    #[prost(oneof = "ballista_physical_plan_node::PhysicalPlanType", tags = "1, 2, 3")]
+    #[prost(
+        oneof = "ballista_physical_plan_node::PhysicalPlanType",
+        tags = "1, 2, 3, 4"
+    )]
     pub physical_plan_type: ::core::option::Option<
         ballista_physical_plan_node::PhysicalPlanType,
     >,
@@ -20,6 +23,8 @@ pub mod ballista_physical_plan_node {
         ShuffleReader(super::ShuffleReaderExecNode),
         #[prost(message, tag = "3")]
         UnresolvedShuffle(super::UnresolvedShuffleExecNode),
+        #[prost(message, tag = "4")]
+        RangeSample(super::RangeSampleExecNode),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -37,6 +42,33 @@ pub struct ShuffleWriterExecNode {
     pub output_partitioning: ::core::option::Option<
         ::datafusion_proto::protobuf::PhysicalHashRepartition,
     >,
+    /// Codec of the shuffle files, empty if they are not compressed
+    #[prost(string, tag = "5")]
+    pub compression: ::prost::alloc::string::String,
+    /// Write one data file and one index file per input partition
+    #[prost(bool, tag = "6")]
+    pub sort_based: bool,
+    /// Range partitioning of the output, whose boundaries are computed from the sample read
+    /// by the second input of the writer
+    #[prost(message, optional, tag = "7")]
+    pub range_partitioning: ::core::option::Option<RangeRepartition>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RangeRepartition {
+    #[prost(message, repeated, tag = "1")]
+    pub sort_expr: ::prost::alloc::vec::Vec<
+        ::datafusion_proto::protobuf::PhysicalSortExprNode,
+    >,
+    #[prost(uint64, tag = "2")]
+    pub partition_count: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RangeSampleExecNode {
+    /// Number of rows sampled from each partition of the input
+    #[prost(uint64, tag = "1")]
+    pub sample_size: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -47,6 +79,9 @@ pub struct UnresolvedShuffleExecNode {
     pub schema: ::core::option::Option<::datafusion_proto::protobuf::Schema>,
     #[prost(uint32, tag = "4")]
     pub output_partition_count: u32,
+    /// Whether every task reads all the partitions of the stage as a single partition
+    #[prost(bool, tag = "5")]
+    pub broadcast: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -58,6 +93,9 @@ pub struct ShuffleReaderExecNode {
     /// The stage to read from
     #[prost(uint32, tag = "3")]
     pub stage_id: u32,
+    /// Whether every task reads all the partitions of the stage as a single partition
+    #[prost(bool, tag = "4")]
+    pub broadcast: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -98,6 +136,73 @@ pub struct ExecutionGraph {
     pub end_time: u64,
     #[prost(uint64, tag = "13")]
     pub queued_at: u64,
+    #[prost(string, tag = "14")]
+    pub user: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "15")]
+    pub priority: u32,
+    #[prost(string, tag = "16")]
+    pub pool: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "17")]
+    pub adaptive: ::core::option::Option<AdaptiveExecutionConfig>,
+    #[prost(string, tag = "18")]
+    pub shuffle_compression: ::prost::alloc::string::String,
+    #[prost(bool, tag = "19")]
+    pub shuffle_sort_based: bool,
+    #[prost(bool, tag = "20")]
+    pub repartition_sorts: bool,
+    #[prost(uint64, tag = "21")]
+    pub task_memory: u64,
+    #[prost(uint32, tag = "22")]
+    pub task_cpus: u32,
+    /// labels which the executors running the tasks of the job must have
+    #[prost(message, repeated, tag = "23")]
+    pub executor_selector: ::prost::alloc::vec::Vec<KeyValuePair>,
+    /// time in seconds after which the running job is aborted, zero means no timeout
+    #[prost(uint64, tag = "24")]
+    pub timeout_seconds: u64,
+    #[prost(uint64, tag = "25")]
+    pub task_timeout_seconds: u64,
+    #[prost(bool, tag = "26")]
+    pub result_cache: bool,
+    /// trace context of the span of the job, which the spans of its stages are children of
+    #[prost(message, repeated, tag = "27")]
+    pub trace_context: ::prost::alloc::vec::Vec<KeyValuePair>,
+    #[prost(uint64, tag = "28")]
+    pub range_sample_size: u64,
+}
+/// Summary of a job archived in the job history, which jobs are listed from without
+/// reading their execution graphs
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobHistorySummary {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub job_name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub user: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub status: ::core::option::Option<JobStatus>,
+    #[prost(uint64, tag = "5")]
+    pub num_stages: u64,
+    #[prost(uint64, tag = "6")]
+    pub queued_at: u64,
+    #[prost(uint64, tag = "7")]
+    pub start_time: u64,
+    #[prost(uint64, tag = "8")]
+    pub end_time: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AdaptiveExecutionConfig {
+    #[prost(uint64, tag = "1")]
+    pub target_partition_bytes: u64,
+    #[prost(uint64, tag = "2")]
+    pub skew_factor: u64,
+    #[prost(uint64, tag = "3")]
+    pub skew_threshold_bytes: u64,
+    #[prost(uint64, tag = "4")]
+    pub broadcast_threshold_bytes: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -324,6 +429,12 @@ pub struct FetchPartition {
     pub host: ::prost::alloc::string::String,
     #[prost(uint32, tag = "6")]
     pub port: u32,
+    /// Codec of the shuffle file, its record batches are sent compressed with it
+    #[prost(string, tag = "7")]
+    pub compression: ::prost::alloc::string::String,
+    /// Index of a sort-based shuffle file, empty if the whole file is the partition
+    #[prost(string, tag = "8")]
+    pub index_path: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -340,6 +451,10 @@ pub struct PartitionLocation {
     pub partition_stats: ::core::option::Option<PartitionStats>,
     #[prost(string, tag = "5")]
     pub path: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub compression: ::prost::alloc::string::String,
+    #[prost(string, tag = "7")]
+    pub index_path: ::prost::alloc::string::String,
 }
 /// Unique identifier for a materialized partition of data
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -463,6 +578,8 @@ pub struct ExecutorMetadata {
     pub grpc_port: u32,
     #[prost(message, optional, tag = "5")]
     pub specification: ::core::option::Option<ExecutorSpecification>,
+    #[prost(message, repeated, tag = "6")]
+    pub labels: ::prost::alloc::vec::Vec<KeyValuePair>,
 }
 /// Used by grpc
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -476,6 +593,8 @@ pub struct ExecutorRegistration {
     pub grpc_port: u32,
     #[prost(message, optional, tag = "5")]
     pub specification: ::core::option::Option<ExecutorSpecification>,
+    #[prost(message, repeated, tag = "6")]
+    pub labels: ::prost::alloc::vec::Vec<KeyValuePair>,
     /// "optional" keyword is stable in protoc 3.15 but prost is still on 3.14 (see <https://github.com/tokio-rs/prost/issues/430> and <https://github.com/tokio-rs/prost/pull/455>)
     /// this syntax is ugly but is binary compatible with the "optional" keyword (see <https://stackoverflow.com/questions/42622015/how-to-define-an-optional-field-in-protobuf-3>)
     #[prost(oneof = "executor_registration::OptionalHost", tags = "2")]
@@ -508,18 +627,31 @@ pub struct ExecutorHeartbeat {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecutorMetric {
-    /// TODO add more metrics
-    #[prost(oneof = "executor_metric::Metric", tags = "1")]
+    #[prost(oneof = "executor_metric::Metric", tags = "1, 2, 3, 4, 5, 6")]
     pub metric: ::core::option::Option<executor_metric::Metric>,
 }
 /// Nested message and enum types in `ExecutorMetric`.
 pub mod executor_metric {
-    /// TODO add more metrics
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Metric {
+        /// Memory available to the executor host, in bytes
         #[prost(uint64, tag = "1")]
         AvailableMemory(u64),
+        /// Memory used on the executor host, in bytes
+        #[prost(uint64, tag = "2")]
+        UsedMemory(u64),
+        /// Average usage of all the CPUs since the previous heartbeat, in percent
+        #[prost(float, tag = "3")]
+        CpuUsage(f32),
+        /// Free space of the file system holding the work dir, in bytes
+        #[prost(uint64, tag = "4")]
+        AvailableDisk(u64),
+        #[prost(uint32, tag = "5")]
+        RunningTasks(u32),
+        /// Size of the shuffle files held in the work dir, in bytes
+        #[prost(uint64, tag = "6")]
+        ShuffleBytes(u64),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -552,18 +684,22 @@ pub struct ExecutorSpecification {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecutorResource {
-    /// TODO add more resources
-    #[prost(oneof = "executor_resource::Resource", tags = "1")]
+    #[prost(oneof = "executor_resource::Resource", tags = "1, 2, 3")]
     pub resource: ::core::option::Option<executor_resource::Resource>,
 }
 /// Nested message and enum types in `ExecutorResource`.
 pub mod executor_resource {
-    /// TODO add more resources
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Resource {
         #[prost(uint32, tag = "1")]
         TaskSlots(u32),
+        /// Memory in bytes which can be reserved by tasks
+        #[prost(uint64, tag = "2")]
+        Memory(u64),
+        /// Number of CPUs which can be reserved by tasks
+        #[prost(uint32, tag = "3")]
+        Cpus(u32),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -573,6 +709,10 @@ pub struct AvailableTaskSlots {
     pub executor_id: ::prost::alloc::string::String,
     #[prost(uint32, tag = "2")]
     pub slots: u32,
+    #[prost(uint64, tag = "3")]
+    pub memory: u64,
+    #[prost(uint32, tag = "4")]
+    pub cpus: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -683,6 +823,11 @@ pub struct ShuffleWritePartition {
     pub num_rows: u64,
     #[prost(uint64, tag = "5")]
     pub num_bytes: u64,
+    #[prost(string, tag = "6")]
+    pub compression: ::prost::alloc::string::String,
+    /// Index of the byte ranges of the partitions in a sort-based shuffle file
+    #[prost(string, tag = "7")]
+    pub index_path: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -755,6 +900,9 @@ pub struct TaskDefinition {
     pub launch_time: u64,
     #[prost(message, repeated, tag = "11")]
     pub props: ::prost::alloc::vec::Vec<KeyValuePair>,
+    /// trace context of the span of the stage
+    #[prost(message, repeated, tag = "12")]
+    pub trace_context: ::prost::alloc::vec::Vec<KeyValuePair>,
 }
 /// A set of tasks in the same stage
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -776,6 +924,8 @@ pub struct MultiTaskDefinition {
     pub launch_time: u64,
     #[prost(message, repeated, tag = "9")]
     pub props: ::prost::alloc::vec::Vec<KeyValuePair>,
+    #[prost(message, repeated, tag = "10")]
+    pub trace_context: ::prost::alloc::vec::Vec<KeyValuePair>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -875,6 +1025,9 @@ pub struct UpdateTaskStatusResult {
 pub struct ExecuteQueryParams {
     #[prost(message, repeated, tag = "4")]
     pub settings: ::prost::alloc::vec::Vec<KeyValuePair>,
+    /// trace context of the span of the client, which the span of the job is a child of
+    #[prost(message, repeated, tag = "5")]
+    pub trace_context: ::prost::alloc::vec::Vec<KeyValuePair>,
     #[prost(oneof = "execute_query_params::Query", tags = "1, 2")]
     pub query: ::core::option::Option<execute_query_params::Query>,
     #[prost(oneof = "execute_query_params::OptionalSessionId", tags = "3")]
@@ -971,7 +1124,7 @@ pub struct ExecuteQuerySuccessResult {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecuteQueryFailureResult {
-    #[prost(oneof = "execute_query_failure_result::Failure", tags = "1, 2, 3")]
+    #[prost(oneof = "execute_query_failure_result::Failure", tags = "1, 2, 3, 4")]
     pub failure: ::core::option::Option<execute_query_failure_result::Failure>,
 }
 /// Nested message and enum types in `ExecuteQueryFailureResult`.
@@ -985,6 +1138,9 @@ pub mod execute_query_failure_result {
         PlanParsingFailure(::prost::alloc::string::String),
         #[prost(string, tag = "3")]
         SqlParsingFailure(::prost::alloc::string::String),
+        /// the scheduler has reached its limit of queued jobs or of jobs of the session
+        #[prost(string, tag = "4")]
+        JobRejected(::prost::alloc::string::String),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -1010,8 +1166,11 @@ pub struct SuccessfulJob {
 pub struct QueuedJob {
     #[prost(uint64, tag = "1")]
     pub queued_at: u64,
+    /// position of the job among the jobs waiting for running jobs to finish, starting
+    /// from 1. Zero if the job isn't waiting
+    #[prost(uint32, tag = "2")]
+    pub position: u32,
 }
-/// TODO: add progress report
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RunningJob {
@@ -1021,6 +1180,44 @@ pub struct RunningJob {
     pub started_at: u64,
     #[prost(string, tag = "3")]
     pub scheduler: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "4")]
+    pub progress: ::core::option::Option<JobProgress>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobProgress {
+    #[prost(message, repeated, tag = "1")]
+    pub stages: ::prost::alloc::vec::Vec<StageProgress>,
+    #[prost(uint32, tag = "2")]
+    pub completed_tasks: u32,
+    #[prost(uint32, tag = "3")]
+    pub total_tasks: u32,
+    /// The rows and bytes written by the completed tasks of all the stages
+    #[prost(uint64, tag = "4")]
+    pub output_rows: u64,
+    #[prost(uint64, tag = "5")]
+    pub output_bytes: u64,
+    /// Estimated from the completed tasks of all the stages
+    #[prost(double, tag = "6")]
+    pub percent_complete: f64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StageProgress {
+    #[prost(uint32, tag = "1")]
+    pub stage_id: u32,
+    /// The state of the stage, e.g. Running
+    #[prost(string, tag = "2")]
+    pub state: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub completed_tasks: u32,
+    #[prost(uint32, tag = "4")]
+    pub total_tasks: u32,
+    /// The rows and bytes written by the completed tasks of the stage
+    #[prost(uint64, tag = "5")]
+    pub output_rows: u64,
+    #[prost(uint64, tag = "6")]
+    pub output_bytes: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -1033,6 +1230,9 @@ pub struct FailedJob {
     pub started_at: u64,
     #[prost(uint64, tag = "4")]
     pub ended_at: u64,
+    /// whether the job was aborted because it ran for longer than its timeout
+    #[prost(bool, tag = "5")]
+    pub timed_out: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -1067,6 +1267,18 @@ pub struct GetJobStatusResult {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchJobParams {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchJobResult {
+    #[prost(message, optional, tag = "1")]
+    pub status: ::core::option::Option<JobStatus>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetFileMetadataParams {
     #[prost(string, tag = "1")]
     pub path: ::prost::alloc::string::String,
@@ -1533,6 +1745,32 @@ pub mod scheduler_grpc_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Streams the status of a job each time it changes, until the job has finished
+        pub async fn watch_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::WatchJobParams>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::WatchJobResult>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ballista.protobuf.SchedulerGrpc/WatchJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("ballista.protobuf.SchedulerGrpc", "WatchJob"));
+            self.inner.server_streaming(req, path, codec).await
+        }
         /// Used by Executor to tell Scheduler it is stopped.
         pub async fn executor_stopped(
             &mut self,
@@ -1909,6 +2147,17 @@ pub mod scheduler_grpc_server {
             tonic::Response<super::GetJobStatusResult>,
             tonic::Status,
         >;
+        /// Server streaming response type for the WatchJob method.
+        type WatchJobStream: futures_core::Stream<
+                Item = std::result::Result<super::WatchJobResult, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Streams the status of a job each time it changes, until the job has finished
+        async fn watch_job(
+            &self,
+            request: tonic::Request<super::WatchJobParams>,
+        ) -> std::result::Result<tonic::Response<Self::WatchJobStream>, tonic::Status>;
         /// Used by Executor to tell Scheduler it is stopped.
         async fn executor_stopped(
             &self,
@@ -2466,6 +2715,51 @@ pub mod scheduler_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/ballista.protobuf.SchedulerGrpc/WatchJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchJobSvc<T: SchedulerGrpc>(pub Arc<T>);
+                    impl<
+                        T: SchedulerGrpc,
+                    > tonic::server::ServerStreamingService<super::WatchJobParams>
+                    for WatchJobSvc<T> {
+                        type Response = super::WatchJobResult;
+                        type ResponseStream = T::WatchJobStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::WatchJobParams>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { (*inner).watch_job(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ballista.protobuf.SchedulerGrpc/ExecutorStopped" => {
                     #[allow(non_camel_case_types)]
                     struct ExecutorStoppedSvc<T: SchedulerGrpc>(pub Arc<T>);
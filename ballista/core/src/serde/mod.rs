@@ -21,11 +21,15 @@
 use crate::{error::BallistaError, serde::scheduler::Action as BallistaAction};
 
 use arrow_flight::sql::ProstMessageExt;
+use datafusion::arrow::compute::SortOptions;
 use datafusion::common::DataFusionError;
 use datafusion::execution::FunctionRegistry;
+use datafusion::physical_plan::expressions::PhysicalSortExpr;
 use datafusion::physical_plan::{ExecutionPlan, Partitioning};
 use datafusion_proto::common::proto_error;
-use datafusion_proto::physical_plan::from_proto::parse_protobuf_hash_partitioning;
+use datafusion_proto::physical_plan::from_proto::{
+    parse_physical_expr, parse_protobuf_hash_partitioning,
+};
 use datafusion_proto::protobuf::{LogicalPlanNode, PhysicalPlanNode};
 use datafusion_proto::{
     convert_required,
@@ -40,7 +44,8 @@ use std::sync::Arc;
 use std::{convert::TryInto, io::Cursor};
 
 use crate::execution_plans::{
-    ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec,
+    RangePartitioning, RangeSampleExec, ShuffleReaderExec, ShuffleWriterExec,
+    UnresolvedShuffleExec,
 };
 use crate::serde::protobuf::ballista_physical_plan_node::PhysicalPlanType;
 use crate::serde::scheduler::PartitionLocation;
@@ -142,19 +147,70 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
             PhysicalPlanType::ShuffleWriter(shuffle_writer) => {
                 let input = inputs[0].clone();
 
-                let shuffle_output_partitioning = parse_protobuf_hash_partitioning(
-                    shuffle_writer.output_partitioning.as_ref(),
-                    registry,
-                    input.schema().as_ref(),
-                )?;
-
-                Ok(Arc::new(ShuffleWriterExec::try_new(
-                    shuffle_writer.job_id.clone(),
-                    shuffle_writer.stage_id as usize,
-                    input,
-                    "".to_string(), // this is intentional but hacky - the executor will fill this in
-                    shuffle_output_partitioning,
-                )?))
+                let compression = shuffle_writer
+                    .compression
+                    .parse()
+                    .map_err(DataFusionError::Internal)?;
+
+                let shuffle_writer_exec = if let Some(range) =
+                    &shuffle_writer.range_partitioning
+                {
+                    let sort_exprs = range
+                        .sort_expr
+                        .iter()
+                        .map(|sort_expr| {
+                            let expr = sort_expr.expr.as_ref().ok_or_else(|| {
+                                proto_error("Unexpected empty range sort expression")
+                            })?;
+                            Ok(PhysicalSortExpr {
+                                expr: parse_physical_expr(
+                                    expr,
+                                    registry,
+                                    input.schema().as_ref(),
+                                )?,
+                                options: SortOptions {
+                                    descending: !sort_expr.asc,
+                                    nulls_first: sort_expr.nulls_first,
+                                },
+                            })
+                        })
+                        .collect::<Result<Vec<_>, DataFusionError>>()?;
+                    let sample = inputs.get(1).cloned().ok_or_else(|| {
+                        proto_error("Missing the sample input of a range partitioning")
+                    })?;
+
+                    ShuffleWriterExec::try_new_range(
+                        shuffle_writer.job_id.clone(),
+                        shuffle_writer.stage_id as usize,
+                        input,
+                        "".to_string(), // this is intentional but hacky - the executor will fill this in
+                        RangePartitioning {
+                            sort_exprs,
+                            partition_count: range.partition_count as usize,
+                            sample,
+                        },
+                    )?
+                } else {
+                    let shuffle_output_partitioning = parse_protobuf_hash_partitioning(
+                        shuffle_writer.output_partitioning.as_ref(),
+                        registry,
+                        input.schema().as_ref(),
+                    )?;
+
+                    ShuffleWriterExec::try_new(
+                        shuffle_writer.job_id.clone(),
+                        shuffle_writer.stage_id as usize,
+                        input,
+                        "".to_string(), // this is intentional but hacky - the executor will fill this in
+                        shuffle_output_partitioning,
+                    )?
+                };
+
+                Ok(Arc::new(
+                    shuffle_writer_exec
+                        .with_compression(compression)
+                        .with_sort_based(shuffle_writer.sort_based),
+                ))
             }
             PhysicalPlanType::ShuffleReader(shuffle_reader) => {
                 let stage_id = shuffle_reader.stage_id as usize;
@@ -175,8 +231,15 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                             .collect::<Result<Vec<_>, _>>()
                     })
                     .collect::<Result<Vec<_>, DataFusionError>>()?;
-                let shuffle_reader =
-                    ShuffleReaderExec::try_new(stage_id, partition_location, schema)?;
+                let shuffle_reader = if shuffle_reader.broadcast {
+                    ShuffleReaderExec::try_new_broadcast(
+                        stage_id,
+                        partition_location,
+                        schema,
+                    )?
+                } else {
+                    ShuffleReaderExec::try_new(stage_id, partition_location, schema)?
+                };
                 Ok(Arc::new(shuffle_reader))
             }
             PhysicalPlanType::UnresolvedShuffle(unresolved_shuffle) => {
@@ -186,8 +249,15 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                     schema,
                     output_partition_count: unresolved_shuffle.output_partition_count
                         as usize,
+                    broadcast: unresolved_shuffle.broadcast,
                 }))
             }
+            PhysicalPlanType::RangeSample(range_sample) => {
+                Ok(Arc::new(RangeSampleExec::new(
+                    inputs[0].clone(),
+                    range_sample.sample_size as usize,
+                )))
+            }
         }
     }
 
@@ -197,9 +267,32 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
         buf: &mut Vec<u8>,
     ) -> Result<(), DataFusionError> {
         if let Some(exec) = node.as_any().downcast_ref::<ShuffleWriterExec>() {
+            let range_partitioning = exec
+                .range_partitioning()
+                .map(|range| {
+                    let sort_expr = range
+                        .sort_exprs
+                        .iter()
+                        .map(|sort_expr| {
+                            Ok(datafusion_proto::protobuf::PhysicalSortExprNode {
+                                expr: Some(Box::new(sort_expr.expr.clone().try_into()?)),
+                                asc: !sort_expr.options.descending,
+                                nulls_first: sort_expr.options.nulls_first,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, DataFusionError>>()?;
+                    Ok::<_, DataFusionError>(protobuf::RangeRepartition {
+                        sort_expr,
+                        partition_count: range.partition_count as u64,
+                    })
+                })
+                .transpose()?;
+
             // note that we use shuffle_output_partitioning() rather than output_partitioning()
             // to get the true output partitioning
             let output_partitioning = match exec.shuffle_output_partitioning() {
+                // the partition count of a range partitioning is part of the range itself
+                _ if range_partitioning.is_some() => None,
                 Some(Partitioning::Hash(exprs, partition_count)) => {
                     Some(datafusion_proto::protobuf::PhysicalHashRepartition {
                         hash_expr: exprs
@@ -224,6 +317,9 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                         stage_id: exec.stage_id() as u32,
                         input: None,
                         output_partitioning,
+                        compression: exec.compression().to_string(),
+                        sort_based: exec.sort_based(),
+                        range_partitioning,
                     },
                 )),
             };
@@ -258,6 +354,7 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                         stage_id,
                         partition,
                         schema: Some(exec.schema().as_ref().try_into()?),
+                        broadcast: exec.broadcast,
                     },
                 )),
             };
@@ -275,6 +372,7 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                         stage_id: exec.stage_id as u32,
                         schema: Some(exec.schema().as_ref().try_into()?),
                         output_partition_count: exec.output_partition_count as u32,
+                        broadcast: exec.broadcast,
                     },
                 )),
             };
@@ -284,6 +382,21 @@ impl PhysicalExtensionCodec for BallistaPhysicalExtensionCodec {
                 ))
             })?;
 
+            Ok(())
+        } else if let Some(exec) = node.as_any().downcast_ref::<RangeSampleExec>() {
+            let proto = protobuf::BallistaPhysicalPlanNode {
+                physical_plan_type: Some(PhysicalPlanType::RangeSample(
+                    protobuf::RangeSampleExecNode {
+                        sample_size: exec.sample_size() as u64,
+                    },
+                )),
+            };
+            proto.encode(buf).map_err(|e| {
+                DataFusionError::Internal(format!(
+                    "failed to encode range sample execution plan: {e:?}"
+                ))
+            })?;
+
             Ok(())
         } else {
             Err(DataFusionError::Internal(
@@ -32,8 +32,9 @@ use std::time::Duration;
 
 use crate::error::BallistaError;
 use crate::serde::scheduler::{
-    Action, ExecutorData, ExecutorMetadata, ExecutorSpecification, PartitionId,
-    PartitionLocation, PartitionStats, SimpleFunctionRegistry, TaskDefinition,
+    Action, ExecutorData, ExecutorMetadata, ExecutorMetrics, ExecutorSpecification,
+    PartitionId, PartitionLocation, PartitionStats, SimpleFunctionRegistry,
+    TaskDefinition,
 };
 
 use crate::serde::{protobuf, BallistaCodec};
@@ -52,6 +53,11 @@ impl TryInto<Action> for protobuf::Action {
                     path: fetch.path,
                     host: fetch.host,
                     port: fetch.port as u16,
+                    compression: fetch
+                        .compression
+                        .parse()
+                        .map_err(BallistaError::General)?,
+                    index_path: Some(fetch.index_path).filter(|p| !p.is_empty()),
                 })
             }
             _ => Err(BallistaError::General(
@@ -122,6 +128,8 @@ impl TryInto<PartitionLocation> for protobuf::PartitionLocation {
                 })?
                 .into(),
             path: self.path,
+            compression: self.compression.parse().map_err(BallistaError::General)?,
+            index_path: Some(self.index_path).filter(|p| !p.is_empty()),
         })
     }
 }
@@ -225,6 +233,11 @@ impl Into<ExecutorMetadata> for protobuf::ExecutorMetadata {
             port: self.port as u16,
             grpc_port: self.grpc_port as u16,
             specification: self.specification.unwrap().into(),
+            labels: self
+                .labels
+                .into_iter()
+                .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                .collect(),
         }
     }
 }
@@ -232,12 +245,35 @@ impl Into<ExecutorMetadata> for protobuf::ExecutorMetadata {
 #[allow(clippy::from_over_into)]
 impl Into<ExecutorSpecification> for protobuf::ExecutorSpecification {
     fn into(self) -> ExecutorSpecification {
-        let mut ret = ExecutorSpecification { task_slots: 0 };
+        use protobuf::executor_resource::Resource;
+
+        // executors which don't report their memory or CPUs are not limited by them
+        let mut ret = ExecutorSpecification::new(0);
         for resource in self.resources {
-            if let Some(protobuf::executor_resource::Resource::TaskSlots(task_slots)) =
-                resource.resource
-            {
-                ret.task_slots = task_slots
+            match resource.resource {
+                Some(Resource::TaskSlots(task_slots)) => ret.task_slots = task_slots,
+                Some(Resource::Memory(memory)) => ret.memory = memory,
+                Some(Resource::Cpus(cpus)) => ret.cpus = cpus,
+                None => {}
+            }
+        }
+        ret
+    }
+}
+
+impl From<&[protobuf::ExecutorMetric]> for ExecutorMetrics {
+    fn from(metrics: &[protobuf::ExecutorMetric]) -> Self {
+        use protobuf::executor_metric::Metric;
+
+        let mut ret = ExecutorMetrics::default();
+        for metric in metrics.iter().filter_map(|m| m.metric.as_ref()) {
+            match metric {
+                Metric::AvailableMemory(bytes) => ret.available_memory = Some(*bytes),
+                Metric::UsedMemory(bytes) => ret.used_memory = Some(*bytes),
+                Metric::CpuUsage(percent) => ret.cpu_usage = Some(*percent),
+                Metric::AvailableDisk(bytes) => ret.available_disk = Some(*bytes),
+                Metric::RunningTasks(tasks) => ret.running_tasks = Some(*tasks),
+                Metric::ShuffleBytes(bytes) => ret.shuffle_bytes = Some(*bytes),
             }
         }
         ret
@@ -247,26 +283,28 @@ impl Into<ExecutorSpecification> for protobuf::ExecutorSpecification {
 #[allow(clippy::from_over_into)]
 impl Into<ExecutorData> for protobuf::ExecutorData {
     fn into(self) -> ExecutorData {
-        let mut ret = ExecutorData {
-            executor_id: self.executor_id,
-            total_task_slots: 0,
-            available_task_slots: 0,
-        };
+        use protobuf::executor_resource::Resource;
+
+        let mut ret = ExecutorData::new(self.executor_id, ExecutorSpecification::new(0));
         for resource in self.resources {
-            if let Some(task_slots) = resource.total {
-                if let Some(protobuf::executor_resource::Resource::TaskSlots(
-                    task_slots,
-                )) = task_slots.resource
-                {
-                    ret.total_task_slots = task_slots
+            if let Some(total) = resource.total {
+                match total.resource {
+                    Some(Resource::TaskSlots(task_slots)) => {
+                        ret.total_task_slots = task_slots
+                    }
+                    Some(Resource::Memory(memory)) => ret.total_memory = memory,
+                    Some(Resource::Cpus(cpus)) => ret.total_cpus = cpus,
+                    None => {}
                 }
             };
-            if let Some(task_slots) = resource.available {
-                if let Some(protobuf::executor_resource::Resource::TaskSlots(
-                    task_slots,
-                )) = task_slots.resource
-                {
-                    ret.available_task_slots = task_slots
+            if let Some(available) = resource.available {
+                match available.resource {
+                    Some(Resource::TaskSlots(task_slots)) => {
+                        ret.available_task_slots = task_slots
+                    }
+                    Some(Resource::Memory(memory)) => ret.available_memory = memory,
+                    Some(Resource::Cpus(cpus)) => ret.available_cpus = cpus,
+                    None => {}
                 }
             };
         }
@@ -324,6 +362,11 @@ pub fn get_task_definition<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
     let launch_time = task.launch_time;
     let task_id = task.task_id as usize;
     let session_id = task.session_id;
+    let trace_context = task
+        .trace_context
+        .into_iter()
+        .map(|kv_pair| (kv_pair.key, kv_pair.value))
+        .collect();
 
     Ok(TaskDefinition {
         task_id,
@@ -337,6 +380,7 @@ pub fn get_task_definition<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
         session_id,
         props,
         function_registry,
+        trace_context,
     })
 }
 
@@ -391,6 +435,11 @@ pub fn get_task_definition_vec<
     let launch_time = multi_task.launch_time;
     let task_ids = multi_task.task_ids;
     let session_id = multi_task.session_id;
+    let trace_context: HashMap<String, String> = multi_task
+        .trace_context
+        .into_iter()
+        .map(|kv_pair| (kv_pair.key, kv_pair.value))
+        .collect();
 
     task_ids
         .iter()
@@ -407,6 +456,7 @@ pub fn get_task_definition_vec<
                 session_id: session_id.clone(),
                 props: props.clone(),
                 function_registry: function_registry.clone(),
+                trace_context: trace_context.clone(),
             })
         })
         .collect()
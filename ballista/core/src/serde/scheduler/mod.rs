@@ -30,6 +30,7 @@ use datafusion::physical_plan::ExecutionPlan;
 use datafusion::physical_plan::Partitioning;
 use serde::Serialize;
 
+use crate::config::ShuffleCompression;
 use crate::error::BallistaError;
 
 pub mod from_proto;
@@ -46,6 +47,9 @@ pub enum Action {
         path: String,
         host: String,
         port: u16,
+        compression: ShuffleCompression,
+        /// Index of the byte ranges of the partitions of a sort-based shuffle file
+        index_path: Option<String>,
     },
 }
 
@@ -74,6 +78,9 @@ pub struct PartitionLocation {
     pub executor_meta: ExecutorMetadata,
     pub partition_stats: PartitionStats,
     pub path: String,
+    pub compression: ShuffleCompression,
+    /// Index of the byte range of this partition, if the shuffle is sort-based
+    pub index_path: Option<String>,
 }
 
 /// Meta-data for an executor, used when fetching shuffle partitions from other executors
@@ -84,12 +91,49 @@ pub struct ExecutorMetadata {
     pub port: u16,
     pub grpc_port: u16,
     pub specification: ExecutorSpecification,
+    /// Attributes of the executor, which jobs select the executors running their tasks by
+    pub labels: HashMap<String, String>,
+}
+
+impl ExecutorMetadata {
+    /// Whether the executor has all the labels of the selector
+    pub fn matches(&self, selector: &HashMap<String, String>) -> bool {
+        selector
+            .iter()
+            .all(|(key, value)| self.labels.get(key) == Some(value))
+    }
 }
 
 /// Specification of an executor, indicting executor resources, like total task slots
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct ExecutorSpecification {
     pub task_slots: u32,
+    /// Memory in bytes which can be reserved by tasks, `u64::MAX` if it is not limited
+    pub memory: u64,
+    /// Number of CPUs which can be reserved by tasks, `u32::MAX` if it is not limited
+    pub cpus: u32,
+}
+
+impl ExecutorSpecification {
+    /// Specification of an executor with the given task slots, whose memory and CPUs
+    /// are not limited
+    pub fn new(task_slots: u32) -> Self {
+        Self {
+            task_slots,
+            memory: u64::MAX,
+            cpus: u32::MAX,
+        }
+    }
+
+    pub fn with_memory(mut self, memory: u64) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub fn with_cpus(mut self, cpus: u32) -> Self {
+        self.cpus = cpus;
+        self
+    }
 }
 
 /// From Spark, available resources for an executor, like available task slots
@@ -98,6 +142,40 @@ pub struct ExecutorData {
     pub executor_id: String,
     pub total_task_slots: u32,
     pub available_task_slots: u32,
+    pub total_memory: u64,
+    pub available_memory: u64,
+    pub total_cpus: u32,
+    pub available_cpus: u32,
+}
+
+impl ExecutorData {
+    /// Resources of a new executor, which are all available
+    pub fn new(executor_id: impl Into<String>, spec: ExecutorSpecification) -> Self {
+        Self {
+            executor_id: executor_id.into(),
+            total_task_slots: spec.task_slots,
+            available_task_slots: spec.task_slots,
+            total_memory: spec.memory,
+            available_memory: spec.memory,
+            total_cpus: spec.cpus,
+            available_cpus: spec.cpus,
+        }
+    }
+}
+
+/// Resource usage reported by an executor in its heartbeats. A metric is `None` if the
+/// executor did not report it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ExecutorMetrics {
+    pub available_memory: Option<u64>,
+    pub used_memory: Option<u64>,
+    /// Average usage of all the CPUs since the previous heartbeat, in percent
+    pub cpu_usage: Option<f32>,
+    /// Free space of the file system holding the work dir
+    pub available_disk: Option<u64>,
+    pub running_tasks: Option<u32>,
+    /// Size of the shuffle files held in the work dir
+    pub shuffle_bytes: Option<u64>,
 }
 
 pub struct ExecutorDataChange {
@@ -136,6 +214,18 @@ impl PartitionStats {
         }
     }
 
+    pub fn num_rows(&self) -> Option<u64> {
+        self.num_rows
+    }
+
+    pub fn num_batches(&self) -> Option<u64> {
+        self.num_batches
+    }
+
+    pub fn num_bytes(&self) -> Option<u64> {
+        self.num_bytes
+    }
+
     pub fn arrow_struct_repr(self) -> Field {
         Field::new(
             "partition_stats",
@@ -289,6 +379,8 @@ pub struct TaskDefinition {
     pub session_id: String,
     pub props: Arc<HashMap<String, String>>,
     pub function_registry: Arc<SimpleFunctionRegistry>,
+    /// Trace context of the span of the stage, which the span of the task is a child of
+    pub trace_context: HashMap<String, String>,
 }
 
 #[derive(Debug)]
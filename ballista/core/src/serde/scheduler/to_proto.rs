@@ -25,8 +25,8 @@ use crate::serde::protobuf;
 use datafusion_proto::protobuf as datafusion_protobuf;
 
 use crate::serde::scheduler::{
-    Action, ExecutorData, ExecutorMetadata, ExecutorSpecification, PartitionId,
-    PartitionLocation, PartitionStats,
+    Action, ExecutorData, ExecutorMetadata, ExecutorMetrics, ExecutorSpecification,
+    PartitionId, PartitionLocation, PartitionStats,
 };
 use datafusion::physical_plan::Partitioning;
 use protobuf::{action::ActionType, operator_metric, NamedCount, NamedGauge, NamedTime};
@@ -43,6 +43,8 @@ impl TryInto<protobuf::Action> for Action {
                 path,
                 host,
                 port,
+                compression,
+                index_path,
             } => Ok(protobuf::Action {
                 action_type: Some(ActionType::FetchPartition(protobuf::FetchPartition {
                     job_id,
@@ -51,6 +53,8 @@ impl TryInto<protobuf::Action> for Action {
                     path,
                     host,
                     port: port as u32,
+                    compression: compression.to_string(),
+                    index_path: index_path.unwrap_or_default(),
                 })),
                 settings: vec![],
             }),
@@ -79,6 +83,8 @@ impl TryInto<protobuf::PartitionLocation> for PartitionLocation {
             executor_meta: Some(self.executor_meta.into()),
             partition_stats: Some(self.partition_stats.into()),
             path: self.path,
+            compression: self.compression.to_string(),
+            index_path: self.index_path.unwrap_or_default(),
         })
     }
 }
@@ -191,6 +197,11 @@ impl Into<protobuf::ExecutorMetadata> for ExecutorMetadata {
             port: self.port as u32,
             grpc_port: self.grpc_port as u32,
             specification: Some(self.specification.into()),
+            labels: self
+                .labels
+                .into_iter()
+                .map(|(key, value)| protobuf::KeyValuePair { key, value })
+                .collect(),
         }
     }
 }
@@ -199,9 +210,11 @@ impl Into<protobuf::ExecutorMetadata> for ExecutorMetadata {
 impl Into<protobuf::ExecutorSpecification> for ExecutorSpecification {
     fn into(self) -> protobuf::ExecutorSpecification {
         protobuf::ExecutorSpecification {
-            resources: vec![protobuf::executor_resource::Resource::TaskSlots(
-                self.task_slots,
-            )]
+            resources: vec![
+                protobuf::executor_resource::Resource::TaskSlots(self.task_slots),
+                protobuf::executor_resource::Resource::Memory(self.memory),
+                protobuf::executor_resource::Resource::Cpus(self.cpus),
+            ]
             .into_iter()
             .map(|r| protobuf::ExecutorResource { resource: Some(r) })
             .collect(),
@@ -209,6 +222,28 @@ impl Into<protobuf::ExecutorSpecification> for ExecutorSpecification {
     }
 }
 
+#[allow(clippy::from_over_into)]
+impl Into<Vec<protobuf::ExecutorMetric>> for ExecutorMetrics {
+    fn into(self) -> Vec<protobuf::ExecutorMetric> {
+        use protobuf::executor_metric::Metric;
+
+        [
+            self.available_memory.map(Metric::AvailableMemory),
+            self.used_memory.map(Metric::UsedMemory),
+            self.cpu_usage.map(Metric::CpuUsage),
+            self.available_disk.map(Metric::AvailableDisk),
+            self.running_tasks.map(Metric::RunningTasks),
+            self.shuffle_bytes.map(Metric::ShuffleBytes),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|metric| protobuf::ExecutorMetric {
+            metric: Some(metric),
+        })
+        .collect()
+    }
+}
+
 struct ExecutorResourcePair {
     total: protobuf::executor_resource::Resource,
     available: protobuf::executor_resource::Resource,
@@ -219,14 +254,30 @@ impl Into<protobuf::ExecutorData> for ExecutorData {
     fn into(self) -> protobuf::ExecutorData {
         protobuf::ExecutorData {
             executor_id: self.executor_id,
-            resources: vec![ExecutorResourcePair {
-                total: protobuf::executor_resource::Resource::TaskSlots(
-                    self.total_task_slots,
-                ),
-                available: protobuf::executor_resource::Resource::TaskSlots(
-                    self.available_task_slots,
-                ),
-            }]
+            resources: vec![
+                ExecutorResourcePair {
+                    total: protobuf::executor_resource::Resource::TaskSlots(
+                        self.total_task_slots,
+                    ),
+                    available: protobuf::executor_resource::Resource::TaskSlots(
+                        self.available_task_slots,
+                    ),
+                },
+                ExecutorResourcePair {
+                    total: protobuf::executor_resource::Resource::Memory(
+                        self.total_memory,
+                    ),
+                    available: protobuf::executor_resource::Resource::Memory(
+                        self.available_memory,
+                    ),
+                },
+                ExecutorResourcePair {
+                    total: protobuf::executor_resource::Resource::Cpus(self.total_cpus),
+                    available: protobuf::executor_resource::Resource::Cpus(
+                        self.available_cpus,
+                    ),
+                },
+            ]
             .into_iter()
             .map(|r| protobuf::ExecutorResourcePair {
                 total: Some(protobuf::ExecutorResource {
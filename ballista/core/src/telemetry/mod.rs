@@ -0,0 +1,68 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Distributed tracing of jobs across the scheduler and executors with OpenTelemetry.
+//!
+//! The spans of a job, its stages, their tasks and the shuffle fetches of the tasks
+//! form one trace. The trace context is propagated to other processes in the
+//! `trace_context` fields of the messages submitting jobs and launching tasks.
+//!
+//! Spans are only exported, and their trace context propagated, when built with the
+//! `otlp` feature. Without it the functions of this module do nothing.
+
+#[cfg(feature = "otlp")]
+mod otlp;
+
+#[cfg(feature = "otlp")]
+pub use otlp::{otlp_layer, set_parent, shutdown, trace_context};
+
+#[cfg(not(feature = "otlp"))]
+pub use disabled::{otlp_layer, set_parent, shutdown, trace_context};
+
+#[cfg(not(feature = "otlp"))]
+mod disabled {
+    use std::collections::HashMap;
+
+    use tracing::{Span, Subscriber};
+    use tracing_subscriber::layer::Identity;
+    use tracing_subscriber::Layer;
+
+    use crate::error::{BallistaError, Result};
+
+    /// Fail if an OTLP endpoint is set, since spans can't be exported without the
+    /// `otlp` feature
+    pub fn otlp_layer<S: Subscriber>(
+        service_name: &str,
+        otlp_endpoint: Option<&str>,
+    ) -> Result<Option<impl Layer<S>>> {
+        match otlp_endpoint {
+            Some(otlp_endpoint) => Err(BallistaError::General(format!(
+                "Can't export the spans of {service_name} to {otlp_endpoint}, \
+                 it was built without the otlp feature"
+            ))),
+            None => Ok(None::<Identity>),
+        }
+    }
+
+    pub fn shutdown() {}
+
+    pub fn trace_context(_span: &Span) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    pub fn set_parent(_span: &Span, _trace_context: &HashMap<String, String>) {}
+}
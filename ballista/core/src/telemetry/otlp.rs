@@ -0,0 +1,129 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Export of spans over OTLP and propagation of their trace context
+
+use std::collections::HashMap;
+
+use opentelemetry::{global, KeyValue};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::error::{BallistaError, Result};
+
+/// Create the layer exporting the spans of a service to the OTLP collector listening
+/// at `otlp_endpoint`, such as `http://localhost:4317`. No spans are exported if the
+/// endpoint is not set.
+pub fn otlp_layer<S>(
+    service_name: &str,
+    otlp_endpoint: Option<&str>,
+) -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let otlp_endpoint = match otlp_endpoint {
+        Some(otlp_endpoint) => otlp_endpoint,
+        None => return Ok(None),
+    };
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(otlp_endpoint),
+        )
+        .with_trace_config(trace::config().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", service_name.to_owned()),
+        ])))
+        .install_batch(runtime::Tokio)
+        .map_err(|e| {
+            BallistaError::General(format!(
+                "Failed to export spans to {otlp_endpoint}: {e:?}"
+            ))
+        })?;
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Export the remaining spans before the process exits
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Trace context of a span, which the spans of other processes can be children of.
+/// Empty if the spans are not exported.
+pub fn trace_context(span: &Span) -> HashMap<String, String> {
+    let mut trace_context = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut trace_context)
+    });
+    trace_context
+}
+
+/// Make a span the child of the span of another process with the given trace context
+pub fn set_parent(span: &Span, trace_context: &HashMap<String, String>) {
+    if !trace_context.is_empty() {
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(trace_context)
+        });
+        span.set_parent(parent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_propagate_trace_context() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let job_span = info_span!("job");
+            let trace_context = trace_context(&job_span);
+            assert!(trace_context.contains_key("traceparent"));
+
+            // the span of another process joins the trace of the job
+            let stage_span = info_span!(parent: None, "stage");
+            set_parent(&stage_span, &trace_context);
+            assert_eq!(
+                job_span.context().span().span_context().trace_id(),
+                stage_span.context().span().span_context().trace_id()
+            );
+
+            let unrelated_span = info_span!(parent: None, "stage");
+            assert_ne!(
+                job_span.context().span().span_context().trace_id(),
+                unrelated_span.context().span().span_context().trace_id()
+            );
+        });
+    }
+}
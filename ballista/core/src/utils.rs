@@ -18,14 +18,16 @@
 use crate::config::BallistaConfig;
 use crate::error::{BallistaError, Result};
 use crate::execution_plans::{
-    DistributedQueryExec, ShuffleWriterExec, UnresolvedShuffleExec,
+    DistributedQueryExec, JobProgressListeners, ShuffleWriterExec, UnresolvedShuffleExec,
 };
 use crate::object_store_registry::with_object_store_registry;
 use crate::serde::scheduler::PartitionStats;
 
 use async_trait::async_trait;
 use datafusion::arrow::datatypes::Schema;
-use datafusion::arrow::{ipc::writer::FileWriter, record_batch::RecordBatch};
+use datafusion::arrow::ipc::writer::{FileWriter, IpcWriteOptions};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::object_store::ObjectStoreUrl;
 use datafusion::datasource::physical_plan::{CsvExec, ParquetExec};
 use datafusion::error::DataFusionError;
 use datafusion::execution::context::{
@@ -48,6 +50,7 @@ use datafusion_proto::logical_plan::{
 };
 use futures::StreamExt;
 use log::error;
+use object_store::path::Path;
 use std::io::{BufWriter, Write};
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -55,7 +58,10 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fs::File, pin::Pin};
 use tonic::codegen::StdError;
-use tonic::transport::{Channel, Error, Server};
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Error, Identity, Server, ServerTlsConfig,
+};
+use url::Url;
 
 /// Default session builder using the provided configuration
 pub fn default_session_builder(config: SessionConfig) -> SessionState {
@@ -72,6 +78,7 @@ pub fn default_session_builder(config: SessionConfig) -> SessionState {
 pub async fn write_stream_to_disk(
     stream: &mut Pin<Box<dyn RecordBatchStream + Send>>,
     path: &str,
+    write_options: IpcWriteOptions,
     disk_write_metric: &metrics::Time,
 ) -> Result<PartitionStats> {
     let file = File::create(path).map_err(|e| {
@@ -82,7 +89,8 @@ pub async fn write_stream_to_disk(
     let mut num_rows = 0;
     let mut num_batches = 0;
     let mut num_bytes = 0;
-    let mut writer = FileWriter::try_new(file, stream.schema().as_ref())?;
+    let mut writer =
+        FileWriter::try_new_with_options(file, stream.schema().as_ref(), write_options)?;
 
     while let Some(result) = stream.next().await {
         let batch = result?;
@@ -116,6 +124,68 @@ pub async fn collect_stream(
     Ok(batches)
 }
 
+/// Split a shuffle location which refers to an object store, e.g. `s3://bucket/shuffle`,
+/// into the URL of the store and the path of the object within it.
+///
+/// Returns `None` if the location is a path on the local file system.
+pub fn parse_object_store_location(location: &str) -> Option<(ObjectStoreUrl, Path)> {
+    let url = Url::parse(location).ok()?;
+    // Windows paths such as `C:\data` parse as URLs with a single letter scheme
+    if url.scheme().len() < 2 || url.cannot_be_a_base() {
+        return None;
+    }
+    let store_url = ObjectStoreUrl::parse(&url[..url::Position::BeforePath]).ok()?;
+    let path = Path::from_url_path(url.path()).ok()?;
+    Some((store_url, path))
+}
+
+/// The URL of an object within an object store, which is the reverse of
+/// [`parse_object_store_location`]
+pub fn object_store_location(store_url: &ObjectStoreUrl, path: &Path) -> String {
+    format!("{}{}", store_url.as_str(), path)
+}
+
+/// Whether the shuffle location refers to an object store rather than the local
+/// file system of the executor which wrote it
+pub fn is_object_store_location(location: &str) -> bool {
+    parse_object_store_location(location).is_some()
+}
+
+/// Delete the shuffle files of a job which were written below the given object store URL
+pub async fn remove_object_store_job_data(
+    shuffle_object_store_url: &str,
+    job_id: &str,
+    runtime: &RuntimeEnv,
+) -> Result<()> {
+    let (store_url, path) = parse_object_store_location(shuffle_object_store_url)
+        .ok_or_else(|| {
+            BallistaError::General(format!(
+                "Invalid shuffle object store URL: {shuffle_object_store_url}"
+            ))
+        })?;
+    let object_store = runtime.object_store(&store_url)?;
+    let prefix = path.child(job_id);
+
+    let to_ballista_error = |e: object_store::Error| {
+        BallistaError::General(format!(
+            "Failed to remove shuffle files of job {job_id}: {e:?}"
+        ))
+    };
+    let mut objects = object_store
+        .list(Some(&prefix))
+        .await
+        .map_err(to_ballista_error)?;
+    while let Some(meta) = objects.next().await {
+        let meta = meta.map_err(to_ballista_error)?;
+        match object_store.delete(&meta.location).await {
+            // another executor may have removed the same object concurrently
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+            Err(e) => return Err(to_ballista_error(e)),
+        }
+    }
+    Ok(())
+}
+
 pub fn produce_diagram(filename: &str, stages: &[Arc<ShuffleWriterExec>]) -> Result<()> {
     let write_file = File::create(filename)?;
     let mut w = BufWriter::new(&write_file);
@@ -237,13 +307,19 @@ pub fn create_df_ctx_with_ballista_query_planner<T: 'static + AsLogicalPlan>(
     scheduler_url: String,
     session_id: String,
     config: &BallistaConfig,
+    tls: Option<GrpcTlsConfig>,
 ) -> SessionContext {
     let planner: Arc<BallistaQueryPlanner<T>> =
         Arc::new(BallistaQueryPlanner::new(scheduler_url, config.clone()));
 
-    let session_config = SessionConfig::new()
+    let mut session_config = SessionConfig::new()
         .with_target_partitions(config.default_shuffle_partitions())
-        .with_information_schema(true);
+        .with_information_schema(true)
+        .with_extension(Arc::new(JobProgressListeners::default()));
+    // the query planner connects to the scheduler and executors with these settings
+    if let Some(tls) = tls {
+        session_config = session_config.with_extension(Arc::new(tls));
+    }
     let mut session_state = SessionState::with_config_rt(
         session_config,
         Arc::new(
@@ -326,14 +402,141 @@ impl<T: 'static + AsLogicalPlan> QueryPlanner for BallistaQueryPlanner<T> {
     }
 }
 
+/// TLS settings of the gRPC and Flight services of a Ballista cluster and of the clients
+/// connecting to them
+#[derive(Clone, Default)]
+pub struct GrpcTlsConfig {
+    /// PEM encoded certificate chain and private key. Servers present it to clients,
+    /// clients present it to servers which require mutual TLS
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    /// PEM encoded CA certificate. Clients verify the server certificate with it, and
+    /// servers verify client certificates with it if `client_auth` is set
+    ca_cert: Option<Vec<u8>>,
+    /// Whether servers require clients to present a certificate signed by `ca_cert`
+    client_auth: bool,
+    /// Name to verify the server certificate against instead of the host connected to
+    domain: Option<String>,
+}
+
+impl GrpcTlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the certificate, private key and optional CA certificate from PEM files
+    pub fn try_from_pem_files(
+        cert_file: &str,
+        key_file: &str,
+        ca_cert_file: Option<&str>,
+    ) -> Result<Self> {
+        let config = Self::new().with_identity(read_pem(cert_file)?, read_pem(key_file)?);
+        Ok(match ca_cert_file {
+            Some(ca_cert_file) => config.with_ca_cert(read_pem(ca_cert_file)?),
+            None => config,
+        })
+    }
+
+    pub fn with_identity(mut self, cert: Vec<u8>, key: Vec<u8>) -> Self {
+        self.identity = Some((cert, key));
+        self
+    }
+
+    pub fn with_ca_cert(mut self, ca_cert: Vec<u8>) -> Self {
+        self.ca_cert = Some(ca_cert);
+        self
+    }
+
+    pub fn with_client_auth(mut self, client_auth: bool) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub fn identity(&self) -> Option<(&[u8], &[u8])> {
+        self.identity
+            .as_ref()
+            .map(|(cert, key)| (cert.as_slice(), key.as_slice()))
+    }
+
+    pub fn ca_cert(&self) -> Option<&[u8]> {
+        self.ca_cert.as_deref()
+    }
+
+    pub fn client_auth(&self) -> bool {
+        self.client_auth
+    }
+
+    /// The TLS settings of a tonic server
+    pub fn server_tls_config(&self) -> Result<ServerTlsConfig> {
+        let (cert, key) = self.identity.as_ref().ok_or_else(|| {
+            BallistaError::General(
+                "A certificate and private key are required to serve TLS".to_owned(),
+            )
+        })?;
+        let config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if !self.client_auth {
+            return Ok(config);
+        }
+        let ca_cert = self.ca_cert.as_ref().ok_or_else(|| {
+            BallistaError::General(
+                "A CA certificate is required to verify client certificates".to_owned(),
+            )
+        })?;
+        Ok(config.client_ca_root(Certificate::from_pem(ca_cert)))
+    }
+
+    /// The TLS settings of a tonic client
+    pub fn client_tls_config(&self) -> ClientTlsConfig {
+        let mut config = ClientTlsConfig::new();
+        if let Some(ca_cert) = &self.ca_cert {
+            config = config.ca_certificate(Certificate::from_pem(ca_cert));
+        }
+        if let Some((cert, key)) = &self.identity {
+            config = config.identity(Identity::from_pem(cert, key));
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain);
+        }
+        config
+    }
+}
+
+impl std::fmt::Debug for GrpcTlsConfig {
+    // Keep the private key out of logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GrpcTlsConfig")
+            .field("identity", &self.identity.is_some())
+            .field("ca_cert", &self.ca_cert.is_some())
+            .field("client_auth", &self.client_auth)
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| {
+        BallistaError::General(format!("Failed to read PEM file {path}: {e}"))
+    })
+}
+
 pub async fn create_grpc_client_connection<D>(
     dst: D,
+    tls: Option<&GrpcTlsConfig>,
 ) -> std::result::Result<Channel, Error>
 where
     D: std::convert::TryInto<tonic::transport::Endpoint>,
     D::Error: Into<StdError>,
 {
-    let endpoint = tonic::transport::Endpoint::new(dst)?
+    let endpoint = tonic::transport::Endpoint::new(dst)?;
+    let endpoint = match tls {
+        Some(tls) => with_https_scheme(endpoint)?.tls_config(tls.client_tls_config())?,
+        None => endpoint,
+    };
+    let endpoint = endpoint
         .connect_timeout(Duration::from_secs(20))
         .timeout(Duration::from_secs(20))
         // Disable Nagle's Algorithm since we don't want packets to wait
@@ -345,14 +548,35 @@ where
     endpoint.connect().await
 }
 
-pub fn create_grpc_server() -> Server {
-    Server::builder()
+/// Tonic only uses TLS for `https` URLs, while Ballista addresses its services with
+/// `http` URLs
+fn with_https_scheme(
+    endpoint: tonic::transport::Endpoint,
+) -> std::result::Result<tonic::transport::Endpoint, Error> {
+    let uri = endpoint.uri();
+    if uri.scheme_str() != Some("http") {
+        return Ok(endpoint);
+    }
+    let authority = uri.authority().map(|a| a.as_str()).unwrap_or_default();
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or_default();
+    tonic::transport::Endpoint::from_shared(format!("https://{authority}{path}"))
+}
+
+pub fn create_grpc_server(
+    tls: Option<&GrpcTlsConfig>,
+) -> std::result::Result<Server, BallistaError> {
+    let server = Server::builder();
+    let server = match tls {
+        Some(tls) => server.tls_config(tls.server_tls_config()?)?,
+        None => server,
+    };
+    Ok(server
         .timeout(Duration::from_secs(20))
         // Disable Nagle's Algorithm since we don't want packets to wait
         .tcp_nodelay(true)
         .tcp_keepalive(Option::Some(Duration::from_secs(3600)))
         .http2_keepalive_interval(Option::Some(Duration::from_secs(300)))
-        .http2_keepalive_timeout(Option::Some(Duration::from_secs(20)))
+        .http2_keepalive_timeout(Option::Some(Duration::from_secs(20))))
 }
 
 pub fn collect_plan_metrics(plan: &dyn ExecutionPlan) -> Vec<MetricsSet> {
@@ -20,7 +20,9 @@
 use anyhow::Result;
 use std::sync::Arc;
 
+use ballista_core::config::parse_labels;
 use ballista_core::print_version;
+use ballista_core::utils::GrpcTlsConfig;
 use ballista_executor::executor_process::{
     start_executor_process, ExecutorProcessConfig,
 };
@@ -60,25 +62,47 @@ async fn main() -> Result<()> {
         opt.bind_port
     );
 
+    let grpc_tls = match (&opt.tls_cert_file, &opt.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => Some(
+            GrpcTlsConfig::try_from_pem_files(
+                cert_file,
+                key_file,
+                opt.tls_ca_cert_file.as_deref(),
+            )?
+            .with_client_auth(opt.tls_client_auth),
+        ),
+        (None, None) => None,
+        _ => anyhow::bail!("tls_cert_file and tls_key_file must be set together"),
+    };
+
+    let labels = parse_labels(&opt.labels).map_err(anyhow::Error::msg)?;
+
     let config = ExecutorProcessConfig {
         special_mod_log_level: opt.log_level_setting,
         external_host: opt.external_host,
         bind_host: opt.bind_host,
         port: opt.bind_port,
         grpc_port: opt.bind_grpc_port,
+        metrics_port: opt.metrics_port,
         scheduler_host: opt.scheduler_host,
         scheduler_port: opt.scheduler_port,
         scheduler_connect_timeout_seconds: opt.scheduler_connect_timeout_seconds,
         concurrent_tasks: opt.concurrent_tasks,
+        memory: opt.memory,
+        cpus: opt.cpus,
+        labels,
         task_scheduling_policy: opt.task_scheduling_policy,
         work_dir: opt.work_dir,
+        shuffle_object_store_url: opt.shuffle_object_store_url,
         log_dir: opt.log_dir,
         log_file_name_prefix,
         log_rotation_policy: opt.log_rotation_policy,
+        otlp_endpoint: opt.otlp_endpoint,
         print_thread_info: opt.print_thread_info,
         job_data_ttl_seconds: opt.job_data_ttl_seconds,
         job_data_clean_up_interval_seconds: opt.job_data_clean_up_interval_seconds,
         grpc_server_max_decoding_message_size: opt.grpc_server_max_decoding_message_size,
+        grpc_tls,
         executor_heartbeat_interval_seconds: opt.executor_heartbeat_interval_seconds,
         data_cache_policy: opt.data_cache_policy,
         cache_dir: opt.cache_dir,
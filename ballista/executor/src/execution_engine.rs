@@ -68,13 +68,26 @@ impl ExecutionEngine for DefaultExecutionEngine {
             plan.as_any().downcast_ref::<ShuffleWriterExec>()
         {
             // recreate the shuffle writer with the correct working directory
-            ShuffleWriterExec::try_new(
-                job_id,
-                stage_id,
-                plan.children()[0].clone(),
-                work_dir.to_string(),
-                shuffle_writer.shuffle_output_partitioning().cloned(),
-            )
+            match shuffle_writer.range_partitioning() {
+                Some(range) => ShuffleWriterExec::try_new_range(
+                    job_id,
+                    stage_id,
+                    plan.children()[0].clone(),
+                    work_dir.to_string(),
+                    range.clone(),
+                ),
+                None => ShuffleWriterExec::try_new(
+                    job_id,
+                    stage_id,
+                    plan.children()[0].clone(),
+                    work_dir.to_string(),
+                    shuffle_writer.shuffle_output_partitioning().cloned(),
+                ),
+            }
+            .map(|exec| {
+                exec.with_compression(shuffle_writer.compression())
+                    .with_sort_based(shuffle_writer.sort_based())
+            })
         } else {
             Err(DataFusionError::Internal(
                 "Plan passed to new_query_stage_exec is not a ShuffleWriterExec"
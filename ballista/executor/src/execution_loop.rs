@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use ballista_core::config::{BALLISTA_TASK_MEMORY, BALLISTA_TASK_TIMEOUT_SECONDS};
 use datafusion::config::ConfigOptions;
 use datafusion::physical_plan::ExecutionPlan;
 
@@ -27,7 +28,7 @@ use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::cpu_bound_executor::DedicatedExecutor;
 use crate::executor::Executor;
-use crate::{as_task_status, TaskExecutionTimes};
+use crate::{as_task_status, task_span, TaskExecutionTimes};
 use ballista_core::error::BallistaError;
 use ballista_core::serde::scheduler::{ExecutorSpecification, PartitionId};
 use ballista_core::serde::BallistaCodec;
@@ -45,6 +46,7 @@ use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{sync::Arc, time::Duration};
 use tonic::transport::Channel;
+use tracing::Instrument;
 
 pub async fn poll_loop<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>(
     mut scheduler: SchedulerGrpcClient<Channel>,
@@ -169,15 +171,37 @@ async fn run_received_task<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
     );
     info!("Received task {}", task_identity);
 
+    let trace_context: HashMap<String, String> = task
+        .trace_context
+        .into_iter()
+        .map(|kv_pair| (kv_pair.key, kv_pair.value))
+        .collect();
     let mut task_props = HashMap::new();
     for kv_pair in task.props {
         task_props.insert(kv_pair.key, kv_pair.value);
     }
+    let memory_limit = task_props
+        .get(BALLISTA_TASK_MEMORY)
+        .and_then(|memory| memory.parse().ok())
+        .filter(|memory| *memory > 0);
+    let timeout = task_props
+        .get(BALLISTA_TASK_TIMEOUT_SECONDS)
+        .and_then(|timeout| timeout.parse().ok())
+        .filter(|timeout| *timeout > 0)
+        .map(Duration::from_secs);
     let mut config = ConfigOptions::new();
     for (k, v) in task_props {
+        // the ballista settings of the task are not session settings
+        if k.starts_with("ballista.") {
+            continue;
+        }
         config.set(&k, &v)?;
     }
-    let session_config = SessionConfig::from(config);
+    let mut session_config = SessionConfig::from(config);
+    // shuffle readers connect to other executors with these settings
+    if let Some(tls) = &executor.grpc_tls {
+        session_config = session_config.with_extension(Arc::new(tls.clone()));
+    }
 
     let mut task_scalar_functions = HashMap::new();
     let mut task_aggregate_functions = HashMap::new();
@@ -192,7 +216,7 @@ async fn run_received_task<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
     for window_func in executor.window_functions.clone() {
         task_window_functions.insert(window_func.0, window_func.1);
     }
-    let runtime = executor.get_runtime(false);
+    let runtime = executor.get_runtime(false, memory_limit);
     let session_id = task.session_id.clone();
     let task_context = Arc::new(TaskContext::new(
         Some(task_identity.clone()),
@@ -217,7 +241,7 @@ async fn run_received_task<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
         job_id.clone(),
         stage_id as usize,
         plan,
-        &executor.work_dir,
+        executor.shuffle_dir(),
     )?;
     dedicated_executor.spawn(async move {
         use std::panic::AssertUnwindSafe;
@@ -226,13 +250,19 @@ async fn run_received_task<T: 'static + AsLogicalPlan, U: 'static + AsExecutionP
             stage_id: stage_id as usize,
             partition_id: partition_id as usize,
         };
-
-        let execution_result = match AssertUnwindSafe(executor.execute_query_stage(
-            task_id as usize,
-            part.clone(),
-            query_stage_exec.clone(),
-            task_context,
-        ))
+        let span = task_span(task_id as usize, &part, &trace_context);
+
+        let execution_result = match AssertUnwindSafe(
+            executor
+                .execute_query_stage(
+                    task_id as usize,
+                    part.clone(),
+                    query_stage_exec.clone(),
+                    task_context,
+                    timeout,
+                )
+                .instrument(span),
+        )
         .catch_unwind()
         .await
         {
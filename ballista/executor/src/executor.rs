@@ -25,8 +25,10 @@ use ballista_core::error::BallistaError;
 use ballista_core::serde::protobuf;
 use ballista_core::serde::protobuf::ExecutorRegistration;
 use ballista_core::serde::scheduler::PartitionId;
+use ballista_core::utils::GrpcTlsConfig;
 use dashmap::DashMap;
 use datafusion::execution::context::TaskContext;
+use datafusion::execution::memory_pool::{GreedyMemoryPool, MemoryPool};
 use datafusion::execution::runtime_env::RuntimeEnv;
 use datafusion::logical_expr::WindowUDF;
 use datafusion::physical_plan::udaf::AggregateUDF;
@@ -37,6 +39,7 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 pub struct TasksDrainedFuture(pub Arc<Executor>);
 
@@ -52,7 +55,9 @@ impl Future for TasksDrainedFuture {
     }
 }
 
-type AbortHandles = Arc<DashMap<(usize, PartitionId), AbortHandle>>;
+/// Handles to abort the running tasks, with the memory pools the tasks reserve memory in
+type AbortHandles =
+    Arc<DashMap<(usize, PartitionId), (AbortHandle, Arc<dyn MemoryPool>)>>;
 
 /// Ballista executor
 #[derive(Clone)]
@@ -63,6 +68,12 @@ pub struct Executor {
     /// Directory for storing partial results
     pub work_dir: String,
 
+    /// Optional object store URL for storing shuffle files instead of [`work_dir`]
+    pub shuffle_object_store_url: Option<String>,
+
+    /// TLS settings for connecting to the scheduler and other executors
+    pub grpc_tls: Option<GrpcTlsConfig>,
+
     /// Scalar functions that are registered in the Executor
     pub scalar_functions: HashMap<String, Arc<ScalarUDF>>,
 
@@ -108,6 +119,8 @@ impl Executor {
         Self {
             metadata,
             work_dir: work_dir.to_owned(),
+            shuffle_object_store_url: None,
+            grpc_tls: None,
             // TODO add logic to dynamically load UDF/UDAFs libs from files
             scalar_functions: HashMap::new(),
             aggregate_functions: HashMap::new(),
@@ -121,11 +134,32 @@ impl Executor {
                 .unwrap_or_else(|| Arc::new(DefaultExecutionEngine {})),
         }
     }
+
+    /// Write shuffle files to the given object store URL instead of the work dir
+    pub fn with_shuffle_object_store_url(
+        mut self,
+        shuffle_object_store_url: Option<String>,
+    ) -> Self {
+        self.shuffle_object_store_url = shuffle_object_store_url;
+        self
+    }
+
+    /// Connect to the scheduler and other executors with TLS
+    pub fn with_grpc_tls(mut self, grpc_tls: Option<GrpcTlsConfig>) -> Self {
+        self.grpc_tls = grpc_tls;
+        self
+    }
 }
 
 impl Executor {
-    pub fn get_runtime(&self, data_cache: bool) -> Arc<RuntimeEnv> {
-        if data_cache {
+    /// Get the runtime of a task. If `memory_limit` is set, the task gets a memory pool
+    /// of its own which fails its allocations beyond that number of bytes.
+    pub fn get_runtime(
+        &self,
+        data_cache: bool,
+        memory_limit: Option<usize>,
+    ) -> Arc<RuntimeEnv> {
+        let runtime = if data_cache {
             if let Some(runtime) = self.runtime_with_data_cache.clone() {
                 runtime
             } else {
@@ -133,30 +167,71 @@ impl Executor {
             }
         } else {
             self.runtime.clone()
+        };
+        match memory_limit {
+            Some(memory_limit) => Arc::new(RuntimeEnv {
+                memory_pool: Arc::new(GreedyMemoryPool::new(memory_limit)),
+                disk_manager: runtime.disk_manager.clone(),
+                object_store_registry: runtime.object_store_registry.clone(),
+            }),
+            None => runtime,
         }
     }
 
     /// Execute one partition of a query stage and persist the result to disk in IPC format. On
     /// success, return a RecordBatch containing metadata about the results, including path
-    /// and statistics.
+    /// and statistics. If `timeout` is set, the task fails once it has run for that long.
     pub async fn execute_query_stage(
         &self,
         task_id: usize,
         partition: PartitionId,
         query_stage_exec: Arc<dyn QueryStageExecutor>,
         task_ctx: Arc<TaskContext>,
+        timeout: Option<Duration>,
     ) -> Result<Vec<protobuf::ShuffleWritePartition>, BallistaError> {
+        let memory_pool = task_ctx.runtime_env().memory_pool.clone();
         let (task, abort_handle) = futures::future::abortable(
             query_stage_exec.execute_query_stage(partition.partition_id, task_ctx),
         );
 
         self.abort_handles
-            .insert((task_id, partition.clone()), abort_handle);
-
-        let partitions = task.await??;
+            .insert((task_id, partition.clone()), (abort_handle, memory_pool));
+        self.metrics_collector
+            .record_task_started(&partition.job_id, partition.stage_id);
+        let start = Instant::now();
+
+        let partitions =
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, task)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Ok(Err(BallistaError::General(format!(
+                            "Task {task_id} timed out after {timeout:?}"
+                        ))))
+                    }),
+                None => task.await,
+            };
 
         self.abort_handles.remove(&(task_id, partition.clone()));
 
+        // the task is only aborted when it is cancelled
+        let cancelled = partitions.is_err();
+        let partitions = partitions
+            .map_err(BallistaError::from)
+            .and_then(|partitions| partitions);
+        if cancelled {
+            self.metrics_collector
+                .record_task_cancelled(&partition.job_id, partition.stage_id);
+        } else {
+            self.metrics_collector.record_task_finished(
+                &partition.job_id,
+                partition.stage_id,
+                start.elapsed(),
+                partitions.as_deref().ok(),
+            );
+        }
+        let partitions = partitions?;
+
         self.metrics_collector.record_stage(
             &partition.job_id,
             partition.stage_id,
@@ -174,7 +249,7 @@ impl Executor {
         stage_id: usize,
         partition_id: usize,
     ) -> Result<bool, BallistaError> {
-        if let Some((_, handle)) = self.abort_handles.remove(&(
+        if let Some((_, (handle, _))) = self.abort_handles.remove(&(
             task_id,
             PartitionId {
                 job_id,
@@ -193,9 +268,34 @@ impl Executor {
         &self.work_dir
     }
 
+    /// The location query stages write their shuffle files to, either the object store URL
+    /// if one is configured or the work dir
+    pub fn shuffle_dir(&self) -> &str {
+        self.shuffle_object_store_url
+            .as_deref()
+            .unwrap_or(&self.work_dir)
+    }
+
     pub fn active_task_count(&self) -> usize {
         self.abort_handles.len()
     }
+
+    /// Memory reserved by the running tasks, in the memory pool shared by the tasks and
+    /// in the memory pools of their own of the tasks with a memory limit
+    pub fn memory_reserved(&self) -> usize {
+        let mut pools = vec![self.runtime.memory_pool.clone()];
+        for task in self.abort_handles.iter() {
+            let (_, pool) = task.value();
+            // compare the addresses of the pools, without their vtables
+            if !pools
+                .iter()
+                .any(|p| Arc::as_ptr(p) as *const () == Arc::as_ptr(pool) as *const ())
+            {
+                pools.push(pool.clone());
+            }
+        }
+        pools.iter().map(|pool| pool.reserved()).sum()
+    }
 }
 
 #[cfg(test)]
@@ -207,6 +307,7 @@ mod test {
     use ballista_core::execution_plans::ShuffleWriterExec;
     use ballista_core::serde::protobuf::ExecutorRegistration;
     use datafusion::execution::context::TaskContext;
+    use datafusion::execution::memory_pool::MemoryConsumer;
 
     use crate::execution_engine::DefaultQueryStageExec;
     use ballista_core::serde::scheduler::PartitionId;
@@ -216,7 +317,7 @@ mod test {
         DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
         SendableRecordBatchStream, Statistics,
     };
-    use datafusion::prelude::SessionContext;
+    use datafusion::prelude::{SessionConfig, SessionContext};
     use futures::Stream;
     use std::any::Any;
     use std::pin::Pin;
@@ -330,6 +431,7 @@ mod test {
             grpc_port: 0,
             specification: None,
             optional_host: None,
+            labels: vec![],
         };
 
         let ctx = SessionContext::new();
@@ -355,7 +457,13 @@ mod test {
                 partition_id: 0,
             };
             let task_result = executor_clone
-                .execute_query_stage(1, part, Arc::new(query_stage_exec), ctx.task_ctx())
+                .execute_query_stage(
+                    1,
+                    part,
+                    Arc::new(query_stage_exec),
+                    ctx.task_ctx(),
+                    None,
+                )
                 .await;
             sender.send(task_result).expect("sending result");
         });
@@ -384,4 +492,163 @@ mod test {
         let inner_result = result.unwrap().unwrap();
         assert!(inner_result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_task_timeout() {
+        let work_dir = TempDir::new().unwrap();
+        let work_dir = work_dir.path().to_str().unwrap();
+
+        let shuffle_write = ShuffleWriterExec::try_new(
+            "job-id".to_owned(),
+            1,
+            Arc::new(NeverendingOperator),
+            work_dir.to_owned(),
+            None,
+        )
+        .expect("creating shuffle writer");
+
+        let executor_registration = ExecutorRegistration {
+            id: "executor".to_string(),
+            port: 0,
+            grpc_port: 0,
+            specification: None,
+            optional_host: None,
+            labels: vec![],
+        };
+        let ctx = SessionContext::new();
+        let executor = Executor::new(
+            executor_registration,
+            work_dir,
+            ctx.runtime_env(),
+            None,
+            Arc::new(LoggingMetricsCollector {}),
+            2,
+            None,
+        );
+
+        let part = PartitionId {
+            job_id: "job-id".to_owned(),
+            stage_id: 1,
+            partition_id: 0,
+        };
+        let result = executor
+            .execute_query_stage(
+                1,
+                part,
+                Arc::new(DefaultQueryStageExec::new(shuffle_write)),
+                ctx.task_ctx(),
+                Some(Duration::from_millis(100)),
+            )
+            .await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("timed out"), "{error}");
+        // the task can no longer be cancelled
+        assert!(!executor
+            .cancel_task(1, "job-id".to_owned(), 1, 0)
+            .await
+            .unwrap());
+    }
+    #[test]
+    fn test_runtime_memory_limit() {
+        let work_dir = TempDir::new().unwrap();
+        let executor_registration = ExecutorRegistration {
+            id: "executor".to_string(),
+            port: 0,
+            grpc_port: 0,
+            specification: None,
+            optional_host: None,
+            labels: vec![],
+        };
+        let executor = Executor::new(
+            executor_registration,
+            work_dir.path().to_str().unwrap(),
+            SessionContext::new().runtime_env(),
+            None,
+            Arc::new(LoggingMetricsCollector {}),
+            2,
+            None,
+        );
+
+        let runtime = executor.get_runtime(false, Some(1024));
+        let mut reservation = MemoryConsumer::new("task").register(&runtime.memory_pool);
+        assert!(reservation.try_grow(1024).is_ok());
+        assert!(reservation.try_grow(1).is_err());
+
+        // tasks without a limit share the memory pool of the executor
+        let runtime = executor.get_runtime(false, None);
+        assert!(Arc::ptr_eq(&executor.runtime, &runtime));
+    }
+
+    #[tokio::test]
+    async fn test_memory_reserved() {
+        let work_dir = TempDir::new().unwrap();
+        let work_dir = work_dir.path().to_str().unwrap();
+        let executor_registration = ExecutorRegistration {
+            id: "executor".to_string(),
+            port: 0,
+            grpc_port: 0,
+            specification: None,
+            optional_host: None,
+            labels: vec![],
+        };
+        let executor = Executor::new(
+            executor_registration,
+            work_dir,
+            SessionContext::new().runtime_env(),
+            None,
+            Arc::new(LoggingMetricsCollector {}),
+            2,
+            None,
+        );
+
+        let mut shared_reservation =
+            MemoryConsumer::new("shared").register(&executor.runtime.memory_pool);
+        shared_reservation.grow(256);
+
+        // a task with a memory limit reserves memory in a pool of its own
+        let runtime = executor.get_runtime(false, Some(1024));
+        let mut task_reservation =
+            MemoryConsumer::new("task").register(&runtime.memory_pool);
+        task_reservation.grow(512);
+        let ctx = SessionContext::with_config_rt(SessionConfig::new(), runtime);
+        let shuffle_write = ShuffleWriterExec::try_new(
+            "job-id".to_owned(),
+            1,
+            Arc::new(NeverendingOperator),
+            work_dir.to_owned(),
+            None,
+        )
+        .expect("creating shuffle writer");
+
+        let executor_clone = executor.clone();
+        let task = tokio::task::spawn(async move {
+            let part = PartitionId {
+                job_id: "job-id".to_owned(),
+                stage_id: 1,
+                partition_id: 0,
+            };
+            executor_clone
+                .execute_query_stage(
+                    1,
+                    part,
+                    Arc::new(DefaultQueryStageExec::new(shuffle_write)),
+                    ctx.task_ctx(),
+                    None,
+                )
+                .await
+        });
+        while executor.active_task_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(768, executor.memory_reserved());
+
+        assert!(executor
+            .cancel_task(1, "job-id".to_owned(), 1, 0)
+            .await
+            .unwrap());
+        assert!(task.await.unwrap().is_err());
+        assert_eq!(256, executor.memory_reserved());
+    }
 }
@@ -17,6 +17,8 @@
 
 //! Ballista Executor Process
 
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -27,13 +29,19 @@ use anyhow::{Context, Result};
 use arrow_flight::flight_service_server::FlightServiceServer;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use log::{error, info, warn};
+use sysinfo::{System, SystemExt};
 use tempfile::TempDir;
 use tokio::fs::DirEntry;
 use tokio::signal;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::{fs, time};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
@@ -54,11 +62,13 @@ use ballista_core::serde::protobuf::executor_status::Status;
 use ballista_core::serde::protobuf::{
     executor_registration, scheduler_grpc_client::SchedulerGrpcClient,
     ExecutorRegistration, ExecutorResource, ExecutorSpecification, ExecutorStatus,
-    ExecutorStoppedParams, HeartBeatParams,
+    ExecutorStoppedParams, HeartBeatParams, KeyValuePair,
 };
 use ballista_core::serde::BallistaCodec;
+use ballista_core::telemetry;
 use ballista_core::utils::{
     create_grpc_client_connection, create_grpc_server, get_time_before,
+    parse_object_store_location, GrpcTlsConfig,
 };
 use ballista_core::BALLISTA_VERSION;
 
@@ -66,7 +76,7 @@ use crate::execution_engine::ExecutionEngine;
 use crate::executor::{Executor, TasksDrainedFuture};
 use crate::executor_server::TERMINATING;
 use crate::flight_service::BallistaFlightService;
-use crate::metrics::LoggingMetricsCollector;
+use crate::metrics::default_metrics_collector;
 use crate::shutdown::Shutdown;
 use crate::shutdown::ShutdownNotifier;
 use crate::terminate;
@@ -77,17 +87,31 @@ pub struct ExecutorProcessConfig {
     pub external_host: Option<String>,
     pub port: u16,
     pub grpc_port: u16,
+    /// Port of the HTTP endpoint serving the executor metrics, disabled if 0
+    pub metrics_port: u16,
     pub scheduler_host: String,
     pub scheduler_port: u16,
     pub scheduler_connect_timeout_seconds: u16,
     pub concurrent_tasks: usize,
+    /// Memory in bytes which can be reserved by tasks, the total memory of the host
+    /// if 0
+    pub memory: u64,
+    /// Number of CPUs which can be reserved by tasks, all the cores of the host if 0
+    pub cpus: usize,
+    /// Labels of the executor, which jobs select the executors running their tasks by
+    pub labels: HashMap<String, String>,
     pub task_scheduling_policy: TaskSchedulingPolicy,
     pub log_dir: Option<String>,
     pub work_dir: Option<String>,
+    /// Optional object store URL that shuffle files are written to instead of `work_dir`
+    pub shuffle_object_store_url: Option<String>,
     pub special_mod_log_level: String,
     pub print_thread_info: bool,
     pub log_file_name_prefix: String,
     pub log_rotation_policy: LogRotationPolicy,
+    /// Endpoint of the OpenTelemetry collector which the spans of the tasks are
+    /// exported to, no spans are exported if `None`
+    pub otlp_endpoint: Option<String>,
     pub job_data_ttl_seconds: u64,
     pub job_data_clean_up_interval_seconds: u64,
     pub data_cache_policy: Option<DataCachePolicy>,
@@ -96,6 +120,9 @@ pub struct ExecutorProcessConfig {
     pub cache_io_concurrency: u32,
     /// The maximum size of a decoded message at the grpc server side.
     pub grpc_server_max_decoding_message_size: u32,
+    /// TLS settings of the gRPC and Flight services, and of the connections to the
+    /// scheduler and other executors. Plaintext is used if `None`
+    pub grpc_tls: Option<GrpcTlsConfig>,
     pub executor_heartbeat_interval_seconds: u64,
     /// Optional execution engine to use to execute physical plans, will default to
     /// DataFusion if none is provided.
@@ -128,6 +155,11 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
             .with_thread_ids(opt.print_thread_info)
             .with_writer(log_file)
             .with_env_filter(log_filter)
+            .finish()
+            .with(telemetry::otlp_layer(
+                "ballista-executor",
+                opt.otlp_endpoint.as_deref(),
+            )?)
             .init();
     } else {
         // Console layer
@@ -137,6 +169,11 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
             .with_thread_ids(opt.print_thread_info)
             .with_writer(io::stdout)
             .with_env_filter(log_filter)
+            .finish()
+            .with(telemetry::otlp_layer(
+                "ballista-executor",
+                opt.otlp_endpoint.as_deref(),
+            )?)
             .init();
     }
 
@@ -164,9 +201,47 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
         opt.concurrent_tasks
     };
 
+    let memory = if opt.memory == 0 {
+        let mut system = System::new();
+        system.refresh_memory();
+        system.total_memory()
+    } else {
+        opt.memory
+    };
+
+    let cpus = if opt.cpus == 0 {
+        num_cpus::get()
+    } else {
+        opt.cpus
+    };
+
     info!("Running with config:");
     info!("work_dir: {}", work_dir);
     info!("concurrent_tasks: {}", concurrent_tasks);
+    info!("memory: {}", memory);
+    info!("cpus: {}", cpus);
+    info!("labels: {:?}", opt.labels);
+
+    let resources = vec![
+        ExecutorResource {
+            resource: Some(Resource::TaskSlots(concurrent_tasks as u32)),
+        },
+        ExecutorResource {
+            resource: Some(Resource::Memory(memory)),
+        },
+        ExecutorResource {
+            resource: Some(Resource::Cpus(cpus as u32)),
+        },
+    ];
+
+    let labels: Vec<KeyValuePair> = opt
+        .labels
+        .iter()
+        .map(|(key, value)| KeyValuePair {
+            key: key.clone(),
+            value: value.clone(),
+        })
+        .collect();
 
     // assign this executor an unique ID
     let executor_id = Uuid::new_v4().to_string();
@@ -179,10 +254,9 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
         port: opt.port as u32,
         grpc_port: opt.grpc_port as u32,
         specification: Some(ExecutorSpecification {
-            resources: vec![ExecutorResource {
-                resource: Some(Resource::TaskSlots(concurrent_tasks as u32)),
-            }],
+            resources: resources.clone(),
         }),
+        labels: labels.clone(),
     };
 
     let config = RuntimeConfig::new().with_temp_file_path(work_dir.clone());
@@ -195,55 +269,88 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
 
     // Set the object store registry
     #[cfg(not(windows))]
-    let runtime_with_data_cache = {
+    let data_cache = {
         let cache_dir = opt.cache_dir.clone();
         let cache_capacity = opt.cache_capacity;
         let cache_io_concurrency = opt.cache_io_concurrency;
-        let cache_layer =
-            opt.data_cache_policy
-                .map(|data_cache_policy| match data_cache_policy {
-                    DataCachePolicy::LocalDiskFile => {
-                        let cache_dir = cache_dir.unwrap();
-                        let cache_layer = FileCacheLayer::new(
-                            cache_capacity as usize,
-                            cache_io_concurrency,
-                            LocalDiskMedium::new(cache_dir),
-                        );
-                        CacheLayer::LocalDiskFile(Arc::new(cache_layer))
-                    }
-                });
-        if let Some(cache_layer) = cache_layer {
-            let registry = Arc::new(CachedBasedObjectStoreRegistry::new(
-                runtime.object_store_registry.clone(),
-                cache_layer,
-            ));
-            Some(Arc::new(RuntimeEnv {
-                memory_pool: runtime.memory_pool.clone(),
-                disk_manager: runtime.disk_manager.clone(),
-                object_store_registry: registry,
-            }))
-        } else {
-            None
-        }
+        opt.data_cache_policy
+            .map(|data_cache_policy| match data_cache_policy {
+                DataCachePolicy::LocalDiskFile => {
+                    let cache_dir = cache_dir.unwrap();
+                    let cache_layer = FileCacheLayer::new(
+                        cache_capacity as usize,
+                        cache_io_concurrency,
+                        LocalDiskMedium::new(cache_dir),
+                    );
+                    CacheLayer::LocalDiskFile(Arc::new(cache_layer))
+                }
+            })
     };
+    #[cfg(not(windows))]
+    let runtime_with_data_cache = data_cache.clone().map(|cache_layer| {
+        let registry = Arc::new(CachedBasedObjectStoreRegistry::new(
+            runtime.object_store_registry.clone(),
+            cache_layer,
+        ));
+        Arc::new(RuntimeEnv {
+            memory_pool: runtime.memory_pool.clone(),
+            disk_manager: runtime.disk_manager.clone(),
+            object_store_registry: registry,
+        })
+    });
     #[cfg(windows)]
     let runtime_with_data_cache = { None };
 
-    let metrics_collector = Arc::new(LoggingMetricsCollector::default());
+    // Running totals of the hits and misses of the data cache
+    #[cfg(not(windows))]
+    let data_cache_counts = data_cache.map(|cache_layer| -> DataCacheCounts {
+        Arc::new(move || match &cache_layer {
+            CacheLayer::LocalDiskFile(cache_layer) => (
+                cache_layer.metrics().get_hit_count(),
+                cache_layer.metrics().get_miss_count(),
+            ),
+            CacheLayer::LocalMemoryFile(cache_layer) => (
+                cache_layer.metrics().get_hit_count(),
+                cache_layer.metrics().get_miss_count(),
+            ),
+        })
+    });
+    #[cfg(windows)]
+    let data_cache_counts = None;
+
+    if let Some(shuffle_object_store_url) = &opt.shuffle_object_store_url {
+        let (store_url, _) = parse_object_store_location(shuffle_object_store_url)
+            .ok_or_else(|| {
+                BallistaError::General(format!(
+                    "Invalid shuffle object store URL: {shuffle_object_store_url}"
+                ))
+            })?;
+        // fail fast if there is no object store registered for the URL
+        runtime
+            .object_store(&store_url)
+            .context("Could not find object store for shuffle files")?;
+        info!("shuffle_object_store_url: {}", shuffle_object_store_url);
+    }
 
-    let executor = Arc::new(Executor::new(
-        executor_meta,
-        &work_dir,
-        runtime,
-        runtime_with_data_cache,
-        metrics_collector,
-        concurrent_tasks,
-        opt.execution_engine.clone(),
-    ));
+    let metrics_collector = default_metrics_collector()?;
+
+    let executor = Arc::new(
+        Executor::new(
+            executor_meta,
+            &work_dir,
+            runtime,
+            runtime_with_data_cache,
+            metrics_collector,
+            concurrent_tasks,
+            opt.execution_engine.clone(),
+        )
+        .with_shuffle_object_store_url(opt.shuffle_object_store_url.clone())
+        .with_grpc_tls(opt.grpc_tls.clone()),
+    );
 
     let connect_timeout = opt.scheduler_connect_timeout_seconds as u64;
     let connection = if connect_timeout == 0 {
-        create_grpc_client_connection(scheduler_url)
+        create_grpc_client_connection(scheduler_url, opt.grpc_tls.as_ref())
             .await
             .context("Could not connect to scheduler")
     } else {
@@ -255,9 +362,12 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
         while x.is_none()
             && Instant::now().elapsed().as_secs() - start_time < connect_timeout
         {
-            match create_grpc_client_connection(scheduler_url.clone())
-                .await
-                .context("Could not connect to scheduler")
+            match create_grpc_client_connection(
+                scheduler_url.clone(),
+                opt.grpc_tls.as_ref(),
+            )
+            .await
+            .context("Could not connect to scheduler")
             {
                 Ok(conn) => {
                     info!("Connected to scheduler at {}", scheduler_url);
@@ -353,8 +463,21 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
     };
     service_handlers.push(tokio::spawn(flight_server_run(
         addr,
+        opt.grpc_tls.clone(),
         shutdown_noti.subscribe_for_shutdown(),
     )));
+    if opt.metrics_port > 0 {
+        let metrics_addr = format!("{}:{}", opt.bind_host, opt.metrics_port);
+        let metrics_addr = metrics_addr
+            .parse()
+            .with_context(|| format!("Could not parse address: {metrics_addr}"))?;
+        service_handlers.push(tokio::spawn(metrics_server_run(
+            metrics_addr,
+            executor.clone(),
+            data_cache_counts,
+            shutdown_noti.subscribe_for_shutdown(),
+        )));
+    }
 
     let tasks_drained = TasksDrainedFuture(executor);
 
@@ -404,11 +527,8 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
                         .map(executor_registration::OptionalHost::Host),
                     port: opt.port as u32,
                     grpc_port: opt.grpc_port as u32,
-                    specification: Some(ExecutorSpecification {
-                        resources: vec![ExecutorResource {
-                            resource: Some(Resource::TaskSlots(concurrent_tasks as u32)),
-                        }],
-                    }),
+                    specification: Some(ExecutorSpecification { resources }),
+                    labels,
                 }),
             })
             .await
@@ -450,12 +570,14 @@ pub async fn start_executor_process(opt: Arc<ExecutorProcessConfig>) -> Result<(
     // Wait for all related components to finish the shutdown processing.
     let _ = shutdown_complete_rx.recv().await;
     info!("Executor stopped.");
+    telemetry::shutdown();
     Ok(())
 }
 
 // Arrow flight service
 async fn flight_server_run(
     addr: SocketAddr,
+    tls: Option<GrpcTlsConfig>,
     mut grpc_shutdown: Shutdown,
 ) -> Result<(), BallistaError> {
     let service = BallistaFlightService::new();
@@ -466,7 +588,7 @@ async fn flight_server_run(
     );
 
     let shutdown_signal = grpc_shutdown.recv();
-    let server_future = create_grpc_server()
+    let server_future = create_grpc_server(tls.as_ref())?
         .add_service(server)
         .serve_with_shutdown(addr, shutdown_signal);
 
@@ -476,6 +598,78 @@ async fn flight_server_run(
     })
 }
 
+/// Returns the running totals of the hits and misses of the data cache
+type DataCacheCounts = Arc<dyn Fn() -> (u64, u64) + Send + Sync>;
+
+// HTTP endpoint serving the executor metrics at /metrics
+async fn metrics_server_run(
+    addr: SocketAddr,
+    executor: Arc<Executor>,
+    data_cache_counts: Option<DataCacheCounts>,
+    mut shutdown: Shutdown,
+) -> Result<(), BallistaError> {
+    let make_service = make_service_fn(move |_| {
+        let executor = executor.clone();
+        let data_cache_counts = data_cache_counts.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let response = if request.method() == Method::GET
+                    && request.uri().path() == "/metrics"
+                {
+                    metrics_response(&executor, data_cache_counts.as_ref())
+                } else {
+                    status_response(StatusCode::NOT_FOUND)
+                };
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    info!(
+        "Ballista v{} Rust Executor Metrics Server listening on {:?}",
+        BALLISTA_VERSION, addr
+    );
+    Server::try_bind(&addr)
+        .map_err(|e| {
+            BallistaError::General(format!("Could not start metrics server: {e:?}"))
+        })?
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown.recv())
+        .await
+        .map_err(|e| BallistaError::General(format!("Metrics server error: {e:?}")))
+}
+
+fn metrics_response(
+    executor: &Executor,
+    data_cache_counts: Option<&DataCacheCounts>,
+) -> Response<Body> {
+    let metrics_collector = &executor.metrics_collector;
+    // the memory usage and data cache figures are sampled when they are scraped
+    metrics_collector.record_memory_reserved(executor.memory_reserved());
+    if let Some(data_cache_counts) = data_cache_counts {
+        let (hits, misses) = data_cache_counts();
+        metrics_collector.record_data_cache(hits, misses);
+    }
+
+    match metrics_collector.gather_metrics() {
+        Ok(Some((buffer, content_type))) => Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(buffer))
+            .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR)),
+        Ok(None) => status_response(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Error gathering executor metrics: {:?}", e);
+            status_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
 // Check the status of long running services
 async fn check_services(
     service_handlers: &mut FuturesUnordered<JoinHandle<Result<(), BallistaError>>>,
@@ -604,10 +798,22 @@ pub async fn satisfy_dir_ttl(dir: DirEntry, ttl_seconds: u64) -> Result<bool> {
 
 #[cfg(test)]
 mod tests {
-    use super::clean_shuffle_data_loop;
+    use super::{clean_shuffle_data_loop, flight_server_run};
+    use crate::shutdown::ShutdownNotifier;
+    use ballista_core::client::BallistaClient;
+    use ballista_core::config::ShuffleCompression;
+    use ballista_core::serde::scheduler::PartitionId;
+    use ballista_core::utils::GrpcTlsConfig;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::arrow::ipc::writer::FileWriter;
+    use datafusion::arrow::record_batch::RecordBatch;
+    use futures::TryStreamExt;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
     use std::fs;
     use std::fs::File;
     use std::io::Write;
+    use std::sync::Arc;
     use std::time::Duration;
     use tempfile::TempDir;
 
@@ -639,4 +845,131 @@ mod tests {
         let count2 = fs::read_dir(work_dir.clone()).unwrap().count();
         assert_eq!(count2, 0);
     }
+
+    struct TestCerts {
+        ca_cert: Vec<u8>,
+        server: (Vec<u8>, Vec<u8>),
+        client: (Vec<u8>, Vec<u8>),
+    }
+
+    /// Generate a CA, and a server and client certificate signed by it
+    fn generate_certs() -> TestCerts {
+        let mut ca_params = CertificateParams::new(vec![]);
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = Certificate::from_params(ca_params).unwrap();
+        let signed = |names: Vec<String>| {
+            let cert = Certificate::from_params(CertificateParams::new(names)).unwrap();
+            (
+                cert.serialize_pem_with_signer(&ca).unwrap().into_bytes(),
+                cert.serialize_private_key_pem().into_bytes(),
+            )
+        };
+        TestCerts {
+            ca_cert: ca.serialize_pem().unwrap().into_bytes(),
+            server: signed(vec!["localhost".to_string()]),
+            client: signed(vec!["client".to_string()]),
+        }
+    }
+
+    /// Write a shuffle file with a single batch of 3 rows
+    fn write_shuffle_file(dir: &TempDir) -> String {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap();
+        let path = dir.path().join("data.arrow");
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// Start a Flight server on a free port and return the port
+    async fn start_flight_server(tls: GrpcTlsConfig, shutdown: &ShutdownNotifier) -> u16 {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = format!("127.0.0.1:{port}").parse().unwrap();
+        tokio::spawn(flight_server_run(
+            addr,
+            Some(tls),
+            shutdown.subscribe_for_shutdown(),
+        ));
+        // wait for the server to accept connections
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        port
+    }
+
+    async fn fetch_rows(
+        port: u16,
+        path: &str,
+        tls: Option<&GrpcTlsConfig>,
+    ) -> ballista_core::error::Result<usize> {
+        let mut client = BallistaClient::try_new("127.0.0.1", port, tls).await?;
+        let batches: Vec<RecordBatch> = client
+            .fetch_partition(
+                "executor",
+                &PartitionId::new("job", 1, 0),
+                path,
+                "127.0.0.1",
+                port,
+                ShuffleCompression::None,
+                None,
+            )
+            .await?
+            .try_collect()
+            .await?;
+        Ok(batches.iter().map(|b| b.num_rows()).sum())
+    }
+
+    #[tokio::test]
+    async fn test_flight_server_tls() {
+        let certs = generate_certs();
+        let dir = TempDir::new().unwrap();
+        let path = write_shuffle_file(&dir);
+        let shutdown = ShutdownNotifier::new();
+        let (cert, key) = certs.server;
+        let port =
+            start_flight_server(GrpcTlsConfig::new().with_identity(cert, key), &shutdown)
+                .await;
+
+        let client_tls = GrpcTlsConfig::new()
+            .with_ca_cert(certs.ca_cert)
+            .with_domain("localhost");
+        assert_eq!(fetch_rows(port, &path, Some(&client_tls)).await.unwrap(), 3);
+        assert!(fetch_rows(port, &path, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flight_server_mutual_tls() {
+        let certs = generate_certs();
+        let dir = TempDir::new().unwrap();
+        let path = write_shuffle_file(&dir);
+        let shutdown = ShutdownNotifier::new();
+        let (cert, key) = certs.server;
+        let server_tls = GrpcTlsConfig::new()
+            .with_identity(cert, key)
+            .with_ca_cert(certs.ca_cert.clone())
+            .with_client_auth(true);
+        let port = start_flight_server(server_tls, &shutdown).await;
+
+        let client_tls = GrpcTlsConfig::new()
+            .with_ca_cert(certs.ca_cert)
+            .with_domain("localhost");
+        assert!(fetch_rows(port, &path, Some(&client_tls)).await.is_err());
+
+        let (cert, key) = certs.client;
+        let client_tls = client_tls.with_identity(cert, key);
+        assert_eq!(fetch_rows(port, &path, Some(&client_tls)).await.unwrap(), 3);
+    }
 }
@@ -27,12 +27,15 @@ use tokio::sync::mpsc;
 use log::{debug, error, info, warn};
 use tonic::transport::Channel;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
-use ballista_core::config::BALLISTA_DATA_CACHE_ENABLED;
+use ballista_core::config::{
+    BALLISTA_DATA_CACHE_ENABLED, BALLISTA_TASK_MEMORY, BALLISTA_TASK_TIMEOUT_SECONDS,
+};
 use ballista_core::error::BallistaError;
 use ballista_core::serde::protobuf::{
     executor_grpc_server::{ExecutorGrpc, ExecutorGrpcServer},
-    executor_metric, executor_status,
+    executor_status,
     scheduler_grpc_client::SchedulerGrpcClient,
     CancelTasksParams, CancelTasksResult, ExecutorMetric, ExecutorStatus,
     HeartBeatParams, LaunchMultiTaskParams, LaunchMultiTaskResult, LaunchTaskParams,
@@ -45,7 +48,9 @@ use ballista_core::serde::scheduler::from_proto::{
 use ballista_core::serde::scheduler::PartitionId;
 use ballista_core::serde::scheduler::TaskDefinition;
 use ballista_core::serde::BallistaCodec;
-use ballista_core::utils::{create_grpc_client_connection, create_grpc_server};
+use ballista_core::utils::{
+    create_grpc_client_connection, create_grpc_server, remove_object_store_job_data,
+};
 use dashmap::DashMap;
 use datafusion::config::ConfigOptions;
 use datafusion::execution::TaskContext;
@@ -57,8 +62,9 @@ use tokio::task::JoinHandle;
 use crate::cpu_bound_executor::DedicatedExecutor;
 use crate::executor::Executor;
 use crate::executor_process::ExecutorProcessConfig;
+use crate::metrics::SystemMetrics;
 use crate::shutdown::ShutdownNotifier;
-use crate::{as_task_status, TaskExecutionTimes};
+use crate::{as_task_status, task_span, TaskExecutionTimes};
 
 type ServerHandle = JoinHandle<Result<(), BallistaError>>;
 type SchedulerClients = Arc<DashMap<String, SchedulerGrpcClient<Channel>>>;
@@ -90,6 +96,11 @@ pub async fn startup<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>(
     let (tx_task_status, rx_task_status) =
         mpsc::channel::<CuratorTaskStatus>(channel_buf_size);
 
+    let system_metrics = SystemMetrics::start(
+        executor.work_dir(),
+        Duration::from_secs(config.executor_heartbeat_interval_seconds),
+        shutdown_noti,
+    );
     let executor_server = ExecutorServer::new(
         scheduler.clone(),
         executor.clone(),
@@ -99,6 +110,7 @@ pub async fn startup<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>(
             tx_stop: stop_send,
         },
         codec,
+        system_metrics,
     );
 
     // 1. Start executor grpc service
@@ -116,9 +128,10 @@ pub async fn startup<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>(
                 config.grpc_server_max_decoding_message_size as usize,
             );
         let mut grpc_shutdown = shutdown_noti.subscribe_for_shutdown();
+        let tls = config.grpc_tls.clone();
         tokio::spawn(async move {
             let shutdown_signal = grpc_shutdown.recv();
-            let grpc_server_future = create_grpc_server()
+            let grpc_server_future = create_grpc_server(tls.as_ref())?
                 .add_service(server)
                 .serve_with_shutdown(addr, shutdown_signal);
             grpc_server_future.await.map_err(|e| {
@@ -185,6 +198,7 @@ pub struct ExecutorServer<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPl
     codec: BallistaCodec<T, U>,
     scheduler_to_register: SchedulerGrpcClient<Channel>,
     schedulers: SchedulerClients,
+    system_metrics: SystemMetrics,
 }
 
 #[derive(Clone)]
@@ -209,6 +223,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
         executor: Arc<Executor>,
         executor_env: ExecutorEnv,
         codec: BallistaCodec<T, U>,
+        system_metrics: SystemMetrics,
     ) -> Self {
         Self {
             _start_time: SystemTime::now()
@@ -220,6 +235,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
             codec,
             scheduler_to_register,
             schedulers: Default::default(),
+            system_metrics,
         }
     }
 
@@ -233,7 +249,11 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
             Ok(scheduler)
         } else {
             let scheduler_url = format!("http://{scheduler_id}");
-            let connection = create_grpc_client_connection(scheduler_url).await?;
+            let connection = create_grpc_client_connection(
+                scheduler_url,
+                self.executor.grpc_tls.as_ref(),
+            )
+            .await?;
             let scheduler = SchedulerGrpcClient::new(connection);
 
             {
@@ -322,6 +342,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
             partition_id,
         };
 
+        let span = task_span(task_id, &part, &task.trace_context);
+
         let query_stage_exec = self
             .executor
             .execution_engine
@@ -329,29 +351,43 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
                 job_id.clone(),
                 stage_id,
                 plan,
-                &self.executor.work_dir,
+                self.executor.shuffle_dir(),
             )
             .unwrap();
 
+        let task_props = task.props;
+        let timeout = task_props
+            .get(BALLISTA_TASK_TIMEOUT_SECONDS)
+            .and_then(|timeout| timeout.parse().ok())
+            .filter(|timeout| *timeout > 0)
+            .map(Duration::from_secs);
+
         let task_context = {
-            let task_props = task.props;
             let data_cache = task_props
                 .get(BALLISTA_DATA_CACHE_ENABLED)
                 .map(|data_cache| data_cache.parse().unwrap_or(false))
                 .unwrap_or(false);
+            let memory_limit = task_props
+                .get(BALLISTA_TASK_MEMORY)
+                .and_then(|memory| memory.parse().ok())
+                .filter(|memory| *memory > 0);
             let mut config = ConfigOptions::new();
             for (k, v) in task_props.iter() {
                 if let Err(e) = config.set(k, v) {
                     debug!("Fail to set session config for ({},{}): {:?}", k, v, e);
                 }
             }
-            let session_config = SessionConfig::from(config);
+            let mut session_config = SessionConfig::from(config);
+            // shuffle readers connect to other executors with these settings
+            if let Some(tls) = &self.executor.grpc_tls {
+                session_config = session_config.with_extension(Arc::new(tls.clone()));
+            }
 
             let function_registry = task.function_registry;
             if data_cache {
                 info!("Data cache will be enabled for {}", task_identity);
             }
-            let runtime = self.executor.get_runtime(data_cache);
+            let runtime = self.executor.get_runtime(data_cache, memory_limit);
 
             Arc::new(TaskContext::new(
                 Some(task_identity.clone()),
@@ -373,7 +409,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
                 part.clone(),
                 query_stage_exec.clone(),
                 task_context,
+                timeout,
             )
+            .instrument(span)
             .await;
         info!("Done with task {}", task_identity);
         debug!("Statistics: {:?}", execution_result);
@@ -420,13 +458,10 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorServer<T,
             .unwrap();
     }
 
-    // TODO populate with real metrics
     fn get_executor_metrics(&self) -> Vec<ExecutorMetric> {
-        let available_memory = ExecutorMetric {
-            metric: Some(executor_metric::Metric::AvailableMemory(u64::MAX)),
-        };
-        let executor_metrics = vec![available_memory];
-        executor_metrics
+        self.system_metrics
+            .get(self.executor.active_task_count())
+            .into()
     }
 }
 
@@ -640,7 +675,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorGrpc
                     scheduler_id: scheduler_id.clone(),
                     task: get_task_definition(
                         task,
-                        self.executor.get_runtime(false),
+                        self.executor.get_runtime(false, None),
                         self.executor.scalar_functions.clone(),
                         self.executor.aggregate_functions.clone(),
                         self.executor.window_functions.clone(),
@@ -668,7 +703,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorGrpc
         for multi_task in multi_tasks {
             let multi_task: Vec<TaskDefinition> = get_task_definition_vec(
                 multi_task,
-                self.executor.get_runtime(false),
+                self.executor.get_runtime(false, None),
                 self.executor.scalar_functions.clone(),
                 self.executor.aggregate_functions.clone(),
                 self.executor.window_functions.clone(),
@@ -745,6 +780,20 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> ExecutorGrpc
     ) -> Result<Response<RemoveJobDataResult>, Status> {
         let job_id = request.into_inner().job_id;
 
+        if let Some(shuffle_object_store_url) = &self.executor.shuffle_object_store_url {
+            info!(
+                "Remove shuffle files for job {:?} from object store",
+                job_id
+            );
+            remove_object_store_job_data(
+                shuffle_object_store_url,
+                &job_id,
+                self.executor.get_runtime(false, None).as_ref(),
+            )
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        }
+
         let work_dir = PathBuf::from(&self.executor.work_dir);
         let mut path = work_dir.clone();
         path.push(&job_id);
@@ -23,6 +23,7 @@ use std::pin::Pin;
 
 use arrow_flight::SchemaAsIpc;
 use ballista_core::error::BallistaError;
+use ballista_core::execution_plans::read_sort_shuffle_partition;
 use ballista_core::serde::decode_protobuf;
 use ballista_core::serde::scheduler::Action as BallistaAction;
 
@@ -33,11 +34,11 @@ use arrow_flight::{
     PutResult, SchemaResult, Ticket,
 };
 use datafusion::arrow::{
-    error::ArrowError, ipc::reader::FileReader, record_batch::RecordBatch,
+    datatypes::SchemaRef, error::ArrowError, ipc::reader::FileReader,
+    record_batch::RecordBatch,
 };
 use futures::{Stream, StreamExt};
 use log::{debug, info, warn};
-use std::io::{Read, Seek};
 use tokio::sync::mpsc::channel;
 use tokio::{
     sync::mpsc::{Receiver, Sender},
@@ -49,6 +50,7 @@ use tonic::{Request, Response, Status, Streaming};
 
 type FlightDataSender = Sender<Result<FlightData, Status>>;
 type FlightDataReceiver = Receiver<Result<FlightData, Status>>;
+type BatchIterator = Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>> + Send>;
 
 /// Service implementing the Apache Arrow Flight Protocol
 #[derive(Clone)]
@@ -89,17 +91,40 @@ impl FlightService for BallistaFlightService {
             decode_protobuf(&ticket.ticket).map_err(|e| from_ballista_err(&e))?;
 
         match &action {
-            BallistaAction::FetchPartition { path, .. } => {
+            BallistaAction::FetchPartition {
+                path,
+                partition_id,
+                compression,
+                index_path,
+                ..
+            } => {
                 debug!("FetchPartition reading {}", path);
-                let file = File::open(path)
-                    .map_err(|e| {
-                        BallistaError::General(format!(
-                            "Failed to open partition file at {path}: {e:?}"
-                        ))
-                    })
-                    .map_err(|e| from_ballista_err(&e))?;
-                let reader =
-                    FileReader::try_new(file, None).map_err(|e| from_arrow_err(&e))?;
+                let (schema, batches): (SchemaRef, BatchIterator) = match index_path {
+                    // only the byte range of the partition is read from a sort-based
+                    // shuffle file
+                    Some(index_path) => {
+                        let reader =
+                            read_sort_shuffle_partition(path, index_path, *partition_id)
+                                .map_err(|e| from_ballista_err(&e))?;
+                        (reader.schema(), Box::new(reader))
+                    }
+                    None => {
+                        let file = File::open(path)
+                            .map_err(|e| {
+                                BallistaError::General(format!(
+                                    "Failed to open partition file at {path}: {e:?}"
+                                ))
+                            })
+                            .map_err(|e| from_ballista_err(&e))?;
+                        let reader = FileReader::try_new(file, None)
+                            .map_err(|e| from_arrow_err(&e))?;
+                        (reader.schema(), Box::new(reader))
+                    }
+                };
+                // send the record batches compressed like they are in the shuffle file
+                let options = compression
+                    .ipc_write_options()
+                    .map_err(|e| from_arrow_err(&e))?;
 
                 let (tx, rx): (FlightDataSender, FlightDataReceiver) = channel(2);
 
@@ -107,7 +132,9 @@ impl FlightService for BallistaFlightService {
                 // Arrow IPC reader does not implement Sync + Send so we need to use a channel
                 // to communicate
                 task::spawn(async move {
-                    if let Err(e) = stream_flight_data(file_path, reader, tx).await {
+                    if let Err(e) =
+                        stream_flight_data(file_path, schema, batches, options, tx).await
+                    {
                         warn!("Error streaming results: {:?}", e);
                     }
                 });
@@ -223,20 +250,18 @@ fn create_flight_iter(
     }
 }
 
-async fn stream_flight_data<T>(
+async fn stream_flight_data(
     file_path: String,
-    reader: FileReader<T>,
+    schema: SchemaRef,
+    batches: BatchIterator,
+    options: IpcWriteOptions,
     tx: FlightDataSender,
-) -> Result<(), Status>
-where
-    T: Read + Seek,
-{
-    let options = arrow::ipc::writer::IpcWriteOptions::default();
-    let schema_flight_data = SchemaAsIpc::new(reader.schema().as_ref(), &options).into();
+) -> Result<(), Status> {
+    let schema_flight_data = SchemaAsIpc::new(schema.as_ref(), &options).into();
     send_response(&tx, Ok(schema_flight_data)).await?;
 
     let mut row_count = 0;
-    for batch in reader {
+    for batch in batches {
         if let Ok(x) = &batch {
             row_count += x.num_rows();
         }
@@ -33,13 +33,17 @@ mod standalone;
 
 pub use standalone::new_standalone_executor;
 
+use std::collections::HashMap;
+
 use log::info;
+use tracing::{info_span, Span};
 
 use ballista_core::serde::protobuf::{
     task_status, FailedTask, OperatorMetricsSet, ShuffleWritePartition, SuccessfulTask,
     TaskStatus,
 };
 use ballista_core::serde::scheduler::PartitionId;
+use ballista_core::telemetry;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TaskExecutionTimes {
@@ -48,6 +52,24 @@ pub struct TaskExecutionTimes {
     end_exec_time: u64,
 }
 
+/// Span of a task, the child of the span of its stage on the scheduler
+fn task_span(
+    task_id: usize,
+    partition_id: &PartitionId,
+    trace_context: &HashMap<String, String>,
+) -> Span {
+    let span = info_span!(
+        parent: None,
+        "task",
+        job_id = %partition_id.job_id,
+        stage_id = partition_id.stage_id,
+        partition_id = partition_id.partition_id,
+        task_id,
+    );
+    telemetry::set_parent(&span, trace_context);
+    span
+}
+
 pub fn as_task_status(
     execution_result: ballista_core::error::Result<Vec<ShuffleWritePartition>>,
     executor_id: String,
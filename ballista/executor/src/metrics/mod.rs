@@ -15,9 +15,20 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+mod system;
+
 use crate::execution_engine::QueryStageExecutor;
+#[cfg(feature = "prometheus")]
+use crate::metrics::prometheus::PrometheusMetricsCollector;
+use ballista_core::error::Result;
+use ballista_core::serde::protobuf::ShuffleWritePartition;
 use log::info;
 use std::sync::Arc;
+use std::time::Duration;
+
+pub use system::{SystemMetrics, SystemMetricsCollector};
 
 /// `ExecutorMetricsCollector` records metrics for `ShuffleWriteExec`
 /// after they are executed.
@@ -33,6 +44,36 @@ pub trait ExecutorMetricsCollector: Send + Sync {
         partition: usize,
         plan: Arc<dyn QueryStageExecutor>,
     );
+
+    /// Record that a task of a stage started running
+    fn record_task_started(&self, _job_id: &str, _stage_id: usize) {}
+
+    /// Record that a task of a stage finished after running for `duration`. `output`
+    /// holds the shuffle partitions written by the task if it succeeded.
+    fn record_task_finished(
+        &self,
+        _job_id: &str,
+        _stage_id: usize,
+        _duration: Duration,
+        _output: Option<&[ShuffleWritePartition]>,
+    ) {
+    }
+
+    /// Record that a running task of a stage was cancelled
+    fn record_task_cancelled(&self, _job_id: &str, _stage_id: usize) {}
+
+    /// Record the memory reserved by the running tasks in the memory pools of the
+    /// executor
+    fn record_memory_reserved(&self, _bytes: usize) {}
+
+    /// Record the total numbers of hits and misses of the data cache of the executor
+    fn record_data_cache(&self, _hits: u64, _misses: u64) {}
+
+    /// Gather the current metric set served by the metrics endpoint of the executor, as
+    /// a tuple of its content and content type. `None` if the metrics are not exported.
+    fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>> {
+        Ok(None)
+    }
 }
 
 /// Implementation of `ExecutorMetricsCollector` which logs the completed
@@ -54,3 +95,15 @@ impl ExecutorMetricsCollector for LoggingMetricsCollector {
         );
     }
 }
+
+/// Return the default metrics collector of the executor, which exports the metrics
+/// to Prometheus if the `prometheus-metrics` feature is enabled
+#[cfg(feature = "prometheus")]
+pub fn default_metrics_collector() -> Result<Arc<dyn ExecutorMetricsCollector>> {
+    PrometheusMetricsCollector::current()
+}
+
+#[cfg(not(feature = "prometheus"))]
+pub fn default_metrics_collector() -> Result<Arc<dyn ExecutorMetricsCollector>> {
+    Ok(Arc::new(LoggingMetricsCollector::default()))
+}
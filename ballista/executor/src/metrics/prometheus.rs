@@ -0,0 +1,308 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::execution_engine::QueryStageExecutor;
+use crate::metrics::ExecutorMetricsCollector;
+use ballista_core::error::{BallistaError, Result};
+use ballista_core::serde::protobuf::ShuffleWritePartition;
+
+use once_cell::sync::OnceCell;
+use prometheus::{
+    register_gauge_with_registry, register_histogram_with_registry,
+    register_int_counter_with_registry, register_int_gauge_with_registry, Gauge,
+    Histogram, IntCounter, IntGauge, Registry,
+};
+use prometheus::{Encoder, TextEncoder};
+use std::sync::Arc;
+use std::time::Duration;
+
+static COLLECTOR: OnceCell<Arc<dyn ExecutorMetricsCollector>> = OnceCell::new();
+
+/// ExecutorMetricsCollector implementation based on Prometheus. By default this will
+/// track 12 metrics:
+/// *task_exec_time_seconds* - Histogram of the execution time of finished tasks in
+/// seconds
+/// *task_completed_total* - Counter of successful tasks
+/// *task_failed_total* - Counter of failed tasks
+/// *task_cancelled_total* - Counter of cancelled tasks
+/// *active_tasks* - Number of running tasks
+/// *shuffle_write_rows_total* - Counter of rows written to shuffle partitions
+/// *shuffle_write_bytes_total* - Counter of bytes written to shuffle partitions
+/// *shuffle_fetch_time_seconds* - Histogram of the time tasks spent fetching shuffle
+/// partitions in seconds
+/// *memory_reserved_bytes* - Memory reserved by running tasks
+/// *data_cache_hits_total* - Counter of data cache hits
+/// *data_cache_misses_total* - Counter of data cache misses
+/// *data_cache_hit_ratio* - Ratio of data cache hits to lookups
+pub struct PrometheusMetricsCollector {
+    registry: Registry,
+    execution_time: Histogram,
+    completed: IntCounter,
+    failed: IntCounter,
+    cancelled: IntCounter,
+    active_tasks: IntGauge,
+    shuffle_write_rows: IntCounter,
+    shuffle_write_bytes: IntCounter,
+    shuffle_fetch_time: Histogram,
+    memory_reserved: IntGauge,
+    data_cache_hits: IntCounter,
+    data_cache_misses: IntCounter,
+    data_cache_hit_ratio: Gauge,
+}
+
+fn registration_error(e: prometheus::Error) -> BallistaError {
+    BallistaError::Internal(format!("Error registering metric: {e:?}"))
+}
+
+impl PrometheusMetricsCollector {
+    pub fn new(registry: &Registry) -> Result<Self> {
+        let execution_time = register_histogram_with_registry!(
+            "task_exec_time_seconds",
+            "Histogram of task execution time in seconds",
+            vec![0.1_f64, 0.5_f64, 1_f64, 5_f64, 30_f64, 60_f64, 300_f64],
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let completed = register_int_counter_with_registry!(
+            "task_completed_total",
+            "Counter of successful tasks",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let failed = register_int_counter_with_registry!(
+            "task_failed_total",
+            "Counter of failed tasks",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let cancelled = register_int_counter_with_registry!(
+            "task_cancelled_total",
+            "Counter of cancelled tasks",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let active_tasks = register_int_gauge_with_registry!(
+            "active_tasks",
+            "Number of running tasks",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let shuffle_write_rows = register_int_counter_with_registry!(
+            "shuffle_write_rows_total",
+            "Counter of rows written to shuffle partitions",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let shuffle_write_bytes = register_int_counter_with_registry!(
+            "shuffle_write_bytes_total",
+            "Counter of bytes written to shuffle partitions",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let shuffle_fetch_time = register_histogram_with_registry!(
+            "shuffle_fetch_time_seconds",
+            "Histogram of the time tasks spent fetching shuffle partitions in seconds",
+            vec![0.01_f64, 0.05_f64, 0.1_f64, 0.5_f64, 1_f64, 5_f64, 30_f64],
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let memory_reserved = register_int_gauge_with_registry!(
+            "memory_reserved_bytes",
+            "Memory reserved by running tasks in bytes",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let data_cache_hits = register_int_counter_with_registry!(
+            "data_cache_hits_total",
+            "Counter of data cache hits",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let data_cache_misses = register_int_counter_with_registry!(
+            "data_cache_misses_total",
+            "Counter of data cache misses",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        let data_cache_hit_ratio = register_gauge_with_registry!(
+            "data_cache_hit_ratio",
+            "Ratio of data cache hits to lookups",
+            registry
+        )
+        .map_err(registration_error)?;
+
+        Ok(Self {
+            registry: registry.clone(),
+            execution_time,
+            completed,
+            failed,
+            cancelled,
+            active_tasks,
+            shuffle_write_rows,
+            shuffle_write_bytes,
+            shuffle_fetch_time,
+            memory_reserved,
+            data_cache_hits,
+            data_cache_misses,
+            data_cache_hit_ratio,
+        })
+    }
+
+    pub fn current() -> Result<Arc<dyn ExecutorMetricsCollector>> {
+        COLLECTOR
+            .get_or_try_init(|| {
+                let collector = Self::new(::prometheus::default_registry())?;
+
+                Ok(Arc::new(collector) as Arc<dyn ExecutorMetricsCollector>)
+            })
+            .map(|arc| arc.clone())
+    }
+}
+
+impl ExecutorMetricsCollector for PrometheusMetricsCollector {
+    fn record_stage(
+        &self,
+        _job_id: &str,
+        _stage_id: usize,
+        _partition: usize,
+        plan: Arc<dyn QueryStageExecutor>,
+    ) {
+        let fetch_time: usize = plan
+            .collect_plan_metrics()
+            .iter()
+            .filter_map(|metrics| metrics.sum_by_name("fetch_time"))
+            .map(|value| value.as_usize())
+            .sum();
+        // stages which don't read shuffle partitions have no fetch time
+        if fetch_time > 0 {
+            self.shuffle_fetch_time
+                .observe(Duration::from_nanos(fetch_time as u64).as_secs_f64());
+        }
+    }
+
+    fn record_task_started(&self, _job_id: &str, _stage_id: usize) {
+        self.active_tasks.inc();
+    }
+
+    fn record_task_finished(
+        &self,
+        _job_id: &str,
+        _stage_id: usize,
+        duration: Duration,
+        output: Option<&[ShuffleWritePartition]>,
+    ) {
+        self.active_tasks.dec();
+        self.execution_time.observe(duration.as_secs_f64());
+        match output {
+            Some(partitions) => {
+                self.completed.inc();
+                for partition in partitions {
+                    self.shuffle_write_rows.inc_by(partition.num_rows);
+                    self.shuffle_write_bytes.inc_by(partition.num_bytes);
+                }
+            }
+            None => self.failed.inc(),
+        }
+    }
+
+    fn record_task_cancelled(&self, _job_id: &str, _stage_id: usize) {
+        self.active_tasks.dec();
+        self.cancelled.inc();
+    }
+
+    fn record_memory_reserved(&self, bytes: usize) {
+        self.memory_reserved.set(bytes as i64);
+    }
+
+    fn record_data_cache(&self, hits: u64, misses: u64) {
+        // the cache reports running totals
+        self.data_cache_hits
+            .inc_by(hits.saturating_sub(self.data_cache_hits.get()));
+        self.data_cache_misses
+            .inc_by(misses.saturating_sub(self.data_cache_misses.get()));
+        if hits + misses > 0 {
+            self.data_cache_hit_ratio
+                .set(hits as f64 / (hits + misses) as f64);
+        }
+    }
+
+    fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>> {
+        let encoder = TextEncoder::new();
+
+        let metric_families = self.registry.gather();
+        let mut buffer = vec![];
+        encoder.encode(&metric_families, &mut buffer).map_err(|e| {
+            BallistaError::Internal(format!("Error encoding prometheus metrics: {e:?}"))
+        })?;
+
+        Ok(Some((buffer, encoder.format_type().to_owned())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gather_metrics() -> Result<()> {
+        let collector = PrometheusMetricsCollector::new(&Registry::new())?;
+        let output = ShuffleWritePartition {
+            num_rows: 10,
+            num_bytes: 100,
+            ..Default::default()
+        };
+        collector.record_task_started("job", 1);
+        collector.record_task_finished("job", 1, Duration::from_secs(1), Some(&[output]));
+        collector.record_task_started("job", 1);
+        collector.record_task_finished("job", 1, Duration::from_secs(1), None);
+        collector.record_task_started("job", 1);
+        collector.record_task_cancelled("job", 1);
+        collector.record_task_started("job", 1);
+        collector.record_memory_reserved(1024);
+
+        let (metrics, content_type) = collector.gather_metrics()?.unwrap();
+        assert_eq!(TextEncoder::new().format_type(), content_type);
+        let metrics = String::from_utf8(metrics).unwrap();
+        for expected in [
+            "task_completed_total 1",
+            "task_failed_total 1",
+            "task_cancelled_total 1",
+            "task_exec_time_seconds_count 2",
+            "active_tasks 1",
+            "shuffle_write_rows_total 10",
+            "shuffle_write_bytes_total 100",
+            "memory_reserved_bytes 1024",
+        ] {
+            assert!(
+                metrics.lines().any(|line| line == expected),
+                "{expected} not found in {metrics}"
+            );
+        }
+        Ok(())
+    }
+}
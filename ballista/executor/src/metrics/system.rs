@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ballista_core::serde::scheduler::ExecutorMetrics;
+use log::warn;
+use parking_lot::RwLock;
+use sysinfo::{CpuExt, DiskExt, System, SystemExt};
+
+use crate::shutdown::ShutdownNotifier;
+
+/// The latest resource usage of the executor host. Sampling walks the whole work dir,
+/// so it runs on the blocking thread pool on an interval of its own and heartbeats
+/// report the latest sample.
+#[derive(Clone, Default)]
+pub struct SystemMetrics {
+    latest: Arc<RwLock<ExecutorMetrics>>,
+}
+
+impl SystemMetrics {
+    /// Start sampling the resource usage of the host every `interval` until the
+    /// executor shuts down
+    pub fn start(
+        work_dir: impl Into<PathBuf>,
+        interval: Duration,
+        shutdown_noti: &ShutdownNotifier,
+    ) -> Self {
+        let system_metrics = Self::default();
+        let latest = system_metrics.latest.clone();
+        let mut collector = SystemMetricsCollector::new(work_dir);
+        let mut shutdown = shutdown_noti.subscribe_for_shutdown();
+        tokio::spawn(async move {
+            while !shutdown.is_shutdown() {
+                let sample = tokio::task::spawn_blocking(move || {
+                    let metrics = collector.collect();
+                    (collector, metrics)
+                })
+                .await;
+                match sample {
+                    Ok((sampled_collector, metrics)) => {
+                        collector = sampled_collector;
+                        *latest.write() = metrics;
+                    }
+                    Err(e) => {
+                        warn!("Stop sampling the resource usage of the host: {:?}", e);
+                        return;
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {},
+                    _ = shutdown.recv() => return,
+                }
+            }
+        });
+        system_metrics
+    }
+
+    /// The latest sample of the resource usage of the host
+    pub fn get(&self, running_tasks: usize) -> ExecutorMetrics {
+        ExecutorMetrics {
+            running_tasks: Some(running_tasks as u32),
+            ..*self.latest.read()
+        }
+    }
+}
+
+/// Samples the resource usage of the executor host, which is reported to the scheduler
+/// in heartbeats
+pub struct SystemMetricsCollector {
+    work_dir: PathBuf,
+    system: System,
+}
+
+impl SystemMetricsCollector {
+    pub fn new(work_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            work_dir: work_dir.into(),
+            system: System::new(),
+        }
+    }
+
+    /// Refresh the resource usage of the host. The CPU usage is averaged since the
+    /// previous call.
+    pub fn collect(&mut self) -> ExecutorMetrics {
+        self.system.refresh_memory();
+        self.system.refresh_cpu();
+        self.system.refresh_disks_list();
+
+        ExecutorMetrics {
+            available_memory: Some(self.system.available_memory()),
+            used_memory: Some(self.system.used_memory()),
+            cpu_usage: Some(self.system.global_cpu_info().cpu_usage()),
+            available_disk: self.available_disk(),
+            running_tasks: None,
+            shuffle_bytes: Some(dir_size(&self.work_dir)),
+        }
+    }
+
+    /// Free space of the disk mounted closest to the work dir
+    fn available_disk(&self) -> Option<u64> {
+        let work_dir = self.work_dir.canonicalize().ok()?;
+        self.system
+            .disks()
+            .iter()
+            .filter(|disk| work_dir.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space())
+    }
+}
+
+/// Total size of the files in a directory and its subdirectories
+fn dir_size(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect() -> std::io::Result<()> {
+        let work_dir = TempDir::new()?;
+        let stage_dir = work_dir.path().join("job").join("1").join("0");
+        fs::create_dir_all(&stage_dir)?;
+        fs::write(stage_dir.join("data-0.arrow"), [0; 100])?;
+        fs::write(stage_dir.join("data-1.arrow"), [0; 20])?;
+
+        let mut collector = SystemMetricsCollector::new(work_dir.path());
+        let metrics = collector.collect();
+        assert_eq!(None, metrics.running_tasks);
+        assert_eq!(Some(120), metrics.shuffle_bytes);
+        assert!(metrics.available_memory.is_some());
+        assert!(metrics.used_memory.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sample_in_background() -> std::io::Result<()> {
+        let work_dir = TempDir::new()?;
+        fs::write(work_dir.path().join("data-0.arrow"), [0; 100])?;
+
+        let shutdown_noti = ShutdownNotifier::new();
+        let system_metrics = SystemMetrics::start(
+            work_dir.path(),
+            Duration::from_millis(10),
+            &shutdown_noti,
+        );
+        for _ in 0..100 {
+            if system_metrics.get(0).shuffle_bytes.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let metrics = system_metrics.get(3);
+        assert_eq!(Some(3), metrics.running_tasks);
+        assert_eq!(Some(100), metrics.shuffle_bytes);
+
+        // later samples pick up new shuffle files
+        fs::write(work_dir.path().join("data-1.arrow"), [0; 20])?;
+        for _ in 0..100 {
+            if system_metrics.get(0).shuffle_bytes == Some(120) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(Some(120), system_metrics.get(0).shuffle_bytes);
+        Ok(())
+    }
+}
@@ -60,12 +60,8 @@ pub async fn new_standalone_executor<
         port: addr.port() as u32,
         // TODO Make it configurable
         grpc_port: 50020,
-        specification: Some(
-            ExecutorSpecification {
-                task_slots: concurrent_tasks as u32,
-            }
-            .into(),
-        ),
+        specification: Some(ExecutorSpecification::new(concurrent_tasks as u32).into()),
+        labels: vec![],
     };
     let work_dir = TempDir::new()?
         .into_path()
@@ -91,7 +87,7 @@ pub async fn new_standalone_executor<
     let service = BallistaFlightService::new();
     let server = FlightServiceServer::new(service);
     tokio::spawn(
-        create_grpc_server()
+        create_grpc_server(None)?
             .add_service(server)
             .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(
                 listener,
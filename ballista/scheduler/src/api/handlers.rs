@@ -12,11 +12,15 @@
 
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 use crate::scheduler_server::SchedulerServer;
-use crate::state::execution_graph::ExecutionStage;
+use crate::state::execution_graph::{ExecutionGraph, ExecutionStage};
 use crate::state::execution_graph_dot::ExecutionGraphDot;
 use ballista_core::serde::protobuf::job_status::Status;
+use ballista_core::serde::protobuf::JobStatus;
+use ballista_core::serde::scheduler::ExecutorMetrics;
+use ballista_core::utils::default_session_builder;
 use ballista_core::BALLISTA_VERSION;
 use datafusion::physical_plan::metrics::{MetricValue, MetricsSet, Time};
+use datafusion::prelude::{SessionConfig, SessionContext};
 use datafusion_proto::logical_plan::AsLogicalPlan;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 use graphviz_rust::cmd::{CommandArg, Format};
@@ -24,6 +28,7 @@ use graphviz_rust::exec;
 use graphviz_rust::printer::PrinterContext;
 use http::header::CONTENT_TYPE;
 
+use std::collections::HashMap;
 use std::time::Duration;
 use warp::Rejection;
 
@@ -44,16 +49,63 @@ pub struct ExecutorMetaResponse {
     pub host: String,
     pub port: u16,
     pub last_seen: u128,
+    pub metrics: ExecutorMetrics,
+    pub labels: HashMap<String, String>,
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct JobResponse {
     pub job_id: String,
     pub job_name: String,
+    pub user: Option<String>,
     pub job_status: String,
     pub num_stages: usize,
     pub completed_stages: usize,
+    pub completed_tasks: u32,
+    pub total_tasks: u32,
+    pub output_rows: u64,
+    pub output_bytes: u64,
     pub percent_complete: u8,
+    pub stages: Vec<StageProgressResponse>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct HistoryJobResponse {
+    pub job_id: String,
+    pub job_name: String,
+    pub user: String,
+    pub job_status: String,
+    pub num_stages: usize,
+    pub queued_at: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Page of the jobs listed from the job history, the most recent ones first
+#[derive(Debug, serde::Deserialize)]
+#[serde(default)]
+pub struct HistoryJobsQuery {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for HistoryJobsQuery {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct StageProgressResponse {
+    pub stage_id: u32,
+    pub stage_status: String,
+    pub completed_tasks: u32,
+    pub total_tasks: u32,
+    pub output_rows: u64,
+    pub output_bytes: u64,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -92,11 +144,13 @@ pub(crate) async fn get_executors<T: AsLogicalPlan, U: AsExecutionPlan>(
         .await
         .unwrap_or_default()
         .into_iter()
-        .map(|(metadata, duration)| ExecutorMetaResponse {
+        .map(|(metadata, duration, metrics)| ExecutorMetaResponse {
             id: metadata.id,
             host: metadata.host,
             port: metadata.port,
             last_seen: duration.as_millis(),
+            metrics,
+            labels: metadata.labels,
         })
         .collect();
 
@@ -119,46 +173,34 @@ pub(crate) async fn get_jobs<T: AsLogicalPlan, U: AsExecutionPlan>(
     let jobs: Vec<JobResponse> = jobs
         .iter()
         .map(|job| {
-            let status = &job.status;
-            let job_status = match &status.status {
-                Some(Status::Queued(_)) => "Queued".to_string(),
-                Some(Status::Running(_)) => "Running".to_string(),
-                Some(Status::Failed(error)) => format!("Failed: {}", error.error),
-                Some(Status::Successful(completed)) => {
-                    let num_rows = completed
-                        .partition_location
-                        .iter()
-                        .map(|p| {
-                            p.partition_stats.as_ref().map(|s| s.num_rows).unwrap_or(0)
-                        })
-                        .sum::<i64>();
-                    let num_rows_term = if num_rows == 1 { "row" } else { "rows" };
-                    let num_partitions = completed.partition_location.len();
-                    let num_partitions_term = if num_partitions == 1 {
-                        "partition"
-                    } else {
-                        "partitions"
-                    };
-                    format!(
-                        "Completed. Produced {} {} containing {} {}. Elapsed time: {} ms.",
-                        num_partitions, num_partitions_term, num_rows, num_rows_term,
-                        job.end_time - job.start_time
-                    )
-                }
-                _ => "Invalid State".to_string(),
-            };
+            let job_status =
+                describe_job_status(&job.status, job.end_time - job.start_time);
 
-            // calculate progress based on completed stages for now, but we could use completed
-            // tasks in the future to make this more accurate
-            let percent_complete =
-                ((job.completed_stages as f32 / job.num_stages as f32) * 100_f32) as u8;
+            let progress = &job.progress;
             JobResponse {
                 job_id: job.job_id.to_string(),
                 job_name: job.job_name.to_string(),
+                user: job.user.clone(),
                 job_status,
                 num_stages: job.num_stages,
                 completed_stages: job.completed_stages,
-                percent_complete,
+                completed_tasks: progress.completed_tasks,
+                total_tasks: progress.total_tasks,
+                output_rows: progress.output_rows,
+                output_bytes: progress.output_bytes,
+                percent_complete: progress.percent_complete as u8,
+                stages: progress
+                    .stages
+                    .iter()
+                    .map(|stage| StageProgressResponse {
+                        stage_id: stage.stage_id,
+                        stage_status: stage.state.clone(),
+                        completed_tasks: stage.completed_tasks,
+                        total_tasks: stage.total_tasks,
+                        output_rows: stage.output_rows,
+                        output_bytes: stage.output_bytes,
+                    })
+                    .collect(),
             }
         })
         .collect();
@@ -166,6 +208,37 @@ pub(crate) async fn get_jobs<T: AsLogicalPlan, U: AsExecutionPlan>(
     Ok(warp::reply::json(&jobs))
 }
 
+fn describe_job_status(status: &JobStatus, elapsed_millis: u64) -> String {
+    match &status.status {
+        Some(Status::Queued(_)) => "Queued".to_string(),
+        Some(Status::Running(_)) => "Running".to_string(),
+        Some(Status::Failed(error)) => format!("Failed: {}", error.error),
+        Some(Status::Successful(completed)) => {
+            let num_rows = completed
+                .partition_location
+                .iter()
+                .map(|p| p.partition_stats.as_ref().map(|s| s.num_rows).unwrap_or(0))
+                .sum::<i64>();
+            let num_rows_term = if num_rows == 1 { "row" } else { "rows" };
+            let num_partitions = completed.partition_location.len();
+            let num_partitions_term = if num_partitions == 1 {
+                "partition"
+            } else {
+                "partitions"
+            };
+            format!(
+                "Completed. Produced {} {} containing {} {}. Elapsed time: {} ms.",
+                num_partitions,
+                num_partitions_term,
+                num_rows,
+                num_rows_term,
+                elapsed_millis
+            )
+        }
+        _ => "Invalid State".to_string(),
+    }
+}
+
 pub(crate) async fn cancel_job<T: AsLogicalPlan, U: AsExecutionPlan>(
     data_server: SchedulerServer<T, U>,
     job_id: String,
@@ -207,60 +280,63 @@ pub(crate) async fn get_query_stages<T: AsLogicalPlan, U: AsExecutionPlan>(
         .await
         .map_err(|_| warp::reject())?
     {
-        Ok(warp::reply::json(&QueryStagesResponse {
-            stages: graph
-                .as_ref()
-                .stages()
-                .iter()
-                .map(|(id, stage)| {
-                    let mut summary = QueryStageSummary {
-                        stage_id: id.to_string(),
-                        stage_status: stage.variant_name().to_string(),
-                        input_rows: 0,
-                        output_rows: 0,
-                        elapsed_compute: "".to_string(),
-                    };
-                    match stage {
-                        ExecutionStage::Running(running_stage) => {
-                            summary.input_rows = running_stage
-                                .stage_metrics
-                                .as_ref()
-                                .map(|m| get_combined_count(m.as_slice(), "input_rows"))
-                                .unwrap_or(0);
-                            summary.output_rows = running_stage
-                                .stage_metrics
-                                .as_ref()
-                                .map(|m| get_combined_count(m.as_slice(), "output_rows"))
-                                .unwrap_or(0);
-                            summary.elapsed_compute = running_stage
-                                .stage_metrics
-                                .as_ref()
-                                .map(|m| get_elapsed_compute_nanos(m.as_slice()))
-                                .unwrap_or_default();
-                        }
-                        ExecutionStage::Successful(completed_stage) => {
-                            summary.input_rows = get_combined_count(
-                                &completed_stage.stage_metrics,
-                                "input_rows",
-                            );
-                            summary.output_rows = get_combined_count(
-                                &completed_stage.stage_metrics,
-                                "output_rows",
-                            );
-                            summary.elapsed_compute =
-                                get_elapsed_compute_nanos(&completed_stage.stage_metrics);
-                        }
-                        _ => {}
-                    }
-                    summary
-                })
-                .collect(),
-        }))
+        Ok(warp::reply::json(&query_stages(graph.as_ref())))
     } else {
         Ok(warp::reply::json(&QueryStagesResponse { stages: vec![] }))
     }
 }
 
+fn query_stages(graph: &ExecutionGraph) -> QueryStagesResponse {
+    QueryStagesResponse {
+        stages: graph
+            .stages()
+            .iter()
+            .map(|(id, stage)| {
+                let mut summary = QueryStageSummary {
+                    stage_id: id.to_string(),
+                    stage_status: stage.variant_name().to_string(),
+                    input_rows: 0,
+                    output_rows: 0,
+                    elapsed_compute: "".to_string(),
+                };
+                match stage {
+                    ExecutionStage::Running(running_stage) => {
+                        summary.input_rows = running_stage
+                            .stage_metrics
+                            .as_ref()
+                            .map(|m| get_combined_count(m.as_slice(), "input_rows"))
+                            .unwrap_or(0);
+                        summary.output_rows = running_stage
+                            .stage_metrics
+                            .as_ref()
+                            .map(|m| get_combined_count(m.as_slice(), "output_rows"))
+                            .unwrap_or(0);
+                        summary.elapsed_compute = running_stage
+                            .stage_metrics
+                            .as_ref()
+                            .map(|m| get_elapsed_compute_nanos(m.as_slice()))
+                            .unwrap_or_default();
+                    }
+                    ExecutionStage::Successful(completed_stage) => {
+                        summary.input_rows = get_combined_count(
+                            &completed_stage.stage_metrics,
+                            "input_rows",
+                        );
+                        summary.output_rows = get_combined_count(
+                            &completed_stage.stage_metrics,
+                            "output_rows",
+                        );
+                        summary.elapsed_compute =
+                            get_elapsed_compute_nanos(&completed_stage.stage_metrics);
+                    }
+                    _ => {}
+                }
+                summary
+            })
+            .collect(),
+    }
+}
+
 fn get_elapsed_compute_nanos(metrics: &[MetricsSet]) -> String {
     let nanos: usize = metrics
         .iter()
@@ -337,19 +413,147 @@ pub(crate) async fn get_job_svg_graph<T: AsLogicalPlan, U: AsExecutionPlan>(
 ) -> Result<String, Rejection> {
     let dot = get_job_dot_graph(data_server, job_id).await;
     match dot {
-        Ok(dot) => {
-            let graph = graphviz_rust::parse(&dot);
-            if let Ok(graph) = graph {
-                exec(
-                    graph,
-                    &mut PrinterContext::default(),
-                    vec![CommandArg::Format(Format::Svg)],
-                )
-                .map_err(|_| warp::reject())
-            } else {
-                Ok("Cannot parse graph".to_string())
-            }
+        Ok(dot) => dot_to_svg(&dot),
+        _ => Ok("Not Found".to_string()),
+    }
+}
+
+fn dot_to_svg(dot: &str) -> Result<String, Rejection> {
+    let graph = graphviz_rust::parse(dot);
+    if let Ok(graph) = graph {
+        exec(
+            graph,
+            &mut PrinterContext::default(),
+            vec![CommandArg::Format(Format::Svg)],
+        )
+        .map_err(|_| warp::reject())
+    } else {
+        Ok("Cannot parse graph".to_string())
+    }
+}
+
+/// Get the execution graph of a job archived in the job history
+async fn get_history_graph<T: AsLogicalPlan, U: AsExecutionPlan>(
+    data_server: &SchedulerServer<T, U>,
+    job_id: &str,
+) -> Result<Option<ExecutionGraph>, Rejection> {
+    let job_history = match &data_server.state.job_history {
+        Some(job_history) => job_history,
+        None => return Ok(None),
+    };
+    match job_history
+        .get_job(job_id)
+        .await
+        .map_err(|_| warp::reject())?
+    {
+        Some(graph) => {
+            // the session of the job may be gone, the plans only need the default one
+            let session_ctx =
+                SessionContext::with_state(default_session_builder(SessionConfig::new()));
+            let graph = ExecutionGraph::decode_execution_graph(
+                graph,
+                &data_server.state.codec,
+                &session_ctx,
+            )
+            .await
+            .map_err(|_| warp::reject())?;
+            Ok(Some(graph))
         }
+        None => Ok(None),
+    }
+}
+
+/// Return a page of the jobs archived in the job history
+pub(crate) async fn get_history_jobs<T: AsLogicalPlan, U: AsExecutionPlan>(
+    data_server: SchedulerServer<T, U>,
+    query: HistoryJobsQuery,
+) -> Result<impl warp::Reply, Rejection> {
+    let jobs = match &data_server.state.job_history {
+        Some(job_history) => job_history
+            .get_jobs(query.offset, query.limit)
+            .await
+            .map_err(|_| warp::reject())?,
+        None => vec![],
+    };
+
+    let jobs: Vec<HistoryJobResponse> = jobs
+        .into_iter()
+        .map(|job| {
+            let job_status = job
+                .status
+                .as_ref()
+                .map(|status| {
+                    describe_job_status(
+                        status,
+                        job.end_time.saturating_sub(job.start_time),
+                    )
+                })
+                .unwrap_or_else(|| "Invalid State".to_string());
+            HistoryJobResponse {
+                job_status,
+                num_stages: job.num_stages as usize,
+                job_id: job.job_id,
+                job_name: job.job_name,
+                user: job.user,
+                queued_at: job.queued_at,
+                start_time: job.start_time,
+                end_time: job.end_time,
+            }
+        })
+        .collect();
+
+    Ok(warp::reply::json(&jobs))
+}
+
+/// Get the archived execution graph for the specified job id
+pub(crate) async fn get_history_query_stages<T: AsLogicalPlan, U: AsExecutionPlan>(
+    data_server: SchedulerServer<T, U>,
+    job_id: String,
+) -> Result<impl warp::Reply, Rejection> {
+    if let Some(graph) = get_history_graph(&data_server, &job_id).await? {
+        Ok(warp::reply::json(&query_stages(&graph)))
+    } else {
+        Ok(warp::reply::json(&QueryStagesResponse { stages: vec![] }))
+    }
+}
+
+/// Generate a dot graph for the specified archived job id and return as plain text
+pub(crate) async fn get_history_job_dot_graph<T: AsLogicalPlan, U: AsExecutionPlan>(
+    data_server: SchedulerServer<T, U>,
+    job_id: String,
+) -> Result<String, Rejection> {
+    if let Some(graph) = get_history_graph(&data_server, &job_id).await? {
+        ExecutionGraphDot::generate(&graph).map_err(|_| warp::reject())
+    } else {
+        Ok("Not Found".to_string())
+    }
+}
+
+/// Generate a dot graph for the specified archived job id and query stage and return
+/// as plain text
+pub(crate) async fn get_history_query_stage_dot_graph<
+    T: AsLogicalPlan,
+    U: AsExecutionPlan,
+>(
+    data_server: SchedulerServer<T, U>,
+    job_id: String,
+    stage_id: usize,
+) -> Result<String, Rejection> {
+    if let Some(graph) = get_history_graph(&data_server, &job_id).await? {
+        ExecutionGraphDot::generate_for_query_stage(&graph, stage_id)
+            .map_err(|_| warp::reject())
+    } else {
+        Ok("Not Found".to_string())
+    }
+}
+
+/// Generate an SVG graph for the specified archived job id and return it as plain text
+pub(crate) async fn get_history_job_svg_graph<T: AsLogicalPlan, U: AsExecutionPlan>(
+    data_server: SchedulerServer<T, U>,
+    job_id: String,
+) -> Result<String, Rejection> {
+    match get_history_job_dot_graph(data_server, job_id).await {
+        Ok(dot) => dot_to_svg(&dot),
         _ => Ok("Not Found".to_string()),
     }
 }
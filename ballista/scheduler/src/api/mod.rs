@@ -121,6 +121,38 @@ pub fn get_routes<T: AsLogicalPlan + Clone, U: 'static + AsExecutionPlan>(
         .and(with_data_server(scheduler_server.clone()))
         .and_then(|job_id, data_server| handlers::get_job_svg_graph(data_server, job_id));
 
+    let route_history_jobs = warp::path!("api" / "history" / "jobs")
+        .and(with_data_server(scheduler_server.clone()))
+        .and(warp::query::<handlers::HistoryJobsQuery>())
+        .and_then(|data_server, query| handlers::get_history_jobs(data_server, query));
+
+    let route_history_query_stages =
+        warp::path!("api" / "history" / "job" / String / "stages")
+            .and(with_data_server(scheduler_server.clone()))
+            .and_then(|job_id, data_server| {
+                handlers::get_history_query_stages(data_server, job_id)
+            });
+
+    let route_history_job_dot = warp::path!("api" / "history" / "job" / String / "dot")
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(|job_id, data_server| {
+            handlers::get_history_job_dot_graph(data_server, job_id)
+        });
+
+    let route_history_query_stage_dot =
+        warp::path!("api" / "history" / "job" / String / "stage" / usize / "dot")
+            .and(with_data_server(scheduler_server.clone()))
+            .and_then(|job_id, stage_id, data_server| {
+                handlers::get_history_query_stage_dot_graph(data_server, job_id, stage_id)
+            });
+
+    let route_history_job_dot_svg =
+        warp::path!("api" / "history" / "job" / String / "dot_svg")
+            .and(with_data_server(scheduler_server.clone()))
+            .and_then(|job_id, data_server| {
+                handlers::get_history_job_svg_graph(data_server, job_id)
+            });
+
     let route_scheduler_metrics = warp::path!("api" / "metrics")
         .and(with_data_server(scheduler_server))
         .and_then(|data_server| handlers::get_scheduler_metrics(data_server));
@@ -133,6 +165,11 @@ pub fn get_routes<T: AsLogicalPlan + Clone, U: 'static + AsExecutionPlan>(
         .or(route_job_dot)
         .or(route_query_stage_dot)
         .or(route_job_dot_svg)
+        .or(route_history_jobs)
+        .or(route_history_query_stages)
+        .or(route_history_job_dot)
+        .or(route_history_query_stage_dot)
+        .or(route_history_job_dot_svg)
         .or(route_scheduler_metrics);
     routes.boxed()
 }
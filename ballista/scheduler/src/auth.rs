@@ -0,0 +1,390 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Authentication of Flight SQL clients
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ballista_core::error::{BallistaError, Result};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use log::warn;
+use tonic::Status;
+
+use crate::config::FlightSqlAuthConfig;
+
+/// An authenticated user of the scheduler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub name: String,
+}
+
+impl Principal {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+/// Credentials presented by a client in the `authorization` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// `Basic` authentication with a username and password
+    Basic { username: String, password: String },
+    /// `Bearer` authentication with a token
+    Bearer(String),
+}
+
+impl Credentials {
+    /// Parse the value of an `authorization` header
+    pub fn from_authorization(authorization: &str) -> std::result::Result<Self, Status> {
+        let basic = "Basic ";
+        let bearer = "Bearer ";
+        if let Some(base64) = authorization.strip_prefix(basic) {
+            let bytes = base64::decode(base64)
+                .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
+            let str = String::from_utf8(bytes)
+                .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
+            let (username, password) = str.split_once(':').ok_or_else(|| {
+                Status::invalid_argument("Invalid authorization header")
+            })?;
+            Ok(Credentials::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            })
+        } else if let Some(token) = authorization.strip_prefix(bearer) {
+            Ok(Credentials::Bearer(token.to_string()))
+        } else {
+            Err(Status::invalid_argument(format!(
+                "Auth type not implemented: {authorization}"
+            )))
+        }
+    }
+}
+
+/// Verifies the credentials of Flight SQL clients
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    /// Return the principal the credentials belong to, or `Status::unauthenticated`
+    /// if they are not valid
+    async fn authenticate(
+        &self,
+        credentials: &Credentials,
+    ) -> std::result::Result<Principal, Status>;
+}
+
+/// Create the authenticator for the given configuration
+pub fn create_authenticator(
+    config: &FlightSqlAuthConfig,
+) -> Result<Arc<dyn Authenticator>> {
+    let authenticator: Arc<dyn Authenticator> = match config {
+        FlightSqlAuthConfig::Builtin => {
+            warn!("Flight SQL clients are authenticated with the built-in admin user");
+            Arc::new(StaticUserAuthenticator::new(HashMap::from([(
+                "admin".to_string(),
+                "password".to_string(),
+            )])))
+        }
+        FlightSqlAuthConfig::StaticUsers(path) => {
+            Arc::new(StaticUserAuthenticator::try_from_file(path)?)
+        }
+        FlightSqlAuthConfig::Htpasswd(path) => {
+            Arc::new(HtpasswdAuthenticator::try_from_file(path)?)
+        }
+        FlightSqlAuthConfig::Jwt(path) => {
+            Arc::new(JwtAuthenticator::try_from_file(path)?)
+        }
+    };
+    Ok(authenticator)
+}
+
+/// Authenticates users with passwords from a file in which each line is `user:password`
+pub struct StaticUserAuthenticator {
+    users: HashMap<String, String>,
+}
+
+impl StaticUserAuthenticator {
+    pub fn new(users: HashMap<String, String>) -> Self {
+        Self { users }
+    }
+
+    pub fn try_from_file(path: &str) -> Result<Self> {
+        Ok(Self::new(parse_user_file(&read_file(path)?)?))
+    }
+}
+
+#[async_trait]
+impl Authenticator for StaticUserAuthenticator {
+    async fn authenticate(
+        &self,
+        credentials: &Credentials,
+    ) -> std::result::Result<Principal, Status> {
+        match credentials {
+            Credentials::Basic { username, password }
+                if self.users.get(username) == Some(password) =>
+            {
+                Ok(Principal::new(username))
+            }
+            _ => Err(Status::unauthenticated("Invalid credentials!")),
+        }
+    }
+}
+
+/// Authenticates users with an htpasswd file, in which each line is `user:hash`.
+/// Only bcrypt hashes, as created by `htpasswd -B`, are supported.
+pub struct HtpasswdAuthenticator {
+    users: HashMap<String, String>,
+}
+
+impl HtpasswdAuthenticator {
+    pub fn try_new(users: HashMap<String, String>) -> Result<Self> {
+        if let Some((user, _)) = users.iter().find(|(_, hash)| !is_bcrypt_hash(hash)) {
+            return Err(BallistaError::General(format!(
+                "Password of user {user} is not a bcrypt hash"
+            )));
+        }
+        Ok(Self { users })
+    }
+
+    pub fn try_from_file(path: &str) -> Result<Self> {
+        Self::try_new(parse_user_file(&read_file(path)?)?)
+    }
+}
+
+#[async_trait]
+impl Authenticator for HtpasswdAuthenticator {
+    async fn authenticate(
+        &self,
+        credentials: &Credentials,
+    ) -> std::result::Result<Principal, Status> {
+        if let Credentials::Basic { username, password } = credentials {
+            if let Some(hash) = self.users.get(username) {
+                let (password, hash) = (password.clone(), hash.clone());
+                // bcrypt is deliberately expensive, so keep it off the async runtime
+                let verified =
+                    tokio::task::spawn_blocking(move || bcrypt::verify(password, &hash))
+                        .await
+                        .map_err(|e| Status::internal(e.to_string()))?
+                        .unwrap_or(false);
+                if verified {
+                    return Ok(Principal::new(username));
+                }
+            }
+        }
+        Err(Status::unauthenticated("Invalid credentials!"))
+    }
+}
+
+/// Authenticates clients with a JSON Web Token as bearer token. The `sub` claim of
+/// the token is the principal.
+///
+/// Tokens signed with HMAC are verified with the secret in the key file, tokens signed
+/// with RSA are verified with the PEM encoded public key in the key file.
+pub struct JwtAuthenticator {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+impl JwtAuthenticator {
+    pub fn try_new(key: &[u8]) -> Result<Self> {
+        let (key, algorithm) = if key.starts_with(b"-----BEGIN") {
+            let key = DecodingKey::from_rsa_pem(key).map_err(|e| {
+                BallistaError::General(format!("Invalid RSA public key: {e}"))
+            })?;
+            (key, Algorithm::RS256)
+        } else {
+            (DecodingKey::from_secret(key), Algorithm::HS256)
+        };
+        Ok(Self {
+            key,
+            validation: Validation::new(algorithm),
+        })
+    }
+
+    pub fn try_from_file(path: &str) -> Result<Self> {
+        Self::try_new(read_file(path)?.trim().as_bytes())
+    }
+}
+
+#[async_trait]
+impl Authenticator for JwtAuthenticator {
+    async fn authenticate(
+        &self,
+        credentials: &Credentials,
+    ) -> std::result::Result<Principal, Status> {
+        match credentials {
+            Credentials::Bearer(token) => {
+                let token =
+                    jsonwebtoken::decode::<Claims>(token, &self.key, &self.validation)
+                        .map_err(|e| {
+                            Status::unauthenticated(format!("Invalid token: {e}"))
+                        })?;
+                Ok(Principal::new(token.claims.sub))
+            }
+            _ => Err(Status::unauthenticated(
+                "A bearer token is required for authentication",
+            )),
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        BallistaError::General(format!("Failed to read auth file {path}: {e}"))
+    })
+}
+
+/// Parse lines of `user:secret`, skipping empty lines and comments starting with `#`
+fn parse_user_file(content: &str) -> Result<HashMap<String, String>> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split_once(':')
+                .map(|(user, secret)| (user.to_string(), secret.to_string()))
+                .ok_or_else(|| {
+                    BallistaError::General(format!("Invalid line in auth file: {line}"))
+                })
+        })
+        .collect()
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    fn basic(username: &str, password: &str) -> Credentials {
+        Credentials::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_credentials() {
+        let header = format!("Basic {}", base64::encode("admin:pass:word"));
+        assert_eq!(
+            Credentials::from_authorization(&header).unwrap(),
+            basic("admin", "pass:word")
+        );
+        assert_eq!(
+            Credentials::from_authorization("Bearer token").unwrap(),
+            Credentials::Bearer("token".to_string())
+        );
+        assert!(Credentials::from_authorization("Digest foo").is_err());
+    }
+
+    #[tokio::test]
+    async fn static_users() -> Result<()> {
+        let users = parse_user_file("# users\nalice:secret\n\nbob:hunter2\n")?;
+        let authenticator = StaticUserAuthenticator::new(users);
+
+        let principal = authenticator
+            .authenticate(&basic("alice", "secret"))
+            .await
+            .unwrap();
+        assert_eq!(principal, Principal::new("alice"));
+
+        assert!(authenticator
+            .authenticate(&basic("alice", "hunter2"))
+            .await
+            .is_err());
+        assert!(authenticator
+            .authenticate(&basic("carol", "secret"))
+            .await
+            .is_err());
+        assert!(parse_user_file("no separator").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn htpasswd() -> Result<()> {
+        let hash = bcrypt::hash("secret", 4).unwrap();
+        let users = parse_user_file(&format!("alice:{hash}"))?;
+        let authenticator = HtpasswdAuthenticator::try_new(users)?;
+
+        let principal = authenticator
+            .authenticate(&basic("alice", "secret"))
+            .await
+            .unwrap();
+        assert_eq!(principal, Principal::new("alice"));
+
+        assert!(authenticator
+            .authenticate(&basic("alice", "wrong"))
+            .await
+            .is_err());
+        assert!(HtpasswdAuthenticator::try_new(parse_user_file("bob:plain")?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn jwt() -> Result<()> {
+        let secret = b"local-secret";
+        let authenticator = JwtAuthenticator::try_new(secret)?;
+
+        #[derive(serde::Serialize)]
+        struct TestClaims {
+            sub: String,
+            exp: u64,
+        }
+        let claims = TestClaims {
+            sub: "alice".to_string(),
+            exp: jsonwebtoken::get_current_timestamp() + 60,
+        };
+        let token = jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap();
+
+        let principal = authenticator
+            .authenticate(&Credentials::Bearer(token))
+            .await
+            .unwrap();
+        assert_eq!(principal, Principal::new("alice"));
+
+        let forged = jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"other-secret"),
+        )
+        .unwrap();
+        assert!(authenticator
+            .authenticate(&Credentials::Bearer(forged))
+            .await
+            .is_err());
+        assert!(authenticator
+            .authenticate(&basic("alice", "secret"))
+            .await
+            .is_err());
+        Ok(())
+    }
+}
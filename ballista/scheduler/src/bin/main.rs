@@ -25,12 +25,18 @@ use anyhow::Result;
 use crate::config::{Config, ResultExt};
 use ballista_core::config::LogRotationPolicy;
 use ballista_core::print_version;
+use ballista_core::telemetry;
+use ballista_core::utils::GrpcTlsConfig;
 use ballista_scheduler::cluster::BallistaCluster;
 use ballista_scheduler::cluster::ClusterStorage;
 use ballista_scheduler::config::{
-    ClusterStorageConfig, SchedulerConfig, TaskDistribution, TaskDistributionPolicy,
+    ClusterStorageConfig, FlightSqlAuth, FlightSqlAuthConfig, JobScheduling,
+    JobSchedulingPolicy, SchedulerConfig, SpeculationConfig, TaskDistribution,
+    TaskDistributionPolicy,
 };
 use ballista_scheduler::scheduler_process::start_server;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
 #[macro_use]
@@ -69,6 +75,7 @@ async fn main() -> Result<()> {
 
     let rust_log = env::var(EnvFilter::DEFAULT_ENV);
     let log_filter = EnvFilter::new(rust_log.unwrap_or(special_mod_log_level));
+    let otlp_endpoint = opt.otlp_endpoint;
     // File layer
     if let Some(log_dir) = log_dir {
         let log_file = match opt.log_rotation_policy {
@@ -91,6 +98,11 @@ async fn main() -> Result<()> {
             .with_thread_ids(print_thread_info)
             .with_writer(log_file)
             .with_env_filter(log_filter)
+            .finish()
+            .with(telemetry::otlp_layer(
+                "ballista-scheduler",
+                otlp_endpoint.as_deref(),
+            )?)
             .init();
     } else {
         // Console layer
@@ -100,6 +112,11 @@ async fn main() -> Result<()> {
             .with_thread_ids(print_thread_info)
             .with_writer(io::stdout)
             .with_env_filter(log_filter)
+            .finish()
+            .with(telemetry::otlp_layer(
+                "ballista-scheduler",
+                otlp_endpoint.as_deref(),
+            )?)
             .init();
     }
 
@@ -136,6 +153,70 @@ async fn main() -> Result<()> {
         }
     };
 
+    let job_scheduling = match opt.job_scheduling_policy {
+        JobScheduling::Fifo => JobSchedulingPolicy::Fifo,
+        JobScheduling::Priority => JobSchedulingPolicy::Priority,
+        JobScheduling::Fair => JobSchedulingPolicy::Fair {
+            pool_weights: JobSchedulingPolicy::parse_pool_weights(&opt.job_pool_weights)
+                .map_err(|e| anyhow::anyhow!(e))?,
+        },
+    };
+
+    let speculation = if opt.speculation {
+        if opt.speculation_multiplier < 1.0 {
+            anyhow::bail!(
+                "speculation_multiplier must be at least 1, got {}",
+                opt.speculation_multiplier
+            );
+        }
+        if opt.speculation_quantile <= 0.0 || opt.speculation_quantile > 1.0 {
+            anyhow::bail!(
+                "speculation_quantile must be in (0, 1], got {}",
+                opt.speculation_quantile
+            );
+        }
+        Some(SpeculationConfig {
+            multiplier: opt.speculation_multiplier,
+            quantile: opt.speculation_quantile,
+            min_task_duration_ms: opt.speculation_min_task_duration_ms,
+            interval_ms: opt.speculation_interval_ms,
+        })
+    } else {
+        None
+    };
+
+    let flight_sql_auth_file = || {
+        if opt.flight_sql_auth_file.is_empty() {
+            Err(anyhow::anyhow!(
+                "flight_sql_auth_file is required for {:?} authentication",
+                opt.flight_sql_auth
+            ))
+        } else {
+            Ok(opt.flight_sql_auth_file.clone())
+        }
+    };
+    let flight_sql_auth = match opt.flight_sql_auth {
+        FlightSqlAuth::Builtin => FlightSqlAuthConfig::Builtin,
+        FlightSqlAuth::StaticUsers => {
+            FlightSqlAuthConfig::StaticUsers(flight_sql_auth_file()?)
+        }
+        FlightSqlAuth::Htpasswd => FlightSqlAuthConfig::Htpasswd(flight_sql_auth_file()?),
+        FlightSqlAuth::Jwt => FlightSqlAuthConfig::Jwt(flight_sql_auth_file()?),
+    };
+
+    let grpc_tls = match (&opt.tls_cert_file, &opt.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => Some(
+            GrpcTlsConfig::try_from_pem_files(
+                cert_file,
+                key_file,
+                opt.tls_ca_cert_file.as_deref(),
+            )?
+            .with_client_auth(opt.tls_client_auth),
+        ),
+        (None, None) => None,
+        _ => anyhow::bail!("tls_cert_file and tls_key_file must be set together"),
+    };
+
     let config = SchedulerConfig {
         namespace: opt.namespace,
         external_host: opt.external_host,
@@ -143,6 +224,8 @@ async fn main() -> Result<()> {
         scheduling_policy: opt.scheduler_policy,
         event_loop_buffer_size: opt.event_loop_buffer_size,
         task_distribution,
+        job_scheduling,
+        speculation,
         finished_job_data_clean_up_interval_seconds: opt
             .finished_job_data_clean_up_interval_seconds,
         finished_job_state_clean_up_interval_seconds: opt
@@ -157,10 +240,19 @@ async fn main() -> Result<()> {
         grpc_server_max_decoding_message_size: opt.grpc_server_max_decoding_message_size,
         executor_timeout_seconds: opt.executor_timeout_seconds,
         expire_dead_executor_interval_seconds: opt.expire_dead_executor_interval_seconds,
+        executor_min_available_disk_bytes: opt.executor_min_available_disk_bytes,
+        max_running_jobs: opt.max_running_jobs,
+        max_pending_jobs: opt.max_pending_jobs,
+        max_jobs_per_session: opt.max_jobs_per_session,
+        job_history_location: opt.job_history_location,
+        job_history_retention_seconds: opt.job_history_retention_seconds,
+        flight_sql_auth,
+        grpc_tls,
     };
 
     let cluster = BallistaCluster::new_from_config(&config).await?;
 
     start_server(cluster, addr, Arc::new(config)).await?;
+    telemetry::shutdown();
     Ok(())
 }
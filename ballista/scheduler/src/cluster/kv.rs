@@ -18,14 +18,13 @@
 use crate::cluster::storage::{KeyValueStore, Keyspace, Lock, Operation, WatchEvent};
 use crate::cluster::{
     bind_task_bias, bind_task_consistent_hash, bind_task_round_robin, get_scan_files,
-    is_skip_consistent_hash, BoundTask, ClusterState, ExecutorHeartbeatStream,
-    ExecutorSlot, JobState, JobStateEvent, JobStateEventStream, JobStatus,
-    TaskDistributionPolicy, TopologyNode,
+    is_skip_consistent_hash, ActiveJobs, BoundTask, ClusterState,
+    ExecutorHeartbeatStream, ExecutorSlot, JobState, JobStateEvent, JobStateEventStream,
+    JobStatus, TaskDistributionPolicy, TaskResources, TopologyNode,
 };
 use crate::scheduler_server::{timestamp_secs, SessionBuilder};
 use crate::state::execution_graph::ExecutionGraph;
 use crate::state::session_manager::create_datafusion_context;
-use crate::state::task_manager::JobInfoCache;
 use crate::state::{decode_into, decode_protobuf};
 use async_trait::async_trait;
 use ballista_core::config::BallistaConfig;
@@ -162,7 +161,8 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                         .map(|heartbeat| heartbeat.timestamp)
                         .unwrap_or(0),
                     slots.slots,
-                );
+                )
+                .with_resources(slots);
                 if let Some(existing_node) = nodes.get(node.name()) {
                     if existing_node.last_seen_ts < node.last_seen_ts {
                         nodes.insert(node.name().to_string(), node);
@@ -217,7 +217,7 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
     async fn bind_schedulable_tasks(
         &self,
         distribution: TaskDistributionPolicy,
-        active_jobs: Arc<HashMap<String, JobInfoCache>>,
+        active_jobs: ActiveJobs,
         executors: Option<HashSet<String>>,
     ) -> Result<Vec<BoundTask>> {
         let lock = self.store.lock(Keyspace::Slots, "global").await?;
@@ -295,7 +295,7 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                         let ch_topology = ch_topology.unwrap();
                         for node in ch_topology.nodes() {
                             if let Some(data) = executor_data.get_mut(&node.id) {
-                                data.slots = node.available_slots;
+                                node.update(data);
                             } else {
                                 error!("Fail to find executor data for {}", &node.id);
                             }
@@ -319,9 +319,11 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
 
     async fn unbind_tasks(&self, executor_slots: Vec<ExecutorSlot>) -> Result<()> {
         let mut increments = HashMap::new();
-        for (executor_id, num_slots) in executor_slots {
-            let v = increments.entry(executor_id).or_insert_with(|| 0);
-            *v += num_slots;
+        for (executor_id, resources) in executor_slots {
+            let v = increments
+                .entry(executor_id)
+                .or_insert_with(TaskResources::default);
+            *v = *v + resources;
         }
 
         let lock = self.store.lock(Keyspace::Slots, "all").await?;
@@ -337,8 +339,8 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                 })?;
 
             for executor_slots in slots.task_slots.iter_mut() {
-                if let Some(slots) = increments.get(&executor_slots.executor_id) {
-                    executor_slots.slots += *slots;
+                if let Some(resources) = increments.get(&executor_slots.executor_id) {
+                    resources.release(executor_slots);
                 }
             }
 
@@ -374,6 +376,8 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
         let available_slots = AvailableTaskSlots {
             executor_id,
             slots: spec.available_task_slots,
+            memory: spec.available_memory,
+            cpus: spec.available_cpus,
         };
 
         let lock = self.store.lock(Keyspace::Slots, "all").await?;
@@ -545,6 +549,7 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                 job_name: job_name.clone(),
                 status: Some(Status::Queued(QueuedJob {
                     queued_at: *queued_at,
+                    position: 0,
                 })),
             }))
         } else {
@@ -604,6 +609,7 @@ impl<S: KeyValueStore, T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                     queued_at,
                     started_at: 0,
                     ended_at: 0,
+                    timed_out: false,
                 })),
             };
 
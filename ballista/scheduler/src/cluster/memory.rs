@@ -17,8 +17,9 @@
 
 use crate::cluster::{
     bind_task_bias, bind_task_consistent_hash, bind_task_round_robin, get_scan_files,
-    is_skip_consistent_hash, BoundTask, ClusterState, ExecutorSlot, JobState,
-    JobStateEvent, JobStateEventStream, JobStatus, TaskDistributionPolicy, TopologyNode,
+    is_skip_consistent_hash, ActiveJobs, BoundTask, ClusterState, ExecutorSlot, JobState,
+    JobStateEvent, JobStateEventStream, JobStatus, TaskDistributionPolicy, TaskResources,
+    TopologyNode,
 };
 use crate::state::execution_graph::ExecutionGraph;
 use async_trait::async_trait;
@@ -35,7 +36,6 @@ use datafusion::prelude::SessionContext;
 use crate::cluster::event::ClusterEventSender;
 use crate::scheduler_server::{timestamp_millis, timestamp_secs, SessionBuilder};
 use crate::state::session_manager::create_datafusion_context;
-use crate::state::task_manager::JobInfoCache;
 use ballista_core::serde::protobuf::job_status::Status;
 use log::{error, info, warn};
 use std::collections::{HashMap, HashSet};
@@ -81,7 +81,8 @@ impl InMemoryClusterState {
                         .map(|heartbeat| heartbeat.timestamp)
                         .unwrap_or(0),
                     slots.slots,
-                );
+                )
+                .with_resources(slots);
                 if let Some(existing_node) = nodes.get(node.name()) {
                     if existing_node.last_seen_ts < node.last_seen_ts {
                         nodes.insert(node.name().to_string(), node);
@@ -100,7 +101,7 @@ impl ClusterState for InMemoryClusterState {
     async fn bind_schedulable_tasks(
         &self,
         distribution: TaskDistributionPolicy,
-        active_jobs: Arc<HashMap<String, JobInfoCache>>,
+        active_jobs: ActiveJobs,
         executors: Option<HashSet<String>>,
     ) -> Result<Vec<BoundTask>> {
         let mut guard = self.task_slots.lock().await;
@@ -161,7 +162,7 @@ impl ClusterState for InMemoryClusterState {
                     let ch_topology = ch_topology.unwrap();
                     for node in ch_topology.nodes() {
                         if let Some(data) = guard.get_mut(&node.id) {
-                            data.slots = node.available_slots;
+                            node.update(data);
                         } else {
                             error!("Fail to find executor data for {}", &node.id);
                         }
@@ -176,16 +177,18 @@ impl ClusterState for InMemoryClusterState {
 
     async fn unbind_tasks(&self, executor_slots: Vec<ExecutorSlot>) -> Result<()> {
         let mut increments = HashMap::new();
-        for (executor_id, num_slots) in executor_slots {
-            let v = increments.entry(executor_id).or_insert_with(|| 0);
-            *v += num_slots;
+        for (executor_id, resources) in executor_slots {
+            let v = increments
+                .entry(executor_id)
+                .or_insert_with(TaskResources::default);
+            *v = *v + resources;
         }
 
         let mut guard = self.task_slots.lock().await;
 
-        for (executor_id, num_slots) in increments {
+        for (executor_id, resources) in increments {
             if let Some(data) = guard.get_mut(&executor_id) {
-                data.slots += num_slots;
+                resources.release(data);
             }
         }
 
@@ -217,6 +220,8 @@ impl ClusterState for InMemoryClusterState {
             AvailableTaskSlots {
                 executor_id,
                 slots: spec.available_task_slots,
+                memory: spec.available_memory,
+                cpus: spec.available_cpus,
             },
         );
 
@@ -310,14 +315,16 @@ impl InMemoryJobState {
 impl JobState for InMemoryJobState {
     async fn submit_job(&self, job_id: String, graph: &ExecutionGraph) -> Result<()> {
         if self.queued_jobs.get(&job_id).is_some() {
-            self.running_jobs
-                .insert(job_id.clone(), graph.status().clone());
+            let status = graph.status().clone();
+            self.running_jobs.insert(job_id.clone(), status.clone());
             self.queued_jobs.remove(&job_id);
 
             self.job_event_sender.send(&JobStateEvent::JobAcquired {
-                job_id,
+                job_id: job_id.clone(),
                 owner: self.scheduler.clone(),
             });
+            self.job_event_sender
+                .send(&JobStateEvent::JobUpdated { job_id, status });
 
             Ok(())
         } else {
@@ -334,6 +341,7 @@ impl JobState for InMemoryJobState {
                 job_name: job_name.clone(),
                 status: Some(Status::Queued(QueuedJob {
                     queued_at: *queued_at,
+                    position: 0,
                 })),
             }));
         }
@@ -374,17 +382,17 @@ impl JobState for InMemoryJobState {
             Some(Status::Successful(_)) | Some(Status::Failed(_))
         ) {
             self.completed_jobs
-                .insert(job_id.to_string(), (status, Some(graph.clone())));
+                .insert(job_id.to_string(), (status.clone(), Some(graph.clone())));
             self.running_jobs.remove(job_id);
-        } else if let Some(old_status) =
-            self.running_jobs.insert(job_id.to_string(), status)
-        {
-            self.job_event_sender.send(&JobStateEvent::JobUpdated {
-                job_id: job_id.to_string(),
-                status: old_status,
-            })
+        } else {
+            self.running_jobs.insert(job_id.to_string(), status.clone());
         }
 
+        self.job_event_sender.send(&JobStateEvent::JobUpdated {
+            job_id: job_id.to_string(),
+            status,
+        });
+
         Ok(())
     }
 
@@ -458,22 +466,22 @@ impl JobState for InMemoryJobState {
 
     async fn fail_unscheduled_job(&self, job_id: &str, reason: String) -> Result<()> {
         if let Some((job_id, (job_name, queued_at))) = self.queued_jobs.remove(job_id) {
-            self.completed_jobs.insert(
-                job_id.clone(),
-                (
-                    JobStatus {
-                        job_id,
-                        job_name,
-                        status: Some(Status::Failed(FailedJob {
-                            error: reason,
-                            queued_at,
-                            started_at: 0,
-                            ended_at: timestamp_millis(),
-                        })),
-                    },
-                    None,
-                ),
-            );
+            let status = JobStatus {
+                job_id: job_id.clone(),
+                job_name,
+                status: Some(Status::Failed(FailedJob {
+                    error: reason,
+                    queued_at,
+                    started_at: 0,
+                    ended_at: timestamp_millis(),
+                    timed_out: false,
+                })),
+            };
+            self.completed_jobs
+                .insert(job_id.clone(), (status.clone(), None));
+
+            self.job_event_sender
+                .send(&JobStateEvent::JobUpdated { job_id, status });
 
             Ok(())
         } else {
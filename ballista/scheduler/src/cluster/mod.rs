@@ -17,6 +17,8 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::iter::Sum;
+use std::ops::Add;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -49,9 +51,13 @@ use crate::cluster::memory::{InMemoryClusterState, InMemoryJobState};
 use crate::cluster::storage::etcd::EtcdClient;
 use crate::cluster::storage::sled::SledClient;
 use crate::cluster::storage::KeyValueStore;
-use crate::config::{ClusterStorageConfig, SchedulerConfig, TaskDistributionPolicy};
+use crate::config::{
+    ClusterStorageConfig, JobSchedulingPolicy, SchedulerConfig, TaskDistributionPolicy,
+};
 use crate::scheduler_server::SessionBuilder;
-use crate::state::execution_graph::{create_task_info, ExecutionGraph, TaskDescription};
+use crate::state::execution_graph::{
+    create_task_info, ExecutionGraph, JobProperties, TaskDescription,
+};
 use crate::state::task_manager::JobInfoCache;
 
 pub mod event;
@@ -212,8 +218,71 @@ pub type ExecutorHeartbeatStream = Pin<Box<dyn Stream<Item = ExecutorHeartbeat>
 /// BoundTask.0 is the executor id; While BoundTask.1 is the task description.
 pub type BoundTask = (String, TaskDescription);
 
-/// ExecutorSlot.0 is the executor id; While ExecutorSlot.1 is for slot number.
-pub type ExecutorSlot = (String, u32);
+/// ExecutorSlot.0 is the executor id; While ExecutorSlot.1 is for the reserved resources.
+pub type ExecutorSlot = (String, TaskResources);
+
+/// Resources reserved on an executor by bound tasks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskResources {
+    pub slots: u32,
+    /// Memory in bytes
+    pub memory: u64,
+    pub cpus: u32,
+}
+
+impl TaskResources {
+    /// Resources reserved by a single task of a job with the given properties
+    pub fn for_task(properties: &JobProperties) -> Self {
+        Self {
+            slots: 1,
+            memory: properties.task_memory,
+            cpus: properties.task_cpus,
+        }
+    }
+
+    /// Whether the available resources of an executor are enough for these ones
+    pub(crate) fn fits(&self, available: &AvailableTaskSlots) -> bool {
+        available.slots >= self.slots
+            && available.memory >= self.memory
+            && available.cpus >= self.cpus
+    }
+
+    /// Take these resources from the available resources of an executor
+    pub(crate) fn reserve(&self, available: &mut AvailableTaskSlots) {
+        available.slots -= self.slots;
+        available.memory -= self.memory;
+        available.cpus -= self.cpus;
+    }
+
+    /// Give these resources back to the available resources of an executor
+    pub(crate) fn release(&self, available: &mut AvailableTaskSlots) {
+        available.slots += self.slots;
+        // the resources of executors which don't limit them are u64::MAX and u32::MAX
+        available.memory = available.memory.saturating_add(self.memory);
+        available.cpus = available.cpus.saturating_add(self.cpus);
+    }
+}
+
+impl Add for TaskResources {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            slots: self.slots.saturating_add(other.slots),
+            memory: self.memory.saturating_add(other.memory),
+            cpus: self.cpus.saturating_add(other.cpus),
+        }
+    }
+}
+
+impl Sum for TaskResources {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The running jobs, in the order in which their tasks should be bound to executor slots
+pub type ActiveJobs = Arc<Vec<(String, JobInfoCache)>>;
 
 /// A trait that contains the necessary method to maintain a globally consistent view of cluster resources
 #[tonic::async_trait]
@@ -229,7 +298,7 @@ pub trait ClusterState: Send + Sync + 'static {
     async fn bind_schedulable_tasks(
         &self,
         distribution: TaskDistributionPolicy,
-        active_jobs: Arc<HashMap<String, JobInfoCache>>,
+        active_jobs: ActiveJobs,
         executors: Option<HashSet<String>>,
     ) -> Result<Vec<BoundTask>>;
 
@@ -378,9 +447,174 @@ pub trait JobState: Send + Sync {
     ) -> Result<Option<Arc<SessionContext>>>;
 }
 
+/// Order the running jobs by the job scheduling policy, so that the tasks of the jobs
+/// that come first are bound to executor slots first
+pub(crate) async fn order_active_jobs(
+    mut jobs: Vec<(String, JobInfoCache)>,
+    policy: &JobSchedulingPolicy,
+) -> Vec<(String, JobInfoCache)> {
+    let by_priority = |a: &JobInfoCache, b: &JobInfoCache| {
+        b.properties
+            .priority
+            .cmp(&a.properties.priority)
+            .then(a.queued_at.cmp(&b.queued_at))
+    };
+    match policy {
+        JobSchedulingPolicy::Fifo => {
+            jobs.sort_by_key(|(_, job)| job.queued_at);
+        }
+        JobSchedulingPolicy::Priority => {
+            jobs.sort_by(|(_, a), (_, b)| by_priority(a, b));
+        }
+        JobSchedulingPolicy::Fair { pool_weights } => {
+            let mut running_tasks: HashMap<String, usize> = HashMap::new();
+            for (_, job) in jobs.iter_mut() {
+                job.pool_weight =
+                    Some(pool_weights.get(&job.properties.pool).copied().unwrap_or(1));
+                let tasks = job.execution_graph.read().await.running_tasks().len();
+                *running_tasks
+                    .entry(job.properties.pool.clone())
+                    .or_default() += tasks;
+            }
+            let weight =
+                |pool: &str| pool_weights.get(pool).copied().unwrap_or(1) as usize;
+            // Serve the pool with the fewest running tasks relative to its weight first,
+            // comparing running_a / weight_a with running_b / weight_b
+            jobs.sort_by(|(_, a), (_, b)| {
+                let (pool_a, pool_b) = (&a.properties.pool, &b.properties.pool);
+                (running_tasks[pool_a] * weight(pool_b))
+                    .cmp(&(running_tasks[pool_b] * weight(pool_a)))
+                    .then_with(|| pool_a.cmp(pool_b))
+                    .then_with(|| by_priority(a, b))
+            });
+        }
+    }
+    jobs
+}
+
+/// The running jobs whose executor selector matches the given executor
+pub(crate) fn active_jobs_for_executor(
+    active_jobs: ActiveJobs,
+    executor: &ExecutorMetadata,
+) -> ActiveJobs {
+    let matches =
+        |job: &JobInfoCache| executor.matches(&job.properties.executor_selector);
+    if active_jobs.iter().all(|(_, job)| matches(job)) {
+        return active_jobs;
+    }
+    Arc::new(
+        active_jobs
+            .iter()
+            .filter(|(_, job)| matches(job))
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Split the running jobs into runs of consecutive jobs with the same executor selector,
+/// each paired with the IDs of the executors matching the selector. Binding the tasks
+/// of the runs one after the other keeps the order of the jobs.
+pub(crate) fn split_active_jobs_by_selector(
+    active_jobs: ActiveJobs,
+    executors: &[ExecutorMetadata],
+) -> Vec<(ActiveJobs, HashSet<String>)> {
+    let mut runs: Vec<(&HashMap<String, String>, Vec<(String, JobInfoCache)>)> = vec![];
+    for (job_id, job) in active_jobs.iter() {
+        let selector = &job.properties.executor_selector;
+        match runs.last_mut() {
+            Some((run_selector, jobs)) if *run_selector == selector => {
+                jobs.push((job_id.clone(), job.clone()))
+            }
+            _ => runs.push((selector, vec![(job_id.clone(), job.clone())])),
+        }
+    }
+    runs.into_iter()
+        .map(|(selector, jobs)| {
+            let executors = executors
+                .iter()
+                .filter(|executor| executor.matches(selector))
+                .map(|executor| executor.id.clone())
+                .collect();
+            (Arc::new(jobs), executors)
+        })
+        .collect()
+}
+
+/// The number of slots the jobs of each pool may be bound in one scheduling round under
+/// the fair job scheduling policy. The free slots are split between the pools with
+/// pending tasks in proportion to their weights, and the slots a pool cannot use are
+/// split between the others. Jobs of other policies are not limited.
+#[derive(Debug, Default)]
+pub(crate) struct PoolSlotQuotas {
+    quotas: HashMap<String, usize>,
+}
+
+impl PoolSlotQuotas {
+    pub(crate) async fn new(active_jobs: &ActiveJobs, total_slots: usize) -> Self {
+        // The weight and the number of pending tasks of each pool
+        let mut pools: HashMap<String, (usize, usize)> = HashMap::new();
+        for (_, job) in active_jobs.iter() {
+            if let Some(weight) = job.pool_weight {
+                let pending = job.execution_graph.read().await.available_tasks();
+                pools
+                    .entry(job.properties.pool.clone())
+                    .or_insert((weight.max(1) as usize, 0))
+                    .1 += pending;
+            }
+        }
+        let mut pools = pools
+            .into_iter()
+            .filter(|(_, (_, pending))| *pending > 0)
+            .collect::<Vec<_>>();
+        pools.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut quotas = HashMap::new();
+        let mut free_slots = total_slots;
+        while !pools.is_empty() && free_slots > 0 {
+            let total_weight: usize = pools.iter().map(|(_, (weight, _))| weight).sum();
+            // Pools with fewer pending tasks than their share can bind all of them
+            let (satisfied, unsatisfied): (Vec<_>, Vec<_>) =
+                pools.into_iter().partition(|(_, (weight, pending))| {
+                    pending * total_weight <= free_slots * weight
+                });
+            if satisfied.is_empty() {
+                let mut left_over = free_slots;
+                for (pool, (weight, _)) in unsatisfied.iter() {
+                    let share = free_slots * weight / total_weight;
+                    left_over -= share;
+                    quotas.insert(pool.clone(), share);
+                }
+                // Hand out the slots left over by rounding down one per pool
+                for (pool, _) in unsatisfied.iter().take(left_over) {
+                    *quotas.entry(pool.clone()).or_default() += 1;
+                }
+                break;
+            }
+            for (pool, (_, pending)) in satisfied {
+                free_slots -= pending;
+                quotas.insert(pool, pending);
+            }
+            pools = unsatisfied;
+        }
+        Self { quotas }
+    }
+
+    /// Take a slot from the quota of the pool. Returns false if the quota is used up.
+    pub(crate) fn try_take(&mut self, pool: &str) -> bool {
+        match self.quotas.get_mut(pool) {
+            Some(0) => false,
+            Some(quota) => {
+                *quota -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+}
+
 pub(crate) async fn bind_task_bias(
     mut slots: Vec<&mut AvailableTaskSlots>,
-    active_jobs: Arc<HashMap<String, JobInfoCache>>,
+    active_jobs: ActiveJobs,
     if_skip: fn(Arc<dyn ExecutionPlan>) -> bool,
 ) -> Vec<BoundTask> {
     let mut schedulable_tasks: Vec<BoundTask> = vec![];
@@ -394,9 +628,8 @@ pub(crate) async fn bind_task_bias(
     // Sort the slots by descending order
     slots.sort_by(|a, b| Ord::cmp(&b.slots, &a.slots));
 
-    let mut idx_slot = 0usize;
-    let mut slot = &mut slots[idx_slot];
-    for (job_id, job_info) in active_jobs.iter() {
+    let mut pool_quotas = PoolSlotQuotas::new(&active_jobs, total_slots as usize).await;
+    'jobs: for (job_id, job_info) in active_jobs.iter() {
         if !matches!(job_info.status, Some(job_status::Status::Running(_))) {
             debug!(
                 "Job {} is not in running status and will be skipped",
@@ -404,6 +637,7 @@ pub(crate) async fn bind_task_bias(
             );
             continue;
         }
+        let resources = TaskResources::for_task(&job_info.properties);
         let mut graph = job_info.execution_graph.write().await;
         let session_id = graph.session_id().to_string();
         let mut black_list = vec![];
@@ -420,6 +654,7 @@ pub(crate) async fn bind_task_bias(
             }
             // We are sure that it will at least bind one task by going through the following logic.
             // It will not go into a dead loop.
+            let trace_context = running_stage.trace_context();
             let runnable_tasks = running_stage
                 .task_infos
                 .iter_mut()
@@ -428,13 +663,23 @@ pub(crate) async fn bind_task_bias(
                 .take(total_slots as usize)
                 .collect::<Vec<_>>();
             for (partition_id, task_info) in runnable_tasks {
-                // Assign [`slot`] with a slot available slot number larger than 0
-                while slot.slots == 0 {
-                    idx_slot += 1;
-                    if idx_slot >= slots.len() {
-                        return schedulable_tasks;
+                // Assign [`slot`] with the first slot with enough resources for the task
+                let slot = match slots.iter_mut().find(|slot| resources.fits(slot)) {
+                    Some(slot) => slot,
+                    None => {
+                        debug!(
+                            "Not enough resources left for the tasks of job {}",
+                            job_id
+                        );
+                        continue 'jobs;
                     }
-                    slot = &mut slots[idx_slot];
+                };
+                if !pool_quotas.try_take(&job_info.properties.pool) {
+                    debug!(
+                        "Pool {} has used up its slots for the tasks of job {}",
+                        job_info.properties.pool, job_id
+                    );
+                    continue 'jobs;
                 }
                 let executor_id = slot.executor_id.clone();
                 let task_id = *task_id_gen;
@@ -454,20 +699,22 @@ pub(crate) async fn bind_task_bias(
                     task_attempt: running_stage.task_failure_numbers[partition_id],
                     data_cache: false,
                     plan: running_stage.plan.clone(),
+                    trace_context: trace_context.clone(),
                 };
                 schedulable_tasks.push((executor_id, task_desc));
 
-                slot.slots -= 1;
+                resources.reserve(slot);
             }
         }
     }
 
+    bind_speculative_tasks(&mut slots, &active_jobs, &mut schedulable_tasks).await;
     schedulable_tasks
 }
 
 pub(crate) async fn bind_task_round_robin(
     mut slots: Vec<&mut AvailableTaskSlots>,
-    active_jobs: Arc<HashMap<String, JobInfoCache>>,
+    active_jobs: ActiveJobs,
     if_skip: fn(Arc<dyn ExecutionPlan>) -> bool,
 ) -> Vec<BoundTask> {
     let mut schedulable_tasks: Vec<BoundTask> = vec![];
@@ -482,8 +729,9 @@ pub(crate) async fn bind_task_round_robin(
     // Sort the slots by descending order
     slots.sort_by(|a, b| Ord::cmp(&b.slots, &a.slots));
 
+    let mut pool_quotas = PoolSlotQuotas::new(&active_jobs, total_slots as usize).await;
     let mut idx_slot = 0usize;
-    for (job_id, job_info) in active_jobs.iter() {
+    'jobs: for (job_id, job_info) in active_jobs.iter() {
         if !matches!(job_info.status, Some(job_status::Status::Running(_))) {
             debug!(
                 "Job {} is not in running status and will be skipped",
@@ -491,6 +739,7 @@ pub(crate) async fn bind_task_round_robin(
             );
             continue;
         }
+        let resources = TaskResources::for_task(&job_info.properties);
         let mut graph = job_info.execution_graph.write().await;
         let session_id = graph.session_id().to_string();
         let mut black_list = vec![];
@@ -507,6 +756,7 @@ pub(crate) async fn bind_task_round_robin(
             }
             // We are sure that it will at least bind one task by going through the following logic.
             // It will not go into a dead loop.
+            let trace_context = running_stage.trace_context();
             let runnable_tasks = running_stage
                 .task_infos
                 .iter_mut()
@@ -515,15 +765,27 @@ pub(crate) async fn bind_task_round_robin(
                 .take(total_slots as usize)
                 .collect::<Vec<_>>();
             for (partition_id, task_info) in runnable_tasks {
-                // Move to the index which has available slots
-                if idx_slot >= slots.len() {
-                    idx_slot = 0;
+                // Move back to the first slot with enough resources for the task if
+                // the next one doesn't have them
+                if idx_slot >= slots.len() || !resources.fits(&slots[idx_slot]) {
+                    match slots.iter().position(|slot| resources.fits(slot)) {
+                        Some(idx) => idx_slot = idx,
+                        None => {
+                            debug!(
+                                "Not enough resources left for the tasks of job {}",
+                                job_id
+                            );
+                            continue 'jobs;
+                        }
+                    }
                 }
-                if slots[idx_slot].slots == 0 {
-                    idx_slot = 0;
+                if !pool_quotas.try_take(&job_info.properties.pool) {
+                    debug!(
+                        "Pool {} has used up its slots for the tasks of job {}",
+                        job_info.properties.pool, job_id
+                    );
+                    continue 'jobs;
                 }
-                // Since the slots is a vector with descending order, and the total available slots is larger than 0,
-                // we are sure the available slot number at idx_slot is larger than 1
                 let slot = &mut slots[idx_slot];
                 let executor_id = slot.executor_id.clone();
                 let task_id = *task_id_gen;
@@ -543,11 +805,12 @@ pub(crate) async fn bind_task_round_robin(
                     task_attempt: running_stage.task_failure_numbers[partition_id],
                     data_cache: false,
                     plan: running_stage.plan.clone(),
+                    trace_context: trace_context.clone(),
                 };
                 schedulable_tasks.push((executor_id, task_desc));
 
                 idx_slot += 1;
-                slot.slots -= 1;
+                resources.reserve(slot);
                 total_slots -= 1;
                 if total_slots == 0 {
                     return schedulable_tasks;
@@ -556,9 +819,36 @@ pub(crate) async fn bind_task_round_robin(
         }
     }
 
+    bind_speculative_tasks(&mut slots, &active_jobs, &mut schedulable_tasks).await;
     schedulable_tasks
 }
 
+/// Bind the speculative copies of straggler tasks to the slots left over by the regular
+/// tasks. A copy is never bound to the executor running the original task.
+async fn bind_speculative_tasks(
+    slots: &mut [&mut AvailableTaskSlots],
+    active_jobs: &ActiveJobs,
+    schedulable_tasks: &mut Vec<BoundTask>,
+) {
+    for (_, job_info) in active_jobs.iter() {
+        if slots.iter().all(|slot| slot.slots == 0) {
+            return;
+        }
+        if !matches!(job_info.status, Some(job_status::Status::Running(_))) {
+            continue;
+        }
+        let resources = TaskResources::for_task(&job_info.properties);
+        let mut graph = job_info.execution_graph.write().await;
+        schedulable_tasks.extend(graph.bind_speculative_tasks(|original_executor| {
+            let slot = slots.iter_mut().find(|slot| {
+                resources.fits(slot) && slot.executor_id != original_executor
+            })?;
+            resources.reserve(slot);
+            Some(slot.executor_id.clone())
+        }));
+    }
+}
+
 type GetScanFilesFunc = fn(
     &str,
     Arc<dyn ExecutionPlan>,
@@ -568,7 +858,7 @@ pub(crate) async fn bind_task_consistent_hash(
     topology_nodes: HashMap<String, TopologyNode>,
     num_replicas: usize,
     tolerance: usize,
-    active_jobs: Arc<HashMap<String, JobInfoCache>>,
+    active_jobs: ActiveJobs,
     get_scan_files: GetScanFilesFunc,
 ) -> Result<(Vec<BoundTask>, Option<ConsistentHash<TopologyNode>>)> {
     let mut total_slots = 0usize;
@@ -597,6 +887,7 @@ pub(crate) async fn bind_task_consistent_hash(
             );
             continue;
         }
+        let resources = TaskResources::for_task(&job_info.properties);
         let mut graph = job_info.execution_graph.write().await;
         let session_id = graph.session_id().to_string();
         let mut black_list = vec![];
@@ -614,6 +905,7 @@ pub(crate) async fn bind_task_consistent_hash(
             }
             let pre_total_slots = total_slots;
             let scan_files = &scan_files[0];
+            let trace_context = running_stage.trace_context();
             let tolerance_list = vec![0, tolerance];
             // First round with 0 tolerance consistent hashing policy
             // Second round with [`tolerance`] tolerance consistent hashing policy
@@ -632,10 +924,13 @@ pub(crate) async fn bind_task_consistent_hash(
                     // Later when splitting files for tasks in datafusion, it's better to
                     // introduce this hash based policy besides the file number policy or file size policy.
                     let file_for_hash = &partition_files[0];
-                    if let Some(node) = ch_topology.get_mut_with_tolerance(
-                        file_for_hash.object_meta.location.as_ref().as_bytes(),
-                        tolerance,
-                    ) {
+                    if let Some(node) = ch_topology
+                        .get_mut_with_tolerance(
+                            file_for_hash.object_meta.location.as_ref().as_bytes(),
+                            tolerance,
+                        )
+                        .filter(|node| node.fits(&resources))
+                    {
                         let executor_id = node.id.clone();
                         let task_id = *task_id_gen;
                         *task_id_gen += 1;
@@ -656,10 +951,11 @@ pub(crate) async fn bind_task_consistent_hash(
                                 [partition_id],
                             data_cache,
                             plan: running_stage.plan.clone(),
+                            trace_context: trace_context.clone(),
                         };
                         schedulable_tasks.push((executor_id, task_desc));
 
-                        node.available_slots -= 1;
+                        node.reserve(&resources);
                         total_slots -= 1;
                         if total_slots == 0 {
                             return Ok((schedulable_tasks, Some(ch_topology)));
@@ -716,6 +1012,8 @@ pub struct TopologyNode {
     pub name: String,
     pub last_seen_ts: u64,
     pub available_slots: u32,
+    pub available_memory: u64,
+    pub available_cpus: u32,
 }
 
 impl TopologyNode {
@@ -731,8 +1029,36 @@ impl TopologyNode {
             name: format!("{host}:{port}"),
             last_seen_ts,
             available_slots,
+            available_memory: u64::MAX,
+            available_cpus: u32::MAX,
         }
     }
+
+    fn with_resources(mut self, available: &AvailableTaskSlots) -> Self {
+        self.available_slots = available.slots;
+        self.available_memory = available.memory;
+        self.available_cpus = available.cpus;
+        self
+    }
+
+    fn fits(&self, resources: &TaskResources) -> bool {
+        self.available_slots >= resources.slots
+            && self.available_memory >= resources.memory
+            && self.available_cpus >= resources.cpus
+    }
+
+    fn reserve(&mut self, resources: &TaskResources) {
+        self.available_slots -= resources.slots;
+        self.available_memory -= resources.memory;
+        self.available_cpus -= resources.cpus;
+    }
+
+    /// Update the available resources of an executor with the ones left on this node
+    fn update(&self, available: &mut AvailableTaskSlots) {
+        available.slots = self.available_slots;
+        available.memory = self.available_memory;
+        available.cpus = self.available_cpus;
+    }
 }
 
 impl consistent_hash::node::Node for TopologyNode {
@@ -759,10 +1085,11 @@ mod test {
     use ballista_core::serde::scheduler::{ExecutorMetadata, ExecutorSpecification};
 
     use crate::cluster::{
-        bind_task_bias, bind_task_consistent_hash, bind_task_round_robin, BoundTask,
-        TopologyNode,
+        bind_task_bias, bind_task_consistent_hash, bind_task_round_robin,
+        order_active_jobs, split_active_jobs_by_selector, BoundTask, TopologyNode,
     };
-    use crate::state::execution_graph::ExecutionGraph;
+    use crate::config::JobSchedulingPolicy;
+    use crate::state::execution_graph::{ExecutionGraph, JobProperties};
     use crate::state::task_manager::JobInfoCache;
     use crate::test_utils::{mock_completed_task, test_aggregation_plan_with_job_id};
 
@@ -878,6 +1205,45 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_bind_task_bias_with_task_memory() -> Result<()> {
+        const GB: u64 = 1 << 30;
+        let graph = mock_graph("job_a", 8, 7)
+            .await?
+            .with_properties(JobProperties {
+                task_memory: 4 * GB,
+                ..Default::default()
+            });
+        let active_jobs = vec![(graph.job_id().to_string(), JobInfoCache::new(graph))];
+        let mut available_slots = mock_available_slots();
+        for (slots, memory) in available_slots.iter_mut().zip([16 * GB, 4 * GB, 8 * GB]) {
+            slots.memory = memory;
+        }
+        let available_slots_ref: Vec<&mut AvailableTaskSlots> =
+            available_slots.iter_mut().collect();
+
+        let bound_tasks =
+            bind_task_bias(available_slots_ref, Arc::new(active_jobs), |_| false).await;
+
+        // The executors with the most slots run out of memory first, and the last task
+        // doesn't fit anywhere
+        let mut entry_a = HashMap::new();
+        entry_a.insert("executor_3".to_string(), 2);
+        entry_a.insert("executor_2".to_string(), 1);
+        entry_a.insert("executor_1".to_string(), 3);
+        let mut expected = HashMap::new();
+        expected.insert("job_a".to_string(), entry_a);
+        assert_eq!(expected, get_result(bound_tasks));
+
+        let available = available_slots
+            .iter()
+            .map(|slots| (slots.slots, slots.memory))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(0, 4 * GB), (4, 0), (5, 0)], available);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_bind_task_consistent_hash() -> Result<()> {
         let num_partition = 8usize;
@@ -976,6 +1342,166 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_order_active_jobs() -> Result<()> {
+        let jobs = vec![
+            mock_job("job_a", 2, 0, "etl", 1).await?,
+            mock_job("job_b", 0, 5, "etl", 2).await?,
+            mock_job("job_c", 1, 0, "adhoc", 3).await?,
+        ];
+        let order = |policy: JobSchedulingPolicy| {
+            let jobs = jobs.clone();
+            async move {
+                order_active_jobs(jobs, &policy)
+                    .await
+                    .into_iter()
+                    .map(|(job_id, _)| job_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            vec!["job_a", "job_b", "job_c"],
+            order(JobSchedulingPolicy::Fifo).await
+        );
+        assert_eq!(
+            vec!["job_b", "job_a", "job_c"],
+            order(JobSchedulingPolicy::Priority).await
+        );
+        // The adhoc pool has fewer running tasks than the etl pool
+        assert_eq!(
+            vec!["job_c", "job_b", "job_a"],
+            order(JobSchedulingPolicy::Fair {
+                pool_weights: HashMap::new()
+            })
+            .await
+        );
+        // With weight 3, 2 running tasks are less than the fair share of the etl pool
+        assert_eq!(
+            vec!["job_b", "job_a", "job_c"],
+            order(JobSchedulingPolicy::Fair {
+                pool_weights: HashMap::from([("etl".to_string(), 3)])
+            })
+            .await
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bind_task_fair() -> Result<()> {
+        // Each job has 8 pending tasks
+        let job = |job_id: &str, pool: &str| {
+            let (job_id, pool) = (job_id.to_string(), pool.to_string());
+            async move {
+                let graph =
+                    mock_graph(&job_id, 8, 8)
+                        .await?
+                        .with_properties(JobProperties {
+                            pool,
+                            ..Default::default()
+                        });
+                Result::Ok((job_id, JobInfoCache::new(graph)))
+            }
+        };
+        let policy = JobSchedulingPolicy::Fair {
+            pool_weights: HashMap::from([("etl".to_string(), 3)]),
+        };
+
+        // Both pools have more pending tasks than their share of the 8 free slots
+        let jobs = order_active_jobs(
+            vec![
+                job("job_a", "etl").await?,
+                job("job_b", "etl").await?,
+                job("job_c", "adhoc").await?,
+            ],
+            &policy,
+        )
+        .await;
+        let mut available_slots = mock_available_slots();
+        available_slots.truncate(1);
+        available_slots[0].slots = 8;
+        let bound_tasks =
+            bind_task_bias(available_slots.iter_mut().collect(), Arc::new(jobs), |_| {
+                false
+            })
+            .await;
+        let result = get_result(bound_tasks);
+        assert_eq!(6, result["job_a"]["executor_1"]);
+        assert!(!result.contains_key("job_b"));
+        assert_eq!(2, result["job_c"]["executor_1"]);
+
+        // The slots the etl pool cannot use go to the adhoc pool
+        let jobs = order_active_jobs(
+            vec![job("job_a", "etl").await?, job("job_c", "adhoc").await?],
+            &policy,
+        )
+        .await;
+        let mut available_slots = mock_available_slots();
+        available_slots.truncate(1);
+        available_slots[0].slots = 12;
+        let bound_tasks = bind_task_round_robin(
+            available_slots.iter_mut().collect(),
+            Arc::new(jobs),
+            |_| false,
+        )
+        .await;
+        let result = get_result(bound_tasks);
+        assert_eq!(8, result["job_a"]["executor_1"]);
+        assert_eq!(4, result["job_c"]["executor_1"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_active_jobs_by_selector() -> Result<()> {
+        let etl = HashMap::from([("team".to_string(), "etl".to_string())]);
+        let mut jobs = vec![
+            mock_job("job_a", 0, 0, "default", 1).await?,
+            mock_job("job_b", 0, 0, "default", 2).await?,
+            mock_job("job_c", 0, 0, "default", 3).await?,
+            mock_job("job_d", 0, 0, "default", 4).await?,
+        ];
+        jobs[1].1.properties.executor_selector = etl.clone();
+        jobs[2].1.properties.executor_selector = etl.clone();
+        let executor = |id: &str, labels: HashMap<String, String>| ExecutorMetadata {
+            id: id.to_string(),
+            host: "localhost".to_string(),
+            port: 50051,
+            grpc_port: 50052,
+            specification: ExecutorSpecification::new(4),
+            labels,
+        };
+        let executors = vec![
+            executor("executor_1", HashMap::new()),
+            executor("executor_2", etl),
+        ];
+
+        let runs = split_active_jobs_by_selector(Arc::new(jobs), &executors)
+            .into_iter()
+            .map(|(jobs, executors)| {
+                let job_ids = jobs
+                    .iter()
+                    .map(|(job_id, _)| job_id.clone())
+                    .collect::<Vec<_>>();
+                let mut executors = executors.into_iter().collect::<Vec<_>>();
+                executors.sort();
+                (job_ids, executors)
+            })
+            .collect::<Vec<_>>();
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (ids(&["job_a"]), ids(&["executor_1", "executor_2"])),
+                (ids(&["job_b", "job_c"]), ids(&["executor_2"])),
+                (ids(&["job_d"]), ids(&["executor_1", "executor_2"])),
+            ],
+            runs
+        );
+
+        Ok(())
+    }
+
     fn get_result(
         bound_tasks: Vec<BoundTask>,
     ) -> HashMap<String, HashMap<String, usize>> {
@@ -994,16 +1520,39 @@ mod test {
 
     async fn mock_active_jobs(
         num_partition: usize,
-    ) -> Result<HashMap<String, JobInfoCache>> {
+    ) -> Result<Vec<(String, JobInfoCache)>> {
         let graph_a = mock_graph("job_a", num_partition, 2).await?;
 
         let graph_b = mock_graph("job_b", num_partition, 7).await?;
 
-        let mut active_jobs = HashMap::new();
-        active_jobs.insert(graph_a.job_id().to_string(), JobInfoCache::new(graph_a));
-        active_jobs.insert(graph_b.job_id().to_string(), JobInfoCache::new(graph_b));
+        Ok(vec![
+            (graph_a.job_id().to_string(), JobInfoCache::new(graph_a)),
+            (graph_b.job_id().to_string(), JobInfoCache::new(graph_b)),
+        ])
+    }
+
+    async fn mock_job(
+        job_id: &str,
+        num_running_task: usize,
+        priority: u32,
+        pool: &str,
+        queued_at: u64,
+    ) -> Result<(String, JobInfoCache)> {
+        let mut graph = test_aggregation_plan_with_job_id(4, job_id)
+            .await
+            .with_properties(JobProperties {
+                priority,
+                pool: pool.to_string(),
+                ..Default::default()
+            });
+        graph.revive();
+        for _ in 0..num_running_task {
+            graph.pop_next_task("executor_0")?;
+        }
 
-        Ok(active_jobs)
+        let mut job_info = JobInfoCache::new(graph);
+        job_info.queued_at = queued_at;
+        Ok((job_id.to_string(), job_info))
     }
 
     async fn mock_graph(
@@ -1017,7 +1566,8 @@ mod test {
             host: "localhost".to_string(),
             port: 50051,
             grpc_port: 50052,
-            specification: ExecutorSpecification { task_slots: 32 },
+            specification: ExecutorSpecification::new(32),
+            labels: Default::default(),
         };
 
         if let Some(task) = graph.pop_next_task(&executor.id)? {
@@ -1042,14 +1592,20 @@ mod test {
             AvailableTaskSlots {
                 executor_id: "executor_1".to_string(),
                 slots: 3,
+                memory: u64::MAX,
+                cpus: u32::MAX,
             },
             AvailableTaskSlots {
                 executor_id: "executor_2".to_string(),
                 slots: 5,
+                memory: u64::MAX,
+                cpus: u32::MAX,
             },
             AvailableTaskSlots {
                 executor_id: "executor_3".to_string(),
                 slots: 7,
+                memory: u64::MAX,
+                cpus: u32::MAX,
             },
         ]
     }
@@ -173,6 +173,11 @@ pub async fn test_job_lifecycle<S: JobState>(
         .submit_job(&graph)
         .await?
         .assert_job_running(&job_id)
+        .await?
+        .assert_event(JobStateEvent::JobUpdated {
+            job_id: job_id.clone(),
+            status: graph.status().clone(),
+        })
         .await?;
 
     drain_tasks(&mut graph)?;
@@ -181,6 +186,11 @@ pub async fn test_job_lifecycle<S: JobState>(
     test.update_job(&graph)
         .await?
         .assert_job_successful(&job_id)
+        .await?
+        .assert_event(JobStateEvent::JobUpdated {
+            job_id: job_id.clone(),
+            status: graph.status().clone(),
+        })
         .await?;
 
     Ok(())
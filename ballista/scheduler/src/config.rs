@@ -19,7 +19,9 @@
 //! Ballista scheduler specific configuration
 
 use ballista_core::config::TaskSchedulingPolicy;
+use ballista_core::utils::GrpcTlsConfig;
 use clap::ArgEnum;
+use std::collections::HashMap;
 use std::fmt;
 
 /// Configurations for the ballista scheduler of scheduling jobs and tasks
@@ -38,6 +40,11 @@ pub struct SchedulerConfig {
     pub event_loop_buffer_size: u32,
     /// Policy of distributing tasks to available executor slots. For a cluster with single scheduler, round-robin is recommended
     pub task_distribution: TaskDistributionPolicy,
+    /// Policy of ordering the running jobs when binding their tasks to executor slots
+    pub job_scheduling: JobSchedulingPolicy,
+    /// If provided, copies of straggler tasks are launched on other executors and the
+    /// attempt finishing first wins
+    pub speculation: Option<SpeculationConfig>,
     /// The delayed interval for cleaning up finished job data, mainly the shuffle data, 0 means the cleaning up is disabled
     pub finished_job_data_clean_up_interval_seconds: u64,
     /// The delayed interval for cleaning up finished job state stored in the backend, 0 means the cleaning up is disabled.
@@ -60,6 +67,29 @@ pub struct SchedulerConfig {
     pub executor_timeout_seconds: u64,
     /// The interval to check expired or dead executors
     pub expire_dead_executor_interval_seconds: u64,
+    /// The minimum free space in bytes of the work dir disk of an executor, reported in
+    /// its heartbeats, for tasks to be assigned to it. Zero means disable.
+    pub executor_min_available_disk_bytes: u64,
+    /// The maximum number of jobs being planned or running at the same time. Further jobs
+    /// wait in a queue until running jobs finish. Zero means unlimited.
+    pub max_running_jobs: usize,
+    /// The maximum number of jobs waiting for running jobs to finish, beyond which new
+    /// jobs are rejected. Zero means unlimited.
+    pub max_pending_jobs: usize,
+    /// The maximum number of unfinished jobs of a session, beyond which new jobs of the
+    /// session are rejected. Zero means unlimited.
+    pub max_jobs_per_session: usize,
+    /// If provided, the execution graphs of finished jobs are archived to this local
+    /// directory or object store URL, and served by the history REST API
+    pub job_history_location: Option<String>,
+    /// Time in seconds after which archived jobs are deleted from the job history,
+    /// counted from when the jobs were queued. Zero means jobs are kept forever.
+    pub job_history_retention_seconds: u64,
+    /// How clients of the Flight SQL service are authenticated
+    pub flight_sql_auth: FlightSqlAuthConfig,
+    /// TLS settings of the scheduler's services and of the connections to executors.
+    /// Plaintext is used if `None`
+    pub grpc_tls: Option<GrpcTlsConfig>,
 }
 
 impl Default for SchedulerConfig {
@@ -71,6 +101,8 @@ impl Default for SchedulerConfig {
             scheduling_policy: TaskSchedulingPolicy::PullStaged,
             event_loop_buffer_size: 10000,
             task_distribution: TaskDistributionPolicy::Bias,
+            job_scheduling: JobSchedulingPolicy::Fifo,
+            speculation: None,
             finished_job_data_clean_up_interval_seconds: 300,
             finished_job_state_clean_up_interval_seconds: 3600,
             advertise_flight_sql_endpoint: None,
@@ -81,6 +113,14 @@ impl Default for SchedulerConfig {
             grpc_server_max_decoding_message_size: 16777216,
             executor_timeout_seconds: 180,
             expire_dead_executor_interval_seconds: 15,
+            executor_min_available_disk_bytes: 0,
+            max_running_jobs: 0,
+            max_pending_jobs: 0,
+            max_jobs_per_session: 0,
+            job_history_location: None,
+            job_history_retention_seconds: 0,
+            flight_sql_auth: FlightSqlAuthConfig::Builtin,
+            grpc_tls: None,
         }
     }
 }
@@ -148,6 +188,16 @@ impl SchedulerConfig {
         self
     }
 
+    pub fn with_job_scheduling(mut self, policy: JobSchedulingPolicy) -> Self {
+        self.job_scheduling = policy;
+        self
+    }
+
+    pub fn with_speculation(mut self, config: SpeculationConfig) -> Self {
+        self.speculation = Some(config);
+        self
+    }
+
     pub fn with_cluster_storage(mut self, config: ClusterStorageConfig) -> Self {
         self.cluster_storage = config;
         self
@@ -167,6 +217,46 @@ impl SchedulerConfig {
         self.grpc_server_max_decoding_message_size = value;
         self
     }
+
+    pub fn with_executor_min_available_disk_bytes(mut self, value: u64) -> Self {
+        self.executor_min_available_disk_bytes = value;
+        self
+    }
+
+    pub fn with_max_running_jobs(mut self, value: usize) -> Self {
+        self.max_running_jobs = value;
+        self
+    }
+
+    pub fn with_max_pending_jobs(mut self, value: usize) -> Self {
+        self.max_pending_jobs = value;
+        self
+    }
+
+    pub fn with_max_jobs_per_session(mut self, value: usize) -> Self {
+        self.max_jobs_per_session = value;
+        self
+    }
+
+    pub fn with_job_history_location(mut self, location: impl Into<String>) -> Self {
+        self.job_history_location = Some(location.into());
+        self
+    }
+
+    pub fn with_job_history_retention_seconds(mut self, value: u64) -> Self {
+        self.job_history_retention_seconds = value;
+        self
+    }
+
+    pub fn with_flight_sql_auth(mut self, config: FlightSqlAuthConfig) -> Self {
+        self.flight_sql_auth = config;
+        self
+    }
+
+    pub fn with_grpc_tls(mut self, config: GrpcTlsConfig) -> Self {
+        self.grpc_tls = Some(config);
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -178,6 +268,48 @@ pub enum ClusterStorageConfig {
     Sled(Option<String>),
 }
 
+/// Authentication method of the Flight SQL service
+///
+/// It needs to be visible to code generated by configure_me
+#[derive(Clone, ArgEnum, Copy, Debug, serde::Deserialize)]
+pub enum FlightSqlAuth {
+    /// Only accept the built-in `admin` user with password `password`
+    Builtin,
+    /// Accept the users of a file in which each line is `user:password`
+    StaticUsers,
+    /// Accept the users of an htpasswd file with bcrypt hashed passwords
+    Htpasswd,
+    /// Accept bearer JSON Web Tokens signed with a local secret or RSA public key
+    Jwt,
+}
+
+impl std::str::FromStr for FlightSqlAuth {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ArgEnum::from_str(s, true)
+    }
+}
+
+impl parse_arg::ParseArgFromStr for FlightSqlAuth {
+    fn describe_type<W: fmt::Write>(mut writer: W) -> fmt::Result {
+        write!(
+            writer,
+            "The authentication method of the Flight SQL service"
+        )
+    }
+}
+
+/// Authentication of the Flight SQL service, with the path of the file holding the
+/// users or key where needed
+#[derive(Clone, Debug)]
+pub enum FlightSqlAuthConfig {
+    Builtin,
+    StaticUsers(String),
+    Htpasswd(String),
+    Jwt(String),
+}
+
 /// Policy of distributing tasks to available executor slots
 ///
 /// It needs to be visible to code generated by configure_me
@@ -227,3 +359,112 @@ pub enum TaskDistributionPolicy {
         tolerance: usize,
     },
 }
+
+/// Policy of ordering the running jobs when binding their tasks to executor slots
+///
+/// It needs to be visible to code generated by configure_me
+#[derive(Clone, ArgEnum, Copy, Debug, serde::Deserialize)]
+pub enum JobScheduling {
+    /// Serve jobs in the order in which they were queued
+    Fifo,
+    /// Serve jobs with a higher `ballista.job.priority` first
+    Priority,
+    /// Share executor slots across the pools set by `ballista.job.pool`, weighted by
+    /// the pool weights
+    Fair,
+}
+
+impl std::str::FromStr for JobScheduling {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ArgEnum::from_str(s, true)
+    }
+}
+
+impl parse_arg::ParseArgFromStr for JobScheduling {
+    fn describe_type<W: fmt::Write>(mut writer: W) -> fmt::Result {
+        write!(writer, "The job scheduling policy for the scheduler")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JobSchedulingPolicy {
+    /// Serve jobs in the order in which they were queued
+    Fifo,
+    /// Serve jobs with a higher priority first, and jobs of the same priority in the
+    /// order in which they were queued
+    Priority,
+    /// Serve the pool with the fewest running tasks relative to its weight first and
+    /// split the free slots between the pools in proportion to their weights. Jobs
+    /// within a pool are served by priority. Pools without a weight have weight 1.
+    Fair { pool_weights: HashMap<String, u32> },
+}
+
+impl JobSchedulingPolicy {
+    /// Parse pool weights given as a comma separated list of `pool=weight`
+    pub fn parse_pool_weights(
+        weights: &str,
+    ) -> std::result::Result<HashMap<String, u32>, String> {
+        weights
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (pool, weight) = s.split_once('=').ok_or_else(|| {
+                    format!("Invalid pool weight {s}, expected pool=weight")
+                })?;
+                match weight.trim().parse::<u32>() {
+                    Ok(weight) if weight > 0 => Ok((pool.trim().to_string(), weight)),
+                    _ => Err(format!("Invalid weight of pool {pool}: {weight}")),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Settings of the speculative execution of straggler tasks
+#[derive(Debug, Clone)]
+pub struct SpeculationConfig {
+    /// A running task is a straggler once it has run this many times longer than the
+    /// median successful task of its stage
+    pub multiplier: f64,
+    /// The fraction of the tasks of a stage which must have succeeded before the
+    /// stragglers of the stage are looked for
+    pub quantile: f64,
+    /// Tasks which have run for less than this time are never speculated
+    pub min_task_duration_ms: u64,
+    /// The interval to look for stragglers
+    pub interval_ms: u64,
+}
+
+impl Default for SpeculationConfig {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.5,
+            quantile: 0.75,
+            min_task_duration_ms: 1000,
+            interval_ms: 1000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JobSchedulingPolicy;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_pool_weights() {
+        assert_eq!(
+            JobSchedulingPolicy::parse_pool_weights("etl=1, adhoc = 3").unwrap(),
+            HashMap::from([("etl".to_string(), 1), ("adhoc".to_string(), 3)])
+        );
+        assert!(JobSchedulingPolicy::parse_pool_weights("")
+            .unwrap()
+            .is_empty());
+        assert!(JobSchedulingPolicy::parse_pool_weights("etl").is_err());
+        assert!(JobSchedulingPolicy::parse_pool_weights("etl=0").is_err());
+        assert!(JobSchedulingPolicy::parse_pool_weights("etl=x").is_err());
+    }
+}
@@ -17,6 +17,10 @@
 
 use arrow_flight::flight_descriptor::DescriptorType;
 use arrow_flight::flight_service_server::FlightService;
+use arrow_flight::sql::metadata::{
+    SqlInfoData, SqlInfoDataBuilder, XdbcTypeInfo, XdbcTypeInfoData,
+    XdbcTypeInfoDataBuilder,
+};
 use arrow_flight::sql::server::FlightSqlService;
 use arrow_flight::sql::{
     ActionBeginSavepointRequest, ActionBeginSavepointResult,
@@ -29,13 +33,16 @@ use arrow_flight::sql::{
     CommandGetImportedKeys, CommandGetPrimaryKeys, CommandGetSqlInfo,
     CommandGetTableTypes, CommandGetTables, CommandGetXdbcTypeInfo,
     CommandPreparedStatementQuery, CommandPreparedStatementUpdate, CommandStatementQuery,
-    CommandStatementSubstraitPlan, CommandStatementUpdate, SqlInfo, TicketStatementQuery,
+    CommandStatementSubstraitPlan, CommandStatementUpdate, Nullable, Searchable, SqlInfo,
+    SqlNullOrdering, SqlOuterJoinsSupportLevel, SqlSupportedCaseSensitivity,
+    SqlSupportedGroupBy, SqlSupportedUnions, TicketStatementQuery, XdbcDataType,
 };
 use arrow_flight::{
     Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
-    HandshakeResponse, Location, Ticket,
+    HandshakeResponse, Location, PutResult, Ticket,
 };
 use log::{debug, error, warn};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::pin::Pin;
 use std::str::FromStr;
@@ -44,10 +51,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use tonic::{Request, Response, Status, Streaming};
 
+use crate::auth::{Authenticator, Credentials, Principal};
 use crate::scheduler_server::SchedulerServer;
+use crate::state::execution_graph::JobProperties;
 use arrow_flight::flight_service_client::FlightServiceClient;
 use arrow_flight::sql::ProstMessageExt;
-use arrow_flight::utils::batches_to_flight_data;
+use arrow_flight::utils::{batches_to_flight_data, flight_data_to_arrow_batch};
 use arrow_flight::SchemaAsIpc;
 use ballista_core::config::BallistaConfig;
 use ballista_core::serde::protobuf;
@@ -56,16 +65,21 @@ use ballista_core::serde::protobuf::job_status;
 use ballista_core::serde::protobuf::JobStatus;
 use ballista_core::serde::protobuf::SuccessfulJob;
 use ballista_core::utils::create_grpc_client_connection;
+use ballista_core::BALLISTA_VERSION;
 use dashmap::DashMap;
 use datafusion::arrow;
-use datafusion::arrow::array::{ArrayRef, StringArray};
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt64Array};
 use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::arrow::error::ArrowError;
 use datafusion::arrow::ipc::writer::{IpcDataGenerator, IpcWriteOptions};
+use datafusion::arrow::ipc::{root_as_message, MessageHeader};
 use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::tree_node::{TreeNode, VisitRecursion};
 use datafusion::common::DFSchemaRef;
-use datafusion::logical_expr::LogicalPlan;
+use datafusion::datasource::TableType;
+use datafusion::logical_expr::{DdlStatement, Expr, LogicalPlan, SetVariable, Statement};
 use datafusion::prelude::SessionContext;
+use datafusion::scalar::ScalarValue;
 use datafusion_proto::protobuf::{LogicalPlanNode, PhysicalPlanNode};
 use prost::Message;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -77,44 +91,68 @@ use uuid::Uuid;
 
 pub struct FlightSqlServiceImpl {
     server: SchedulerServer<LogicalPlanNode, PhysicalPlanNode>,
-    statements: Arc<DashMap<Uuid, LogicalPlan>>,
-    contexts: Arc<DashMap<Uuid, Arc<SessionContext>>>,
+    authenticator: Arc<dyn Authenticator>,
+    statements: Arc<DashMap<Uuid, PreparedStatement>>,
+    sessions: Arc<DashMap<Uuid, FlightSqlSession>>,
+}
+
+/// A prepared statement and the parameters bound to it
+#[derive(Clone)]
+struct PreparedStatement {
+    /// Unoptimized plan of the statement, whose placeholders are substituted with the
+    /// parameters before it is executed
+    plan: LogicalPlan,
+    parameter_schema: SchemaRef,
+    parameters: Vec<ScalarValue>,
+}
+
+impl PreparedStatement {
+    fn new(plan: LogicalPlan) -> Result<Self, Status> {
+        let parameter_schema = Arc::new(parameter_schema(&plan)?);
+        Ok(Self {
+            plan,
+            parameter_schema,
+            parameters: vec![],
+        })
+    }
+
+    /// The plan of the statement with the bound parameters
+    fn bound_plan(&self) -> Result<LogicalPlan, Status> {
+        self.plan
+            .clone()
+            .with_param_values(self.parameters.clone())
+            .map_err(|e| {
+                Status::invalid_argument(format!("Error binding parameters: {e}"))
+            })
+    }
+}
+
+/// A session created by a successful handshake
+#[derive(Clone)]
+struct FlightSqlSession {
+    ctx: Arc<SessionContext>,
+    principal: Principal,
+    /// Id of the session in the job state, which is kept when the context of the
+    /// session is replaced by an update of its configuration
+    session_id: String,
+    config: BallistaConfig,
 }
 
-const TABLE_TYPES: [&str; 2] = ["TABLE", "VIEW"];
+const TABLE_TYPES: [&str; 3] = ["TABLE", "VIEW", "LOCAL TEMPORARY"];
 
 impl FlightSqlServiceImpl {
-    pub fn new(server: SchedulerServer<LogicalPlanNode, PhysicalPlanNode>) -> Self {
+    pub fn new(
+        server: SchedulerServer<LogicalPlanNode, PhysicalPlanNode>,
+        authenticator: Arc<dyn Authenticator>,
+    ) -> Self {
         Self {
             server,
+            authenticator,
             statements: Default::default(),
-            contexts: Default::default(),
+            sessions: Default::default(),
         }
     }
 
-    #[allow(deprecated)]
-    fn tables(&self, ctx: Arc<SessionContext>) -> Result<RecordBatch, ArrowError> {
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("catalog_name", DataType::Utf8, true),
-            Field::new("db_schema_name", DataType::Utf8, true),
-            Field::new("table_name", DataType::Utf8, false),
-            Field::new("table_type", DataType::Utf8, false),
-        ]));
-        let tables = ctx.tables()?; // resolved in #501
-        let names: Vec<_> = tables.iter().map(|it| Some(it.as_str())).collect();
-        let types: Vec<_> = names.iter().map(|_| Some("TABLE")).collect();
-        let cats: Vec<_> = names.iter().map(|_| None).collect();
-        let schemas: Vec<_> = names.iter().map(|_| None).collect();
-        let rb = RecordBatch::try_new(
-            schema,
-            [cats, schemas, names, types]
-                .iter()
-                .map(|i| Arc::new(StringArray::from(i.clone())) as ArrayRef)
-                .collect::<Vec<_>>(),
-        )?;
-        Ok(rb)
-    }
-
     fn table_types() -> Result<RecordBatch, ArrowError> {
         let schema = Arc::new(Schema::new(vec![Field::new(
             "table_type",
@@ -130,7 +168,7 @@ impl FlightSqlServiceImpl {
         )
     }
 
-    async fn create_ctx(&self) -> Result<Uuid, Status> {
+    async fn create_session(&self, principal: Principal) -> Result<Uuid, Status> {
         let config_builder = BallistaConfig::builder();
         let config = config_builder
             .build()
@@ -145,11 +183,100 @@ impl FlightSqlServiceImpl {
                 Status::internal(format!("Failed to create SessionContext: {e:?}"))
             })?;
         let handle = Uuid::new_v4();
-        self.contexts.insert(handle, ctx);
+        let session = FlightSqlSession {
+            session_id: ctx.session_id(),
+            ctx,
+            principal,
+            config,
+        };
+        self.sessions.insert(handle, session);
         Ok(handle)
     }
 
-    fn get_ctx<T>(&self, req: &Request<T>) -> Result<Arc<SessionContext>, Status> {
+    /// Persist a setting of a session through the session manager, which replaces the
+    /// context of the session by one with the new configuration. The tables of the
+    /// session are kept.
+    async fn update_session(
+        &self,
+        handle: Uuid,
+        session: &FlightSqlSession,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Status> {
+        let config = session
+            .config
+            .settings()
+            .iter()
+            .fold(BallistaConfig::builder(), |builder, (k, v)| {
+                builder.set(k, v)
+            })
+            .set(key, value)
+            .build()
+            .map_err(|e| Status::invalid_argument(format!("Error setting {key}: {e}")))?;
+        let ctx = self
+            .server
+            .state
+            .session_manager
+            .update_session(&session.session_id, &config)
+            .await
+            .map_err(|e| {
+                Status::internal(format!("Failed to update SessionContext: {e:?}"))
+            })?;
+        for catalog_name in session.ctx.catalog_names() {
+            if let Some(catalog) = session.ctx.catalog(&catalog_name) {
+                ctx.register_catalog(catalog_name, catalog);
+            }
+        }
+        debug!("Set {} to {} in session {}", key, value, session.session_id);
+        self.sessions.insert(
+            handle,
+            FlightSqlSession {
+                ctx,
+                config,
+                ..session.clone()
+            },
+        );
+        Ok(())
+    }
+
+    /// Execute a statement of a session which doesn't return rows, returning the number
+    /// of rows it affected. `SET` statements are persisted in the session.
+    async fn execute_statement_update(
+        &self,
+        handle: Uuid,
+        session: &FlightSqlSession,
+        query: &str,
+    ) -> Result<i64, Status> {
+        let plan = session
+            .ctx
+            .state()
+            .create_logical_plan(query)
+            .await
+            .map_err(|e| Status::internal(format!("Error building plan: {e}")))?;
+        match plan {
+            LogicalPlan::Statement(Statement::SetVariable(SetVariable {
+                ref variable,
+                ref value,
+                ..
+            })) => {
+                if !variable.starts_with("ballista.") {
+                    // validate the DataFusion setting before it is persisted
+                    execute_update(&session.ctx, plan.clone()).await?;
+                }
+                self.update_session(handle, session, variable, value)
+                    .await?;
+                Ok(0)
+            }
+            LogicalPlan::Ddl(_) | LogicalPlan::Dml(_) | LogicalPlan::Statement(_) => {
+                execute_update(&session.ctx, plan).await
+            }
+            _ => Err(Status::invalid_argument(format!(
+                "Not a statement update, it should be run as a query: {query}"
+            ))),
+        }
+    }
+
+    fn get_session_handle<T>(&self, req: &Request<T>) -> Result<Uuid, Status> {
         let auth = req
             .metadata()
             .get("authorization")
@@ -164,10 +291,14 @@ impl FlightSqlServiceImpl {
         }
         let auth = authorization[bearer.len()..].to_string();
 
-        let handle = Uuid::from_str(auth.as_str())
-            .map_err(|e| Status::internal(format!("Error locking contexts: {e}")))?;
-        if let Some(context) = self.contexts.get(&handle) {
-            Ok(context.clone())
+        Uuid::from_str(auth.as_str())
+            .map_err(|e| Status::internal(format!("Error locking contexts: {e}")))
+    }
+
+    fn get_session<T>(&self, req: &Request<T>) -> Result<FlightSqlSession, Status> {
+        let handle = self.get_session_handle(req)?;
+        if let Some(session) = self.sessions.get(&handle) {
+            Ok(session.clone())
         } else {
             Err(Status::internal(format!(
                 "Context handle not found: {handle}"
@@ -274,6 +405,9 @@ impl FlightSqlServiceImpl {
                     // Use executor ip:port for routing to flight result
                     host: exec_host.clone(),
                     port: exec_port,
+                    // Flight SQL clients may not support compressed record batches
+                    compression: String::default(),
+                    index_path: loc.index_path.clone(),
                 };
                 protobuf::Action {
                     action_type: Some(FetchPartition(fetch)),
@@ -303,43 +437,34 @@ impl FlightSqlServiceImpl {
         Ok(fieps)
     }
 
-    fn make_local_fieps(&self, job_id: &str) -> Result<Vec<FlightEndpoint>, Status> {
-        let (host, port) = ("127.0.0.1".to_string(), 50050); // TODO: use advertise host
-        let fetch = protobuf::FetchPartition {
-            job_id: job_id.to_string(),
-            stage_id: 0,
-            partition_id: 0,
-            path: job_id.to_string(),
-            host: host.clone(),
-            port,
-        };
-        let fetch = protobuf::Action {
-            action_type: Some(FetchPartition(fetch)),
-            settings: vec![],
-        };
-        let authority = format!("{}:{}", &host, &port); // TODO: use advertise host
-        let loc = Location {
-            uri: format!("grpc+tcp://{authority}"),
+    /// FlightInfo of a metadata command, whose data is served by the scheduler when the
+    /// command is sent back as the ticket
+    fn metadata_flight_info(
+        &self,
+        cmd: &impl ProstMessageExt,
+        schema: SchemaRef,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let ticket = Ticket {
+            ticket: cmd.as_any().encode_to_vec().into(),
         };
-        let buf = fetch.as_any().encode_to_vec();
-        let ticket = Ticket { ticket: buf.into() };
+        // without a location, the client fetches the data from the scheduler
         let fiep = FlightEndpoint {
             ticket: Some(ticket),
-            location: vec![loc],
+            location: vec![],
         };
-        let fieps = vec![fiep];
-        Ok(fieps)
+        let schema_bytes = self.schema_to_arrow(schema)?;
+        Ok(Self::create_resp(schema_bytes, vec![fiep], -1, -1))
     }
 
-    fn cache_plan(&self, plan: LogicalPlan) -> Result<Uuid, Status> {
+    fn cache_statement(&self, statement: PreparedStatement) -> Result<Uuid, Status> {
         let handle = Uuid::new_v4();
-        self.statements.insert(handle, plan);
+        self.statements.insert(handle, statement);
         Ok(handle)
     }
 
-    fn get_plan(&self, handle: &Uuid) -> Result<LogicalPlan, Status> {
-        if let Some(plan) = self.statements.get(handle) {
-            Ok(plan.clone())
+    fn get_statement(&self, handle: &Uuid) -> Result<PreparedStatement, Status> {
+        if let Some(statement) = self.statements.get(handle) {
+            Ok(statement.clone())
         } else {
             Err(Status::internal(format!(
                 "Statement handle not found: {handle}"
@@ -347,11 +472,29 @@ impl FlightSqlServiceImpl {
         }
     }
 
-    fn remove_plan(&self, handle: Uuid) -> Result<(), Status> {
+    fn remove_statement(&self, handle: Uuid) -> Result<(), Status> {
         self.statements.remove(&handle);
         Ok(())
     }
 
+    /// Bind the parameters sent with DoPut to a prepared statement. The parameters
+    /// bound before are kept if no parameters are sent.
+    async fn bind_parameters(
+        &self,
+        handle: &Uuid,
+        stream: Streaming<FlightData>,
+    ) -> Result<PreparedStatement, Status> {
+        let mut statement = self.get_statement(handle)?;
+        let batches =
+            decode_parameters(statement.parameter_schema.clone(), stream).await?;
+        if !batches.is_empty() {
+            statement.parameters = parameter_values(&batches)?;
+            debug!("Bound parameters {:?} to {}", statement.parameters, handle);
+            self.statements.insert(*handle, statement.clone());
+        }
+        Ok(statement)
+    }
+
     fn df_schema_to_arrow(&self, schema: &DFSchemaRef) -> Result<Vec<u8>, Status> {
         let arrow_schema: Schema = (&**schema).into();
         let schema_bytes = self.schema_to_arrow(Arc::new(arrow_schema))?;
@@ -371,20 +514,35 @@ impl FlightSqlServiceImpl {
 
     async fn enqueue_job(
         &self,
-        ctx: Arc<SessionContext>,
+        session: &FlightSqlSession,
         plan: &LogicalPlan,
     ) -> Result<String, Status> {
         let job_id = self.server.state.task_manager.generate_job_id();
         let job_name = format!("Flight SQL job {job_id}");
         self.server
-            .submit_job(&job_id, &job_name, ctx, plan)
+            .state
+            .admission_controller
+            .admit(&job_id, &session.session_id)
+            .map_err(|msg| {
+                warn!("{}", msg);
+                Status::resource_exhausted(msg)
+            })?;
+        self.server
+            .submit_job(
+                &job_id,
+                &job_name,
+                JobProperties::from_config(&session.config)
+                    .with_user(&session.principal.name),
+                session.ctx.clone(),
+                plan,
+            )
             .await
             .map_err(|e| {
+                self.server.state.admission_controller.release(&job_id);
                 let msg = format!("Failed to send JobQueued event for {job_id}: {e:?}");
                 error!("{}", msg);
                 Status::internal(msg)
-            })?;
-        Ok(job_id)
+            })
     }
 
     fn create_resp(
@@ -411,10 +569,10 @@ impl FlightSqlServiceImpl {
 
     async fn execute_plan(
         &self,
-        ctx: Arc<SessionContext>,
+        session: &FlightSqlSession,
         plan: &LogicalPlan,
     ) -> Result<Response<FlightInfo>, Status> {
-        let job_id = self.enqueue_job(ctx, plan).await?;
+        let job_id = self.enqueue_job(session, plan).await?;
 
         // poll for job completion
         let mut num_rows = 0;
@@ -458,21 +616,6 @@ impl FlightSqlServiceImpl {
             as Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send + 'static>>);
         Ok(resp)
     }
-
-    fn batch_to_schema_resp(
-        &self,
-        data: &RecordBatch,
-        name: &str,
-    ) -> Result<Response<FlightInfo>, Status> {
-        let num_bytes = data.get_array_memory_size() as i64;
-        let schema = data.schema();
-        let num_rows = data.num_rows() as i64;
-
-        let fieps = self.make_local_fieps(name)?;
-        let schema_bytes = self.schema_to_arrow(schema)?;
-        let resp = Self::create_resp(schema_bytes, fieps, num_rows, num_bytes);
-        Ok(resp)
-    }
 }
 
 #[tonic::async_trait]
@@ -491,34 +634,17 @@ impl FlightSqlService for FlightSqlServiceImpl {
             debug!("{:?}", md);
         }
 
-        let basic = "Basic ";
         let authorization = request
             .metadata()
             .get("authorization")
             .ok_or_else(|| Status::invalid_argument("authorization field not present"))?
             .to_str()
             .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
-        if !authorization.starts_with(basic) {
-            Err(Status::invalid_argument(format!(
-                "Auth type not implemented: {authorization}"
-            )))?;
-        }
-        let base64 = &authorization[basic.len()..];
-        let bytes = base64::decode(base64)
-            .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
-        let str = String::from_utf8(bytes)
-            .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
-        let parts: Vec<_> = str.split(':').collect();
-        if parts.len() != 2 {
-            Err(Status::invalid_argument("Invalid authorization header"))?;
-        }
-        let user = parts[0];
-        let pass = parts[1];
-        if user != "admin" || pass != "password" {
-            Err(Status::unauthenticated("Invalid credentials!"))?
-        }
+        let credentials = Credentials::from_authorization(authorization)?;
+        let principal = self.authenticator.authenticate(&credentials).await?;
+        debug!("Authenticated Flight SQL client as {}", principal.name);
 
-        let token = self.create_ctx().await?;
+        let token = self.create_session(principal).await?;
 
         let result = HandshakeResponse {
             protocol_version: 0,
@@ -541,7 +667,7 @@ impl FlightSqlService for FlightSqlServiceImpl {
         message: arrow_flight::sql::Any,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_fallback type_url: {}", message.type_url);
-        let ctx = self.get_ctx(&request)?;
+        self.get_session(&request)?;
         if !message.is::<protobuf::Action>() {
             Err(Status::unimplemented(format!(
                 "do_get: The defined request is invalid: {}",
@@ -558,38 +684,19 @@ impl FlightSqlService for FlightSqlServiceImpl {
             None => Err(Status::internal("Expected an ActionType but got None!"))?,
         };
 
-        // Well-known job ID: respond with the data
-        match fp.job_id.as_str() {
-            "get_flight_info_table_types" => {
-                debug!("Responding with table types");
-                let rb = FlightSqlServiceImpl::table_types().map_err(|_| {
-                    Status::internal("Error getting table types".to_string())
-                })?;
-                let resp = Self::record_batch_to_resp(rb).await?;
-                return Ok(resp);
-            }
-            "get_flight_info_tables" => {
-                debug!("Responding with tables");
-                let rb = self
-                    .tables(ctx)
-                    .map_err(|_| Status::internal("Error getting tables".to_string()))?;
-                let resp = Self::record_batch_to_resp(rb).await?;
-                return Ok(resp);
-            }
-            _ => {}
-        }
-
         // Proxy the flight
         let addr = format!("http://{}:{}", fp.host, fp.port);
         debug!("Scheduler proxying flight for to {}", addr);
-        let connection =
-            create_grpc_client_connection(addr.clone())
-                .await
-                .map_err(|e| {
-                    Status::internal(format!(
-                    "Error connecting to Ballista scheduler or executor at {addr}: {e:?}"
-                ))
-                })?;
+        let connection = create_grpc_client_connection(
+            addr.clone(),
+            self.server.state.config.grpc_tls.as_ref(),
+        )
+        .await
+        .map_err(|e| {
+            Status::internal(format!(
+                "Error connecting to Ballista scheduler or executor at {addr}: {e:?}"
+            ))
+        })?;
         let mut flight_client = FlightServiceClient::new(connection);
         let buf = action.encode_to_vec();
         let request = Request::new(Ticket { ticket: buf.into() });
@@ -605,11 +712,16 @@ impl FlightSqlService for FlightSqlServiceImpl {
     /// Get a FlightDataStream containing the data related to the supported XDBC types.
     async fn do_get_xdbc_type_info(
         &self,
-        _query: CommandGetXdbcTypeInfo,
-        _request: Request<Ticket>,
+        query: CommandGetXdbcTypeInfo,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_xdbc_type_info");
-        Err(Status::unimplemented("Implement do_get_xdbc_type_info"))
+        self.get_session(&request)?;
+        let data = query
+            .into_builder(&xdbc_type_info_data()?)
+            .build()
+            .map_err(|e| Status::internal(format!("Error getting XDBC types: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
 
     async fn get_flight_info_statement(
@@ -619,9 +731,9 @@ impl FlightSqlService for FlightSqlServiceImpl {
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_statement query:\n{}", query.query);
 
-        let ctx = self.get_ctx(&request)?;
-        let plan = Self::prepare_statement(&query.query, &ctx).await?;
-        let resp = self.execute_plan(ctx, &plan).await?;
+        let session = self.get_session(&request)?;
+        let plan = Self::prepare_statement(&query.query, &session.ctx).await?;
+        let resp = self.execute_plan(&session, &plan).await?;
 
         debug!("Returning flight info...");
         Ok(resp)
@@ -633,11 +745,11 @@ impl FlightSqlService for FlightSqlServiceImpl {
         request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_prepared_statement");
-        let ctx = self.get_ctx(&request)?;
+        let session = self.get_session(&request)?;
         let handle = Uuid::from_slice(handle.prepared_statement_handle.as_ref())
             .map_err(|e| Status::internal(format!("Error decoding handle: {e}")))?;
-        let plan = self.get_plan(&handle)?;
-        let resp = self.execute_plan(ctx, &plan).await?;
+        let plan = self.get_statement(&handle)?.bound_plan()?;
+        let resp = self.execute_plan(&session, &plan).await?;
 
         debug!("Responding to query {}...", handle);
         Ok(resp)
@@ -645,107 +757,102 @@ impl FlightSqlService for FlightSqlServiceImpl {
 
     async fn get_flight_info_catalogs(
         &self,
-        _query: CommandGetCatalogs,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_catalogs");
-        Err(Status::unimplemented("Implement get_flight_info_catalogs"))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, query.clone().into_builder().schema())
     }
     async fn get_flight_info_schemas(
         &self,
-        _query: CommandGetDbSchemas,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_schemas");
-        Err(Status::unimplemented("Implement get_flight_info_schemas"))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, query.clone().into_builder().schema())
     }
 
     async fn get_flight_info_tables(
         &self,
-        _query: CommandGetTables,
+        query: CommandGetTables,
         request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_tables");
-        let ctx = self.get_ctx(&request)?;
-        let data = self
-            .tables(ctx)
-            .map_err(|e| Status::internal(format!("Error getting tables: {e}")))?;
-        let resp = self.batch_to_schema_resp(&data, "get_flight_info_tables")?;
-        Ok(resp)
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, query.clone().into_builder().schema())
     }
 
     async fn get_flight_info_table_types(
         &self,
-        _query: CommandGetTableTypes,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_table_types");
+        self.get_session(&request)?;
         let data = FlightSqlServiceImpl::table_types()
             .map_err(|e| Status::internal(format!("Error getting table types: {e}")))?;
-        let resp = self.batch_to_schema_resp(&data, "get_flight_info_table_types")?;
-        Ok(resp)
+        self.metadata_flight_info(&query, data.schema())
     }
 
     async fn get_flight_info_sql_info(
         &self,
-        _query: CommandGetSqlInfo,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_sql_info");
-        // TODO: implement for FlightSQL JDBC to work
-        Err(Status::unimplemented("Implement CommandGetSqlInfo"))
+        self.get_session(&request)?;
+        let schema = query.clone().into_builder(&sql_info_data()?).schema();
+        self.metadata_flight_info(&query, schema)
     }
     async fn get_flight_info_primary_keys(
         &self,
-        _query: CommandGetPrimaryKeys,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetPrimaryKeys,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_primary_keys");
-        Err(Status::unimplemented(
-            "Implement get_flight_info_primary_keys",
-        ))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, primary_keys_schema())
     }
     async fn get_flight_info_exported_keys(
         &self,
-        _query: CommandGetExportedKeys,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetExportedKeys,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_exported_keys");
-        Err(Status::unimplemented(
-            "Implement get_flight_info_exported_keys",
-        ))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, foreign_keys_schema())
     }
     async fn get_flight_info_imported_keys(
         &self,
-        _query: CommandGetImportedKeys,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetImportedKeys,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_imported_keys");
-        Err(Status::unimplemented(
-            "Implement get_flight_info_imported_keys",
-        ))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, foreign_keys_schema())
     }
     async fn get_flight_info_cross_reference(
         &self,
-        _query: CommandGetCrossReference,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetCrossReference,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_cross_reference");
-        Err(Status::unimplemented(
-            "Implement get_flight_info_cross_reference",
-        ))
+        self.get_session(&request)?;
+        self.metadata_flight_info(&query, foreign_keys_schema())
     }
 
     /// Get a FlightInfo to extract information about the supported XDBC types.
     async fn get_flight_info_xdbc_type_info(
         &self,
-        _query: CommandGetXdbcTypeInfo,
-        _request: Request<FlightDescriptor>,
+        query: CommandGetXdbcTypeInfo,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<FlightInfo>, Status> {
         debug!("get_flight_info_xdbc_type_info");
-        Err(Status::unimplemented(
-            "Implement get_flight_info_xdbc_type_info",
-        ))
+        self.get_session(&request)?;
+        let schema = query.clone().into_builder(&xdbc_type_info_data()?).schema();
+        self.metadata_flight_info(&query, schema)
     }
 
     async fn do_get_statement(
@@ -772,94 +879,138 @@ impl FlightSqlService for FlightSqlServiceImpl {
     }
     async fn do_get_catalogs(
         &self,
-        _query: CommandGetCatalogs,
-        _request: Request<Ticket>,
+        query: CommandGetCatalogs,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_catalogs");
-        Err(Status::unimplemented("Implement do_get_catalogs"))
+        let ctx = self.get_session(&request)?.ctx;
+        let mut builder = query.into_builder();
+        for catalog_name in ctx.catalog_names() {
+            builder.append(catalog_name);
+        }
+        let data = builder
+            .build()
+            .map_err(|e| Status::internal(format!("Error getting catalogs: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
     async fn do_get_schemas(
         &self,
-        _query: CommandGetDbSchemas,
-        _request: Request<Ticket>,
+        query: CommandGetDbSchemas,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_schemas");
-        Err(Status::unimplemented("Implement do_get_schemas"))
+        let ctx = self.get_session(&request)?.ctx;
+        let mut builder = query.into_builder();
+        for catalog_name in ctx.catalog_names() {
+            if let Some(catalog) = ctx.catalog(&catalog_name) {
+                for schema_name in catalog.schema_names() {
+                    builder.append(&catalog_name, schema_name);
+                }
+            }
+        }
+        let data = builder
+            .build()
+            .map_err(|e| Status::internal(format!("Error getting schemas: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
     async fn do_get_tables(
         &self,
-        _query: CommandGetTables,
-        _request: Request<Ticket>,
+        query: CommandGetTables,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_tables");
-        Err(Status::unimplemented("Implement do_get_tables"))
+        let ctx = self.get_session(&request)?.ctx;
+        let data = get_tables(&ctx, query)
+            .await
+            .map_err(|e| Status::internal(format!("Error getting tables: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
     async fn do_get_table_types(
         &self,
         _query: CommandGetTableTypes,
-        _request: Request<Ticket>,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_table_types");
-        Err(Status::unimplemented("Implement do_get_table_types"))
+        self.get_session(&request)?;
+        let data = FlightSqlServiceImpl::table_types()
+            .map_err(|e| Status::internal(format!("Error getting table types: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
     async fn do_get_sql_info(
         &self,
-        _query: CommandGetSqlInfo,
-        _request: Request<Ticket>,
+        query: CommandGetSqlInfo,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_sql_info");
-        Err(Status::unimplemented("Implement do_get_sql_info"))
+        self.get_session(&request)?;
+        let data = query
+            .into_builder(&sql_info_data()?)
+            .build()
+            .map_err(|e| Status::internal(format!("Error getting SqlInfo: {e}")))?;
+        Self::record_batch_to_resp(data).await
     }
     async fn do_get_primary_keys(
         &self,
         _query: CommandGetPrimaryKeys,
-        _request: Request<Ticket>,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_primary_keys");
-        Err(Status::unimplemented("Implement do_get_primary_keys"))
+        self.get_session(&request)?;
+        // DataFusion tables have no keys
+        Self::record_batch_to_resp(RecordBatch::new_empty(primary_keys_schema())).await
     }
     async fn do_get_exported_keys(
         &self,
         _query: CommandGetExportedKeys,
-        _request: Request<Ticket>,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_exported_keys");
-        Err(Status::unimplemented("Implement do_get_exported_keys"))
+        self.get_session(&request)?;
+        Self::record_batch_to_resp(RecordBatch::new_empty(foreign_keys_schema())).await
     }
     async fn do_get_imported_keys(
         &self,
         _query: CommandGetImportedKeys,
-        _request: Request<Ticket>,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_imported_keys");
-        Err(Status::unimplemented("Implement do_get_imported_keys"))
+        self.get_session(&request)?;
+        Self::record_batch_to_resp(RecordBatch::new_empty(foreign_keys_schema())).await
     }
     async fn do_get_cross_reference(
         &self,
         _query: CommandGetCrossReference,
-        _request: Request<Ticket>,
+        request: Request<Ticket>,
     ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
         debug!("do_get_cross_reference");
-        Err(Status::unimplemented("Implement do_get_cross_reference"))
+        self.get_session(&request)?;
+        Self::record_batch_to_resp(RecordBatch::new_empty(foreign_keys_schema())).await
     }
     // do_put
     async fn do_put_statement_update(
         &self,
-        _ticket: CommandStatementUpdate,
-        _request: Request<Streaming<FlightData>>,
+        ticket: CommandStatementUpdate,
+        request: Request<Streaming<FlightData>>,
     ) -> Result<i64, Status> {
-        debug!("do_put_statement_update");
-        Err(Status::unimplemented("Implement do_put_statement_update"))
+        debug!("do_put_statement_update query:\n{}", ticket.query);
+        let handle = self.get_session_handle(&request)?;
+        let session = self.get_session(&request)?;
+        self.execute_statement_update(handle, &session, &ticket.query)
+            .await
     }
     async fn do_put_prepared_statement_query(
         &self,
-        _query: CommandPreparedStatementQuery,
-        _request: Request<Streaming<FlightData>>,
+        query: CommandPreparedStatementQuery,
+        request: Request<Streaming<FlightData>>,
     ) -> Result<Response<<Self as FlightService>::DoPutStream>, Status> {
         debug!("do_put_prepared_statement_query");
-        Err(Status::unimplemented(
-            "Implement do_put_prepared_statement_query",
-        ))
+        self.get_session(&request)?;
+        let handle = Uuid::from_slice(query.prepared_statement_handle.as_ref())
+            .map_err(|e| Status::internal(format!("Error decoding handle: {e}")))?;
+        // the statement is executed with the bound parameters by a later GetFlightInfo
+        self.bind_parameters(&handle, request.into_inner()).await?;
+        let stream = futures::stream::empty::<Result<PutResult, Status>>();
+        Ok(Response::new(Box::pin(stream)))
     }
     async fn do_put_prepared_statement_update(
         &self,
@@ -867,11 +1018,14 @@ impl FlightSqlService for FlightSqlServiceImpl {
         request: Request<Streaming<FlightData>>,
     ) -> Result<i64, Status> {
         debug!("do_put_prepared_statement_update");
-        let ctx = self.get_ctx(&request)?;
+        let session = self.get_session(&request)?;
         let handle = Uuid::from_slice(handle.prepared_statement_handle.as_ref())
             .map_err(|e| Status::internal(format!("Error decoding handle: {e}")))?;
-        let plan = self.get_plan(&handle)?;
-        let _ = self.execute_plan(ctx, &plan).await?;
+        let plan = self
+            .bind_parameters(&handle, request.into_inner())
+            .await?
+            .bound_plan()?;
+        let _ = self.execute_plan(&session, &plan).await?;
         debug!("Sending -1 rows affected");
         Ok(-1)
     }
@@ -882,15 +1036,24 @@ impl FlightSqlService for FlightSqlServiceImpl {
         request: Request<Action>,
     ) -> Result<ActionCreatePreparedStatementResult, Status> {
         debug!("do_action_create_prepared_statement");
-        let ctx = self.get_ctx(&request)?;
-        let plan = Self::prepare_statement(&query.query, &ctx).await?;
+        let ctx = self.get_session(&request)?.ctx;
+        // placeholders are substituted into the unoptimized plan, which is optimized
+        // once it is submitted as a job
+        let plan = ctx
+            .sql(&query.query)
+            .await
+            .map(|df| df.into_unoptimized_plan())
+            .map_err(|e| Status::internal(format!("Error building plan: {e}")))?;
         let schema_bytes = self.df_schema_to_arrow(plan.schema())?;
-        let handle = self.cache_plan(plan)?;
+        let statement = PreparedStatement::new(plan)?;
+        let parameter_schema_bytes =
+            self.schema_to_arrow(statement.parameter_schema.clone())?;
+        let handle = self.cache_statement(statement)?;
         debug!("Prepared statement {}:\n{}", handle, query.query);
         let res = ActionCreatePreparedStatementResult {
             prepared_statement_handle: handle.as_bytes().to_vec().into(),
             dataset_schema: schema_bytes.into(),
-            parameter_schema: Vec::new().into(), // TODO: parameters
+            parameter_schema: parameter_schema_bytes.into(),
         };
         Ok(res)
     }
@@ -908,7 +1071,7 @@ impl FlightSqlService for FlightSqlServiceImpl {
             })
             .map_err(|e| Status::internal(format!("Failed to parse handle: {e:?}")))?;
 
-        self.remove_plan(handle)
+        self.remove_statement(handle)
     }
 
     /// Get a FlightInfo for executing a substrait plan.
@@ -998,5 +1161,542 @@ impl FlightSqlService for FlightSqlServiceImpl {
     }
 
     /// Register a new SqlInfo result, making it available when calling GetSqlInfo.
-    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+    /// The SqlInfo of Ballista is static, see [sql_info_data].
+    async fn register_sql_info(&self, id: i32, result: &SqlInfo) {
+        debug!("Ignoring registration of SqlInfo {:?} as {}", result, id);
+    }
+}
+
+/// The tables of the catalogs of a session, filtered by a GetTables command
+async fn get_tables(
+    ctx: &SessionContext,
+    query: CommandGetTables,
+) -> Result<RecordBatch, ArrowError> {
+    let mut builder = query.into_builder();
+    for catalog_name in ctx.catalog_names() {
+        let catalog = match ctx.catalog(&catalog_name) {
+            Some(catalog) => catalog,
+            None => continue,
+        };
+        for schema_name in catalog.schema_names() {
+            let schema = match catalog.schema(&schema_name) {
+                Some(schema) => schema,
+                None => continue,
+            };
+            for table_name in schema.table_names() {
+                if let Some(table) = schema.table(&table_name).await {
+                    builder.append(
+                        &catalog_name,
+                        &schema_name,
+                        &table_name,
+                        table_type_name(table.table_type()),
+                        table.schema().as_ref(),
+                    )?;
+                }
+            }
+        }
+    }
+    builder.build()
+}
+
+/// Name of a table type, one of [TABLE_TYPES]
+fn table_type_name(table_type: TableType) -> &'static str {
+    match table_type {
+        TableType::Base => "TABLE",
+        TableType::View => "VIEW",
+        TableType::Temporary => "LOCAL TEMPORARY",
+    }
+}
+
+/// Schema of the primary keys returned for GetPrimaryKeys
+fn primary_keys_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("db_schema_name", DataType::Utf8, true),
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("column_name", DataType::Utf8, false),
+        Field::new("key_name", DataType::Utf8, true),
+        Field::new("key_sequence", DataType::Int32, false),
+    ]))
+}
+
+/// Schema of the foreign keys returned for GetExportedKeys, GetImportedKeys and
+/// GetCrossReference
+fn foreign_keys_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("pk_catalog_name", DataType::Utf8, true),
+        Field::new("pk_db_schema_name", DataType::Utf8, true),
+        Field::new("pk_table_name", DataType::Utf8, false),
+        Field::new("pk_column_name", DataType::Utf8, false),
+        Field::new("fk_catalog_name", DataType::Utf8, true),
+        Field::new("fk_db_schema_name", DataType::Utf8, true),
+        Field::new("fk_table_name", DataType::Utf8, false),
+        Field::new("fk_column_name", DataType::Utf8, false),
+        Field::new("key_sequence", DataType::Int32, false),
+        Field::new("fk_key_name", DataType::Utf8, true),
+        Field::new("pk_key_name", DataType::Utf8, true),
+        Field::new("update_rule", DataType::UInt8, false),
+        Field::new("delete_rule", DataType::UInt8, false),
+    ]))
+}
+
+/// SqlInfo describing the server and its SQL dialect, which is the one of DataFusion
+fn sql_info_data() -> Result<SqlInfoData, Status> {
+    let mut builder = SqlInfoDataBuilder::new();
+    builder.append(SqlInfo::FlightSqlServerName, "Apache Arrow Ballista");
+    builder.append(SqlInfo::FlightSqlServerVersion, BALLISTA_VERSION);
+    builder.append(SqlInfo::FlightSqlServerReadOnly, false);
+    builder.append(SqlInfo::FlightSqlServerSql, true);
+    builder.append(SqlInfo::FlightSqlServerSubstrait, false);
+    builder.append(SqlInfo::SqlDdlCatalog, false);
+    builder.append(SqlInfo::SqlDdlSchema, true);
+    builder.append(SqlInfo::SqlDdlTable, true);
+    builder.append(
+        SqlInfo::SqlIdentifierCase,
+        SqlSupportedCaseSensitivity::SqlCaseSensitivityLowercase as i32,
+    );
+    builder.append(SqlInfo::SqlIdentifierQuoteChar, "\"");
+    builder.append(SqlInfo::SqlAllTablesAreSelectable, true);
+    builder.append(
+        SqlInfo::SqlNullOrdering,
+        SqlNullOrdering::SqlNullsSortedHigh as i32,
+    );
+    builder.append(SqlInfo::SqlSearchStringEscape, "\\");
+    builder.append(SqlInfo::SqlSupportsColumnAliasing, true);
+    builder.append(SqlInfo::SqlNullPlusNullIsNull, true);
+    builder.append(SqlInfo::SqlSupportsTableCorrelationNames, true);
+    builder.append(SqlInfo::SqlSupportsExpressionsInOrderBy, true);
+    builder.append(SqlInfo::SqlSupportsOrderByUnrelated, true);
+    builder.append(
+        SqlInfo::SqlSupportedGroupBy,
+        1 << SqlSupportedGroupBy::SqlGroupByUnrelated as i32,
+    );
+    builder.append(SqlInfo::SqlSupportsLikeEscapeClause, false);
+    builder.append(SqlInfo::SqlSupportsNonNullableColumns, true);
+    builder.append(
+        SqlInfo::SqlOuterJoinsSupportLevel,
+        SqlOuterJoinsSupportLevel::SqlFullOuterJoins as i32,
+    );
+    builder.append(SqlInfo::SqlSelectForUpdateSupported, false);
+    builder.append(SqlInfo::SqlStoredProceduresSupported, false);
+    builder.append(SqlInfo::SqlCorrelatedSubqueriesSupported, true);
+    builder.append(
+        SqlInfo::SqlSupportedUnions,
+        1 << SqlSupportedUnions::SqlUnion as i32
+            | 1 << SqlSupportedUnions::SqlUnionAll as i32,
+    );
+    builder.append(SqlInfo::SqlTransactionsSupported, false);
+    builder
+        .build()
+        .map_err(|e| Status::internal(format!("Error building SqlInfo: {e}")))
+}
+
+/// The SQL types reported to XDBC clients, with the Arrow types they are mapped to by
+/// DataFusion
+fn xdbc_type_info_data() -> Result<XdbcTypeInfoData, Status> {
+    // name, type, column size and whether the type is numeric
+    let types = [
+        ("BOOLEAN", XdbcDataType::XdbcBit, Some(1), false),
+        ("TINYINT", XdbcDataType::XdbcTinyint, Some(3), true),
+        ("SMALLINT", XdbcDataType::XdbcSmallint, Some(5), true),
+        ("INTEGER", XdbcDataType::XdbcInteger, Some(10), true),
+        ("BIGINT", XdbcDataType::XdbcBigint, Some(19), true),
+        ("REAL", XdbcDataType::XdbcReal, Some(7), true),
+        ("DOUBLE", XdbcDataType::XdbcDouble, Some(15), true),
+        ("DECIMAL", XdbcDataType::XdbcDecimal, Some(38), true),
+        ("VARCHAR", XdbcDataType::XdbcVarchar, None, false),
+        ("DATE", XdbcDataType::XdbcDate, Some(10), false),
+        ("TIME", XdbcDataType::XdbcTime, Some(8), false),
+        ("TIMESTAMP", XdbcDataType::XdbcTimestamp, Some(29), false),
+    ];
+    let mut builder = XdbcTypeInfoDataBuilder::new();
+    for (type_name, data_type, column_size, numeric) in types {
+        let literal_quote = (!numeric && type_name != "BOOLEAN").then(|| "'".to_owned());
+        let decimal = data_type == XdbcDataType::XdbcDecimal;
+        builder.append(XdbcTypeInfo {
+            type_name: type_name.to_owned(),
+            data_type,
+            column_size,
+            literal_prefix: literal_quote.clone(),
+            literal_suffix: literal_quote,
+            create_params: decimal
+                .then(|| vec!["precision".to_owned(), "scale".to_owned()]),
+            nullable: Nullable::NullabilityNullable,
+            case_sensitive: data_type == XdbcDataType::XdbcVarchar,
+            searchable: if data_type == XdbcDataType::XdbcVarchar {
+                Searchable::Full
+            } else {
+                Searchable::Basic
+            },
+            unsigned_attribute: numeric.then_some(false),
+            fixed_prec_scale: false,
+            auto_increment: numeric.then_some(false),
+            local_type_name: Some(type_name.to_owned()),
+            minimum_scale: decimal.then_some(0),
+            maximum_scale: decimal.then_some(38),
+            sql_data_type: data_type,
+            datetime_subcode: None,
+            num_prec_radix: numeric.then_some(10),
+            interval_precision: None,
+        });
+    }
+    builder
+        .build()
+        .map_err(|e| Status::internal(format!("Error building XDBC types: {e}")))
+}
+
+/// Execute a DDL or DML statement against a session on the scheduler, returning the
+/// number of rows it affected. The tables created by DDL statements are registered in
+/// the session, which the jobs of the session are planned with.
+///
+/// DML statements are run by the scheduler itself, as the plans writing to tables
+/// can't be serialized to run on executors. So that queries aren't run outside of the
+/// executors, only the rows of a `VALUES` list can be inserted or used to create a
+/// table.
+async fn execute_update(ctx: &SessionContext, plan: LogicalPlan) -> Result<i64, Status> {
+    if !writes_values_list(&plan) {
+        return Err(Status::invalid_argument(
+            "Writing the results of a query is not supported, only the rows of a VALUES \
+             list can be inserted or used to create a table",
+        ));
+    }
+    let batches = ctx
+        .execute_logical_plan(plan)
+        .await
+        .map_err(|e| Status::internal(format!("Error executing statement: {e}")))?
+        .collect()
+        .await
+        .map_err(|e| Status::internal(format!("Error executing statement: {e}")))?;
+    // DML statements return the number of rows they affected in a count column
+    let affected_rows: u64 = batches
+        .iter()
+        .filter_map(|batch| batch.column_by_name("count"))
+        .filter_map(|counts| counts.as_any().downcast_ref::<UInt64Array>())
+        .flat_map(|counts| counts.iter().flatten())
+        .sum();
+    Ok(affected_rows as i64)
+}
+
+/// Whether the rows written by a DML or `CREATE TABLE` statement are listed in the
+/// statement, like `INSERT INTO t VALUES (1)`, rather than computed by a query. Other
+/// statements don't write rows.
+fn writes_values_list(plan: &LogicalPlan) -> bool {
+    let input = match plan {
+        LogicalPlan::Dml(dml) => &dml.input,
+        LogicalPlan::Ddl(DdlStatement::CreateMemoryTable(create)) => &create.input,
+        _ => return true,
+    };
+    let mut values_list = true;
+    let _ = input.apply(&mut |plan| {
+        values_list = matches!(
+            plan,
+            LogicalPlan::Values(_)
+                | LogicalPlan::EmptyRelation(_)
+                | LogicalPlan::Projection(_)
+                | LogicalPlan::SubqueryAlias(_)
+        ) && !plan.expressions().iter().any(contains_subquery);
+        Ok(if values_list {
+            VisitRecursion::Continue
+        } else {
+            VisitRecursion::Stop
+        })
+    });
+    values_list
+}
+
+fn contains_subquery(expr: &Expr) -> bool {
+    let mut subquery = false;
+    let _ = expr.apply(&mut |expr| {
+        subquery = matches!(
+            expr,
+            Expr::ScalarSubquery(_) | Expr::Exists(_) | Expr::InSubquery(_)
+        );
+        Ok(if subquery {
+            VisitRecursion::Stop
+        } else {
+            VisitRecursion::Continue
+        })
+    });
+    subquery
+}
+
+/// Schema of the placeholders of a plan, with a field named after each placeholder in
+/// the order of their positions. The type of a placeholder is null if it can't be
+/// inferred from the plan.
+fn parameter_schema(plan: &LogicalPlan) -> Result<Schema, Status> {
+    let mut fields = plan
+        .get_parameter_types()
+        .map_err(|e| Status::internal(format!("Error getting parameter types: {e}")))?
+        .into_iter()
+        .map(|(id, data_type)| {
+            let position = id
+                .strip_prefix('$')
+                .and_then(|position| position.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!(
+                        "Invalid placeholder {id}, expected $1, $2, ..."
+                    ))
+                })?;
+            let data_type = data_type.unwrap_or(DataType::Null);
+            Ok((position, Field::new(id, data_type, true)))
+        })
+        .collect::<Result<Vec<_>, Status>>()?;
+    fields.sort_by_key(|(position, _)| *position);
+    Ok(Schema::new(
+        fields
+            .into_iter()
+            .map(|(_, field)| field)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Decode the parameter batches sent with DoPut. The first message of the stream,
+/// holding the schema of the batches, is consumed by the Flight SQL server to read the
+/// command, so the batches are decoded with the parameter schema of the statement
+/// unless the client sends the schema again.
+async fn decode_parameters(
+    mut schema: SchemaRef,
+    mut stream: Streaming<FlightData>,
+) -> Result<Vec<RecordBatch>, Status> {
+    let dictionaries_by_id = HashMap::new();
+    let mut batches = vec![];
+    while let Some(data) = stream.message().await? {
+        if data.data_header.is_empty() {
+            continue;
+        }
+        let message = root_as_message(&data.data_header).map_err(|e| {
+            Status::invalid_argument(format!("Error decoding parameters: {e}"))
+        })?;
+        match message.header_type() {
+            MessageHeader::Schema => {
+                schema = Arc::new(Schema::try_from(&data).map_err(|e| {
+                    Status::invalid_argument(format!("Error decoding parameters: {e}"))
+                })?);
+            }
+            MessageHeader::RecordBatch => {
+                let batch = flight_data_to_arrow_batch(
+                    &data,
+                    schema.clone(),
+                    &dictionaries_by_id,
+                )
+                .map_err(|e| {
+                    Status::invalid_argument(format!("Error decoding parameters: {e}"))
+                })?;
+                batches.push(batch);
+            }
+            header => Err(Status::invalid_argument(format!(
+                "Unsupported message in parameters: {header:?}"
+            )))?,
+        }
+    }
+    Ok(batches)
+}
+
+/// Values of the parameters in the single row of the parameter batches, the value of
+/// the placeholder `$n` being in the n-th column
+fn parameter_values(batches: &[RecordBatch]) -> Result<Vec<ScalarValue>, Status> {
+    let num_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    match batches.iter().find(|batch| batch.num_rows() > 0) {
+        Some(batch) if num_rows == 1 => batch
+            .columns()
+            .iter()
+            .map(|column| {
+                ScalarValue::try_from_array(column, 0).map_err(|e| {
+                    Status::invalid_argument(format!("Error reading parameters: {e}"))
+                })
+            })
+            .collect(),
+        _ => Err(Status::invalid_argument(format!(
+            "Expected a single row of parameters but got {num_rows}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::StaticUserAuthenticator;
+    use crate::config::SchedulerConfig;
+    use crate::test_utils::{test_cluster_context, TestMetricsCollector};
+    use ballista_core::config::BALLISTA_JOB_NAME;
+    use ballista_core::serde::BallistaCodec;
+    use datafusion::arrow::array::Int64Array;
+
+    #[tokio::test]
+    async fn test_bind_parameters() -> Result<(), Status> {
+        let ctx = SessionContext::new();
+        let plan = ctx
+            .sql(
+                "SELECT a FROM (VALUES (1, 'x'), (2, 'y'), (3, 'y')) AS t (a, b) \
+                 WHERE b = $2 AND a > $1",
+            )
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .into_unoptimized_plan();
+        let statement = PreparedStatement::new(plan)?;
+        let schema = statement.parameter_schema.clone();
+        assert_eq!("$1", schema.field(0).name());
+        assert_eq!(&DataType::Int64, schema.field(0).data_type());
+        assert_eq!("$2", schema.field(1).name());
+        assert_eq!(&DataType::Utf8, schema.field(1).data_type());
+        // the plan can't be executed before the parameters are bound
+        assert!(statement.bound_plan().is_err());
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["y"])),
+            ],
+        )
+        .map_err(|e| Status::internal(e.to_string()))?;
+        assert!(parameter_values(&[batch.clone(), batch.clone()]).is_err());
+        let statement = PreparedStatement {
+            parameters: parameter_values(&[batch])?,
+            ..statement
+        };
+        let batches = ctx
+            .execute_logical_plan(statement.bound_plan()?)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .collect()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let num_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(2, num_rows);
+        Ok(())
+    }
+
+    async fn execute_sql(ctx: &SessionContext, sql: &str) -> Result<i64, Status> {
+        let plan = ctx
+            .state()
+            .create_logical_plan(sql)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        execute_update(ctx, plan).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_update() -> Result<(), Status> {
+        let ctx = SessionContext::new();
+        let created = execute_sql(&ctx, "CREATE TABLE t AS VALUES (1), (2)").await?;
+        assert_eq!(0, created);
+        let inserted = execute_sql(&ctx, "INSERT INTO t VALUES (3), (4), (5)").await?;
+        assert_eq!(3, inserted);
+
+        // the results of queries are not written by the scheduler
+        for sql in [
+            "INSERT INTO t SELECT * FROM t",
+            "INSERT INTO t VALUES ((SELECT max(column1) FROM t))",
+            "CREATE TABLE u AS SELECT * FROM t",
+        ] {
+            let error = execute_sql(&ctx, sql).await.unwrap_err();
+            assert_eq!(tonic::Code::InvalidArgument, error.code());
+        }
+        assert!(!ctx
+            .table_exist("u")
+            .map_err(|e| Status::internal(e.to_string()))?);
+
+        execute_sql(&ctx, "DROP TABLE t").await?;
+        assert!(!ctx
+            .table_exist("t")
+            .map_err(|e| Status::internal(e.to_string()))?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_variable() -> Result<(), Status> {
+        let mut scheduler: SchedulerServer<LogicalPlanNode, PhysicalPlanNode> =
+            SchedulerServer::new(
+                "localhost:50050".to_owned(),
+                test_cluster_context(),
+                BallistaCodec::default(),
+                Arc::new(SchedulerConfig::default()),
+                Arc::new(TestMetricsCollector::default()),
+            );
+        scheduler
+            .init()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let service = FlightSqlServiceImpl::new(
+            scheduler,
+            Arc::new(StaticUserAuthenticator::new(HashMap::new())),
+        );
+        let handle = service.create_session(Principal::new("admin")).await?;
+        let session = |handle| service.sessions.get(&handle).unwrap().clone();
+        execute_sql(&session(handle).ctx, "CREATE TABLE t AS VALUES (1)").await?;
+
+        for sql in [
+            "SET ballista.job.name = 'etl'",
+            "SET datafusion.execution.coalesce_batches = false",
+        ] {
+            service
+                .execute_statement_update(handle, &session(handle), sql)
+                .await?;
+        }
+        let updated = session(handle);
+        assert_eq!(
+            Some(&"etl".to_owned()),
+            updated.config.settings().get(BALLISTA_JOB_NAME)
+        );
+        assert!(
+            !updated
+                .ctx
+                .state()
+                .config()
+                .options()
+                .execution
+                .coalesce_batches
+        );
+        // the tables of the session are kept
+        assert!(updated
+            .ctx
+            .table_exist("t")
+            .map_err(|e| Status::internal(e.to_string()))?);
+        // the settings are persisted in the session manager
+        let ctx = service
+            .server
+            .state
+            .session_manager
+            .get_session(&updated.session_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        assert!(!ctx.state().config().options().execution.coalesce_batches);
+
+        // invalid settings are rejected and the session is unchanged
+        let error = service
+            .execute_statement_update(
+                handle,
+                &updated,
+                "SET datafusion.execution.coalesce_batches = 'x'",
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(tonic::Code::Internal, error.code());
+        assert_eq!(updated.config, session(handle).config);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_tables() -> Result<(), Status> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("keys", RecordBatch::new_empty(primary_keys_schema()))
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        for (pattern, expected_rows) in [("key%", 1), ("other", 0)] {
+            let query = CommandGetTables {
+                table_name_filter_pattern: Some(pattern.to_owned()),
+                ..Default::default()
+            };
+            let tables = get_tables(&ctx, query)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+            assert_eq!(expected_rows, tables.num_rows());
+        }
+
+        // the static metadata is valid
+        sql_info_data()?;
+        xdbc_type_info_data()?;
+        Ok(())
+    }
 }
@@ -18,6 +18,8 @@
 #![doc = include_str ! ("../README.md")]
 
 pub mod api;
+#[cfg(feature = "flight-sql")]
+pub mod auth;
 pub mod cluster;
 pub mod config;
 pub mod display;
@@ -21,6 +21,7 @@ pub mod prometheus;
 #[cfg(feature = "prometheus")]
 use crate::metrics::prometheus::PrometheusMetricsCollector;
 use ballista_core::error::Result;
+use ballista_core::serde::scheduler::ExecutorMetrics;
 use std::sync::Arc;
 
 /// Interface for recording metrics events in the scheduler. An instance of `Arc<dyn SchedulerMetricsCollector>`
@@ -52,6 +53,12 @@ pub trait SchedulerMetricsCollector: Send + Sync {
     /// to schedule on an executor but cannot be scheduled because no resources are available.
     fn set_pending_tasks_queue_size(&self, value: u64);
 
+    /// Record the resource usage reported by executor `executor_id` in its last heartbeat
+    fn record_executor_metrics(&self, executor_id: &str, metrics: &ExecutorMetrics);
+
+    /// Stop tracking the resource usage of executor `executor_id` once it is lost
+    fn remove_executor_metrics(&self, executor_id: &str);
+
     /// Gather current metric set that should be returned when calling the scheduler's metrics API
     /// Should return a tuple containing the content of the metric set and the content type (e.g. `application/json`, `text/plain`, etc)
     fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>>;
@@ -68,6 +75,8 @@ impl SchedulerMetricsCollector for NoopMetricsCollector {
     fn record_failed(&self, _job_id: &str, _queued_at: u64, _failed_at: u64) {}
     fn record_cancelled(&self, _job_id: &str) {}
     fn set_pending_tasks_queue_size(&self, _value: u64) {}
+    fn record_executor_metrics(&self, _executor_id: &str, _metrics: &ExecutorMetrics) {}
+    fn remove_executor_metrics(&self, _executor_id: &str) {}
 
     fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>> {
         Ok(None)
@@ -17,11 +17,13 @@
 
 use crate::metrics::SchedulerMetricsCollector;
 use ballista_core::error::{BallistaError, Result};
+use ballista_core::serde::scheduler::ExecutorMetrics;
 
 use once_cell::sync::OnceCell;
 use prometheus::{
-    register_counter_with_registry, register_gauge_with_registry,
-    register_histogram_with_registry, Counter, Gauge, Histogram, Registry,
+    register_counter_with_registry, register_gauge_vec_with_registry,
+    register_gauge_with_registry, register_histogram_with_registry, Counter, Gauge,
+    GaugeVec, Histogram, Registry,
 };
 use prometheus::{Encoder, TextEncoder};
 use std::sync::Arc;
@@ -29,7 +31,7 @@ use std::sync::Arc;
 static COLLECTOR: OnceCell<Arc<dyn SchedulerMetricsCollector>> = OnceCell::new();
 
 /// SchedulerMetricsCollector implementation based on Prometheus. By default this will track
-/// 7 metrics:
+/// 13 metrics:
 /// *job_exec_time_seconds* - Histogram of successful job execution time in seconds
 /// *planning_time_ms* - Histogram of job planning time in milliseconds
 /// *failed* - Counter of failed jobs
@@ -38,6 +40,12 @@ static COLLECTOR: OnceCell<Arc<dyn SchedulerMetricsCollector>> = OnceCell::new()
 /// *job_completed_total* - Counter of completed jobs
 /// *job_submitted_total* - Counter of submitted jobs
 /// *pending_task_queue_size* - Number of pending tasks
+/// *executor_available_memory_bytes* - Available memory of each executor host
+/// *executor_used_memory_bytes* - Used memory of each executor host
+/// *executor_cpu_usage_percent* - CPU usage of each executor host
+/// *executor_available_disk_bytes* - Free space of the work dir disk of each executor
+/// *executor_running_tasks* - Number of tasks running on each executor
+/// *executor_shuffle_bytes* - Size of the shuffle files held by each executor
 pub struct PrometheusMetricsCollector {
     execution_time: Histogram,
     planning_time: Histogram,
@@ -46,6 +54,12 @@ pub struct PrometheusMetricsCollector {
     completed: Counter,
     submitted: Counter,
     pending_queue_size: Gauge,
+    executor_available_memory: GaugeVec,
+    executor_used_memory: GaugeVec,
+    executor_cpu_usage: GaugeVec,
+    executor_available_disk: GaugeVec,
+    executor_running_tasks: GaugeVec,
+    executor_shuffle_bytes: GaugeVec,
 }
 
 impl PrometheusMetricsCollector {
@@ -115,6 +129,37 @@ impl PrometheusMetricsCollector {
             BallistaError::Internal(format!("Error registering metric: {e:?}"))
         })?;
 
+        let executor_gauge = |name: &str, help: &str| {
+            register_gauge_vec_with_registry!(name, help, &["executor_id"], registry)
+                .map_err(|e| {
+                    BallistaError::Internal(format!("Error registering metric: {e:?}"))
+                })
+        };
+        let executor_available_memory = executor_gauge(
+            "executor_available_memory_bytes",
+            "Available memory of the executor host in bytes",
+        )?;
+        let executor_used_memory = executor_gauge(
+            "executor_used_memory_bytes",
+            "Used memory of the executor host in bytes",
+        )?;
+        let executor_cpu_usage = executor_gauge(
+            "executor_cpu_usage_percent",
+            "CPU usage of the executor host in percent",
+        )?;
+        let executor_available_disk = executor_gauge(
+            "executor_available_disk_bytes",
+            "Free space of the executor work dir disk in bytes",
+        )?;
+        let executor_running_tasks = executor_gauge(
+            "executor_running_tasks",
+            "Number of tasks running on the executor",
+        )?;
+        let executor_shuffle_bytes = executor_gauge(
+            "executor_shuffle_bytes",
+            "Size of the shuffle files held by the executor in bytes",
+        )?;
+
         Ok(Self {
             execution_time,
             planning_time,
@@ -123,6 +168,12 @@ impl PrometheusMetricsCollector {
             completed,
             submitted,
             pending_queue_size,
+            executor_available_memory,
+            executor_used_memory,
+            executor_cpu_usage,
+            executor_available_disk,
+            executor_running_tasks,
+            executor_shuffle_bytes,
         })
     }
 
@@ -162,6 +213,43 @@ impl SchedulerMetricsCollector for PrometheusMetricsCollector {
         self.pending_queue_size.set(value as f64);
     }
 
+    fn record_executor_metrics(&self, executor_id: &str, metrics: &ExecutorMetrics) {
+        let gauges = [
+            (&self.executor_available_memory, metrics.available_memory),
+            (&self.executor_used_memory, metrics.used_memory),
+            (&self.executor_available_disk, metrics.available_disk),
+            (
+                &self.executor_running_tasks,
+                metrics.running_tasks.map(u64::from),
+            ),
+            (&self.executor_shuffle_bytes, metrics.shuffle_bytes),
+        ];
+        for (gauge, value) in gauges {
+            if let Some(value) = value {
+                gauge.with_label_values(&[executor_id]).set(value as f64);
+            }
+        }
+        if let Some(cpu_usage) = metrics.cpu_usage {
+            self.executor_cpu_usage
+                .with_label_values(&[executor_id])
+                .set(cpu_usage as f64);
+        }
+    }
+
+    fn remove_executor_metrics(&self, executor_id: &str) {
+        for gauge in [
+            &self.executor_available_memory,
+            &self.executor_used_memory,
+            &self.executor_cpu_usage,
+            &self.executor_available_disk,
+            &self.executor_running_tasks,
+            &self.executor_shuffle_bytes,
+        ] {
+            // the executor may not have reported every metric
+            let _ = gauge.remove_label_values(&[executor_id]);
+        }
+    }
+
     fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>> {
         let encoder = TextEncoder::new();
 
@@ -20,17 +20,26 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use ballista_core::config::ShuffleCompression;
 use ballista_core::error::{BallistaError, Result};
 use ballista_core::{
-    execution_plans::{ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec},
+    execution_plans::{
+        RangePartitioning, RangeSampleExec, ShuffleReaderExec, ShuffleWriterExec,
+        UnresolvedShuffleExec,
+    },
     serde::scheduler::PartitionLocation,
 };
+use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
 use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::filter::FilterExec;
+use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode};
+use datafusion::physical_plan::projection::ProjectionExec;
 use datafusion::physical_plan::repartition::RepartitionExec;
+use datafusion::physical_plan::sorts::sort::SortExec;
 use datafusion::physical_plan::sorts::sort_preserving_merge::SortPreservingMergeExec;
-use datafusion::physical_plan::windows::WindowAggExec;
+use datafusion::physical_plan::windows::{BoundedWindowAggExec, WindowAggExec};
 use datafusion::physical_plan::{
-    with_new_children_if_necessary, ExecutionPlan, Partitioning,
+    with_new_children_if_necessary, Distribution, ExecutionPlan, Partitioning,
 };
 
 use log::{debug, info};
@@ -39,11 +48,48 @@ type PartialQueryStageResult = (Arc<dyn ExecutionPlan>, Vec<Arc<ShuffleWriterExe
 
 pub struct DistributedPlanner {
     next_stage_id: usize,
+    shuffle_compression: ShuffleCompression,
+    shuffle_sort_based: bool,
+    repartition_sorts: bool,
+    range_sample_size: usize,
 }
 
 impl DistributedPlanner {
     pub fn new() -> Self {
-        Self { next_stage_id: 0 }
+        Self {
+            next_stage_id: 0,
+            shuffle_compression: ShuffleCompression::None,
+            shuffle_sort_based: false,
+            repartition_sorts: false,
+            range_sample_size: 100,
+        }
+    }
+
+    /// Compress the shuffle files of the planned stages with the given codec
+    pub fn with_shuffle_compression(mut self, compression: ShuffleCompression) -> Self {
+        self.shuffle_compression = compression;
+        self
+    }
+
+    /// Plan stages whose tasks write a single data file and index file for all of
+    /// their shuffle partitions
+    pub fn with_sort_based_shuffle(mut self, sort_based: bool) -> Self {
+        self.shuffle_sort_based = sort_based;
+        self
+    }
+
+    /// Sort the query results in parallel by range partitioning them on the sort keys,
+    /// instead of merging all the sorted partitions in a single task
+    pub fn with_repartition_sorts(mut self, repartition_sorts: bool) -> Self {
+        self.repartition_sorts = repartition_sorts;
+        self
+    }
+
+    /// Sample the given number of rows from each input partition of a range partitioned
+    /// sort to compute the boundaries of the ranges
+    pub fn with_range_sample_size(mut self, range_sample_size: usize) -> Self {
+        self.range_sample_size = range_sample_size;
+        self
     }
 }
 
@@ -63,6 +109,13 @@ impl DistributedPlanner {
         execution_plan: Arc<dyn ExecutionPlan>,
     ) -> Result<Vec<Arc<ShuffleWriterExec>>> {
         info!("planning query stages for job {}", job_id);
+        if self.repartition_sorts {
+            if let Some(stages) =
+                self.plan_range_partitioned_sort(job_id, &execution_plan)?
+            {
+                return Ok(stages);
+            }
+        }
         let (new_plan, mut stages) =
             self.plan_query_stages_internal(job_id, execution_plan)?;
         stages.push(create_shuffle_writer(
@@ -70,6 +123,8 @@ impl DistributedPlanner {
             self.next_stage_id(),
             new_plan,
             None,
+            self.shuffle_compression,
+            self.shuffle_sort_based,
         )?);
         Ok(stages)
     }
@@ -105,6 +160,8 @@ impl DistributedPlanner {
                 self.next_stage_id(),
                 children[0].clone(),
                 None,
+                self.shuffle_compression,
+                self.shuffle_sort_based,
             )?;
             let unresolved_shuffle = create_unresolved_shuffle(&shuffle_writer);
             stages.push(shuffle_writer);
@@ -122,6 +179,8 @@ impl DistributedPlanner {
                 self.next_stage_id(),
                 children[0].clone(),
                 None,
+                self.shuffle_compression,
+                self.shuffle_sort_based,
             )?;
             let unresolved_shuffle = create_unresolved_shuffle(&shuffle_writer);
             stages.push(shuffle_writer);
@@ -140,6 +199,8 @@ impl DistributedPlanner {
                         self.next_stage_id(),
                         children[0].clone(),
                         Some(repart.partitioning().to_owned()),
+                        self.shuffle_compression,
+                        self.shuffle_sort_based,
                     )?;
                     let unresolved_shuffle = create_unresolved_shuffle(&shuffle_writer);
                     stages.push(shuffle_writer);
@@ -150,12 +211,26 @@ impl DistributedPlanner {
                     Ok((children[0].clone(), stages))
                 }
             }
-        } else if let Some(window) =
-            execution_plan.as_any().downcast_ref::<WindowAggExec>()
+        } else if let Some(join) = execution_plan.as_any().downcast_ref::<HashJoinExec>()
         {
-            Err(BallistaError::NotImplemented(format!(
-                "WindowAggExec with window {window:?}"
-            )))
+            if matches!(join.partition_mode(), PartitionMode::CollectLeft) {
+                children[0] = broadcast_build_side(children[0].clone());
+            }
+            Ok((
+                with_new_children_if_necessary(execution_plan, children)?.into(),
+                stages,
+            ))
+        } else if is_window_exec(execution_plan.as_ref()) {
+            let input = self.plan_window_input(
+                job_id,
+                &execution_plan,
+                children[0].clone(),
+                &mut stages,
+            )?;
+            Ok((
+                with_new_children_if_necessary(execution_plan, vec![input])?.into(),
+                stages,
+            ))
         } else {
             Ok((
                 with_new_children_if_necessary(execution_plan, children)?.into(),
@@ -164,6 +239,173 @@ impl DistributedPlanner {
         }
     }
 
+    /// Plans a query whose results are merged by a [SortPreservingMergeExec] as a range
+    /// partitioned shuffle of the sort input instead, followed by a final stage sorting
+    /// every range in parallel. The ranges are read in order, which concatenates them
+    /// into the sorted results.
+    ///
+    /// The boundaries of the ranges are computed from a sample of the sort input, which
+    /// is collected by a stage of its own. Unless the sort input is cheap to compute
+    /// twice, it is written by a stage of its own first, whose output is read by both
+    /// the sample stage and the range partitioned shuffle.
+    fn plan_range_partitioned_sort<'a>(
+        &'a mut self,
+        job_id: &'a str,
+        execution_plan: &Arc<dyn ExecutionPlan>,
+    ) -> Result<Option<Vec<Arc<ShuffleWriterExec>>>> {
+        let merge = match execution_plan
+            .as_any()
+            .downcast_ref::<SortPreservingMergeExec>()
+        {
+            Some(merge) if merge.fetch().is_none() => merge,
+            _ => return Ok(None),
+        };
+        let sort = match merge.input().as_any().downcast_ref::<SortExec>() {
+            Some(sort) if sort.preserve_partitioning() && sort.fetch().is_none() => sort,
+            _ => return Ok(None),
+        };
+        let partition_count = sort.input().output_partitioning().partition_count();
+        if partition_count <= 1 {
+            return Ok(None);
+        }
+
+        let (input, mut stages) =
+            self.plan_query_stages_internal(job_id, sort.input().clone())?;
+        let input = if is_cheap_to_recompute(&input) {
+            input
+        } else {
+            let input_stage = create_shuffle_writer(
+                job_id,
+                self.next_stage_id(),
+                input,
+                None,
+                self.shuffle_compression,
+                self.shuffle_sort_based,
+            )?;
+            let unresolved_shuffle = create_unresolved_shuffle(&input_stage);
+            stages.push(input_stage);
+            unresolved_shuffle
+        };
+
+        let sample_stage = create_shuffle_writer(
+            job_id,
+            self.next_stage_id(),
+            Arc::new(RangeSampleExec::new(input.clone(), self.range_sample_size)),
+            None,
+            self.shuffle_compression,
+            self.shuffle_sort_based,
+        )?;
+        let sample = Arc::new(UnresolvedShuffleExec::new_broadcast(
+            sample_stage.stage_id(),
+            sample_stage.schema(),
+            sample_stage.output_partitioning().partition_count(),
+        ));
+        stages.push(sample_stage);
+
+        let range_stage = Arc::new(
+            ShuffleWriterExec::try_new_range(
+                job_id.to_owned(),
+                self.next_stage_id(),
+                input,
+                "".to_owned(), // executor will decide on the work_dir path
+                RangePartitioning {
+                    sort_exprs: sort.expr().to_vec(),
+                    partition_count,
+                    sample,
+                },
+            )?
+            .with_compression(self.shuffle_compression)
+            .with_sort_based(self.shuffle_sort_based),
+        );
+        let unresolved_shuffle = create_unresolved_shuffle(&range_stage);
+        stages.push(range_stage);
+
+        let sort = SortExec::new(sort.expr().to_vec(), unresolved_shuffle)
+            .with_preserve_partitioning(true);
+        stages.push(create_shuffle_writer(
+            job_id,
+            self.next_stage_id(),
+            Arc::new(sort),
+            None,
+            self.shuffle_compression,
+            self.shuffle_sort_based,
+        )?);
+        Ok(Some(stages))
+    }
+
+    /// Makes sure the input of a window operator is hash partitioned on the window
+    /// partition keys, so that every partition of a window is evaluated by the same task.
+    ///
+    /// DataFusion usually inserts a hash [RepartitionExec] below the window already, which is
+    /// turned into a shuffle when planning the children. If the input is not hash
+    /// partitioned on a subset of the keys, a hash shuffle is inserted here. When the input
+    /// is a [SortExec], the shuffle is placed below the sort so the window still receives
+    /// sorted partitions.
+    fn plan_window_input<'a>(
+        &'a mut self,
+        job_id: &'a str,
+        window: &Arc<dyn ExecutionPlan>,
+        new_input: Arc<dyn ExecutionPlan>,
+        stages: &mut Vec<Arc<ShuffleWriterExec>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let original_input = window.children()[0].clone();
+        let input_partitioning = original_input.output_partitioning();
+        let partition_keys = match window.required_input_distribution().first() {
+            Some(Distribution::HashPartitioned(keys)) => keys.clone(),
+            _ => return Ok(new_input),
+        };
+        let partitioned_on_keys = match &input_partitioning {
+            // rows with the same keys also have the same values of a subset of the keys
+            Partitioning::Hash(exprs, _) => {
+                !exprs.is_empty()
+                    && exprs.iter().all(|expr| {
+                        partition_keys.iter().any(|key| key.eq(expr.as_any()))
+                    })
+            }
+            _ => false,
+        };
+        if partitioned_on_keys || input_partitioning.partition_count() <= 1 {
+            return Ok(new_input);
+        }
+
+        let partitioning =
+            Partitioning::Hash(partition_keys, input_partitioning.partition_count());
+        if let Some(sort) = new_input.as_any().downcast_ref::<SortExec>() {
+            let shuffle_writer = create_shuffle_writer(
+                job_id,
+                self.next_stage_id(),
+                sort.input().clone(),
+                Some(partitioning),
+                self.shuffle_compression,
+                self.shuffle_sort_based,
+            )?;
+            let unresolved_shuffle = create_unresolved_shuffle(&shuffle_writer);
+            stages.push(shuffle_writer);
+            Ok(
+                with_new_children_if_necessary(new_input, vec![unresolved_shuffle])?
+                    .into(),
+            )
+        } else {
+            let shuffle_writer = create_shuffle_writer(
+                job_id,
+                self.next_stage_id(),
+                new_input,
+                Some(partitioning),
+                self.shuffle_compression,
+                self.shuffle_sort_based,
+            )?;
+            let unresolved_shuffle = create_unresolved_shuffle(&shuffle_writer);
+            stages.push(shuffle_writer);
+            match original_input.output_ordering() {
+                Some(ordering) => Ok(Arc::new(
+                    SortExec::new(ordering.to_vec(), unresolved_shuffle)
+                        .with_preserve_partitioning(true),
+                )),
+                None => Ok(unresolved_shuffle),
+            }
+        }
+    }
+
     /// Generate a new stage ID
     fn next_stage_id(&mut self) -> usize {
         self.next_stage_id += 1;
@@ -171,6 +413,46 @@ impl DistributedPlanner {
     }
 }
 
+/// Whether a stage plan is cheap enough to compute twice: a scan or a shuffle read that
+/// is only filtered, projected or repartitioned round robin
+fn is_cheap_to_recompute(plan: &Arc<dyn ExecutionPlan>) -> bool {
+    let cheap_operator = match plan.as_any().downcast_ref::<RepartitionExec>() {
+        Some(repartition) => {
+            matches!(repartition.partitioning(), Partitioning::RoundRobinBatch(_))
+        }
+        None => {
+            plan.as_any().is::<ProjectionExec>()
+                || plan.as_any().is::<FilterExec>()
+                || plan.as_any().is::<CoalesceBatchesExec>()
+        }
+    };
+    let children = plan.children();
+    children.is_empty() || (cheap_operator && children.iter().all(is_cheap_to_recompute))
+}
+
+fn is_window_exec(plan: &dyn ExecutionPlan) -> bool {
+    plan.as_any().is::<WindowAggExec>() || plan.as_any().is::<BoundedWindowAggExec>()
+}
+
+/// Replaces the coalesced shuffle read of the build side of a `CollectLeft` hash join with
+/// a broadcast shuffle read, so that every task of the stage reads all the partitions of the
+/// build side as a single partition.
+fn broadcast_build_side(build_side: Arc<dyn ExecutionPlan>) -> Arc<dyn ExecutionPlan> {
+    if build_side.as_any().is::<CoalescePartitionsExec>() {
+        if let Some(unresolved_shuffle) = build_side.children()[0]
+            .as_any()
+            .downcast_ref::<UnresolvedShuffleExec>()
+        {
+            return Arc::new(UnresolvedShuffleExec::new_broadcast(
+                unresolved_shuffle.stage_id,
+                unresolved_shuffle.schema.clone(),
+                unresolved_shuffle.output_partition_count,
+            ));
+        }
+    }
+    build_side
+}
+
 fn create_unresolved_shuffle(
     shuffle_writer: &ShuffleWriterExec,
 ) -> Arc<UnresolvedShuffleExec> {
@@ -221,7 +503,14 @@ pub fn remove_unresolved_shuffles(
                 })?
                 .clone();
 
-            for i in 0..unresolved_shuffle.output_partition_count {
+            // The input stage may have been re-planned adaptively into more partitions
+            let output_partition_count = p
+                .keys()
+                .map(|partition| partition + 1)
+                .max()
+                .unwrap_or_default()
+                .max(unresolved_shuffle.output_partition_count);
+            for i in 0..output_partition_count {
                 if let Some(x) = p.get(&i) {
                     relevant_locations.push(x.to_owned());
                 } else {
@@ -241,11 +530,20 @@ pub fn remove_unresolved_shuffles(
                     .collect::<Vec<_>>()
                     .join("\n")
             );
-            new_children.push(Arc::new(ShuffleReaderExec::try_new(
-                unresolved_shuffle.stage_id,
-                relevant_locations,
-                unresolved_shuffle.schema().clone(),
-            )?))
+            let shuffle_reader = if unresolved_shuffle.broadcast {
+                ShuffleReaderExec::try_new_broadcast(
+                    unresolved_shuffle.stage_id,
+                    relevant_locations,
+                    unresolved_shuffle.schema().clone(),
+                )?
+            } else {
+                ShuffleReaderExec::try_new(
+                    unresolved_shuffle.stage_id,
+                    relevant_locations,
+                    unresolved_shuffle.schema().clone(),
+                )?
+            };
+            new_children.push(Arc::new(shuffle_reader))
         } else {
             new_children.push(remove_unresolved_shuffles(child, partition_locations)?);
         }
@@ -262,15 +560,22 @@ pub fn rollback_resolved_shuffles(
     let mut new_children: Vec<Arc<dyn ExecutionPlan>> = vec![];
     for child in stage.children() {
         if let Some(shuffle_reader) = child.as_any().downcast_ref::<ShuffleReaderExec>() {
-            let output_partition_count =
-                shuffle_reader.output_partitioning().partition_count();
+            let output_partition_count = shuffle_reader.partition.len();
             let stage_id = shuffle_reader.stage_id;
 
-            let unresolved_shuffle = Arc::new(UnresolvedShuffleExec::new(
-                stage_id,
-                shuffle_reader.schema(),
-                output_partition_count,
-            ));
+            let unresolved_shuffle = if shuffle_reader.broadcast {
+                Arc::new(UnresolvedShuffleExec::new_broadcast(
+                    stage_id,
+                    shuffle_reader.schema(),
+                    output_partition_count,
+                ))
+            } else {
+                Arc::new(UnresolvedShuffleExec::new(
+                    stage_id,
+                    shuffle_reader.schema(),
+                    output_partition_count,
+                ))
+            };
             new_children.push(unresolved_shuffle);
         } else {
             new_children.push(rollback_resolved_shuffles(child)?);
@@ -284,31 +589,48 @@ fn create_shuffle_writer(
     stage_id: usize,
     plan: Arc<dyn ExecutionPlan>,
     partitioning: Option<Partitioning>,
+    compression: ShuffleCompression,
+    sort_based: bool,
 ) -> Result<Arc<ShuffleWriterExec>> {
-    Ok(Arc::new(ShuffleWriterExec::try_new(
-        job_id.to_owned(),
-        stage_id,
-        plan,
-        "".to_owned(), // executor will decide on the work_dir path
-        partitioning,
-    )?))
+    Ok(Arc::new(
+        ShuffleWriterExec::try_new(
+            job_id.to_owned(),
+            stage_id,
+            plan,
+            "".to_owned(), // executor will decide on the work_dir path
+            partitioning,
+        )?
+        .with_compression(compression)
+        .with_sort_based(sort_based),
+    ))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::planner::DistributedPlanner;
-    use crate::test_utils::datafusion_test_context;
+    use crate::planner::{find_unresolved_shuffles, is_window_exec, DistributedPlanner};
+    use crate::test_utils::{
+        datafusion_test_context, datafusion_test_context_with_config,
+    };
+    use ballista_core::config::ShuffleCompression;
     use ballista_core::error::BallistaError;
-    use ballista_core::execution_plans::UnresolvedShuffleExec;
+    use ballista_core::execution_plans::{
+        RangeSampleExec, ShuffleWriterExec, UnresolvedShuffleExec,
+    };
     use ballista_core::serde::BallistaCodec;
     use datafusion::physical_plan::aggregates::{AggregateExec, AggregateMode};
     use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
-    use datafusion::physical_plan::joins::HashJoinExec;
+    use datafusion::physical_plan::expressions::Column;
+    use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode};
     use datafusion::physical_plan::projection::ProjectionExec;
+    use datafusion::physical_plan::repartition::RepartitionExec;
     use datafusion::physical_plan::sorts::sort::SortExec;
     use datafusion::physical_plan::sorts::sort_preserving_merge::SortPreservingMergeExec;
-    use datafusion::physical_plan::{displayable, ExecutionPlan};
-    use datafusion::prelude::SessionContext;
+    use datafusion::physical_plan::windows::BoundedWindowAggExec;
+    use datafusion::physical_plan::{
+        displayable, with_new_children_if_necessary, ExecutionPlan, Partitioning,
+        PhysicalExpr,
+    };
+    use datafusion::prelude::{SessionConfig, SessionContext};
     use datafusion_proto::physical_plan::AsExecutionPlan;
     use datafusion_proto::protobuf::LogicalPlanNode;
     use datafusion_proto::protobuf::PhysicalPlanNode;
@@ -578,6 +900,62 @@ order by
         Ok(())
     }
 
+    #[tokio::test]
+    async fn distributed_broadcast_join_plan() -> Result<(), BallistaError> {
+        // without repartitioning, the build side of joins is collected by every task
+        let config = SessionConfig::new()
+            .with_target_partitions(2)
+            .with_repartition_joins(false);
+        let ctx = datafusion_test_context_with_config("testdata", config).await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_shipmode, o_orderpriority
+            from lineitem
+            join orders on l_orderkey = o_orderkey",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let mut planner = DistributedPlanner::new();
+        let job_uuid = Uuid::new_v4();
+        let stages = planner.plan_query_stages(&job_uuid.to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent(false));
+        }
+
+        assert_eq!(2, stages.len());
+
+        // csv "lineitem" (2 files) is written unpartitioned
+        assert!(stages[0].shuffle_output_partitioning().is_none());
+
+        let mut join = stages[1].children()[0].clone();
+        while !join.as_any().is::<HashJoinExec>() {
+            join = join.children()[0].clone();
+        }
+        let join = downcast_exec!(join, HashJoinExec);
+        assert!(matches!(join.partition_mode(), PartitionMode::CollectLeft));
+
+        // every join task reads both partitions of the build side as a single partition
+        let build_side = join.children()[0].clone();
+        let build_side = downcast_exec!(build_side, UnresolvedShuffleExec);
+        assert!(build_side.broadcast);
+        assert_eq!(build_side.stage_id, 1);
+        assert_eq!(build_side.output_partition_count, 2);
+        assert_eq!(1, build_side.output_partitioning().partition_count());
+
+        let build_side_serde = roundtrip_operator(&ctx, join.children()[0].clone())?;
+        let build_side_serde = downcast_exec!(build_side_serde, UnresolvedShuffleExec);
+        assert!(build_side_serde.broadcast);
+        assert_eq!(build_side_serde.output_partition_count, 2);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn roundtrip_serde_aggregate() -> Result<(), BallistaError> {
         let ctx = datafusion_test_context("testdata").await?;
@@ -615,6 +993,379 @@ order by
         Ok(())
     }
 
+    #[tokio::test]
+    async fn distributed_window_plan() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, l_extendedprice,
+                rank() over (partition by l_returnflag order by l_extendedprice) as rnk
+            from lineitem",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let mut planner = DistributedPlanner::new();
+        let job_uuid = Uuid::new_v4();
+        let stages = planner.plan_query_stages(&job_uuid.to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent(false));
+        }
+
+        /* Expected result:
+
+        ShuffleWriterExec: Some(Hash([Column { name: "l_returnflag", index: 1 }], 2))
+          CsvExec: file_groups={2 groups: [[testdata/lineitem/partition0.tbl], [testdata/lineitem/partition1.tbl]]}, projection=[l_extendedprice, l_returnflag], has_header=false
+
+        ShuffleWriterExec: None
+          ProjectionExec: expr=[l_returnflag@1 as l_returnflag, l_extendedprice@0 as l_extendedprice, RANK() PARTITION BY [lineitem.l_returnflag] ORDER BY [lineitem.l_extendedprice ASC NULLS LAST] RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW@2 as rnk]
+            BoundedWindowAggExec: wdw=[RANK() PARTITION BY [lineitem.l_returnflag] ORDER BY [lineitem.l_extendedprice ASC NULLS LAST] RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW: ...], mode=[Sorted]
+              SortExec: expr=[l_returnflag@1 ASC NULLS LAST,l_extendedprice@0 ASC NULLS LAST]
+                CoalesceBatchesExec: target_batch_size=8192
+                  UnresolvedShuffleExec
+        */
+
+        assert_eq!(2, stages.len());
+
+        // verify stage 0 is hash partitioned on the window key
+        assert_eq!(
+            2,
+            stages[0]
+                .shuffle_output_partitioning()
+                .unwrap()
+                .partition_count()
+        );
+
+        // verify stage 1 evaluates the window over sorted, shuffled partitions
+        assert!(stages[1].shuffle_output_partitioning().is_none());
+        let mut window = stages[1].children()[0].clone();
+        while !is_window_exec(window.as_ref()) {
+            window = window.children()[0].clone();
+        }
+        assert_eq!(2, window.output_partitioning().partition_count());
+
+        let sort = window.children()[0].clone();
+        let sort = downcast_exec!(sort, SortExec);
+        let coalesce = sort.children()[0].clone();
+        let coalesce = downcast_exec!(coalesce, CoalesceBatchesExec);
+        let unresolved_shuffle = coalesce.children()[0].clone();
+        let unresolved_shuffle =
+            downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 1);
+        assert_eq!(unresolved_shuffle.output_partition_count, 2);
+
+        Ok(())
+    }
+
+    /// Replaces the hash repartition below the window of a window plan, to plan windows
+    /// whose input is not partitioned on the window keys
+    fn replace_window_repartition(
+        plan: Arc<dyn ExecutionPlan>,
+        partitioning: Option<Partitioning>,
+    ) -> Result<Arc<dyn ExecutionPlan>, BallistaError> {
+        if plan.as_any().is::<RepartitionExec>() {
+            let input = plan.children()[0].clone();
+            return Ok(match partitioning {
+                Some(partitioning) => {
+                    Arc::new(RepartitionExec::try_new(input, partitioning)?)
+                }
+                None => input,
+            });
+        }
+        let children = plan
+            .children()
+            .into_iter()
+            .map(|child| replace_window_repartition(child, partitioning.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(with_new_children_if_necessary(plan, children)?.into())
+    }
+
+    #[tokio::test]
+    async fn distributed_window_plan_with_new_shuffle() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, l_extendedprice,
+                rank() over (partition by l_returnflag order by l_extendedprice) as rnk
+            from lineitem",
+            )
+            .await?;
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let l_returnflag: Arc<dyn PhysicalExpr> =
+            Arc::new(Column::new("l_returnflag", 1));
+        let l_extendedprice: Arc<dyn PhysicalExpr> =
+            Arc::new(Column::new("l_extendedprice", 0));
+        // an input which is not partitioned, and an input partitioned on another column
+        for (partitioning, stage_count) in [
+            (None, 2),
+            (Some(Partitioning::Hash(vec![l_extendedprice], 2)), 3),
+        ] {
+            let plan = replace_window_repartition(plan.clone(), partitioning)?;
+            let mut planner = DistributedPlanner::new();
+            let stages = planner.plan_query_stages(&Uuid::new_v4().to_string(), plan)?;
+            assert_eq!(stage_count, stages.len());
+
+            // verify the stage below the window is hash partitioned on the window key
+            let shuffle_stage = &stages[stage_count - 2];
+            match shuffle_stage.shuffle_output_partitioning() {
+                Some(Partitioning::Hash(exprs, 2)) => {
+                    assert_eq!(1, exprs.len());
+                    assert!(exprs[0].eq(l_returnflag.as_any()));
+                }
+                partitioning => panic!("Unexpected partitioning {partitioning:?}"),
+            }
+
+            // verify the window sorts the partitions read from the new shuffle
+            let mut window = stages[stage_count - 1].children()[0].clone();
+            while !is_window_exec(window.as_ref()) {
+                window = window.children()[0].clone();
+            }
+            let sort = window.children()[0].clone();
+            let sort = downcast_exec!(sort, SortExec);
+            let unresolved_shuffle = sort.children()[0].clone();
+            let unresolved_shuffle =
+                downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+            assert_eq!(shuffle_stage.stage_id(), unresolved_shuffle.stage_id);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn roundtrip_serde_window() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, l_extendedprice,
+                rank() over (partition by l_returnflag order by l_extendedprice) as rnk
+            from lineitem",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let mut planner = DistributedPlanner::new();
+        let job_uuid = Uuid::new_v4();
+        let stages = planner.plan_query_stages(&job_uuid.to_string(), plan)?;
+
+        let projection = stages[1].children()[0].clone();
+        let window = projection.children()[0].clone();
+        let window_serde = roundtrip_operator(&ctx, window.clone())?;
+
+        let window = downcast_exec!(window, BoundedWindowAggExec);
+        let window_serde = downcast_exec!(window_serde, BoundedWindowAggExec);
+
+        assert_eq!(format!("{window:?}"), format!("{window_serde:?}"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn distributed_plan_with_shuffle_options() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, sum(l_extendedprice) as sum_price
+            from lineitem
+            group by l_returnflag",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        for (compression, sort_based) in [
+            (ShuffleCompression::Lz4, false),
+            (ShuffleCompression::None, true),
+            (ShuffleCompression::Zstd, true),
+        ] {
+            let mut planner = DistributedPlanner::new()
+                .with_shuffle_compression(compression)
+                .with_sort_based_shuffle(sort_based);
+            let job_uuid = Uuid::new_v4();
+            let stages =
+                planner.plan_query_stages(&job_uuid.to_string(), plan.clone())?;
+
+            assert_eq!(2, stages.len());
+            for stage in stages {
+                assert_eq!(compression, stage.compression());
+                assert_eq!(sort_based, stage.sort_based());
+
+                let stage_serde = roundtrip_operator(&ctx, stage)?;
+                let stage_serde = downcast_exec!(stage_serde, ShuffleWriterExec);
+                assert_eq!(compression, stage_serde.compression());
+                assert_eq!(sort_based, stage_serde.sort_based());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn distributed_range_partitioned_sort() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, sum(l_extendedprice * 1) as sum_disc_price
+            from lineitem
+            group by l_returnflag
+            order by l_returnflag",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let mut planner = DistributedPlanner::new().with_repartition_sorts(true);
+        let job_uuid = Uuid::new_v4();
+        let stages = planner.plan_query_stages(&job_uuid.to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent(false));
+        }
+
+        /* Expected result:
+
+        ShuffleWriterExec: Some(Hash([Column { name: "l_returnflag", index: 0 }], 2))
+          AggregateExec: mode=Partial, gby=[l_returnflag@1 as l_returnflag], aggr=[SUM(lineitem.l_extendedprice * Int64(1))]
+            CsvExec: files={2 groups: [[ballista/scheduler/testdata/lineitem/partition1.tbl], [ballista/scheduler/testdata/lineitem/partition0.tbl]]}, has_header=false, limit=None, projection=[l_extendedprice, l_returnflag]
+
+        ShuffleWriterExec: None
+          ProjectionExec: expr=[l_returnflag@0 as l_returnflag, SUM(lineitem.l_extendedprice * Int64(1))@1 as sum_disc_price]
+            AggregateExec: mode=FinalPartitioned, gby=[l_returnflag@0 as l_returnflag], aggr=[SUM(lineitem.l_extendedprice * Int64(1))]
+              CoalesceBatchesExec: target_batch_size=8192
+                UnresolvedShuffleExec
+
+        ShuffleWriterExec: None
+          RangeSampleExec: sample_size=100
+            UnresolvedShuffleExec
+
+        ShuffleWriterExec: Range([l_returnflag@0 ASC NULLS LAST], 2)
+          UnresolvedShuffleExec
+          UnresolvedShuffleExec: broadcast
+
+        ShuffleWriterExec: None
+          SortExec: expr=[l_returnflag@0 ASC NULLS LAST]
+            UnresolvedShuffleExec
+        */
+
+        assert_eq!(5, stages.len());
+
+        // verify stage 2, the aggregation is computed once for the sample and the ranges
+        let stage2 = stages[1].children()[0].clone();
+        downcast_exec!(stage2, ProjectionExec);
+        assert!(stages[1].shuffle_output_partitioning().is_none());
+
+        // verify stage 3
+        let stage3 = stages[2].children()[0].clone();
+        let sample = downcast_exec!(stage3, RangeSampleExec);
+        assert_eq!(100, sample.sample_size());
+        let unresolved_shuffle = sample.children()[0].clone();
+        let unresolved_shuffle =
+            downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 2);
+
+        // verify stage 4
+        let range = stages[3].range_partitioning().unwrap();
+        assert_eq!(2, range.partition_count);
+        assert_eq!(1, range.sort_exprs.len());
+        let unresolved_shuffle = downcast_exec!(range.sample, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 3);
+        assert!(unresolved_shuffle.broadcast);
+        assert_eq!(2, stages[3].children().len());
+        let unresolved_shuffle = stages[3].children()[0].clone();
+        let unresolved_shuffle =
+            downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 2);
+        assert_eq!(2, stages[3].output_partitioning().partition_count());
+
+        // verify stage 5
+        let stage5 = stages[4].children()[0].clone();
+        let sort = downcast_exec!(stage5, SortExec);
+        assert!(sort.preserve_partitioning());
+        let unresolved_shuffle = sort.children()[0].clone();
+        let unresolved_shuffle =
+            downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 4);
+        assert_eq!(unresolved_shuffle.output_partition_count, 2);
+        assert_eq!(2, stages[4].output_partitioning().partition_count());
+
+        // the range partitioning survives the roundtrip with the sample as second input
+        let stage_serde = roundtrip_operator(&ctx, stages[3].clone())?;
+        let stage_serde = downcast_exec!(stage_serde, ShuffleWriterExec);
+        let range_serde = stage_serde.range_partitioning().unwrap();
+        assert_eq!(2, range_serde.partition_count);
+        assert_eq!(format!("{range}"), format!("{range_serde}"));
+        let sample_serde = roundtrip_operator(&ctx, stages[2].clone())?;
+        assert!(sample_serde.children()[0].as_any().is::<RangeSampleExec>());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn distributed_range_partitioned_sort_of_scan() -> Result<(), BallistaError> {
+        let ctx = datafusion_test_context("testdata").await?;
+        let session_state = ctx.state();
+
+        let df = ctx
+            .sql(
+                "select l_returnflag, l_extendedprice
+            from lineitem
+            where l_quantity > 1
+            order by l_returnflag",
+            )
+            .await?;
+
+        let plan = df.into_optimized_plan()?;
+        let plan = session_state.optimize(&plan)?;
+        let plan = session_state.create_physical_plan(&plan).await?;
+
+        let mut planner = DistributedPlanner::new()
+            .with_repartition_sorts(true)
+            .with_range_sample_size(10);
+        let job_uuid = Uuid::new_v4();
+        let stages = planner.plan_query_stages(&job_uuid.to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent(false));
+        }
+
+        // the filtered scan is cheap enough to compute for the sample and the ranges
+        assert_eq!(3, stages.len());
+        let stage1 = stages[0].children()[0].clone();
+        let sample = downcast_exec!(stage1, RangeSampleExec);
+        assert_eq!(10, sample.sample_size());
+        assert!(find_unresolved_shuffles(&stages[0].children()[0])?.is_empty());
+
+        let range = stages[1].range_partitioning().unwrap();
+        let unresolved_shuffle = downcast_exec!(range.sample, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, 1);
+        assert!(find_unresolved_shuffles(&stages[1].children()[0])?.is_empty());
+
+        let stage3 = stages[2].children()[0].clone();
+        downcast_exec!(stage3, SortExec);
+
+        Ok(())
+    }
+
     fn roundtrip_operator(
         ctx: &SessionContext,
         plan: Arc<dyn ExecutionPlan>,
@@ -19,22 +19,33 @@ use anyhow::{Context, Result};
 #[cfg(feature = "flight-sql")]
 use arrow_flight::flight_service_server::FlightServiceServer;
 use futures::future::{self, Either, TryFutureExt};
-use hyper::{server::conn::AddrStream, service::make_service_fn, Server};
-use log::info;
+use hyper::server::accept::{self, Accept};
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use hyper::{service::make_service_fn, Server};
+use log::{debug, info, warn};
+use rustls_pemfile::Item;
 use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use tonic::transport::server::Connected;
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tonic::transport::server::{Connected, TcpConnectInfo};
 use tower::Service;
 
 use datafusion_proto::protobuf::{LogicalPlanNode, PhysicalPlanNode};
 
 use ballista_core::serde::protobuf::scheduler_grpc_server::SchedulerGrpcServer;
 use ballista_core::serde::BallistaCodec;
-use ballista_core::utils::create_grpc_server;
+use ballista_core::utils::{create_grpc_server, GrpcTlsConfig};
 use ballista_core::BALLISTA_VERSION;
 
 use crate::api::{get_routes, EitherBody, Error};
+#[cfg(feature = "flight-sql")]
+use crate::auth::create_authenticator;
 use crate::cluster::BallistaCluster;
 use crate::config::SchedulerConfig;
 use crate::flight_sql::FlightSqlServiceImpl;
@@ -58,6 +69,10 @@ pub async fn start_server(
     );
 
     let metrics_collector = default_metrics_collector()?;
+    let tls = config.grpc_tls.clone();
+
+    #[cfg(feature = "flight-sql")]
+    let authenticator = create_authenticator(&config.flight_sql_auth)?;
 
     let mut scheduler_server: SchedulerServer<LogicalPlanNode, PhysicalPlanNode> =
         SchedulerServer::new(
@@ -70,55 +85,151 @@ pub async fn start_server(
 
     scheduler_server.init().await?;
 
-    Server::bind(&addr)
-        .serve(make_service_fn(move |request: &AddrStream| {
-            let config = &scheduler_server.state.config;
-            let scheduler_grpc_server =
-                SchedulerGrpcServer::new(scheduler_server.clone())
-                    .max_decoding_message_size(
-                        config.grpc_server_max_decoding_message_size as usize,
+    let grpc_server = create_grpc_server(None)?;
+    let make_service = move |connect_info: TcpConnectInfo| {
+        let config = &scheduler_server.state.config;
+        let scheduler_grpc_server = SchedulerGrpcServer::new(scheduler_server.clone())
+            .max_decoding_message_size(
+                config.grpc_server_max_decoding_message_size as usize,
+            );
+
+        let keda_scaler = ExternalScalerServer::new(scheduler_server.clone());
+
+        let tonic_builder = grpc_server
+            .clone()
+            .add_service(scheduler_grpc_server)
+            .add_service(keda_scaler);
+
+        #[cfg(feature = "flight-sql")]
+        let tonic_builder = tonic_builder.add_service(FlightServiceServer::new(
+            FlightSqlServiceImpl::new(scheduler_server.clone(), authenticator.clone()),
+        ));
+
+        let mut tonic = tonic_builder.into_service();
+
+        let mut warp = warp::service(get_routes(scheduler_server.clone()));
+
+        future::ok::<_, Infallible>(tower::service_fn(
+            move |req: hyper::Request<hyper::Body>| {
+                // Set the connect info from hyper to tonic
+                let (mut parts, body) = req.into_parts();
+                parts.extensions.insert(connect_info.clone());
+                let req = http::Request::from_parts(parts, body);
+
+                if req.uri().path().starts_with("/api") {
+                    return Either::Left(
+                        warp.call(req)
+                            .map_ok(|res| res.map(EitherBody::Left))
+                            .map_err(Error::from),
                     );
+                }
+
+                Either::Right(
+                    tonic
+                        .call(req)
+                        .map_ok(|res| res.map(EitherBody::Right))
+                        .map_err(Error::from),
+                )
+            },
+        ))
+    };
+
+    // TLS is terminated here rather than by tonic, as the REST API shares the port
+    let incoming = AddrIncoming::bind(&addr).context("Could not bind scheduler")?;
+    match &tls {
+        Some(tls) => {
+            Server::builder(tls_incoming(incoming, tls)?)
+                .serve(make_service_fn(move |conn: &TlsStream<AddrStream>| {
+                    make_service(conn.get_ref().0.connect_info())
+                }))
+                .await
+        }
+        None => {
+            Server::builder(incoming)
+                .serve(make_service_fn(move |conn: &AddrStream| {
+                    make_service(conn.connect_info())
+                }))
+                .await
+        }
+    }
+    .context("Could not start grpc server")
+}
 
-            let keda_scaler = ExternalScalerServer::new(scheduler_server.clone());
-
-            let tonic_builder = create_grpc_server()
-                .add_service(scheduler_grpc_server)
-                .add_service(keda_scaler);
-
-            #[cfg(feature = "flight-sql")]
-            let tonic_builder = tonic_builder.add_service(FlightServiceServer::new(
-                FlightSqlServiceImpl::new(scheduler_server.clone()),
-            ));
-
-            let mut tonic = tonic_builder.into_service();
-
-            let mut warp = warp::service(get_routes(scheduler_server.clone()));
-
-            let connect_info = request.connect_info();
-            future::ok::<_, Infallible>(tower::service_fn(
-                move |req: hyper::Request<hyper::Body>| {
-                    // Set the connect info from hyper to tonic
-                    let (mut parts, body) = req.into_parts();
-                    parts.extensions.insert(connect_info.clone());
-                    let req = http::Request::from_parts(parts, body);
-
-                    if req.uri().path().starts_with("/api") {
-                        return Either::Left(
-                            warp.call(req)
-                                .map_ok(|res| res.map(EitherBody::Left))
-                                .map_err(Error::from),
-                        );
+/// Accept TLS connections. Handshakes are done concurrently, so that a slow client does
+/// not hold up the others
+fn tls_incoming(
+    mut incoming: AddrIncoming,
+    tls: &GrpcTlsConfig,
+) -> Result<impl Accept<Conn = TlsStream<AddrStream>, Error = std::io::Error>> {
+    let acceptor = TlsAcceptor::from(Arc::new(rustls_server_config(tls)?));
+    let (tx, mut rx) = mpsc::channel::<std::io::Result<TlsStream<AddrStream>>>(128);
+    tokio::spawn(async move {
+        loop {
+            let stream =
+                match future::poll_fn(|cx| Pin::new(&mut incoming).poll_accept(cx)).await
+                {
+                    Some(Ok(stream)) => stream,
+                    Some(Err(e)) => {
+                        warn!("Failed to accept connection: {e}");
+                        continue;
                     }
+                    None => break,
+                };
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                match acceptor.accept(stream).await {
+                    Ok(stream) => {
+                        let _ = tx.send(Ok(stream)).await;
+                    }
+                    Err(e) => debug!("TLS handshake failed: {e}"),
+                }
+            });
+        }
+    });
+    Ok(accept::from_stream(futures::stream::poll_fn(move |cx| {
+        rx.poll_recv(cx)
+    })))
+}
 
-                    Either::Right(
-                        tonic
-                            .call(req)
-                            .map_ok(|res| res.map(EitherBody::Right))
-                            .map_err(Error::from),
-                    )
-                },
-            ))
-        }))
-        .await
-        .context("Could not start grpc server")
+fn rustls_server_config(tls: &GrpcTlsConfig) -> Result<ServerConfig> {
+    let (cert, key) = tls
+        .identity()
+        .context("A certificate and private key are required to serve TLS")?;
+    let certs = rustls_pemfile::certs(&mut &*cert)
+        .context("Invalid certificate")?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = rustls_pemfile::read_all(&mut &*key)
+        .context("Invalid private key")?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                Some(PrivateKey(key))
+            }
+            _ => None,
+        })
+        .context("No private key found")?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = if tls.client_auth() {
+        let ca_cert = tls
+            .ca_cert()
+            .context("A CA certificate is required to verify client certificates")?;
+        let mut roots = RootCertStore::empty();
+        for cert in
+            rustls_pemfile::certs(&mut &*ca_cert).context("Invalid CA certificate")?
+        {
+            roots.add(&Certificate(cert))?;
+        }
+        builder
+            .with_client_cert_verifier(Arc::new(AllowAnyAuthenticatedClient::new(roots)))
+    } else {
+        builder.with_no_client_auth()
+    };
+    let mut config = builder.with_single_cert(certs, key)?;
+    // gRPC needs HTTP/2, the REST API and UI are served over HTTP/1.1 as well
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
 }
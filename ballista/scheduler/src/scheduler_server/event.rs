@@ -19,7 +19,7 @@ use std::fmt::{Debug, Formatter};
 
 use datafusion::logical_expr::LogicalPlan;
 
-use crate::state::execution_graph::RunningTaskInfo;
+use crate::state::execution_graph::{JobProperties, RunningTaskInfo};
 use ballista_core::serde::protobuf::TaskStatus;
 use datafusion::prelude::SessionContext;
 use std::sync::Arc;
@@ -29,6 +29,7 @@ pub enum QueryStageSchedulerEvent {
     JobQueued {
         job_id: String,
         job_name: String,
+        properties: JobProperties,
         session_ctx: Arc<SessionContext>,
         plan: Box<LogicalPlan>,
         queued_at: u64,
@@ -57,6 +58,12 @@ pub enum QueryStageSchedulerEvent {
         queued_at: u64,
         failed_at: u64,
     },
+    // For a job which is still running after its timeout
+    JobTimedOut {
+        job_id: String,
+        timeout_seconds: u64,
+        queued_at: u64,
+    },
     JobUpdated(String),
     JobCancel(String),
     JobDataClean(String),
@@ -109,6 +116,16 @@ impl Debug for QueryStageSchedulerEvent {
                     "JobRunningFailed : job_id={job_id}, fail_message={fail_message}, queued_at={queued_at}, failed_at={failed_at}.",
                 )
             }
+            QueryStageSchedulerEvent::JobTimedOut {
+                job_id,
+                timeout_seconds,
+                ..
+            } => {
+                write!(
+                    f,
+                    "JobTimedOut : job_id={job_id}, timeout_seconds={timeout_seconds}."
+                )
+            }
             QueryStageSchedulerEvent::JobUpdated(job_id) => {
                 write!(f, "JobUpdated : job_id={job_id}.")
             }
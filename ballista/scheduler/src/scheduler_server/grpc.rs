@@ -21,6 +21,7 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 
 use ballista_core::serde::protobuf::executor_registration::OptionalHost;
+use ballista_core::serde::protobuf::job_status;
 use ballista_core::serde::protobuf::scheduler_grpc_server::SchedulerGrpc;
 use ballista_core::serde::protobuf::{
     execute_query_failure_result, execute_query_result, AvailableTaskSlots,
@@ -29,10 +30,10 @@ use ballista_core::serde::protobuf::{
     ExecuteQueryParams, ExecuteQueryResult, ExecuteQuerySuccessResult, ExecutorHeartbeat,
     ExecutorStoppedParams, ExecutorStoppedResult, GetFileMetadataParams,
     GetFileMetadataResult, GetJobStatusParams, GetJobStatusResult, HeartBeatParams,
-    HeartBeatResult, PollWorkParams, PollWorkResult, RegisterExecutorParams,
+    HeartBeatResult, JobStatus, PollWorkParams, PollWorkResult, RegisterExecutorParams,
     RegisterExecutorResult, RemoveSessionParams, RemoveSessionResult,
     UpdateSessionParams, UpdateSessionResult, UpdateTaskStatusParams,
-    UpdateTaskStatusResult,
+    UpdateTaskStatusResult, WatchJobParams, WatchJobResult,
 };
 use ballista_core::serde::scheduler::ExecutorMetadata;
 
@@ -40,21 +41,29 @@ use datafusion::datasource::file_format::parquet::ParquetFormat;
 use datafusion::datasource::file_format::FileFormat;
 use datafusion_proto::logical_plan::AsLogicalPlan;
 use datafusion_proto::physical_plan::AsExecutionPlan;
-use futures::TryStreamExt;
+use futures::{Stream, StreamExt, TryStreamExt};
 use log::{debug, error, info, trace, warn};
 use object_store::{local::LocalFileSystem, path::Path, ObjectStore};
 
 use std::ops::Deref;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::cluster::{bind_task_bias, bind_task_round_robin};
+use crate::cluster::{
+    active_jobs_for_executor, bind_task_bias, bind_task_round_robin, JobStateEvent,
+    JobStateEventStream,
+};
 use crate::config::TaskDistributionPolicy;
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 use datafusion::prelude::SessionContext;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
 use tonic::{Request, Response, Status};
 
 use crate::scheduler_server::SchedulerServer;
+use crate::state::execution_graph::JobProperties;
+use crate::state::SchedulerState;
 
 #[tonic::async_trait]
 impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
@@ -80,29 +89,32 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
             trace!("Received poll_work request for {:?}", metadata);
             let executor_id = metadata.id.clone();
 
+            let metadata = ExecutorMetadata {
+                id: metadata.id,
+                host: metadata
+                    .optional_host
+                    .map(|h| match h {
+                        OptionalHost::Host(host) => host,
+                    })
+                    .unwrap_or_else(|| remote_addr.unwrap().ip().to_string()),
+                port: metadata.port as u16,
+                grpc_port: metadata.grpc_port as u16,
+                specification: metadata.specification.unwrap().into(),
+                labels: metadata
+                    .labels
+                    .into_iter()
+                    .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                    .collect(),
+            };
             // It's not necessary.
             // It's only for the scheduler to have a picture of the whole executor cluster.
+            if let Err(e) = self
+                .state
+                .executor_manager
+                .save_executor_metadata(metadata.clone())
+                .await
             {
-                let metadata = ExecutorMetadata {
-                    id: metadata.id,
-                    host: metadata
-                        .optional_host
-                        .map(|h| match h {
-                            OptionalHost::Host(host) => host,
-                        })
-                        .unwrap_or_else(|| remote_addr.unwrap().ip().to_string()),
-                    port: metadata.port as u16,
-                    grpc_port: metadata.grpc_port as u16,
-                    specification: metadata.specification.unwrap().into(),
-                };
-                if let Err(e) = self
-                    .state
-                    .executor_manager
-                    .save_executor_metadata(metadata)
-                    .await
-                {
-                    warn!("Could not save executor metadata: {:?}", e);
-                }
+                warn!("Could not save executor metadata: {:?}", e);
             }
 
             self.update_task_status(&executor_id, task_status)
@@ -116,12 +128,38 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
                     Status::internal(msg)
                 })?;
 
+            // Don't assign tasks to an executor running out of disk space
+            let num_free_slots =
+                if self.state.executor_manager.has_available_disk(&executor_id) {
+                    num_free_slots
+                } else {
+                    0
+                };
+            // Pulled tasks only reserve a task slot, as the executor limits the number
+            // of tasks it polls for
             let mut available_slots = vec![AvailableTaskSlots {
                 executor_id,
                 slots: num_free_slots,
+                memory: u64::MAX,
+                cpus: u32::MAX,
             }];
             let available_slots = available_slots.iter_mut().collect();
-            let active_jobs = self.state.task_manager.get_running_job_cache();
+            // Tasks are pulled without reviving offers, so the job deadlines are checked
+            // whenever executors poll for work
+            match self.query_stage_event_loop.get_sender() {
+                Ok(event_sender) => {
+                    if let Err(e) = self.state.time_out_jobs(&event_sender).await {
+                        warn!("Fail to time out jobs: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("Get query stage event loop error due to {:?}", e),
+            }
+            let active_jobs = self
+                .state
+                .task_manager
+                .get_running_job_cache(&self.state.config.job_scheduling)
+                .await;
+            let active_jobs = active_jobs_for_executor(active_jobs, &metadata);
             let schedulable_tasks = match self.state.config.task_distribution {
                 TaskDistributionPolicy::Bias => {
                     bind_task_bias(available_slots, active_jobs, |_| false).await
@@ -172,6 +210,11 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
                 port: metadata.port as u16,
                 grpc_port: metadata.grpc_port as u16,
                 specification: metadata.specification.unwrap().into(),
+                labels: metadata
+                    .labels
+                    .into_iter()
+                    .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                    .collect(),
             };
 
             self.do_register_executor(metadata).await.map_err(|e| {
@@ -220,6 +263,11 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
                     port: metadata.port as u16,
                     grpc_port: metadata.grpc_port as u16,
                     specification: metadata.specification.unwrap().into(),
+                    labels: metadata
+                        .labels
+                        .into_iter()
+                        .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                        .collect(),
                 };
 
                 self.do_register_executor(metadata).await.map_err(|e| {
@@ -234,6 +282,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
             }
         }
 
+        self.metrics_collector()
+            .record_executor_metrics(&executor_id, &metrics.as_slice().into());
+
         let executor_heartbeat = ExecutorHeartbeat {
             executor_id,
             timestamp: SystemTime::now()
@@ -425,6 +476,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
             query: Some(query),
             optional_session_id,
             settings,
+            trace_context,
         } = query_params
         {
             let mut query_settings = HashMap::new();
@@ -523,10 +575,36 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
                 .get(BALLISTA_JOB_NAME)
                 .cloned()
                 .unwrap_or_else(|| "None".to_string());
+            let properties = BallistaConfig::with_settings(query_settings)
+                .map(|config| {
+                    JobProperties::from_config(&config).with_trace_context(
+                        trace_context
+                            .into_iter()
+                            .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                            .collect(),
+                    )
+                })
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+            if let Err(msg) = self.state.admission_controller.admit(&job_id, &session_id)
+            {
+                warn!("{}", msg);
+                return Ok(Response::new(ExecuteQueryResult {
+                    result: Some(execute_query_result::Result::Failure(
+                        ExecuteQueryFailureResult {
+                            failure: Some(
+                                execute_query_failure_result::Failure::JobRejected(msg),
+                            ),
+                        },
+                    )),
+                }));
+            }
 
-            self.submit_job(&job_id, &job_name, session_ctx, &plan)
+            let job_id = self
+                .submit_job(&job_id, &job_name, properties, session_ctx, &plan)
                 .await
                 .map_err(|e| {
+                    self.state.admission_controller.release(&job_id);
                     let msg =
                         format!("Failed to send JobQueued event for {job_id}: {e:?}");
                     error!("{}", msg);
@@ -550,7 +628,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
     ) -> Result<Response<GetJobStatusResult>, Status> {
         let job_id = request.into_inner().job_id;
         trace!("Received get_job_status request for job {}", job_id);
-        match self.state.task_manager.get_job_status(&job_id).await {
+        match self.state.get_job_status(&job_id).await {
             Ok(status) => Ok(Response::new(GetJobStatusResult { status })),
             Err(e) => {
                 let msg = format!("Error getting status for job {job_id}: {e:?}");
@@ -560,6 +638,36 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
         }
     }
 
+    type WatchJobStream =
+        Pin<Box<dyn Stream<Item = Result<WatchJobResult, Status>> + Send + 'static>>;
+
+    async fn watch_job(
+        &self,
+        request: Request<WatchJobParams>,
+    ) -> Result<Response<Self::WatchJobStream>, Status> {
+        let job_id = request.into_inner().job_id;
+        trace!("Received watch_job request for job {}", job_id);
+        // Subscribe before reading the current status so no transition is missed
+        let events = self
+            .state
+            .task_manager
+            .job_state_events()
+            .await
+            .map_err(|e| {
+                let msg = format!("Error watching job {job_id}: {e:?}");
+                error!("{}", msg);
+                Status::internal(msg)
+            })?;
+
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(watch_job_status(self.state.clone(), job_id, events, tx));
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|result| (result, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
     async fn executor_stopped(
         &self,
         request: Request<ExecutorStoppedParams>,
@@ -640,6 +748,73 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerGrpc
     }
 }
 
+/// Interval at which the progress of a running job is pushed to watching clients
+/// when no job state event has been received in the meantime
+const WATCH_JOB_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Send the status of a job to a watching client each time it changes, until the
+/// job has finished or the client has gone away
+async fn watch_job_status<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>(
+    state: Arc<SchedulerState<T, U>>,
+    job_id: String,
+    mut events: JobStateEventStream,
+    tx: mpsc::Sender<Result<WatchJobResult, Status>>,
+) {
+    let mut interval = tokio::time::interval(WATCH_JOB_PROGRESS_INTERVAL);
+    let mut events_open = true;
+    let mut last_status: Option<Option<JobStatus>> = None;
+
+    loop {
+        tokio::select! {
+            event = events.next(), if events_open => match event {
+                Some(JobStateEvent::JobUpdated { job_id: updated, .. })
+                | Some(JobStateEvent::JobAcquired { job_id: updated, .. })
+                    if updated == job_id => {}
+                Some(_) => continue,
+                None => {
+                    warn!("Job state event stream closed while watching job {job_id}");
+                    events_open = false;
+                    continue;
+                }
+            },
+            _ = interval.tick() => {}
+        }
+
+        // The status of active jobs is read through the task manager to include progress
+        let status = match state.get_job_status(&job_id).await {
+            Ok(status) => status,
+            Err(e) => {
+                let msg = format!("Error getting status for job {job_id}: {e:?}");
+                error!("{}", msg);
+                let _ = tx.send(Err(Status::internal(msg))).await;
+                return;
+            }
+        };
+        if last_status.as_ref() == Some(&status) {
+            continue;
+        }
+
+        let finished = matches!(
+            status.as_ref().and_then(|s| s.status.as_ref()),
+            Some(job_status::Status::Successful(_)) | Some(job_status::Status::Failed(_))
+        );
+        if tx
+            .send(Ok(WatchJobResult {
+                status: status.clone(),
+            }))
+            .await
+            .is_err()
+        {
+            debug!("Client stopped watching job {job_id}");
+            return;
+        }
+        if finished {
+            return;
+        }
+        last_status = Some(status);
+    }
+}
+
 #[cfg(all(test, feature = "sled"))]
 mod test {
     use std::sync::Arc;
@@ -685,7 +860,8 @@ mod test {
             optional_host: Some(OptionalHost::Host("http://localhost:8080".to_owned())),
             port: 0,
             grpc_port: 0,
-            specification: Some(ExecutorSpecification { task_slots: 2 }.into()),
+            specification: Some(ExecutorSpecification::new(2).into()),
+            labels: vec![],
         };
         let request: Request<PollWorkParams> = Request::new(PollWorkParams {
             metadata: Some(exec_meta.clone()),
@@ -773,7 +949,8 @@ mod test {
             optional_host: Some(OptionalHost::Host("http://localhost:8080".to_owned())),
             port: 0,
             grpc_port: 0,
-            specification: Some(ExecutorSpecification { task_slots: 2 }.into()),
+            specification: Some(ExecutorSpecification::new(2).into()),
+            labels: vec![],
         };
 
         let request: Request<RegisterExecutorParams> =
@@ -858,7 +1035,8 @@ mod test {
             optional_host: Some(OptionalHost::Host("http://localhost:8080".to_owned())),
             port: 0,
             grpc_port: 0,
-            specification: Some(ExecutorSpecification { task_slots: 2 }.into()),
+            specification: Some(ExecutorSpecification::new(2).into()),
+            labels: vec![],
         };
 
         let request: Request<HeartBeatParams> = Request::new(HeartBeatParams {
@@ -911,7 +1089,8 @@ mod test {
             optional_host: Some(OptionalHost::Host("http://localhost:8080".to_owned())),
             port: 0,
             grpc_port: 0,
-            specification: Some(ExecutorSpecification { task_slots: 2 }.into()),
+            specification: Some(ExecutorSpecification::new(2).into()),
+            labels: vec![],
         };
 
         let request: Request<RegisterExecutorParams> =
@@ -30,15 +30,17 @@ use datafusion_proto::logical_plan::AsLogicalPlan;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 
 use crate::cluster::BallistaCluster;
-use crate::config::SchedulerConfig;
+use crate::config::{SchedulerConfig, SpeculationConfig};
 use crate::metrics::SchedulerMetricsCollector;
 use ballista_core::serde::scheduler::{ExecutorData, ExecutorMetadata};
-use log::{error, warn};
+use log::{error, info, warn};
 
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 use crate::scheduler_server::query_stage_scheduler::QueryStageScheduler;
 
+use crate::state::execution_graph::JobProperties;
 use crate::state::executor_manager::ExecutorManager;
+use crate::state::result_cache::result_cache_key;
 
 use crate::state::task_manager::TaskLauncher;
 use crate::state::SchedulerState;
@@ -142,6 +144,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerServer<T
         self.state.init().await?;
         self.query_stage_event_loop.start()?;
         self.expire_dead_executors()?;
+        if let Some(speculation) = self.state.config.speculation.clone() {
+            self.speculate_tasks(speculation)?;
+        }
 
         Ok(())
     }
@@ -158,23 +163,48 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerServer<T
         self.query_stage_scheduler.metrics_collector()
     }
 
+    /// Queue a new job, returning the id of the job whose results answer the plan.
+    /// This is the id of an earlier job if the results of the plan are cached.
     pub(crate) async fn submit_job(
         &self,
         job_id: &str,
         job_name: &str,
+        properties: JobProperties,
         ctx: Arc<SessionContext>,
         plan: &LogicalPlan,
-    ) -> Result<()> {
+    ) -> Result<String> {
+        if properties.result_cache {
+            match result_cache_key(&ctx, plan).await {
+                Ok(Some(key)) => {
+                    if let Some(cached_job_id) = self.state.result_cache.get(&key) {
+                        info!(
+                            "Job {} answered by the cached results of job {}",
+                            job_id, cached_job_id
+                        );
+                        self.state.admission_controller.release(job_id);
+                        return Ok(cached_job_id);
+                    }
+                    self.state.result_cache.watch(job_id, key);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to compute the result cache key of job {job_id}: {e:?}")
+                }
+            }
+        }
+
         self.query_stage_event_loop
             .get_sender()?
             .post_event(QueryStageSchedulerEvent::JobQueued {
                 job_id: job_id.to_owned(),
                 job_name: job_name.to_owned(),
+                properties,
                 session_ctx: ctx,
                 plan: Box::new(plan.clone()),
                 queued_at: timestamp_millis(),
             })
-            .await
+            .await?;
+        Ok(job_id.to_owned())
     }
 
     /// It just send task status update event to the channel,
@@ -271,6 +301,29 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerServer<T
         Ok(())
     }
 
+    /// Periodically look for straggler tasks and launch copies of them when there are
+    /// free slots
+    fn speculate_tasks(&self, config: SpeculationConfig) -> Result<()> {
+        let state = self.state.clone();
+        let event_sender = self.query_stage_event_loop.get_sender()?;
+        tokio::task::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(config.interval_ms)).await;
+                let speculated = state.task_manager.speculate_tasks(&config).await;
+                // In pull mode the copies are bound when executors poll for work
+                if speculated > 0 && state.config.is_push_staged_scheduling() {
+                    if let Err(e) = event_sender
+                        .post_event(QueryStageSchedulerEvent::ReviveOffers)
+                        .await
+                    {
+                        error!("error posting ReviveOffers for speculative tasks: {e:?}");
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
     pub(crate) fn remove_executor(
         executor_manager: ExecutorManager,
         event_sender: EventSender<QueryStageSchedulerEvent>,
@@ -301,11 +354,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerServer<T
     }
 
     async fn do_register_executor(&self, metadata: ExecutorMetadata) -> Result<()> {
-        let executor_data = ExecutorData {
-            executor_id: metadata.id.clone(),
-            total_task_slots: metadata.specification.task_slots,
-            available_task_slots: metadata.specification.task_slots,
-        };
+        let executor_data =
+            ExecutorData::new(metadata.id.clone(), metadata.specification);
 
         // Save the executor to state
         self.state
@@ -340,6 +390,7 @@ pub fn timestamp_millis() -> u64 {
 #[cfg(all(test, feature = "sled"))]
 mod test {
     use std::sync::Arc;
+    use std::time::Duration;
 
     use datafusion::arrow::datatypes::{DataType, Field, Schema};
     use datafusion::logical_expr::{col, sum, LogicalPlan};
@@ -347,11 +398,13 @@ mod test {
     use datafusion::test_util::scan_empty;
     use datafusion_proto::protobuf::LogicalPlanNode;
     use datafusion_proto::protobuf::PhysicalPlanNode;
+    use futures::StreamExt;
+    use tokio::time::timeout;
 
     use ballista_core::config::{
         BallistaConfig, TaskSchedulingPolicy, BALLISTA_DEFAULT_SHUFFLE_PARTITIONS,
     };
-    use ballista_core::error::Result;
+    use ballista_core::error::{BallistaError, Result};
 
     use crate::config::SchedulerConfig;
 
@@ -366,6 +419,7 @@ mod test {
     use ballista_core::serde::BallistaCodec;
 
     use crate::scheduler_server::{timestamp_millis, SchedulerServer};
+    use crate::state::execution_graph::JobProperties;
 
     use crate::test_utils::{
         assert_completed_event, assert_failed_event, assert_no_submitted_event,
@@ -408,7 +462,7 @@ mod test {
         // Submit job
         scheduler
             .state
-            .submit_job(job_id, "", ctx, &plan, 0)
+            .submit_job(job_id, "", JobProperties::default(), ctx, &plan, 0)
             .await
             .expect("submitting plan");
 
@@ -434,6 +488,8 @@ mod test {
                         num_batches: 1,
                         num_rows: 1,
                         num_bytes: 1,
+                        compression: String::default(),
+                        index_path: String::default(),
                     })
                 }
 
@@ -517,6 +573,68 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_watch_job() -> Result<()> {
+        let plan = test_plan();
+        let mut test = SchedulerTest::new(
+            SchedulerConfig::default()
+                .with_scheduler_policy(TaskSchedulingPolicy::PushStaged),
+            Arc::new(TestMetricsCollector::default()),
+            4,
+            1,
+            None,
+        )
+        .await?;
+
+        test.submit("job", "", &plan).await?;
+        let mut statuses = test
+            .watch_job("job")
+            .await?
+            .map(|result| result.map(|result| result.status.and_then(|s| s.status)));
+
+        // The job keeps running until the statuses of its tasks are processed
+        let wait_for_running = async {
+            while let Some(status) = statuses.next().await {
+                if let Some(job_status::Status::Running(_)) = status? {
+                    return Ok(());
+                }
+            }
+            Err(BallistaError::Internal(
+                "Job status stream ended".to_owned(),
+            ))
+        };
+        timeout(Duration::from_secs(10), wait_for_running)
+            .await
+            .expect("timed out waiting for the job to run")?;
+
+        let watcher = tokio::spawn(statuses.collect::<Vec<_>>());
+        loop {
+            test.tick().await?;
+            let status = test.job_status("job").await?.and_then(|s| s.status);
+            if let Some(job_status::Status::Successful(_)) = status {
+                break;
+            }
+        }
+
+        // The stream ends once the job has finished
+        let statuses = timeout(Duration::from_secs(10), watcher)
+            .await
+            .expect("timed out waiting for the job status stream to end")
+            .expect("watching job");
+        assert!(
+            matches!(
+                statuses.last(),
+                Some(Ok(Some(job_status::Status::Successful(_))))
+            ),
+            "Expected the last job status to be successful but it was {statuses:?}"
+        );
+        assert!(statuses[..statuses.len() - 1]
+            .iter()
+            .all(|status| matches!(status, Ok(Some(job_status::Status::Running(_))))));
+
+        Ok(())
+    }
+
     // Simulate a task failure and ensure the job status is updated correctly
     #[tokio::test]
     async fn test_job_failure() -> Result<()> {
@@ -666,13 +784,10 @@ mod test {
                     host: "localhost1".to_string(),
                     port: 8080,
                     grpc_port: 9090,
-                    specification: ExecutorSpecification { task_slots },
-                },
-                ExecutorData {
-                    executor_id: "executor-1".to_owned(),
-                    total_task_slots: task_slots,
-                    available_task_slots: task_slots,
+                    specification: ExecutorSpecification::new(task_slots),
+                    labels: Default::default(),
                 },
+                ExecutorData::new("executor-1", ExecutorSpecification::new(task_slots)),
             ),
             (
                 ExecutorMetadata {
@@ -680,15 +795,15 @@ mod test {
                     host: "localhost2".to_string(),
                     port: 8080,
                     grpc_port: 9090,
-                    specification: ExecutorSpecification {
-                        task_slots: num_partitions as u32 - task_slots,
-                    },
-                },
-                ExecutorData {
-                    executor_id: "executor-2".to_owned(),
-                    total_task_slots: num_partitions as u32 - task_slots,
-                    available_task_slots: num_partitions as u32 - task_slots,
+                    specification: ExecutorSpecification::new(
+                        num_partitions as u32 - task_slots,
+                    ),
+                    labels: Default::default(),
                 },
+                ExecutorData::new(
+                    "executor-2",
+                    ExecutorSpecification::new(num_partitions as u32 - task_slots),
+                ),
             ),
         ]
     }
@@ -34,6 +34,7 @@ use tokio::time::Instant;
 
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 
+use crate::state::admission_controller::PendingJob;
 use crate::state::SchedulerState;
 
 pub(crate) struct QueryStageScheduler<
@@ -61,6 +62,71 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> QueryStageSchedul
     pub(crate) fn metrics_collector(&self) -> &dyn SchedulerMetricsCollector {
         self.metrics_collector.as_ref()
     }
+
+    /// Plan the queued jobs which the admission controller allows to start running
+    fn start_jobs(&self, event_sender: &EventSender<QueryStageSchedulerEvent>) {
+        for job in self.state.admission_controller.start_jobs() {
+            let PendingJob {
+                job_id,
+                job_name,
+                properties,
+                session_ctx,
+                plan,
+                queued_at,
+            } = job;
+            let timeout_seconds = properties.timeout_seconds;
+            let state = self.state.clone();
+            let event_sender = event_sender.clone();
+            tokio::spawn(async move {
+                let event = if let Err(e) = state
+                    .submit_job(
+                        &job_id,
+                        &job_name,
+                        properties,
+                        session_ctx,
+                        &plan,
+                        queued_at,
+                    )
+                    .await
+                {
+                    let fail_message = format!("Error planning job {job_id}: {e:?}");
+                    error!("{}", &fail_message);
+                    QueryStageSchedulerEvent::JobPlanningFailed {
+                        job_id,
+                        fail_message,
+                        queued_at,
+                        failed_at: timestamp_millis(),
+                    }
+                } else {
+                    QueryStageSchedulerEvent::JobSubmitted {
+                        job_id: job_id.clone(),
+                        queued_at,
+                        submitted_at: timestamp_millis(),
+                    }
+                };
+                let submitted =
+                    matches!(event, QueryStageSchedulerEvent::JobSubmitted { .. });
+                if let Err(e) = event_sender.post_event(event).await {
+                    error!("Fail to send event due to {}", e);
+                }
+
+                if submitted && timeout_seconds > 0 {
+                    // The timeout starts when the job is queued
+                    let deadline = queued_at + timeout_seconds * 1000;
+                    let remaining = deadline.saturating_sub(timestamp_millis());
+                    tokio::time::sleep(Duration::from_millis(remaining)).await;
+                    let event = QueryStageSchedulerEvent::JobTimedOut {
+                        job_id,
+                        timeout_seconds,
+                        queued_at,
+                    };
+                    if let Err(e) = event_sender.post_event(event).await {
+                        error!("Fail to send event due to {}", e);
+                    }
+                }
+            });
+        }
+    }
 }
 
 #[async_trait]
@@ -90,6 +156,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
             QueryStageSchedulerEvent::JobQueued {
                 job_id,
                 job_name,
+                properties,
                 session_ctx,
                 plan,
                 queued_at,
@@ -102,34 +169,24 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                     .queue_job(&job_id, &job_name, queued_at)
                 {
                     error!("Fail to queue job {} due to {:?}", job_id, e);
+                    self.state.admission_controller.release(&job_id);
                     return Ok(());
                 }
 
-                let state = self.state.clone();
-                tokio::spawn(async move {
-                    let event = if let Err(e) = state
-                        .submit_job(&job_id, &job_name, session_ctx, &plan, queued_at)
-                        .await
-                    {
-                        let fail_message = format!("Error planning job {job_id}: {e:?}");
-                        error!("{}", &fail_message);
-                        QueryStageSchedulerEvent::JobPlanningFailed {
-                            job_id,
-                            fail_message,
-                            queued_at,
-                            failed_at: timestamp_millis(),
-                        }
-                    } else {
-                        QueryStageSchedulerEvent::JobSubmitted {
-                            job_id,
-                            queued_at,
-                            submitted_at: timestamp_millis(),
-                        }
-                    };
-                    if let Err(e) = event_sender.post_event(event).await {
-                        error!("Fail to send event due to {}", e);
-                    }
+                self.state.admission_controller.enqueue(PendingJob {
+                    job_id: job_id.clone(),
+                    job_name,
+                    properties,
+                    session_ctx,
+                    plan,
+                    queued_at,
                 });
+                self.start_jobs(&event_sender);
+                if let Some(position) =
+                    self.state.admission_controller.queue_position(&job_id)
+                {
+                    info!("Job {} waiting to run at position {}", job_id, position);
+                }
             }
             QueryStageSchedulerEvent::JobSubmitted {
                 job_id,
@@ -168,6 +225,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                         job_id, e
                     );
                 }
+                self.state.result_cache.remove_job(&job_id);
+                self.state.admission_controller.release(&job_id);
+                self.start_jobs(&event_sender);
             }
             QueryStageSchedulerEvent::JobFinished {
                 job_id,
@@ -184,6 +244,11 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                         job_id, e
                     );
                 }
+                if let Err(e) = self.state.cache_job_result(&job_id).await {
+                    warn!("Fail to cache the results of job {} due to {:?}", job_id, e);
+                }
+                self.state.admission_controller.release(&job_id);
+                self.start_jobs(&event_sender);
                 self.state.clean_up_successful_job(job_id);
             }
             QueryStageSchedulerEvent::JobRunningFailed {
@@ -218,8 +283,54 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                         );
                     }
                 }
+                self.state.admission_controller.release(&job_id);
+                self.start_jobs(&event_sender);
                 self.state.clean_up_failed_job(job_id);
             }
+            QueryStageSchedulerEvent::JobTimedOut {
+                job_id,
+                timeout_seconds,
+                queued_at,
+            } => {
+                match self
+                    .state
+                    .task_manager
+                    .time_out_job(&job_id, timeout_seconds)
+                    .await
+                {
+                    Ok(Some(running_tasks)) => {
+                        self.metrics_collector.record_failed(
+                            &job_id,
+                            queued_at,
+                            timestamp_millis(),
+                        );
+
+                        error!(
+                            "Job {} timed out after {} seconds",
+                            job_id, timeout_seconds
+                        );
+                        if !running_tasks.is_empty() {
+                            event_sender
+                                .post_event(QueryStageSchedulerEvent::CancelTasks(
+                                    running_tasks,
+                                ))
+                                .await?;
+                        }
+                        self.state.admission_controller.release(&job_id);
+                        self.start_jobs(&event_sender);
+                        self.state.clean_up_failed_job(job_id);
+                    }
+                    Ok(None) => {
+                        debug!("Job {} finished before timing out", job_id);
+                    }
+                    Err(e) => {
+                        error!(
+                            "Fail to invoke time_out_job for job {} due to {:?}",
+                            job_id, e
+                        );
+                    }
+                }
+            }
             QueryStageSchedulerEvent::JobUpdated(job_id) => {
                 info!("Job {} Updated", job_id);
                 if let Err(e) = self.state.task_manager.update_job(&job_id).await {
@@ -233,20 +344,36 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                 self.metrics_collector.record_cancelled(&job_id);
 
                 info!("Job {} Cancelled", job_id);
-                match self.state.task_manager.cancel_job(&job_id).await {
-                    Ok((running_tasks, _pending_tasks)) => {
-                        event_sender
-                            .post_event(QueryStageSchedulerEvent::CancelTasks(
-                                running_tasks,
-                            ))
-                            .await?;
-                    }
-                    Err(e) => {
+                if self.state.admission_controller.release(&job_id).is_some() {
+                    // the job was still waiting to run, so it has no tasks to cancel
+                    if let Err(e) = self
+                        .state
+                        .task_manager
+                        .fail_unscheduled_job(&job_id, "Cancelled".to_owned())
+                        .await
+                    {
                         error!(
-                            "Fail to invoke cancel_job for job {} due to {:?}",
+                            "Fail to invoke fail_unscheduled_job for job {} due to {:?}",
                             job_id, e
                         );
                     }
+                } else {
+                    match self.state.task_manager.cancel_job(&job_id).await {
+                        Ok((running_tasks, _pending_tasks)) => {
+                            event_sender
+                                .post_event(QueryStageSchedulerEvent::CancelTasks(
+                                    running_tasks,
+                                ))
+                                .await?;
+                        }
+                        Err(e) => {
+                            error!(
+                                "Fail to invoke cancel_job for job {} due to {:?}",
+                                job_id, e
+                            );
+                        }
+                    }
+                    self.start_jobs(&event_sender);
                 }
                 self.state.clean_up_failed_job(job_id);
             }
@@ -256,12 +383,12 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                     tasks_status
                 );
 
-                let num_status = tasks_status.len();
                 if self.state.config.is_push_staged_scheduling() {
-                    self.state
-                        .executor_manager
-                        .unbind_tasks(vec![(executor_id.clone(), num_status as u32)])
-                        .await?;
+                    let tasks = tasks_status
+                        .iter()
+                        .map(|status| (status.job_id.clone(), status.task_id as usize))
+                        .collect();
+                    self.state.unbind_tasks(&executor_id, tasks).await?;
                 }
                 match self
                     .state
@@ -292,6 +419,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                 self.state.revive_offers(event_sender).await?;
             }
             QueryStageSchedulerEvent::ExecutorLost(executor_id, _) => {
+                self.metrics_collector.remove_executor_metrics(&executor_id);
+                self.state.result_cache.remove_executor(&executor_id);
                 match self.state.task_manager.executor_lost(&executor_id).await {
                     Ok(tasks) => {
                         if !tasks.is_empty() {
@@ -324,6 +453,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan>
                 }
             }
             QueryStageSchedulerEvent::JobDataClean(job_id) => {
+                self.state.result_cache.remove_job(&job_id);
                 self.state.executor_manager.clean_up_job_data(job_id);
             }
         }
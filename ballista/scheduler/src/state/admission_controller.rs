@@ -0,0 +1,224 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::SessionContext;
+use parking_lot::Mutex;
+
+use crate::config::SchedulerConfig;
+use crate::state::execution_graph::JobProperties;
+
+/// A queued job which is planned once it is started by the [AdmissionController]
+pub(crate) struct PendingJob {
+    pub job_id: String,
+    pub job_name: String,
+    pub properties: JobProperties,
+    pub session_ctx: Arc<SessionContext>,
+    pub plan: Box<LogicalPlan>,
+    pub queued_at: u64,
+}
+
+#[derive(Default)]
+struct AdmissionState {
+    /// Session of every admitted job which hasn't finished yet
+    sessions: HashMap<String, String>,
+    /// Jobs which are being planned or running
+    running: HashSet<String>,
+    /// Jobs waiting for running jobs to finish, in the order they were queued
+    waiting: VecDeque<PendingJob>,
+}
+
+/// Limits the number of jobs running at the same time and the number of jobs
+/// accepted by the scheduler, overall and per session.
+#[derive(Clone)]
+pub struct AdmissionController {
+    max_running_jobs: usize,
+    max_pending_jobs: usize,
+    max_jobs_per_session: usize,
+    state: Arc<Mutex<AdmissionState>>,
+}
+
+impl AdmissionController {
+    pub fn new(config: &SchedulerConfig) -> Self {
+        Self {
+            max_running_jobs: config.max_running_jobs,
+            max_pending_jobs: config.max_pending_jobs,
+            max_jobs_per_session: config.max_jobs_per_session,
+            state: Arc::new(Mutex::new(AdmissionState::default())),
+        }
+    }
+
+    /// Accept a new job of a session, or return the reason why it is rejected
+    pub fn admit(
+        &self,
+        job_id: &str,
+        session_id: &str,
+    ) -> std::result::Result<(), String> {
+        let mut state = self.state.lock();
+
+        let unfinished_jobs = state.sessions.len();
+        if self.max_running_jobs > 0
+            && self.max_pending_jobs > 0
+            && unfinished_jobs >= self.max_running_jobs + self.max_pending_jobs
+        {
+            return Err(format!(
+                "Job {job_id} rejected as {} jobs are already waiting to run",
+                unfinished_jobs - self.max_running_jobs
+            ));
+        }
+
+        if self.max_jobs_per_session > 0 {
+            let session_jobs = state
+                .sessions
+                .values()
+                .filter(|session| session.as_str() == session_id)
+                .count();
+            if session_jobs >= self.max_jobs_per_session {
+                return Err(format!(
+                    "Job {job_id} rejected as session {session_id} already has {session_jobs} unfinished jobs"
+                ));
+            }
+        }
+
+        state
+            .sessions
+            .insert(job_id.to_owned(), session_id.to_owned());
+        Ok(())
+    }
+
+    /// Add a queued job to the jobs waiting to run
+    pub(crate) fn enqueue(&self, job: PendingJob) {
+        let mut state = self.state.lock();
+        // jobs may be queued without being admitted, which only count towards the
+        // limits of the scheduler
+        state.sessions.entry(job.job_id.clone()).or_default();
+        state.waiting.push_back(job);
+    }
+
+    /// Take the waiting jobs which can start running, in the order they were queued
+    pub(crate) fn start_jobs(&self) -> Vec<PendingJob> {
+        let mut state = self.state.lock();
+        let mut jobs = vec![];
+        while self.max_running_jobs == 0 || state.running.len() < self.max_running_jobs {
+            match state.waiting.pop_front() {
+                Some(job) => {
+                    state.running.insert(job.job_id.clone());
+                    jobs.push(job);
+                }
+                None => break,
+            }
+        }
+        jobs
+    }
+
+    /// Release the place of a finished job. The job is returned if it was still waiting
+    /// to run.
+    pub(crate) fn release(&self, job_id: &str) -> Option<PendingJob> {
+        let mut state = self.state.lock();
+        state.sessions.remove(job_id);
+        state.running.remove(job_id);
+        let position = state.waiting.iter().position(|job| job.job_id == job_id)?;
+        state.waiting.remove(position)
+    }
+
+    /// Position of a job among the jobs waiting to run, starting from 1
+    pub fn queue_position(&self, job_id: &str) -> Option<usize> {
+        let state = self.state.lock();
+        state
+            .waiting
+            .iter()
+            .position(|job| job.job_id == job_id)
+            .map(|position| position + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::logical_expr::LogicalPlanBuilder;
+
+    fn pending_job(job_id: &str) -> PendingJob {
+        PendingJob {
+            job_id: job_id.to_owned(),
+            job_name: String::default(),
+            properties: JobProperties::default(),
+            session_ctx: Arc::new(SessionContext::new()),
+            plan: Box::new(LogicalPlanBuilder::empty(false).build().unwrap()),
+            queued_at: 0,
+        }
+    }
+
+    fn job_ids(jobs: Vec<PendingJob>) -> Vec<String> {
+        jobs.into_iter().map(|job| job.job_id).collect()
+    }
+
+    #[test]
+    fn test_max_running_jobs() {
+        let controller = AdmissionController::new(
+            &SchedulerConfig::default().with_max_running_jobs(2),
+        );
+        for job_id in ["job-1", "job-2", "job-3"] {
+            controller.admit(job_id, "session").unwrap();
+            controller.enqueue(pending_job(job_id));
+        }
+
+        assert_eq!(vec!["job-1", "job-2"], job_ids(controller.start_jobs()));
+        assert_eq!(Some(1), controller.queue_position("job-3"));
+        assert_eq!(None, controller.queue_position("job-1"));
+        assert!(controller.start_jobs().is_empty());
+
+        assert!(controller.release("job-1").is_none());
+        assert_eq!(vec!["job-3"], job_ids(controller.start_jobs()));
+        assert_eq!(None, controller.queue_position("job-3"));
+    }
+
+    #[test]
+    fn test_max_pending_jobs() {
+        let controller = AdmissionController::new(
+            &SchedulerConfig::default()
+                .with_max_running_jobs(1)
+                .with_max_pending_jobs(1),
+        );
+        controller.admit("job-1", "session").unwrap();
+        controller.admit("job-2", "session").unwrap();
+        assert!(controller.admit("job-3", "session").is_err());
+
+        // a waiting job which is cancelled releases its place
+        controller.enqueue(pending_job("job-1"));
+        controller.enqueue(pending_job("job-2"));
+        assert_eq!(vec!["job-1"], job_ids(controller.start_jobs()));
+        assert_eq!(Some(1), controller.queue_position("job-2"));
+        assert!(controller.release("job-2").is_some());
+        controller.admit("job-3", "session").unwrap();
+    }
+
+    #[test]
+    fn test_max_jobs_per_session() {
+        let controller = AdmissionController::new(
+            &SchedulerConfig::default().with_max_jobs_per_session(1),
+        );
+        controller.admit("job-1", "session-1").unwrap();
+        assert!(controller.admit("job-2", "session-1").is_err());
+        controller.admit("job-3", "session-2").unwrap();
+
+        controller.release("job-1");
+        controller.admit("job-2", "session-1").unwrap();
+    }
+}
@@ -23,18 +23,22 @@ use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use datafusion::physical_plan::display::DisplayableExecutionPlan;
-use datafusion::physical_plan::{accept, ExecutionPlan, ExecutionPlanVisitor};
+use datafusion::physical_plan::{
+    accept, ExecutionPlan, ExecutionPlanVisitor, Partitioning,
+};
 use datafusion::prelude::SessionContext;
 use datafusion_proto::logical_plan::AsLogicalPlan;
 use log::{error, info, warn};
+use tracing::{info_span, Span};
 
+use ballista_core::config::{BallistaConfig, ShuffleCompression};
 use ballista_core::error::{BallistaError, Result};
 use ballista_core::execution_plans::{ShuffleWriterExec, UnresolvedShuffleExec};
 use ballista_core::serde::protobuf::failed_task::FailedReason;
 use ballista_core::serde::protobuf::job_status::Status;
 use ballista_core::serde::protobuf::{
-    self, execution_graph_stage::StageType, FailedTask, JobStatus, ResultLost,
-    RunningJob, SuccessfulJob, TaskStatus,
+    self, execution_graph_stage::StageType, FailedTask, JobProgress, JobStatus,
+    ResultLost, RunningJob, SuccessfulJob, TaskStatus,
 };
 use ballista_core::serde::protobuf::{job_status, FailedJob, ShuffleWritePartition};
 use ballista_core::serde::protobuf::{task_status, RunningTask};
@@ -42,12 +46,17 @@ use ballista_core::serde::scheduler::{
     ExecutorMetadata, PartitionId, PartitionLocation, PartitionStats,
 };
 use ballista_core::serde::BallistaCodec;
+use ballista_core::telemetry;
+use ballista_core::utils::is_object_store_location;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 
+use crate::cluster::BoundTask;
+use crate::config::SpeculationConfig;
 use crate::display::print_stage_metrics;
 use crate::planner::DistributedPlanner;
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 use crate::scheduler_server::timestamp_millis;
+use crate::state::execution_graph::adaptive::AdaptivePlanner;
 use crate::state::execution_graph::execution_stage::RunningStage;
 pub(crate) use crate::state::execution_graph::execution_stage::{
     ExecutionStage, FailedStage, ResolvedStage, StageOutput, SuccessfulStage, TaskInfo,
@@ -55,6 +64,7 @@ pub(crate) use crate::state::execution_graph::execution_stage::{
 };
 use crate::state::task_manager::UpdatedStages;
 
+mod adaptive;
 mod execution_stage;
 
 /// Represents the DAG for a distributed query plan.
@@ -108,6 +118,8 @@ pub struct ExecutionGraph {
     job_id: String,
     /// Job name, can be empty string
     job_name: String,
+    /// Properties set by the submitter of this job
+    properties: JobProperties,
     /// Session ID for this job
     session_id: String,
     /// Status of this job
@@ -129,6 +141,133 @@ pub struct ExecutionGraph {
     /// Failed stage attempts, record the failed stage attempts to limit the retry times.
     /// Map from Stage ID -> Set<Stage_ATTPMPT_NUM>
     failed_stage_attempts: HashMap<usize, HashSet<usize>>,
+    /// Span of the job, which is closed once the job finishes. Graphs decoded from the
+    /// persisted state have no span.
+    span: Span,
+}
+
+/// Properties of a job set by its submitter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobProperties {
+    /// The authenticated user who submitted the job, if any
+    pub user: Option<String>,
+    /// Priority of the job, jobs with a higher priority are scheduled first
+    pub priority: u32,
+    /// The pool of the job, executor slots are shared fairly across pools
+    pub pool: String,
+    /// If provided, stages are re-planned with the statistics of their shuffle inputs
+    /// when they are resolved
+    pub adaptive: Option<AdaptiveConfig>,
+    /// Codec used to compress the shuffle files of the job
+    pub shuffle_compression: ShuffleCompression,
+    /// Whether each task writes one data file and index file for all of its shuffle
+    /// partitions
+    pub shuffle_sort_based: bool,
+    /// Whether query results are sorted in parallel by range partitioning them on the
+    /// sort keys
+    pub repartition_sorts: bool,
+    /// Number of rows sampled from each input partition of a range partitioned sort
+    pub range_sample_size: usize,
+    /// Memory in bytes reserved on an executor by each task, which the task is limited
+    /// to. Zero means tasks reserve no memory.
+    pub task_memory: u64,
+    /// Number of CPUs reserved on an executor by each task. Zero means tasks reserve no
+    /// CPUs.
+    pub task_cpus: u32,
+    /// Labels which the executors running the tasks of the job must have
+    pub executor_selector: HashMap<String, String>,
+    /// Time in seconds after which the running job is aborted. Zero means no timeout.
+    pub timeout_seconds: u64,
+    /// Time in seconds after which a task of the job fails on its executor. Zero means
+    /// no timeout.
+    pub task_timeout_seconds: u64,
+    /// Whether the results of the job are reused by later jobs running the same plan
+    pub result_cache: bool,
+    /// Trace context which the span of the job is a child of, replaced by the trace
+    /// context of the span of the job once the job is planned
+    pub trace_context: HashMap<String, String>,
+}
+
+impl Default for JobProperties {
+    fn default() -> Self {
+        Self {
+            user: None,
+            priority: 0,
+            pool: "default".to_string(),
+            adaptive: None,
+            shuffle_compression: ShuffleCompression::None,
+            shuffle_sort_based: false,
+            repartition_sorts: false,
+            range_sample_size: 100,
+            task_memory: 0,
+            task_cpus: 0,
+            executor_selector: HashMap::new(),
+            timeout_seconds: 0,
+            task_timeout_seconds: 0,
+            result_cache: false,
+            trace_context: HashMap::new(),
+        }
+    }
+}
+
+impl JobProperties {
+    /// Properties of a job submitted with the given session config
+    pub fn from_config(config: &BallistaConfig) -> Self {
+        Self {
+            user: None,
+            priority: config.job_priority(),
+            pool: config.job_pool(),
+            adaptive: AdaptiveConfig::from_config(config),
+            shuffle_compression: config.shuffle_compression(),
+            shuffle_sort_based: config.shuffle_sort_based(),
+            repartition_sorts: config.repartition_sorts(),
+            range_sample_size: config.repartition_sorts_sample_size(),
+            task_memory: config.task_memory(),
+            task_cpus: config.task_cpus(),
+            executor_selector: config.executor_selector(),
+            timeout_seconds: config.job_timeout_seconds(),
+            task_timeout_seconds: config.task_timeout_seconds(),
+            result_cache: config.job_result_cache(),
+            trace_context: HashMap::new(),
+        }
+    }
+
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn with_trace_context(mut self, trace_context: HashMap<String, String>) -> Self {
+        self.trace_context = trace_context;
+        self
+    }
+}
+
+/// Settings of the adaptive re-planning of stages
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdaptiveConfig {
+    /// Target size of the shuffle partitions read by a task
+    pub target_partition_bytes: u64,
+    /// A shuffle partition is skewed if it is this many times larger than the median one
+    pub skew_factor: u64,
+    /// Shuffle partitions smaller than this are never considered skewed
+    pub skew_threshold_bytes: u64,
+    /// Build sides of partitioned hash joins smaller than this are collected by every
+    /// probe task instead
+    pub broadcast_threshold_bytes: u64,
+}
+
+impl AdaptiveConfig {
+    /// The adaptive settings of the given session config, if adaptive execution is enabled
+    pub fn from_config(config: &BallistaConfig) -> Option<Self> {
+        config.adaptive_enabled().then(|| Self {
+            target_partition_bytes: config.adaptive_target_partition_bytes() as u64,
+            skew_factor: config.adaptive_skew_factor() as u64,
+            skew_threshold_bytes: config.adaptive_skew_threshold_bytes() as u64,
+            broadcast_threshold_bytes: config.hash_join_single_partition_threshold()
+                as u64,
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -148,13 +287,22 @@ impl ExecutionGraph {
         session_id: &str,
         plan: Arc<dyn ExecutionPlan>,
         queued_at: u64,
+        properties: JobProperties,
     ) -> Result<Self> {
-        let mut planner = DistributedPlanner::new();
-
-        let output_partitions = plan.output_partitioning().partition_count();
+        let mut planner = DistributedPlanner::new()
+            .with_shuffle_compression(properties.shuffle_compression)
+            .with_sort_based_shuffle(properties.shuffle_sort_based)
+            .with_repartition_sorts(properties.repartition_sorts)
+            .with_range_sample_size(properties.range_sample_size);
 
         let shuffle_stages = planner.plan_query_stages(job_id, plan)?;
 
+        // the planner may run the final stage with more partitions than the plan has
+        let output_partitions = shuffle_stages
+            .last()
+            .map(|stage| stage.output_partitioning().partition_count())
+            .unwrap_or_default();
+
         let builder = ExecutionStageBuilder::new();
         let stages = builder.build(shuffle_stages)?;
 
@@ -164,6 +312,7 @@ impl ExecutionGraph {
             scheduler_id: Some(scheduler_id.to_string()),
             job_id: job_id.to_string(),
             job_name: job_name.to_string(),
+            properties,
             session_id: session_id.to_string(),
             status: JobStatus {
                 job_id: job_id.to_string(),
@@ -172,6 +321,7 @@ impl ExecutionGraph {
                     queued_at,
                     started_at,
                     scheduler: scheduler_id.to_string(),
+                    progress: None,
                 })),
             },
             queued_at,
@@ -182,6 +332,8 @@ impl ExecutionGraph {
             output_locations: vec![],
             task_id_gen: 0,
             failed_stage_attempts: HashMap::new(),
+            // the graph is created within the span of the job
+            span: Span::current(),
         })
     }
 
@@ -193,6 +345,19 @@ impl ExecutionGraph {
         self.job_name.as_str()
     }
 
+    pub fn with_properties(mut self, properties: JobProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn properties(&self) -> &JobProperties {
+        &self.properties
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        self.properties.user.as_deref()
+    }
+
     pub fn session_id(&self) -> &str {
         self.session_id.as_str()
     }
@@ -201,6 +366,10 @@ impl ExecutionGraph {
         &self.status
     }
 
+    pub fn queued_at(&self) -> u64 {
+        self.queued_at
+    }
+
     pub fn start_time(&self) -> u64 {
         self.start_time
     }
@@ -223,6 +392,15 @@ impl ExecutionGraph {
         &self.stages
     }
 
+    /// Whether the job is still running after its timeout, which started when the job
+    /// was queued, at the given time in milliseconds
+    pub fn is_timed_out(&self, now: u64) -> bool {
+        let timeout_seconds = self.properties.timeout_seconds;
+        timeout_seconds > 0
+            && matches!(self.status.status, Some(Status::Running(_)))
+            && now.saturating_sub(self.queued_at) >= timeout_seconds * 1000
+    }
+
     /// An ExecutionGraph is successful if all its stages are successful
     pub fn is_successful(&self) -> bool {
         self.stages
@@ -230,6 +408,32 @@ impl ExecutionGraph {
             .all(|s| matches!(s, ExecutionStage::Successful(_)))
     }
 
+    /// The progress of the job, estimated from the completed tasks of all its stages
+    pub fn progress(&self) -> JobProgress {
+        let mut stages = self
+            .stages
+            .values()
+            .map(ExecutionStage::progress)
+            .collect::<Vec<_>>();
+        stages.sort_by_key(|stage| stage.stage_id);
+
+        let completed_tasks = stages.iter().map(|stage| stage.completed_tasks).sum();
+        let total_tasks = stages.iter().map(|stage| stage.total_tasks).sum();
+        let percent_complete = if total_tasks > 0 {
+            completed_tasks as f64 * 100.0 / total_tasks as f64
+        } else {
+            0.0
+        };
+        JobProgress {
+            completed_tasks,
+            total_tasks,
+            output_rows: stages.iter().map(|stage| stage.output_rows).sum(),
+            output_bytes: stages.iter().map(|stage| stage.output_bytes).sum(),
+            percent_complete,
+            stages,
+        }
+    }
+
     pub fn is_complete(&self) -> bool {
         self.stages
             .values()
@@ -244,7 +448,10 @@ impl ExecutionGraph {
             .values()
             .filter_map(|stage| {
                 if let ExecutionStage::Resolved(resolved_stage) = stage {
-                    Some(resolved_stage.to_running())
+                    Some(resolved_stage.to_running(self.stage_span(
+                        resolved_stage.stage_id,
+                        resolved_stage.stage_attempt_num,
+                    )))
                 } else {
                     None
                 }
@@ -304,6 +511,8 @@ impl ExecutionGraph {
         let mut resubmit_successful_stages: HashMap<usize, HashSet<usize>> =
             HashMap::new();
         let mut reset_running_stages: HashMap<usize, HashSet<usize>> = HashMap::new();
+        // The losing attempts of the races between straggler tasks and their copies
+        let mut speculative_losers = vec![];
 
         for (stage_id, stage_task_statuses) in job_task_statuses {
             if let Some(stage) = self.stages.get_mut(&stage_id) {
@@ -328,6 +537,20 @@ impl ExecutionGraph {
                         );
                         let operator_metrics = task_status.metrics.clone();
 
+                        if let Some((task_id, executor_id)) = running_stage
+                            .settle_speculative_task(partition_id, &task_status)
+                        {
+                            info!("Cancel TID {} on executor {} as another attempt of {}/{}/{} finished first",
+                                task_id, executor_id, job_id, stage_id, partition_id);
+                            speculative_losers.push(RunningTaskInfo {
+                                task_id,
+                                job_id: job_id.clone(),
+                                stage_id,
+                                partition_id,
+                                executor_id,
+                            });
+                        }
+
                         if !running_stage
                             .update_task_info(partition_id, task_status.clone())
                         {
@@ -643,7 +866,7 @@ impl ExecutionGraph {
             }
         }
 
-        self.processing_stages_update(UpdatedStages {
+        let mut events = self.processing_stages_update(UpdatedStages {
             resolved_stages,
             successful_stages,
             failed_stages,
@@ -652,7 +875,11 @@ impl ExecutionGraph {
                 .keys()
                 .cloned()
                 .collect(),
-        })
+        })?;
+        if !speculative_losers.is_empty() {
+            events.push(QueryStageSchedulerEvent::CancelTasks(speculative_losers));
+        }
+        Ok(events)
     }
 
     /// Processing stage status update after task status changing
@@ -824,6 +1051,79 @@ impl ExecutionGraph {
             .sum()
     }
 
+    /// Mark the straggler tasks of the running stages for speculative execution.
+    /// Returns the number of newly marked tasks
+    pub fn speculate_tasks(&mut self, config: &SpeculationConfig) -> usize {
+        if !matches!(self.status.status, Some(job_status::Status::Running(_))) {
+            return 0;
+        }
+        let now = timestamp_millis() as u128;
+        let mut speculated = 0;
+        for stage in self.stages.values_mut() {
+            if let ExecutionStage::Running(stage) = stage {
+                let tasks = stage.speculate(config, now);
+                if tasks > 0 {
+                    info!(
+                        "Speculating {} straggler tasks of stage {}/{}",
+                        tasks, self.job_id, stage.stage_id
+                    );
+                }
+                speculated += tasks;
+            }
+        }
+        speculated
+    }
+
+    /// Bind the speculative copies which are waiting for a slot. `next_slot` is given
+    /// the executor running the original task and returns another executor with a free
+    /// slot, if any.
+    pub(crate) fn bind_speculative_tasks(
+        &mut self,
+        mut next_slot: impl FnMut(&str) -> Option<String>,
+    ) -> Vec<BoundTask> {
+        let mut bound_tasks = vec![];
+        if !matches!(self.status.status, Some(job_status::Status::Running(_))) {
+            return bound_tasks;
+        }
+        for stage in self.stages.values_mut() {
+            if let ExecutionStage::Running(stage) = stage {
+                let trace_context = stage.trace_context();
+                for (partition_id, copy) in stage.speculative_tasks.iter_mut() {
+                    let original_executor = match &stage.task_infos[*partition_id] {
+                        Some(TaskInfo {
+                            task_status:
+                                task_status::Status::Running(RunningTask { executor_id }),
+                            ..
+                        }) if copy.is_none() => executor_id,
+                        _ => continue,
+                    };
+                    if let Some(executor_id) = next_slot(original_executor) {
+                        let task_id = self.task_id_gen;
+                        self.task_id_gen += 1;
+                        *copy = Some(create_task_info(executor_id.clone(), task_id));
+
+                        let task_desc = TaskDescription {
+                            session_id: self.session_id.clone(),
+                            partition: PartitionId {
+                                job_id: self.job_id.clone(),
+                                stage_id: stage.stage_id,
+                                partition_id: *partition_id,
+                            },
+                            stage_attempt_num: stage.stage_attempt_num,
+                            task_id,
+                            task_attempt: stage.task_failure_numbers[*partition_id],
+                            data_cache: false,
+                            plan: stage.plan.clone(),
+                            trace_context: trace_context.clone(),
+                        };
+                        bound_tasks.push((executor_id, task_desc));
+                    }
+                }
+            }
+        }
+        bound_tasks
+    }
+
     /// Get next task that can be assigned to the given executor.
     /// This method should only be called when the resulting task is immediately
     /// being launched as the status will be set to Running and it will not be
@@ -913,6 +1213,7 @@ impl ExecutionGraph {
                     task_attempt,
                     data_cache: false,
                     plan: stage.plan.clone(),
+                    trace_context: stage.trace_context(),
                 })
             } else {
                 Err(BallistaError::General(format!("Stage {stage_id} is not a running stage")))
@@ -1062,13 +1363,17 @@ impl ExecutionGraph {
 
                 // For each stage input, check whether there are input locations match that executor
                 // and calculate the resubmit input stages if the input stages are successful.
+                // Input locations in an object store outlive the executor, so they are kept.
                 let mut rollback_stage = false;
                 stage_inputs.iter_mut().for_each(|(input_stage_id, stage_output)| {
                     let mut match_found = false;
                     stage_output.partition_locations.iter_mut().for_each(
                         |(_partition, locs)| {
                             let before_len = locs.len();
-                            locs.retain(|loc| loc.executor_meta.id != executor_id);
+                            locs.retain(|loc| {
+                                loc.executor_meta.id != executor_id
+                                    || is_object_store_location(&loc.path)
+                            });
                             if locs.len() < before_len {
                                 match_found = true;
                             }
@@ -1152,8 +1457,22 @@ impl ExecutionGraph {
     /// Convert unresolved stage to be resolved
     pub fn resolve_stage(&mut self, stage_id: usize) -> Result<bool> {
         if let Some(ExecutionStage::UnResolved(stage)) = self.stages.remove(&stage_id) {
+            let adaptive_planner = self.properties.adaptive.clone().map(|config| {
+                let hash_inputs = stage
+                    .inputs
+                    .keys()
+                    .copied()
+                    .filter(|input| self.is_hash_partitioned(*input))
+                    .collect();
+                AdaptivePlanner::new(config, hash_inputs)
+            });
+            let resolved_stage = stage.to_resolved(adaptive_planner.as_ref())?;
+            // The final stage may have been re-planned into a different number of partitions
+            if resolved_stage.output_links.is_empty() {
+                self.output_partitions = resolved_stage.partitions;
+            }
             self.stages
-                .insert(stage_id, ExecutionStage::Resolved(stage.to_resolved()?));
+                .insert(stage_id, ExecutionStage::Resolved(resolved_stage));
             Ok(true)
         } else {
             warn!(
@@ -1165,6 +1484,21 @@ impl ExecutionGraph {
         }
     }
 
+    /// Whether the shuffle output of the given stage is hash partitioned
+    fn is_hash_partitioned(&self, stage_id: usize) -> bool {
+        let plan = match self.stages.get(&stage_id) {
+            Some(ExecutionStage::Successful(stage)) => &stage.plan,
+            Some(ExecutionStage::Running(stage)) => &stage.plan,
+            _ => return false,
+        };
+        matches!(
+            plan.as_any()
+                .downcast_ref::<ShuffleWriterExec>()
+                .and_then(|writer| writer.shuffle_output_partitioning()),
+            Some(Partitioning::Hash(_, _))
+        )
+    }
+
     /// Convert running stage to be successful
     pub fn succeed_stage(&mut self, stage_id: usize) -> bool {
         if let Some(ExecutionStage::Running(stage)) = self.stages.remove(&stage_id) {
@@ -1253,8 +1587,9 @@ impl ExecutionGraph {
     /// Convert successful stage to be running
     pub fn rerun_successful_stage(&mut self, stage_id: usize) -> bool {
         if let Some(ExecutionStage::Successful(stage)) = self.stages.remove(&stage_id) {
+            let span = self.stage_span(stage_id, stage.stage_attempt_num + 1);
             self.stages
-                .insert(stage_id, ExecutionStage::Running(stage.to_running()));
+                .insert(stage_id, ExecutionStage::Running(stage.to_running(span)));
             true
         } else {
             warn!(
@@ -1266,8 +1601,33 @@ impl ExecutionGraph {
         }
     }
 
+    /// Span of a running stage, the child of the span of the job
+    fn stage_span(&self, stage_id: usize, stage_attempt_num: usize) -> Span {
+        let span = info_span!(
+            parent: &self.span,
+            "stage",
+            job_id = %self.job_id,
+            stage_id,
+            stage_attempt_num,
+        );
+        // graphs decoded from the persisted state have no span of the job
+        telemetry::set_parent(&span, &self.properties.trace_context);
+        span
+    }
+
+    /// Close the spans of the job and of its running stages
+    fn close_spans(&mut self) {
+        self.span = Span::none();
+        for stage in self.stages.values_mut() {
+            if let ExecutionStage::Running(stage) = stage {
+                stage.span = Span::none();
+            }
+        }
+    }
+
     /// fail job with error message
     pub fn fail_job(&mut self, error: String) {
+        self.close_spans();
         self.status = JobStatus {
             job_id: self.job_id.clone(),
             job_name: self.job_name.clone(),
@@ -1276,10 +1636,19 @@ impl ExecutionGraph {
                 queued_at: self.queued_at,
                 started_at: self.start_time,
                 ended_at: self.end_time,
+                timed_out: false,
             })),
         };
     }
 
+    /// Mark the job failed because it has run for longer than its timeout
+    pub fn time_out_job(&mut self, timeout_seconds: u64) {
+        self.fail_job(format!("Job timed out after {timeout_seconds} seconds"));
+        if let Some(Status::Failed(failed)) = self.status.status.as_mut() {
+            failed.timed_out = true;
+        }
+    }
+
     /// Mark the job success
     pub fn succeed_job(&mut self) -> Result<()> {
         if !self.is_successful() {
@@ -1289,8 +1658,12 @@ impl ExecutionGraph {
             )));
         }
 
-        let partition_location = self
-            .output_locations()
+        // the output partitions are read in order, which concatenates the ranges of a
+        // range partitioned sort into the sorted result
+        let mut output_locations = self.output_locations();
+        output_locations
+            .sort_by_key(|l| (l.partition_id.partition_id, l.map_partition_id));
+        let partition_location = output_locations
             .into_iter()
             .map(|l| l.try_into())
             .collect::<Result<Vec<_>>>()?;
@@ -1310,6 +1683,7 @@ impl ExecutionGraph {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64;
+        self.close_spans();
 
         Ok(())
     }
@@ -1383,6 +1757,43 @@ impl ExecutionGraph {
             scheduler_id: (!proto.scheduler_id.is_empty()).then_some(proto.scheduler_id),
             job_id: proto.job_id,
             job_name: proto.job_name,
+            properties: JobProperties {
+                user: (!proto.user.is_empty()).then_some(proto.user),
+                priority: proto.priority,
+                pool: if proto.pool.is_empty() {
+                    JobProperties::default().pool
+                } else {
+                    proto.pool
+                },
+                adaptive: proto.adaptive.map(|adaptive| AdaptiveConfig {
+                    target_partition_bytes: adaptive.target_partition_bytes,
+                    skew_factor: adaptive.skew_factor,
+                    skew_threshold_bytes: adaptive.skew_threshold_bytes,
+                    broadcast_threshold_bytes: adaptive.broadcast_threshold_bytes,
+                }),
+                shuffle_compression: proto
+                    .shuffle_compression
+                    .parse()
+                    .map_err(BallistaError::Internal)?,
+                shuffle_sort_based: proto.shuffle_sort_based,
+                repartition_sorts: proto.repartition_sorts,
+                range_sample_size: proto.range_sample_size as usize,
+                task_memory: proto.task_memory,
+                task_cpus: proto.task_cpus,
+                executor_selector: proto
+                    .executor_selector
+                    .into_iter()
+                    .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                    .collect(),
+                timeout_seconds: proto.timeout_seconds,
+                task_timeout_seconds: proto.task_timeout_seconds,
+                result_cache: proto.result_cache,
+                trace_context: proto
+                    .trace_context
+                    .into_iter()
+                    .map(|kv_pair| (kv_pair.key, kv_pair.value))
+                    .collect(),
+            },
             session_id: proto.session_id,
             status: proto.status.ok_or_else(|| {
                 BallistaError::Internal(
@@ -1397,6 +1808,7 @@ impl ExecutionGraph {
             output_locations,
             task_id_gen: proto.task_id_gen as usize,
             failed_stage_attempts,
+            span: Span::none(),
         })
     }
 
@@ -1462,6 +1874,38 @@ impl ExecutionGraph {
         Ok(protobuf::ExecutionGraph {
             job_id: graph.job_id,
             job_name: graph.job_name,
+            user: graph.properties.user.unwrap_or_default(),
+            priority: graph.properties.priority,
+            pool: graph.properties.pool,
+            adaptive: graph.properties.adaptive.map(|adaptive| {
+                protobuf::AdaptiveExecutionConfig {
+                    target_partition_bytes: adaptive.target_partition_bytes,
+                    skew_factor: adaptive.skew_factor,
+                    skew_threshold_bytes: adaptive.skew_threshold_bytes,
+                    broadcast_threshold_bytes: adaptive.broadcast_threshold_bytes,
+                }
+            }),
+            shuffle_compression: graph.properties.shuffle_compression.to_string(),
+            shuffle_sort_based: graph.properties.shuffle_sort_based,
+            repartition_sorts: graph.properties.repartition_sorts,
+            range_sample_size: graph.properties.range_sample_size as u64,
+            task_memory: graph.properties.task_memory,
+            task_cpus: graph.properties.task_cpus,
+            executor_selector: graph
+                .properties
+                .executor_selector
+                .into_iter()
+                .map(|(key, value)| protobuf::KeyValuePair { key, value })
+                .collect(),
+            timeout_seconds: graph.properties.timeout_seconds,
+            task_timeout_seconds: graph.properties.task_timeout_seconds,
+            result_cache: graph.properties.result_cache,
+            trace_context: graph
+                .properties
+                .trace_context
+                .into_iter()
+                .map(|(key, value)| protobuf::KeyValuePair { key, value })
+                .collect(),
             session_id: graph.session_id,
             status: Some(graph.status),
             queued_at: graph.queued_at,
@@ -1620,6 +2064,8 @@ pub struct TaskDescription {
     pub task_attempt: usize,
     pub data_cache: bool,
     pub plan: Arc<dyn ExecutionPlan>,
+    /// Trace context of the span of the stage of the task
+    pub trace_context: HashMap<String, String>,
 }
 
 impl Debug for TaskDescription {
@@ -1678,6 +2124,10 @@ fn partition_to_location(
                 Some(shuffle.num_bytes),
             ),
             path: shuffle.path,
+            // the codec is only used to send the partition compressed, so it is safe
+            // to fall back to uncompressed for a codec unknown to this scheduler
+            compression: shuffle.compression.parse().unwrap_or_default(),
+            index_path: Some(shuffle.index_path).filter(|p| !p.is_empty()),
         })
         .collect()
 }
@@ -1685,19 +2135,22 @@ fn partition_to_location(
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
+    use std::time::Duration;
 
+    use crate::config::SpeculationConfig;
     use crate::scheduler_server::event::QueryStageSchedulerEvent;
     use ballista_core::error::Result;
     use ballista_core::serde::protobuf::{
         self, failed_task, job_status, ExecutionError, FailedTask, FetchPartitionError,
         IoError, JobStatus, TaskKilled,
     };
+    use ballista_core::serde::scheduler::ExecutorMetadata;
 
-    use crate::state::execution_graph::ExecutionGraph;
+    use crate::state::execution_graph::{ExecutionGraph, TaskDescription};
     use crate::test_utils::{
-        mock_completed_task, mock_executor, mock_failed_task, test_aggregation_plan,
-        test_coalesce_plan, test_join_plan, test_two_aggregations_plan,
-        test_union_all_plan, test_union_plan,
+        mock_completed_task, mock_completed_task_in_object_store, mock_executor,
+        mock_failed_task, test_aggregation_plan, test_coalesce_plan, test_join_plan,
+        test_two_aggregations_plan, test_union_all_plan, test_union_plan,
     };
 
     #[tokio::test]
@@ -1779,6 +2232,83 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_time_out_job() -> Result<()> {
+        let mut agg_graph = test_aggregation_plan(4).await;
+        agg_graph.revive();
+
+        agg_graph.time_out_job(60);
+
+        match &agg_graph.status().status {
+            Some(job_status::Status::Failed(failed)) => {
+                assert!(failed.timed_out);
+                assert_eq!("Job timed out after 60 seconds", failed.error);
+            }
+            other => panic!("Expected a failed job but found {other:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_is_timed_out() -> Result<()> {
+        let mut agg_graph = test_aggregation_plan(4).await;
+        agg_graph.revive();
+        let queued_at = agg_graph.queued_at();
+
+        // Jobs without a timeout never time out
+        assert!(!agg_graph.is_timed_out(queued_at + 3_600_000));
+
+        agg_graph.properties.timeout_seconds = 60;
+        assert!(!agg_graph.is_timed_out(queued_at + 59_999));
+        assert!(agg_graph.is_timed_out(queued_at + 60_000));
+
+        // Finished jobs are no longer timed out
+        agg_graph.time_out_job(60);
+        assert!(!agg_graph.is_timed_out(queued_at + 60_000));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_job_progress() -> Result<()> {
+        let executor = mock_executor("executor-id1".to_string());
+        let mut agg_graph = test_aggregation_plan(4).await;
+
+        // 1 task for the partial aggregation and 4 tasks for the final aggregation
+        let progress = agg_graph.progress();
+        assert_eq!(progress.completed_tasks, 0);
+        assert_eq!(progress.total_tasks, 5);
+        assert_eq!(progress.percent_complete, 0.0);
+
+        agg_graph.revive();
+        if let Some(task) = agg_graph.pop_next_task(&executor.id)? {
+            let task_status = mock_completed_task(task, &executor.id);
+            agg_graph.update_task_status(&executor, vec![task_status], 1, 1)?;
+        }
+
+        let progress = agg_graph.progress();
+        assert_eq!(progress.completed_tasks, 1);
+        assert_eq!(progress.percent_complete, 20.0);
+        // The completed task wrote a row to each of the 4 shuffle partitions
+        assert_eq!(progress.output_rows, 4);
+        assert_eq!(progress.stages.len(), 2);
+        assert_eq!(progress.stages[0].state, "Successful");
+        assert_eq!(progress.stages[0].completed_tasks, 1);
+        assert_eq!(progress.stages[0].output_rows, 4);
+        assert_eq!(progress.stages[1].state, "Resolved");
+        assert_eq!(progress.stages[1].completed_tasks, 0);
+        assert_eq!(progress.stages[1].total_tasks, 4);
+
+        drain_tasks(&mut agg_graph)?;
+
+        let progress = agg_graph.progress();
+        assert_eq!(progress.completed_tasks, 5);
+        assert_eq!(progress.percent_complete, 100.0);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_reset_completed_stage_executor_lost() -> Result<()> {
         let executor1 = mock_executor("executor-id1".to_string());
@@ -1832,6 +2362,52 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_keep_object_store_stage_executor_lost() -> Result<()> {
+        let executor1 = mock_executor("executor-id1".to_string());
+        let executor2 = mock_executor("executor-id2".to_string());
+        let mut join_graph = test_join_plan(4).await;
+
+        join_graph.revive();
+        assert_eq!(join_graph.available_tasks(), 2);
+
+        // Complete the first stage, writing the shuffle output to an object store
+        if let Some(task) = join_graph.pop_next_task(&executor1.id)? {
+            let task_status = mock_completed_task_in_object_store(task, &executor1.id);
+            join_graph.update_task_status(&executor1, vec![task_status], 1, 1)?;
+        }
+
+        // Complete the second stage
+        if let Some(task) = join_graph.pop_next_task(&executor2.id)? {
+            let task_status = mock_completed_task_in_object_store(task, &executor2.id);
+            join_graph.update_task_status(&executor2, vec![task_status], 1, 1)?;
+        }
+
+        join_graph.revive();
+        // There are 4 tasks pending schedule for the 3rd stage
+        assert_eq!(join_graph.available_tasks(), 4);
+
+        // Complete 1 task
+        if let Some(task) = join_graph.pop_next_task(&executor1.id)? {
+            let task_status = mock_completed_task_in_object_store(task, &executor1.id);
+            join_graph.update_task_status(&executor1, vec![task_status], 1, 1)?;
+        }
+        // Mock 1 running task
+        let _task = join_graph.pop_next_task(&executor1.id)?;
+
+        let reset = join_graph.reset_stages_on_lost_executor(&executor1.id)?;
+
+        // Only the running task is reset, the shuffle output of the completed stage
+        // and the completed task are still readable from the object store
+        assert_eq!(reset.0.len(), 1);
+        assert_eq!(join_graph.available_tasks(), 3);
+
+        drain_tasks(&mut join_graph)?;
+        assert!(join_graph.is_successful(), "Failed to complete join plan");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_reset_resolved_stage_executor_lost() -> Result<()> {
         let executor1 = mock_executor("executor-id1".to_string());
@@ -2833,6 +3409,123 @@ mod test {
     //     todo!()
     // }
 
+    #[tokio::test]
+    async fn test_speculative_task_wins() -> Result<()> {
+        let executor1 = mock_executor("executor-id1".to_string());
+        let executor2 = mock_executor("executor-id2".to_string());
+        let (mut agg_graph, straggler) =
+            test_straggler_graph(&executor1, &executor2).await?;
+
+        // The copy of the straggler can't be bound to the executor running the straggler
+        let mut bound = agg_graph.bind_speculative_tasks(|original_executor| {
+            assert_eq!(original_executor, executor2.id);
+            Some(executor1.id.clone())
+        });
+        assert_eq!(bound.len(), 1);
+        assert_eq!(agg_graph.running_tasks().len(), 2);
+        let (executor_id, copy) = bound.pop().unwrap();
+        assert_eq!(executor_id, executor1.id);
+        assert_eq!(copy.partition, straggler.partition);
+
+        // The copy finishes first and the straggler is cancelled
+        let task_status = mock_completed_task(copy, &executor1.id);
+        let events = agg_graph.update_task_status(&executor1, vec![task_status], 1, 1)?;
+        assert!(events.iter().any(|event| matches!(
+            event,
+            QueryStageSchedulerEvent::CancelTasks(tasks)
+                if tasks.len() == 1
+                    && tasks[0].task_id == straggler.task_id
+                    && tasks[0].executor_id == executor2.id
+        )));
+        assert!(agg_graph.running_tasks().is_empty());
+
+        // The late status of the straggler is ignored
+        let task_status = mock_completed_task(straggler, &executor2.id);
+        let events = agg_graph.update_task_status(&executor2, vec![task_status], 1, 1)?;
+        assert!(events.is_empty());
+
+        drain_tasks(&mut agg_graph)?;
+        assert!(agg_graph.is_successful(), "Failed to complete agg plan");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_straggler_task_wins() -> Result<()> {
+        let executor1 = mock_executor("executor-id1".to_string());
+        let executor2 = mock_executor("executor-id2".to_string());
+        let (mut agg_graph, straggler) =
+            test_straggler_graph(&executor1, &executor2).await?;
+
+        let (_, copy) = agg_graph
+            .bind_speculative_tasks(|_| Some(executor1.id.clone()))
+            .pop()
+            .unwrap();
+
+        // The straggler finishes first and the copy is cancelled
+        let task_status = mock_completed_task(straggler, &executor2.id);
+        let events = agg_graph.update_task_status(&executor2, vec![task_status], 1, 1)?;
+        assert!(events.iter().any(|event| matches!(
+            event,
+            QueryStageSchedulerEvent::CancelTasks(tasks)
+                if tasks.len() == 1
+                    && tasks[0].task_id == copy.task_id
+                    && tasks[0].executor_id == executor1.id
+        )));
+
+        // The late failure of the cancelled copy is ignored
+        let task_status = mock_failed_task(
+            copy,
+            FailedTask {
+                error: "Task cancelled".to_string(),
+                retryable: false,
+                count_to_failures: false,
+                failed_reason: Some(failed_task::FailedReason::TaskKilled(TaskKilled {})),
+            },
+        );
+        let events = agg_graph.update_task_status(&executor1, vec![task_status], 1, 1)?;
+        assert!(events.is_empty());
+
+        drain_tasks(&mut agg_graph)?;
+        assert!(agg_graph.is_successful(), "Failed to complete agg plan");
+
+        Ok(())
+    }
+
+    /// Build an aggregation graph whose second stage has 3 successful tasks and a
+    /// straggler running on executor 2, which is marked for speculative execution
+    async fn test_straggler_graph(
+        executor1: &ExecutorMetadata,
+        executor2: &ExecutorMetadata,
+    ) -> Result<(ExecutionGraph, TaskDescription)> {
+        let mut agg_graph = test_aggregation_plan(4).await;
+        agg_graph.revive();
+
+        // Complete the Stage 1
+        if let Some(task) = agg_graph.pop_next_task(&executor1.id)? {
+            let task_status = mock_completed_task(task, &executor1.id);
+            agg_graph.update_task_status(executor1, vec![task_status], 1, 1)?;
+        }
+
+        let straggler = agg_graph.pop_next_task(&executor2.id)?.unwrap();
+        for _ in 0..3 {
+            let task = agg_graph.pop_next_task(&executor1.id)?.unwrap();
+            let task_status = mock_completed_task(task, &executor1.id);
+            agg_graph.update_task_status(executor1, vec![task_status], 1, 1)?;
+        }
+
+        let config = SpeculationConfig {
+            min_task_duration_ms: 0,
+            ..Default::default()
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(agg_graph.speculate_tasks(&config), 1);
+        // A straggler is only speculated once
+        assert_eq!(agg_graph.speculate_tasks(&config), 0);
+
+        Ok((agg_graph, straggler))
+    }
+
     fn drain_tasks(graph: &mut ExecutionGraph) -> Result<()> {
         let executor = mock_executor("executor-id1".to_string());
         while let Some(task) = graph.pop_next_task(&executor.id)? {
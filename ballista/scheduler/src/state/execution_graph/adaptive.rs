@@ -0,0 +1,647 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Adaptive re-planning of a stage when it is resolved, driven by the statistics of the
+//! shuffle partitions written by its input stages.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+
+use datafusion::common::tree_node::{Transformed, TreeNode};
+use datafusion::logical_expr::JoinType;
+use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
+use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::filter::FilterExec;
+use datafusion::physical_plan::joins::{CrossJoinExec, HashJoinExec, PartitionMode};
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::sorts::sort_preserving_merge::SortPreservingMergeExec;
+use datafusion::physical_plan::{with_new_children_if_necessary, ExecutionPlan};
+use log::info;
+
+use ballista_core::error::{BallistaError, Result};
+use ballista_core::execution_plans::{ShuffleReaderExec, ShuffleWriterExec};
+use ballista_core::serde::scheduler::PartitionLocation;
+
+use crate::state::execution_graph::AdaptiveConfig;
+
+/// Re-plans a resolved stage with the statistics of its shuffle inputs:
+/// 1. The build side of a partitioned hash join is collected by every probe task
+///    when it turns out to be small.
+/// 2. Skewed partitions of a partitioned hash join are split across several tasks,
+///    each of them joining a chunk of the skewed side with the whole other side.
+/// 3. Adjacent small partitions are coalesced into a single task.
+///
+/// Only the partitions of hash partitioned inputs are split or coalesced, as the
+/// partitions of the other inputs may need to stay apart, e.g. to keep them sorted.
+pub(crate) struct AdaptivePlanner {
+    config: AdaptiveConfig,
+    /// The input stages whose shuffle output is hash partitioned
+    hash_inputs: HashSet<usize>,
+}
+
+/// What a task of a re-planned stage reads from the partitioned shuffle inputs
+enum TaskInput {
+    /// The given range of shuffle partitions of every input
+    Coalesced(Range<usize>),
+    /// A chunk of a skewed partition of the input with the given index, along with the
+    /// whole partition of the other inputs
+    Split {
+        partition: usize,
+        input: usize,
+        locations: Vec<PartitionLocation>,
+    },
+}
+
+impl AdaptivePlanner {
+    pub(crate) fn new(config: AdaptiveConfig, hash_inputs: HashSet<usize>) -> Self {
+        Self {
+            config,
+            hash_inputs,
+        }
+    }
+
+    pub(crate) fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let plan = self.broadcast_small_build_sides(plan)?;
+        self.repartition_shuffle_reads(plan)
+    }
+
+    /// Broadcast the build side of partitioned hash joins to every probe task when it is
+    /// small. Joins which output the unmatched rows of the build side are left alone, as
+    /// every probe task would output them.
+    fn broadcast_small_build_sides(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let threshold = self.config.broadcast_threshold_bytes;
+        Ok(plan.transform_up(&|plan| {
+            if let Some(join) = plan.as_any().downcast_ref::<HashJoinExec>() {
+                let build_bytes = shuffle_reader(join.left())
+                    .and_then(|reader| total_bytes(reader.partition.iter().flatten()))
+                    .filter(|bytes| *bytes <= threshold);
+                let broadcastable = matches!(
+                    join.join_type(),
+                    JoinType::Inner
+                        | JoinType::Right
+                        | JoinType::RightSemi
+                        | JoinType::RightAnti
+                );
+                if let (PartitionMode::Partitioned, true, Some(bytes)) =
+                    (join.partition_mode(), broadcastable, build_bytes)
+                {
+                    info!(
+                        "Broadcasting the build side of {} bytes of a partitioned hash join",
+                        bytes
+                    );
+                    return Ok(Transformed::Yes(Arc::new(HashJoinExec::try_new(
+                        broadcast_reader(join.left().clone())?,
+                        join.right().clone(),
+                        join.on().to_vec(),
+                        join.filter().cloned(),
+                        join.join_type(),
+                        PartitionMode::CollectLeft,
+                        join.null_equals_null(),
+                    )?)));
+                }
+            }
+            Ok(Transformed::No(plan))
+        })?)
+    }
+
+    /// Coalesce small partitions and split skewed partitions of the partitioned shuffle
+    /// inputs. All of them are re-planned alike, so that they stay co-partitioned.
+    fn repartition_shuffle_reads(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut readers = vec![];
+        collect_partitioned_readers(&plan, &mut readers);
+        let partitions = match readers.first() {
+            Some((_, locations)) if !locations.is_empty() => locations.len(),
+            _ => return Ok(plan),
+        };
+        if readers.iter().any(|(stage_id, locations)| {
+            !self.hash_inputs.contains(stage_id) || locations.len() != partitions
+        }) {
+            return Ok(plan);
+        }
+        // The size of each partition of each input
+        let sizes = match readers
+            .iter()
+            .map(|(_, locations)| {
+                locations
+                    .iter()
+                    .map(total_bytes)
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Option<Vec<Vec<u64>>>>()
+        {
+            Some(sizes) => sizes,
+            None => return Ok(plan),
+        };
+
+        let skewed = self.skewed_partitions(&plan, &sizes);
+        let target_bytes = self.config.target_partition_bytes;
+        let mut tasks = vec![];
+        let mut group = 0..0;
+        let mut group_bytes = 0;
+        for partition in 0..partitions {
+            let bytes = sizes.iter().map(|sizes| sizes[partition]).sum::<u64>();
+            if let Some(input) = skewed.get(&partition) {
+                if !group.is_empty() {
+                    tasks.push(TaskInput::Coalesced(group.clone()));
+                }
+                tasks.extend(
+                    split_locations(&readers[*input].1[partition], target_bytes)
+                        .into_iter()
+                        .map(|locations| TaskInput::Split {
+                            partition,
+                            input: *input,
+                            locations,
+                        }),
+                );
+                group = partition + 1..partition + 1;
+                group_bytes = 0;
+            } else {
+                if !group.is_empty() && group_bytes + bytes > target_bytes {
+                    tasks.push(TaskInput::Coalesced(group.clone()));
+                    group = partition..partition;
+                    group_bytes = 0;
+                }
+                group.end = partition + 1;
+                group_bytes += bytes;
+            }
+        }
+        if !group.is_empty() {
+            tasks.push(TaskInput::Coalesced(group));
+        }
+        if skewed.is_empty() && tasks.len() == partitions {
+            return Ok(plan);
+        }
+        info!(
+            "Re-planned {} shuffle partitions into {} tasks, splitting {} skewed partitions",
+            partitions,
+            tasks.len(),
+            skewed.len()
+        );
+
+        let mut new_locations = readers
+            .iter()
+            .enumerate()
+            .map(|(index, (_, locations))| {
+                tasks
+                    .iter()
+                    .map(|task| match task {
+                        TaskInput::Coalesced(range) => {
+                            locations[range.clone()].iter().flatten().cloned().collect()
+                        }
+                        TaskInput::Split {
+                            partition,
+                            input,
+                            locations: chunk,
+                        } => {
+                            if *input == index {
+                                chunk.clone()
+                            } else {
+                                locations[*partition].clone()
+                            }
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        replace_partitioned_readers(plan, &mut new_locations)
+    }
+
+    /// Find the skewed partitions of a stage joining two partitioned shuffle inputs, along
+    /// with the index of the input to split. A side can only be split if the join does
+    /// not output the unmatched rows of the other side, which is read by several tasks.
+    fn skewed_partitions(
+        &self,
+        plan: &Arc<dyn ExecutionPlan>,
+        sizes: &[Vec<u64>],
+    ) -> HashMap<usize, usize> {
+        let mut skewed = HashMap::new();
+        let join_type = match splittable_join(plan) {
+            Some(join_type) if sizes.len() == 2 => join_type,
+            _ => return skewed,
+        };
+        let splittable = [
+            matches!(
+                join_type,
+                JoinType::Inner
+                    | JoinType::Left
+                    | JoinType::LeftSemi
+                    | JoinType::LeftAnti
+            ),
+            matches!(
+                join_type,
+                JoinType::Inner
+                    | JoinType::Right
+                    | JoinType::RightSemi
+                    | JoinType::RightAnti
+            ),
+        ];
+        let thresholds = sizes
+            .iter()
+            .map(|sizes| {
+                let mut sorted = sizes.clone();
+                sorted.sort_unstable();
+                sorted[sorted.len() / 2]
+                    .saturating_mul(self.config.skew_factor)
+                    .max(self.config.skew_threshold_bytes)
+            })
+            .collect::<Vec<_>>();
+        for partition in 0..sizes[0].len() {
+            // Split the larger side if both sides are skewed
+            if let Some(input) = (0..sizes.len())
+                .filter(|input| {
+                    splittable[*input] && sizes[*input][partition] > thresholds[*input]
+                })
+                .max_by_key(|input| sizes[*input][partition])
+            {
+                skewed.insert(partition, input);
+            }
+        }
+        skewed
+    }
+}
+
+/// The shuffle reader of the given plan, looking through batch coalescing
+fn shuffle_reader(plan: &Arc<dyn ExecutionPlan>) -> Option<&ShuffleReaderExec> {
+    if let Some(coalesce) = plan.as_any().downcast_ref::<CoalesceBatchesExec>() {
+        shuffle_reader(coalesce.input())
+    } else {
+        plan.as_any().downcast_ref::<ShuffleReaderExec>()
+    }
+}
+
+/// Read all the partitions of the shuffle reader of the given plan as a single partition,
+/// looking through batch coalescing
+fn broadcast_reader(plan: Arc<dyn ExecutionPlan>) -> Result<Arc<dyn ExecutionPlan>> {
+    if let Some(reader) = plan.as_any().downcast_ref::<ShuffleReaderExec>() {
+        Ok(Arc::new(ShuffleReaderExec::try_new_broadcast(
+            reader.stage_id,
+            reader.partition.clone(),
+            reader.schema(),
+        )?))
+    } else {
+        let children = plan
+            .children()
+            .into_iter()
+            .map(broadcast_reader)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(with_new_children_if_necessary(plan, children)?.into())
+    }
+}
+
+/// The total size of the given shuffle partitions, if all of them have statistics
+fn total_bytes<'a>(
+    locations: impl IntoIterator<Item = &'a PartitionLocation>,
+) -> Option<u64> {
+    locations
+        .into_iter()
+        .map(|location| location.partition_stats.num_bytes())
+        .sum()
+}
+
+/// The type of the partitioned hash join of a stage reading two shuffle inputs, if the
+/// rows it outputs do not need to stay hash partitioned within the stage
+fn splittable_join(plan: &Arc<dyn ExecutionPlan>) -> Option<JoinType> {
+    let any = plan.as_any();
+    if let Some(join) = any.downcast_ref::<HashJoinExec>() {
+        (matches!(join.partition_mode(), PartitionMode::Partitioned)
+            && shuffle_reader(join.left()).is_some()
+            && shuffle_reader(join.right()).is_some())
+        .then_some(*join.join_type())
+    } else if any.is::<ShuffleWriterExec>()
+        || any.is::<ProjectionExec>()
+        || any.is::<FilterExec>()
+        || any.is::<CoalesceBatchesExec>()
+    {
+        plan.children().first().and_then(splittable_join)
+    } else {
+        None
+    }
+}
+
+/// Whether each task reads its own partitions of the child with the given index. The
+/// other children, like the build side of a `CollectLeft` join, are read as a whole by
+/// every task.
+fn is_partitioned_child(plan: &Arc<dyn ExecutionPlan>, index: usize) -> bool {
+    let any = plan.as_any();
+    if any.is::<CoalescePartitionsExec>() || any.is::<SortPreservingMergeExec>() {
+        false
+    } else if let Some(join) = any.downcast_ref::<HashJoinExec>() {
+        index != 0 || !matches!(join.partition_mode(), PartitionMode::CollectLeft)
+    } else {
+        index != 0 || !any.is::<CrossJoinExec>()
+    }
+}
+
+/// Collect the stage ids and partition locations of the partitioned shuffle readers of
+/// a plan, depth first
+fn collect_partitioned_readers(
+    plan: &Arc<dyn ExecutionPlan>,
+    readers: &mut Vec<(usize, Vec<Vec<PartitionLocation>>)>,
+) {
+    if let Some(reader) = plan.as_any().downcast_ref::<ShuffleReaderExec>() {
+        if !reader.broadcast {
+            readers.push((reader.stage_id, reader.partition.clone()));
+        }
+        return;
+    }
+    for (index, child) in plan.children().iter().enumerate() {
+        if is_partitioned_child(plan, index) {
+            collect_partitioned_readers(child, readers);
+        }
+    }
+}
+
+/// Replace the partition locations of the partitioned shuffle readers of a plan, in the
+/// order in which they were collected
+fn replace_partitioned_readers(
+    plan: Arc<dyn ExecutionPlan>,
+    new_locations: &mut impl Iterator<Item = Vec<Vec<PartitionLocation>>>,
+) -> Result<Arc<dyn ExecutionPlan>> {
+    if let Some(reader) = plan.as_any().downcast_ref::<ShuffleReaderExec>() {
+        if reader.broadcast {
+            return Ok(plan);
+        }
+        let locations = new_locations.next().ok_or_else(|| {
+            BallistaError::Internal(format!(
+                "Missing partition locations of the shuffle reader of stage {}",
+                reader.stage_id
+            ))
+        })?;
+        return Ok(Arc::new(ShuffleReaderExec::try_new(
+            reader.stage_id,
+            locations,
+            reader.schema(),
+        )?));
+    }
+    let children = plan
+        .children()
+        .into_iter()
+        .enumerate()
+        .map(|(index, child)| {
+            if is_partitioned_child(&plan, index) {
+                replace_partitioned_readers(child, new_locations)
+            } else {
+                Ok(child)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(with_new_children_if_necessary(plan, children)?.into())
+}
+
+/// Split the locations of a skewed partition into chunks of about the target size
+fn split_locations(
+    locations: &[PartitionLocation],
+    target_bytes: u64,
+) -> Vec<Vec<PartitionLocation>> {
+    let mut chunks: Vec<Vec<PartitionLocation>> = vec![];
+    let mut chunk_bytes = 0;
+    for location in locations {
+        let bytes = location.partition_stats.num_bytes().unwrap_or_default();
+        match chunks.last_mut() {
+            Some(chunk) if chunk_bytes + bytes <= target_bytes => {
+                chunk.push(location.clone());
+                chunk_bytes += bytes;
+            }
+            _ => {
+                chunks.push(vec![location.clone()]);
+                chunk_bytes = bytes;
+            }
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use ballista_core::error::Result;
+    use ballista_core::execution_plans::ShuffleReaderExec;
+    use ballista_core::serde::scheduler::{
+        PartitionId, PartitionLocation, PartitionStats,
+    };
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::logical_expr::JoinType;
+    use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
+    use datafusion::physical_plan::expressions::Column;
+    use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode};
+    use datafusion::physical_plan::ExecutionPlan;
+
+    use crate::state::execution_graph::adaptive::AdaptivePlanner;
+    use crate::state::execution_graph::AdaptiveConfig;
+    use crate::test_utils::mock_executor;
+
+    fn adaptive_planner(hash_inputs: &[usize]) -> AdaptivePlanner {
+        AdaptivePlanner::new(
+            AdaptiveConfig {
+                target_partition_bytes: 300,
+                skew_factor: 5,
+                skew_threshold_bytes: 100,
+                broadcast_threshold_bytes: 50,
+            },
+            hash_inputs.iter().copied().collect::<HashSet<_>>(),
+        )
+    }
+
+    /// A shuffle reader whose partitions have been written by map tasks of the given sizes
+    fn mock_reader(stage_id: usize, sizes: &[&[u64]]) -> Arc<dyn ExecutionPlan> {
+        let partitions = sizes
+            .iter()
+            .enumerate()
+            .map(|(partition_id, map_sizes)| {
+                map_sizes
+                    .iter()
+                    .enumerate()
+                    .map(|(map_partition_id, bytes)| PartitionLocation {
+                        map_partition_id,
+                        partition_id: PartitionId {
+                            job_id: "job".to_string(),
+                            stage_id,
+                            partition_id,
+                        },
+                        executor_meta: mock_executor("executor".to_string()),
+                        partition_stats: PartitionStats::new(
+                            Some(1),
+                            Some(1),
+                            Some(*bytes),
+                        ),
+                        path: format!("/{stage_id}/{partition_id}/{map_partition_id}"),
+                        compression: Default::default(),
+                        index_path: None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        Arc::new(CoalesceBatchesExec::new(
+            Arc::new(ShuffleReaderExec::try_new(stage_id, partitions, schema).unwrap()),
+            4096,
+        ))
+    }
+
+    fn mock_join(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        join_type: JoinType,
+    ) -> Arc<dyn ExecutionPlan> {
+        Arc::new(
+            HashJoinExec::try_new(
+                left,
+                right,
+                vec![(Column::new("a", 0), Column::new("a", 0))],
+                None,
+                &join_type,
+                PartitionMode::Partitioned,
+                false,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn reader_partitions(plan: &Arc<dyn ExecutionPlan>) -> Vec<Vec<String>> {
+        plan.as_any()
+            .downcast_ref::<ShuffleReaderExec>()
+            .unwrap()
+            .partition
+            .iter()
+            .map(|locations| locations.iter().map(|l| l.path.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce_small_partitions() -> Result<()> {
+        let reader = mock_reader(1, &[&[100], &[100], &[100], &[250], &[60]]);
+
+        let plan = adaptive_planner(&[1]).optimize(reader.clone())?;
+        assert_eq!(plan.output_partitioning().partition_count(), 3);
+        assert_eq!(
+            reader_partitions(&plan.children()[0]),
+            vec![
+                vec!["/1/0/0", "/1/1/0", "/1/2/0"],
+                vec!["/1/3/0"],
+                vec!["/1/4/0"]
+            ]
+        );
+
+        // The partitions of inputs which are not hash partitioned are left alone
+        let plan = adaptive_planner(&[]).optimize(reader)?;
+        assert_eq!(plan.output_partitioning().partition_count(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_skewed_partitions() -> Result<()> {
+        let left = mock_reader(1, &[&[10, 10], &[10, 10], &[200, 200, 200]]);
+        let right = mock_reader(2, &[&[30], &[30], &[30]]);
+
+        let plan = adaptive_planner(&[1, 2]).optimize(mock_join(
+            left.clone(),
+            right.clone(),
+            JoinType::Inner,
+        ))?;
+        let join = plan.as_any().downcast_ref::<HashJoinExec>().unwrap();
+        assert!(matches!(join.partition_mode(), PartitionMode::Partitioned));
+        assert_eq!(
+            reader_partitions(&join.left().children()[0]),
+            vec![
+                vec!["/1/0/0", "/1/0/1", "/1/1/0", "/1/1/1"],
+                vec!["/1/2/0"],
+                vec!["/1/2/1"],
+                vec!["/1/2/2"]
+            ]
+        );
+        assert_eq!(
+            reader_partitions(&join.right().children()[0]),
+            vec![
+                vec!["/2/0/0", "/2/1/0"],
+                vec!["/2/2/0"],
+                vec!["/2/2/0"],
+                vec!["/2/2/0"]
+            ]
+        );
+
+        // The unmatched rows of the right side would be output by every task
+        let plan = adaptive_planner(&[1, 2]).optimize(mock_join(
+            left,
+            right,
+            JoinType::Right,
+        ))?;
+        assert_eq!(plan.output_partitioning().partition_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_shuffle_inputs() -> Result<()> {
+        let plan = adaptive_planner(&[1]).optimize(mock_reader(1, &[]))?;
+        assert_eq!(plan.output_partitioning().partition_count(), 0);
+
+        let plan = adaptive_planner(&[1, 2]).optimize(mock_join(
+            mock_reader(1, &[]),
+            mock_reader(2, &[]),
+            JoinType::Left,
+        ))?;
+        let join = plan.as_any().downcast_ref::<HashJoinExec>().unwrap();
+        assert!(matches!(join.partition_mode(), PartitionMode::Partitioned));
+        assert_eq!(plan.output_partitioning().partition_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_broadcast_small_build_side() -> Result<()> {
+        let left = mock_reader(1, &[&[10], &[10], &[10]]);
+        let right = mock_reader(2, &[&[300], &[300], &[300]]);
+
+        let plan = adaptive_planner(&[1, 2]).optimize(mock_join(
+            left.clone(),
+            right.clone(),
+            JoinType::Inner,
+        ))?;
+        let join = plan.as_any().downcast_ref::<HashJoinExec>().unwrap();
+        assert!(matches!(join.partition_mode(), PartitionMode::CollectLeft));
+        let build_side = join.left().children()[0].clone();
+        let build_side = build_side
+            .as_any()
+            .downcast_ref::<ShuffleReaderExec>()
+            .unwrap();
+        assert!(build_side.broadcast);
+        assert_eq!(build_side.output_partitioning().partition_count(), 1);
+        assert_eq!(plan.output_partitioning().partition_count(), 3);
+
+        // The unmatched rows of the build side would be output by every task
+        let plan =
+            adaptive_planner(&[1, 2]).optimize(mock_join(left, right, JoinType::Left))?;
+        let join = plan.as_any().downcast_ref::<HashJoinExec>().unwrap();
+        assert!(matches!(join.partition_mode(), PartitionMode::Partitioned));
+
+        Ok(())
+    }
+}
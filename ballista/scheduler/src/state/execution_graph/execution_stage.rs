@@ -30,20 +30,25 @@ use datafusion::physical_plan::{ExecutionPlan, Metric};
 use datafusion::prelude::{SessionConfig, SessionContext};
 use datafusion_proto::logical_plan::AsLogicalPlan;
 use log::{debug, warn};
+use tracing::Span;
 
 use ballista_core::error::{BallistaError, Result};
 use ballista_core::execution_plans::ShuffleWriterExec;
 use ballista_core::serde::protobuf::failed_task::FailedReason;
 use ballista_core::serde::protobuf::{
     self, task_info, FailedTask, GraphStageInput, OperatorMetricsSet, ResultLost,
-    SuccessfulTask, TaskStatus,
+    ShuffleWritePartition, StageProgress, SuccessfulTask, TaskStatus,
 };
 use ballista_core::serde::protobuf::{task_status, RunningTask};
 use ballista_core::serde::scheduler::PartitionLocation;
 use ballista_core::serde::BallistaCodec;
+use ballista_core::telemetry;
+use ballista_core::utils::is_object_store_location;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 
+use crate::config::SpeculationConfig;
 use crate::display::DisplayableBallistaExecutionPlan;
+use crate::state::execution_graph::adaptive::AdaptivePlanner;
 
 /// A stage in the ExecutionGraph,
 /// represents a set of tasks (one per each `partition`) which can be executed concurrently.
@@ -97,6 +102,57 @@ impl ExecutionStage {
             ExecutionStage::Failed(stage) => stage.plan.as_ref(),
         }
     }
+
+    /// Get the number of completed tasks of this query stage, along with the rows and bytes
+    /// they have written
+    pub(crate) fn progress(&self) -> StageProgress {
+        let (stage_id, partitions, task_infos): (usize, usize, Vec<&TaskInfo>) =
+            match self {
+                ExecutionStage::UnResolved(stage) => (
+                    stage.stage_id,
+                    get_stage_partitions(stage.plan.clone()),
+                    vec![],
+                ),
+                ExecutionStage::Resolved(stage) => {
+                    (stage.stage_id, stage.partitions, vec![])
+                }
+                ExecutionStage::Running(stage) => (
+                    stage.stage_id,
+                    stage.partitions,
+                    stage.task_infos.iter().flatten().collect(),
+                ),
+                ExecutionStage::Successful(stage) => (
+                    stage.stage_id,
+                    stage.partitions,
+                    stage.task_infos.iter().collect(),
+                ),
+                ExecutionStage::Failed(stage) => (
+                    stage.stage_id,
+                    stage.partitions,
+                    stage.task_infos.iter().flatten().collect(),
+                ),
+            };
+
+        let mut progress = StageProgress {
+            stage_id: stage_id as u32,
+            state: self.variant_name().to_string(),
+            total_tasks: partitions as u32,
+            ..Default::default()
+        };
+        for task_info in task_infos {
+            if let task_status::Status::Successful(SuccessfulTask {
+                partitions, ..
+            }) = &task_info.task_status
+            {
+                progress.completed_tasks += 1;
+                for partition in partitions {
+                    progress.output_rows += partition.num_rows;
+                    progress.output_bytes += partition.num_bytes;
+                }
+            }
+        }
+        progress
+    }
 }
 
 /// For a stage whose input stages are not all completed, we say it's a unresolved stage
@@ -169,6 +225,11 @@ pub(crate) struct RunningStage {
     pub(crate) task_failure_numbers: Vec<usize>,
     /// Combined metrics of the already finished tasks in the stage, If it is None, no task is finished yet.
     pub(crate) stage_metrics: Option<Vec<MetricsSet>>,
+    /// Speculative copies of straggler tasks, keyed by partition id.
+    /// If the TaskInfo is None, the copy is waiting for a slot on another executor
+    pub(crate) speculative_tasks: HashMap<usize, Option<TaskInfo>>,
+    /// Span of the stage, which is closed once the stage is no longer running
+    pub(crate) span: Span,
 }
 
 /// If a stage finishes successfully, its task statuses and metrics will be finalized
@@ -336,8 +397,12 @@ impl UnresolvedStage {
         self.inputs.iter().all(|(_, input)| input.is_complete())
     }
 
-    /// Change to the resolved state
-    pub(super) fn to_resolved(&self) -> Result<ResolvedStage> {
+    /// Change to the resolved state. If an adaptive planner is provided, the plan is
+    /// re-planned with the statistics of the shuffle inputs
+    pub(super) fn to_resolved(
+        &self,
+        adaptive_planner: Option<&AdaptivePlanner>,
+    ) -> Result<ResolvedStage> {
         let input_locations = self
             .inputs
             .iter()
@@ -351,6 +416,10 @@ impl UnresolvedStage {
         // Optimize join order based on new resolved statistics
         let optimize_join = JoinSelection::new();
         let plan = optimize_join.optimize(plan, SessionConfig::default().options())?;
+        let plan = match adaptive_planner {
+            Some(adaptive_planner) => adaptive_planner.optimize(plan)?,
+            None => plan,
+        };
 
         Ok(ResolvedStage::new(
             self.stage_id,
@@ -450,7 +519,7 @@ impl ResolvedStage {
     }
 
     /// Change to the running state
-    pub(super) fn to_running(&self) -> RunningStage {
+    pub(super) fn to_running(&self, span: Span) -> RunningStage {
         RunningStage::new(
             self.stage_id,
             self.stage_attempt_num,
@@ -458,6 +527,7 @@ impl ResolvedStage {
             self.partitions,
             self.output_links.clone(),
             self.inputs.clone(),
+            span,
         )
     }
 
@@ -547,6 +617,7 @@ impl RunningStage {
         partitions: usize,
         output_links: Vec<usize>,
         inputs: HashMap<usize, StageOutput>,
+        span: Span,
     ) -> Self {
         Self {
             stage_id,
@@ -558,9 +629,17 @@ impl RunningStage {
             task_infos: vec![None; partitions],
             task_failure_numbers: vec![0; partitions],
             stage_metrics: None,
+            speculative_tasks: HashMap::new(),
+            span,
         }
     }
 
+    /// Trace context of the span of the stage, which the spans of its tasks are
+    /// children of
+    pub(crate) fn trace_context(&self) -> HashMap<String, String> {
+        telemetry::trace_context(&self.span)
+    }
+
     pub(super) fn to_successful(&self) -> SuccessfulStage {
         let task_infos = self
             .task_infos
@@ -668,11 +747,17 @@ impl RunningStage {
         self.task_infos.iter().filter(|s| s.is_some()).count()
     }
 
-    /// Returns a vector of currently running tasks in this stage
+    /// Returns a vector of currently running tasks in this stage, including the launched
+    /// speculative copies
     pub(super) fn running_tasks(&self) -> Vec<(usize, usize, usize, String)> {
         self.task_infos
             .iter()
             .enumerate()
+            .chain(
+                self.speculative_tasks
+                    .iter()
+                    .map(|(partition, info)| (*partition, info)),
+            )
             .filter_map(|(partition, info)| match info {
                 Some(TaskInfo {task_id,
                          task_status: task_status::Status::Running(RunningTask { executor_id }), ..}) => {
@@ -683,6 +768,106 @@ impl RunningStage {
             .collect()
     }
 
+    /// Mark the running tasks which take much longer than the median successful task of
+    /// this stage for speculative execution.
+    /// Returns the number of newly marked tasks
+    pub(super) fn speculate(&mut self, config: &SpeculationConfig, now: u128) -> usize {
+        let mut durations = self
+            .task_infos
+            .iter()
+            .flatten()
+            .filter(|info| matches!(info.task_status, task_status::Status::Successful(_)))
+            .map(|info| info.finish_time.saturating_sub(info.scheduled_time))
+            .collect::<Vec<_>>();
+        if durations.is_empty()
+            || (durations.len() as f64) < config.quantile * self.partitions as f64
+        {
+            return 0;
+        }
+        durations.sort_unstable();
+        let median = durations[durations.len() / 2];
+        let threshold = ((median as f64 * config.multiplier) as u128)
+            .max(config.min_task_duration_ms as u128);
+
+        let mut speculated = 0;
+        for (partition_id, info) in self.task_infos.iter().enumerate() {
+            if let Some(TaskInfo {
+                task_status: task_status::Status::Running(_),
+                scheduled_time,
+                ..
+            }) = info
+            {
+                if now.saturating_sub(*scheduled_time) > threshold
+                    && !self.speculative_tasks.contains_key(&partition_id)
+                {
+                    self.speculative_tasks.insert(partition_id, None);
+                    speculated += 1;
+                }
+            }
+        }
+        speculated
+    }
+
+    /// Settle the race between a straggler task and its speculative copy when either of
+    /// them finishes. If the copy succeeded, it takes the place of the original task.
+    /// Returns the task id and the executor id of the attempt to be cancelled, if any
+    pub(super) fn settle_speculative_task(
+        &mut self,
+        partition_id: usize,
+        status: &TaskStatus,
+    ) -> Option<(usize, String)> {
+        if matches!(status.status, Some(task_status::Status::Running(_))) {
+            return None;
+        }
+        let copy_task_id = match self.speculative_tasks.get(&partition_id) {
+            Some(Some(copy)) => copy.task_id,
+            Some(None) => {
+                // The copy has not been launched yet, so there is nothing to race with
+                if self.is_current_attempt(partition_id, status) {
+                    self.speculative_tasks.remove(&partition_id);
+                }
+                return None;
+            }
+            None => return None,
+        };
+
+        if status.task_id as usize == copy_task_id {
+            let copy = self.speculative_tasks.remove(&partition_id).flatten()?;
+            if !matches!(status.status, Some(task_status::Status::Successful(_))) {
+                return None;
+            }
+            // The copy won, so the original attempt is replaced and cancelled
+            let original = self.task_infos[partition_id].replace(copy);
+            match original {
+                Some(TaskInfo {
+                    task_id,
+                    task_status: task_status::Status::Running(RunningTask { executor_id }),
+                    ..
+                }) => Some((task_id, executor_id)),
+                _ => None,
+            }
+        } else if self.is_current_attempt(partition_id, status) {
+            // The original attempt finished first, so the copy is cancelled
+            match self.speculative_tasks.remove(&partition_id).flatten() {
+                Some(TaskInfo {
+                    task_id,
+                    task_status: task_status::Status::Running(RunningTask { executor_id }),
+                    ..
+                }) => Some((task_id, executor_id)),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    fn is_current_attempt(&self, partition_id: usize, status: &TaskStatus) -> bool {
+        matches!(
+            &self.task_infos[partition_id],
+            Some(info) if info.task_id == status.task_id as usize
+        )
+    }
+
     /// Returns the number of tasks in this stage which are available for scheduling.
     /// If the stage is not yet resolved, then this will return `0`, otherwise it will
     /// return the number of tasks where the task info is not yet set.
@@ -699,8 +884,9 @@ impl RunningStage {
         debug!("Updating TaskInfo for partition {}", partition_id);
         let task_info = self.task_infos[partition_id].as_ref().unwrap();
         let task_id = task_info.task_id;
-        if (status.task_id as usize) < task_id {
-            warn!("Ignore TaskStatus update with TID {} because there is more recent task attempt with TID {} running for partition {}",
+        // Statuses from earlier attempts and from the losing speculative attempt are stale
+        if (status.task_id as usize) != task_id {
+            warn!("Ignore TaskStatus update with TID {} because there is another task attempt with TID {} for partition {}",
                 status.task_id, task_id, partition_id);
             return false;
         }
@@ -798,9 +984,31 @@ impl RunningStage {
         self.task_infos[partition_id] = None;
     }
 
-    /// Reset the running and completed tasks on a given executor
+    /// Reset the running and completed tasks on a given executor.
+    /// Completed tasks whose output is in an object store are kept.
+    /// Running tasks with a speculative copy on another executor are replaced by the copy.
     /// Returns the number of running tasks that were reset
     pub fn reset_tasks(&mut self, executor: &str) -> usize {
+        let on_executor = |info: &Option<TaskInfo>| {
+            matches!(info, Some(TaskInfo {
+                task_status: task_status::Status::Running(RunningTask { executor_id }),
+                ..
+            }) if *executor == *executor_id)
+        };
+        self.speculative_tasks.retain(|_, copy| !on_executor(copy));
+        let promoted = self
+            .speculative_tasks
+            .iter()
+            .filter(|(partition_id, copy)| {
+                copy.is_some() && on_executor(&self.task_infos[**partition_id])
+            })
+            .map(|(partition_id, _)| *partition_id)
+            .collect::<Vec<_>>();
+        for partition_id in promoted {
+            self.task_infos[partition_id] =
+                self.speculative_tasks.remove(&partition_id).flatten();
+        }
+
         let mut reset = 0;
         for task in self.task_infos.iter_mut() {
             match task {
@@ -815,16 +1023,21 @@ impl RunningStage {
                     task_status:
                         task_status::Status::Successful(SuccessfulTask {
                             executor_id,
-                            partitions: _,
+                            partitions,
                         }),
                     ..
-                }) if *executor == *executor_id => {
+                }) if *executor == *executor_id
+                    && !is_output_in_object_store(partitions) =>
+                {
                     *task = None;
                     reset += 1;
                 }
                 _ => {}
             }
         }
+        let task_infos = &self.task_infos;
+        self.speculative_tasks
+            .retain(|partition_id, _| task_infos[*partition_id].is_some());
         reset
     }
 
@@ -878,7 +1091,7 @@ impl Debug for RunningStage {
 
 impl SuccessfulStage {
     /// Change to the running state and bump the stage attempt number
-    pub fn to_running(&self) -> RunningStage {
+    pub fn to_running(&self, span: Span) -> RunningStage {
         let mut task_infos: Vec<Option<TaskInfo>> = Vec::new();
         for task in self.task_infos.iter() {
             match task {
@@ -905,10 +1118,13 @@ impl SuccessfulStage {
             // It is Ok to forget the previous task failure attempts
             task_failure_numbers: vec![0; self.partitions],
             stage_metrics,
+            speculative_tasks: HashMap::new(),
+            span,
         }
     }
 
-    /// Reset the successful tasks on a given executor
+    /// Reset the successful tasks on a given executor, except for those whose output
+    /// is in an object store.
     /// Returns the number of running tasks that were reset
     pub fn reset_tasks(&mut self, executor: &str) -> usize {
         let mut reset = 0;
@@ -920,10 +1136,13 @@ impl SuccessfulStage {
                     scheduled_time,
                     task_status:
                         task_status::Status::Successful(SuccessfulTask {
-                            executor_id, ..
+                            executor_id,
+                            partitions,
                         }),
                     ..
-                } if *executor == *executor_id => {
+                } if *executor == *executor_id
+                    && !is_output_in_object_store(partitions) =>
+                {
                     *task = TaskInfo {
                         task_id: *task_id,
                         scheduled_time: *scheduled_time,
@@ -1174,6 +1393,15 @@ fn get_stage_partitions(plan: Arc<dyn ExecutionPlan>) -> usize {
         .unwrap_or_else(|| plan.output_partitioning().partition_count())
 }
 
+/// Whether the shuffle output of a successful task was written to an object store.
+/// Such output does not need to be recomputed when the executor which ran the task is lost.
+fn is_output_in_object_store(partitions: &[ShuffleWritePartition]) -> bool {
+    !partitions.is_empty()
+        && partitions
+            .iter()
+            .all(|partition| is_object_store_location(&partition.path))
+}
+
 /// This data structure collects the partition locations for an `ExecutionStage`.
 /// Each `ExecutionStage` will hold a `StageOutput`s for each of its child stages.
 /// When all tasks for the child stage are complete, it will mark the `StageOutput`
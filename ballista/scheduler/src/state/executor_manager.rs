@@ -21,17 +21,18 @@ use ballista_core::error::BallistaError;
 use ballista_core::error::Result;
 use ballista_core::serde::protobuf;
 
-use crate::cluster::{BoundTask, ClusterState, ExecutorSlot};
+use crate::cluster::{
+    split_active_jobs_by_selector, ActiveJobs, BoundTask, ClusterState, TaskResources,
+};
 use crate::config::SchedulerConfig;
 
 use crate::state::execution_graph::RunningTaskInfo;
-use crate::state::task_manager::JobInfoCache;
 use ballista_core::serde::protobuf::executor_grpc_client::ExecutorGrpcClient;
 use ballista_core::serde::protobuf::{
     executor_status, CancelTasksParams, ExecutorHeartbeat, MultiTaskDefinition,
     RemoveJobDataParams, StopExecutorParams,
 };
-use ballista_core::serde::scheduler::{ExecutorData, ExecutorMetadata};
+use ballista_core::serde::scheduler::{ExecutorData, ExecutorMetadata, ExecutorMetrics};
 use ballista_core::utils::{create_grpc_client_connection, get_time_before};
 use dashmap::DashMap;
 use log::{debug, error, info, warn};
@@ -71,30 +72,71 @@ impl ExecutorManager {
     /// If `executors` is provided, only bind slots from the specified executor IDs
     pub async fn bind_schedulable_tasks(
         &self,
-        active_jobs: Arc<HashMap<String, JobInfoCache>>,
+        active_jobs: ActiveJobs,
     ) -> Result<Vec<BoundTask>> {
         if active_jobs.is_empty() {
             warn!("There's no active jobs for binding tasks");
             return Ok(vec![]);
         }
-        let alive_executors = self.get_alive_executors();
-        if alive_executors.is_empty() {
-            warn!("There's no alive executors for binding tasks");
+        let schedulable_executors = self.get_schedulable_executors();
+        if schedulable_executors.is_empty() {
+            warn!("There's no schedulable executors for binding tasks");
             return Ok(vec![]);
         }
-        self.cluster_state
-            .bind_schedulable_tasks(
-                self.config.task_distribution,
-                active_jobs,
-                Some(alive_executors),
-            )
-            .await
+        // Jobs with an executor selector are only bound to the executors matching it
+        let runs = if active_jobs
+            .iter()
+            .any(|(_, job_info)| !job_info.properties.executor_selector.is_empty())
+        {
+            let mut executors = Vec::with_capacity(schedulable_executors.len());
+            for executor_id in schedulable_executors.iter() {
+                match self.get_executor_metadata(executor_id).await {
+                    Ok(metadata) => executors.push(metadata),
+                    Err(e) => {
+                        warn!("Fail to get metadata of executor {}: {}", executor_id, e)
+                    }
+                }
+            }
+            split_active_jobs_by_selector(active_jobs, &executors)
+        } else {
+            vec![(active_jobs, schedulable_executors)]
+        };
+        let mut bound_tasks = vec![];
+        for (active_jobs, executors) in runs {
+            if executors.is_empty() {
+                debug!(
+                    "No schedulable executors match the executor selector of jobs {:?}",
+                    active_jobs
+                        .iter()
+                        .map(|(job_id, _)| job_id)
+                        .collect::<Vec<_>>()
+                );
+                continue;
+            }
+            bound_tasks.extend(
+                self.cluster_state
+                    .bind_schedulable_tasks(
+                        self.config.task_distribution,
+                        active_jobs,
+                        Some(executors),
+                    )
+                    .await?,
+            );
+        }
+        Ok(bound_tasks)
     }
 
-    /// Returned reserved task slots to the pool of available slots. This operation is atomic
-    /// so either the entire pool of reserved task slots it returned or none are.
-    pub async fn unbind_tasks(&self, executor_slots: Vec<ExecutorSlot>) -> Result<()> {
-        self.cluster_state.unbind_tasks(executor_slots).await
+    /// Return the resources reserved by tasks of an executor to the pool of available
+    /// resources. This operation is atomic so either all the resources are returned or
+    /// none are.
+    pub async fn unbind_tasks(
+        &self,
+        executor_id: &str,
+        resources: TaskResources,
+    ) -> Result<()> {
+        self.cluster_state
+            .unbind_tasks(vec![(executor_id.to_owned(), resources)])
+            .await
     }
 
     /// Send rpc to Executors to cancel the running tasks
@@ -193,22 +235,28 @@ impl ExecutorManager {
         }
     }
 
-    /// Get a list of all executors along with the timestamp of their last recorded heartbeat
-    pub async fn get_executor_state(&self) -> Result<Vec<(ExecutorMetadata, Duration)>> {
-        let heartbeat_timestamps: Vec<(String, u64)> = self
+    /// Get a list of all executors along with the timestamp and the metrics of their last
+    /// recorded heartbeat
+    pub async fn get_executor_state(
+        &self,
+    ) -> Result<Vec<(ExecutorMetadata, Duration, ExecutorMetrics)>> {
+        let heartbeats: Vec<(String, u64, ExecutorMetrics)> = self
             .cluster_state
             .executor_heartbeats()
             .into_iter()
-            .map(|(executor_id, heartbeat)| (executor_id, heartbeat.timestamp))
+            .map(|(executor_id, heartbeat)| {
+                let metrics = heartbeat.metrics.as_slice().into();
+                (executor_id, heartbeat.timestamp, metrics)
+            })
             .collect();
 
-        let mut state: Vec<(ExecutorMetadata, Duration)> = vec![];
-        for (executor_id, ts) in heartbeat_timestamps {
+        let mut state: Vec<(ExecutorMetadata, Duration, ExecutorMetrics)> = vec![];
+        for (executor_id, ts, metrics) in heartbeats {
             let duration = Duration::from_secs(ts);
 
             let metadata = self.get_executor_metadata(&executor_id).await?;
 
-            state.push((metadata, duration));
+            state.push((metadata, duration, metrics));
         }
 
         Ok(state)
@@ -244,7 +292,7 @@ impl ExecutorManager {
             metadata.id, specification.total_task_slots
         );
 
-        ExecutorManager::test_connectivity(&metadata).await?;
+        self.test_connectivity(&metadata).await?;
 
         self.cluster_state
             .register_executor(metadata, specification)
@@ -362,6 +410,44 @@ impl ExecutorManager {
             .collect()
     }
 
+    /// Retrieve the set of alive executors which tasks can be assigned to, leaving out
+    /// the ones whose work dir disk has less free space than
+    /// `executor_min_available_disk_bytes`.
+    pub(crate) fn get_schedulable_executors(&self) -> HashSet<String> {
+        let alive_executors = self.get_alive_executors();
+        if self.config.executor_min_available_disk_bytes == 0 {
+            return alive_executors;
+        }
+        alive_executors
+            .into_iter()
+            .filter(|executor_id| {
+                let has_disk_space = self.has_available_disk(executor_id);
+                if !has_disk_space {
+                    debug!(
+                        "Not assigning tasks to executor {} which is low on disk space",
+                        executor_id
+                    );
+                }
+                has_disk_space
+            })
+            .collect()
+    }
+
+    /// Check whether the available disk space last reported by an executor is above the
+    /// configured threshold. Executors which have not reported it are considered to have
+    /// enough space.
+    pub(crate) fn has_available_disk(&self, executor_id: &str) -> bool {
+        let available_disk = self
+            .cluster_state
+            .get_executor_heartbeat(executor_id)
+            .and_then(|heartbeat| {
+                ExecutorMetrics::from(heartbeat.metrics.as_slice()).available_disk
+            });
+        available_disk.map_or(true, |available_disk| {
+            available_disk >= self.config.executor_min_available_disk_bytes
+        })
+    }
+
     /// Return a list of expired executors
     pub(crate) fn get_expired_executors(&self) -> Vec<ExecutorHeartbeat> {
         // Threshold for last heartbeat from Active executor before marking dead
@@ -405,7 +491,11 @@ impl ExecutorManager {
                 "http://{}:{}",
                 executor_metadata.host, executor_metadata.grpc_port
             );
-            let connection = create_grpc_client_connection(executor_url).await?;
+            let connection = create_grpc_client_connection(
+                executor_url,
+                self.config.grpc_tls.as_ref(),
+            )
+            .await?;
             let client = ExecutorGrpcClient::new(connection);
 
             {
@@ -416,22 +506,76 @@ impl ExecutorManager {
     }
 
     #[cfg(not(test))]
-    async fn test_connectivity(metadata: &ExecutorMetadata) -> Result<()> {
+    async fn test_connectivity(&self, metadata: &ExecutorMetadata) -> Result<()> {
         let executor_url = format!("http://{}:{}", metadata.host, metadata.grpc_port);
         debug!("Connecting to executor {:?}", executor_url);
-        let _ = protobuf::executor_grpc_client::ExecutorGrpcClient::connect(executor_url)
-            .await
-            .map_err(|e| {
-                BallistaError::Internal(format!(
-                    "Failed to register executor at {}:{}, could not connect: {:?}",
-                    metadata.host, metadata.grpc_port, e
-                ))
-            })?;
+        let _ =
+            create_grpc_client_connection(executor_url, self.config.grpc_tls.as_ref())
+                .await
+                .map_err(|e| {
+                    BallistaError::Internal(format!(
+                        "Failed to register executor at {}:{}, could not connect: {:?}",
+                        metadata.host, metadata.grpc_port, e
+                    ))
+                })?;
         Ok(())
     }
 
     #[cfg(test)]
-    async fn test_connectivity(_metadata: &ExecutorMetadata) -> Result<()> {
+    async fn test_connectivity(&self, _metadata: &ExecutorMetadata) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use ballista_core::error::Result;
+    use ballista_core::serde::protobuf::{
+        executor_status, ExecutorHeartbeat, ExecutorStatus,
+    };
+    use ballista_core::serde::scheduler::ExecutorMetrics;
+
+    use crate::config::SchedulerConfig;
+    use crate::scheduler_server::timestamp_secs;
+    use crate::state::executor_manager::ExecutorManager;
+    use crate::test_utils::test_cluster_context;
+
+    #[tokio::test]
+    async fn test_schedulable_executors() -> Result<()> {
+        let config =
+            SchedulerConfig::default().with_executor_min_available_disk_bytes(1000);
+        let executor_manager = ExecutorManager::new(
+            test_cluster_context().cluster_state(),
+            Arc::new(config),
+        );
+
+        for (executor_id, available_disk) in
+            [("low", Some(500)), ("high", Some(2000)), ("unknown", None)]
+        {
+            let metrics = ExecutorMetrics {
+                available_disk,
+                ..Default::default()
+            };
+            executor_manager
+                .save_executor_heartbeat(ExecutorHeartbeat {
+                    executor_id: executor_id.to_owned(),
+                    timestamp: timestamp_secs(),
+                    metrics: metrics.into(),
+                    status: Some(ExecutorStatus {
+                        status: Some(executor_status::Status::Active("".to_owned())),
+                    }),
+                })
+                .await?;
+        }
+
+        assert_eq!(3, executor_manager.get_alive_executors().len());
+        let expected: HashSet<String> = ["high".to_owned(), "unknown".to_owned()]
+            .into_iter()
+            .collect();
+        assert_eq!(expected, executor_manager.get_schedulable_executors());
         Ok(())
     }
 }
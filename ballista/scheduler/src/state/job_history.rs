@@ -0,0 +1,315 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ballista_core::error::{BallistaError, Result};
+use ballista_core::object_store_registry::BallistaObjectStoreRegistry;
+use ballista_core::serde::protobuf::{self, job_status};
+use datafusion::datasource::object_store::ObjectStoreRegistry;
+use datafusion::error::DataFusionError;
+use futures::TryStreamExt;
+use log::warn;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path;
+use object_store::ObjectStore;
+use url::Url;
+
+use crate::scheduler_server::timestamp_millis;
+use crate::state::{decode_protobuf, encode_protobuf};
+
+/// Archive of the execution graphs of finished jobs, which keeps them after the job
+/// state is cleaned up from the cluster and across restarts of the scheduler
+#[async_trait]
+pub trait JobHistoryStore: Send + Sync {
+    /// Archive the execution graph of a finished job
+    async fn archive_job(&self, graph: protobuf::ExecutionGraph) -> Result<()>;
+
+    /// Get the archived execution graph of a job
+    async fn get_job(&self, job_id: &str) -> Result<Option<protobuf::ExecutionGraph>>;
+
+    /// Get the summaries of at most `limit` archived jobs, the most recent ones first,
+    /// skipping the `offset` most recent jobs
+    async fn get_jobs(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<protobuf::JobHistorySummary>>;
+}
+
+/// [JobHistoryStore] keeping the execution graph and the summary of every job in their
+/// own files of an object store. The summaries are named after the time the job was
+/// queued, so that pages of jobs are listed without reading the other summaries.
+pub struct ObjectStoreJobHistory {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+    /// How long jobs are kept after they were queued, forever if `None`
+    retention: Option<Duration>,
+}
+
+impl ObjectStoreJobHistory {
+    pub fn new(
+        store: Arc<dyn ObjectStore>,
+        prefix: Path,
+        retention: Option<Duration>,
+    ) -> Self {
+        Self {
+            store,
+            prefix,
+            retention,
+        }
+    }
+
+    /// Create the job history archived in a local directory, or under an object store
+    /// URL such as `s3://bucket/history`
+    pub fn try_new(location: &str, retention: Option<Duration>) -> Result<Self> {
+        match Url::parse(location) {
+            Ok(url) if url.scheme() != "file" => {
+                let store = BallistaObjectStoreRegistry::new().get_store(&url)?;
+                let prefix = Path::from_url_path(url.path()).map_err(|e| {
+                    BallistaError::General(format!(
+                        "Invalid job history location {location}: {e}"
+                    ))
+                })?;
+                Ok(Self::new(store, prefix, retention))
+            }
+            _ => {
+                let dir = location.strip_prefix("file://").unwrap_or(location);
+                fs::create_dir_all(dir)?;
+                let store = LocalFileSystem::new_with_prefix(dir)
+                    .map_err(DataFusionError::from)?;
+                Ok(Self::new(Arc::new(store), Path::default(), retention))
+            }
+        }
+    }
+
+    fn job_path(&self, job_id: &str) -> Path {
+        self.prefix.child("jobs").child(format!("{job_id}.pb"))
+    }
+
+    fn summaries_path(&self) -> Path {
+        self.prefix.child("summaries")
+    }
+
+    fn summary_path(&self, job_id: &str, queued_at: u64) -> Path {
+        self.summaries_path()
+            .child(format!("{queued_at:020}-{job_id}.pb"))
+    }
+
+    async fn read<T: prost::Message + Default>(&self, path: &Path) -> Result<T> {
+        let bytes = self
+            .store
+            .get(path)
+            .await
+            .map_err(DataFusionError::from)?
+            .bytes()
+            .await
+            .map_err(DataFusionError::from)?;
+        decode_protobuf(&bytes)
+    }
+
+    async fn write<T: prost::Message + Default>(
+        &self,
+        path: &Path,
+        message: &T,
+    ) -> Result<()> {
+        let bytes = encode_protobuf(message)?;
+        self.store
+            .put(path, bytes.into())
+            .await
+            .map_err(DataFusionError::from)?;
+        Ok(())
+    }
+
+    /// List the archived jobs as (queued at, job id), the most recent ones first
+    async fn list_jobs(&self) -> Result<Vec<(u64, String)>> {
+        let files = self
+            .store
+            .list(Some(&self.summaries_path()))
+            .await
+            .map_err(DataFusionError::from)?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(DataFusionError::from)?;
+
+        let mut jobs: Vec<(u64, String)> = files
+            .iter()
+            .filter_map(|file| {
+                let name = file.location.filename()?.strip_suffix(".pb")?;
+                let (queued_at, job_id) = name.split_once('-')?;
+                Some((queued_at.parse().ok()?, job_id.to_owned()))
+            })
+            .collect();
+        jobs.sort_by(|a, b| b.cmp(a));
+        Ok(jobs)
+    }
+
+    /// Delete the jobs queued longer ago than the retention period
+    async fn remove_expired_jobs(&self, retention: Duration) -> Result<()> {
+        let expired_before =
+            timestamp_millis().saturating_sub(retention.as_millis() as u64);
+        for (queued_at, job_id) in self.list_jobs().await? {
+            if queued_at < expired_before {
+                self.store
+                    .delete(&self.summary_path(&job_id, queued_at))
+                    .await
+                    .map_err(DataFusionError::from)?;
+                self.store
+                    .delete(&self.job_path(&job_id))
+                    .await
+                    .map_err(DataFusionError::from)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Summarize an execution graph, without the locations of the output partitions of a
+/// successful job
+fn job_summary(graph: &protobuf::ExecutionGraph) -> protobuf::JobHistorySummary {
+    let mut status = graph.status.clone();
+    if let Some(protobuf::JobStatus {
+        status: Some(job_status::Status::Successful(successful)),
+        ..
+    }) = status.as_mut()
+    {
+        successful.partition_location.clear();
+    }
+    protobuf::JobHistorySummary {
+        job_id: graph.job_id.clone(),
+        job_name: graph.job_name.clone(),
+        user: graph.user.clone(),
+        status,
+        num_stages: graph.stages.len() as u64,
+        queued_at: graph.queued_at,
+        start_time: graph.start_time,
+        end_time: graph.end_time,
+    }
+}
+
+#[async_trait]
+impl JobHistoryStore for ObjectStoreJobHistory {
+    async fn archive_job(&self, graph: protobuf::ExecutionGraph) -> Result<()> {
+        self.write(&self.job_path(&graph.job_id), &graph).await?;
+        // The summary is written last, as the jobs are listed from their summaries
+        self.write(
+            &self.summary_path(&graph.job_id, graph.queued_at),
+            &job_summary(&graph),
+        )
+        .await?;
+        if let Some(retention) = self.retention {
+            if let Err(e) = self.remove_expired_jobs(retention).await {
+                warn!("Failed to remove expired jobs from the job history: {e:?}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_job(&self, job_id: &str) -> Result<Option<protobuf::ExecutionGraph>> {
+        match self.read(&self.job_path(job_id)).await {
+            Ok(graph) => Ok(Some(graph)),
+            Err(BallistaError::DataFusionError(DataFusionError::ObjectStore(
+                object_store::Error::NotFound { .. },
+            ))) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_jobs(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<protobuf::JobHistorySummary>> {
+        let mut jobs = vec![];
+        for (queued_at, job_id) in
+            self.list_jobs().await?.into_iter().skip(offset).take(limit)
+        {
+            jobs.push(self.read(&self.summary_path(&job_id, queued_at)).await?);
+        }
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn graph(job_id: &str, queued_at: u64) -> protobuf::ExecutionGraph {
+        protobuf::ExecutionGraph {
+            job_id: job_id.to_owned(),
+            queued_at,
+            ..Default::default()
+        }
+    }
+
+    async fn job_ids(
+        history: &ObjectStoreJobHistory,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        Ok(history
+            .get_jobs(offset, limit)
+            .await?
+            .into_iter()
+            .map(|summary| summary.job_id)
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_archive_jobs() -> Result<()> {
+        let dir = TempDir::new()?;
+        let location = dir.path().join("history");
+        let history = ObjectStoreJobHistory::try_new(location.to_str().unwrap(), None)?;
+
+        history.archive_job(graph("job-1", 1)).await?;
+        history.archive_job(graph("job-2", 2)).await?;
+        history.archive_job(graph("job-3", 3)).await?;
+        assert_eq!(Some(graph("job-1", 1)), history.get_job("job-1").await?);
+        assert_eq!(None, history.get_job("job-4").await?);
+
+        // the history survives a restart of the scheduler
+        let history = ObjectStoreJobHistory::try_new(location.to_str().unwrap(), None)?;
+        assert_eq!(
+            vec!["job-3", "job-2", "job-1"],
+            job_ids(&history, 0, 10).await?
+        );
+        assert_eq!(vec!["job-2"], job_ids(&history, 1, 1).await?);
+        assert!(job_ids(&history, 3, 10).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_expired_jobs() -> Result<()> {
+        let dir = TempDir::new()?;
+        let history = ObjectStoreJobHistory::try_new(
+            dir.path().to_str().unwrap(),
+            Some(Duration::from_secs(3600)),
+        )?;
+        let now = timestamp_millis();
+
+        history.archive_job(graph("job-1", now - 7_200_000)).await?;
+        history.archive_job(graph("job-2", now)).await?;
+        assert_eq!(vec!["job-2"], job_ids(&history, 0, 10).await?);
+        assert_eq!(None, history.get_job("job-1").await?);
+        assert!(history.get_job("job-2").await?.is_some());
+        Ok(())
+    }
+}
@@ -20,23 +20,28 @@ use datafusion::datasource::listing::{ListingTable, ListingTableUrl};
 use datafusion::datasource::source_as_provider;
 use datafusion::error::DataFusionError;
 use std::any::type_name;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
+use crate::scheduler_server::timestamp_millis;
 
+use crate::state::admission_controller::AdmissionController;
 use crate::state::executor_manager::ExecutorManager;
+use crate::state::job_history::{JobHistoryStore, ObjectStoreJobHistory};
+use crate::state::result_cache::ResultCache;
 use crate::state::session_manager::SessionManager;
 use crate::state::task_manager::{TaskLauncher, TaskManager};
 
-use crate::cluster::{BallistaCluster, BoundTask, ExecutorSlot};
+use crate::cluster::{BallistaCluster, BoundTask};
 use crate::config::SchedulerConfig;
-use crate::state::execution_graph::TaskDescription;
+use crate::state::execution_graph::{ExecutionGraph, JobProperties, TaskDescription};
 use ballista_core::error::{BallistaError, Result};
 use ballista_core::event_loop::EventSender;
-use ballista_core::serde::protobuf::TaskStatus;
+use ballista_core::serde::protobuf::{job_status, JobStatus, TaskStatus};
 use ballista_core::serde::BallistaCodec;
+use ballista_core::telemetry;
 use datafusion::logical_expr::LogicalPlan;
 use datafusion::physical_plan::display::DisplayableExecutionPlan;
 use datafusion::prelude::SessionContext;
@@ -44,10 +49,14 @@ use datafusion_proto::logical_plan::AsLogicalPlan;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 use log::{debug, error, info, warn};
 use prost::Message;
+use tracing::{info_span, Instrument};
 
+pub mod admission_controller;
 pub mod execution_graph;
 pub mod execution_graph_dot;
 pub mod executor_manager;
+pub mod job_history;
+pub mod result_cache;
 pub mod session_manager;
 pub mod task_manager;
 
@@ -90,10 +99,32 @@ pub struct SchedulerState<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPl
     pub executor_manager: ExecutorManager,
     pub task_manager: TaskManager<T, U>,
     pub session_manager: SessionManager,
+    pub admission_controller: AdmissionController,
+    pub result_cache: ResultCache,
+    pub job_history: Option<Arc<dyn JobHistoryStore>>,
     pub codec: BallistaCodec<T, U>,
     pub config: Arc<SchedulerConfig>,
 }
 
+/// Create the job history store of the configured location, if any
+fn job_history(config: &SchedulerConfig) -> Option<Arc<dyn JobHistoryStore>> {
+    let location = config.job_history_location.as_ref()?;
+    let retention = match config.job_history_retention_seconds {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    };
+    match ObjectStoreJobHistory::try_new(location, retention) {
+        Ok(job_history) => {
+            info!("Archiving finished jobs to {}", location);
+            Some(Arc::new(job_history))
+        }
+        Err(e) => {
+            error!("Failed to create the job history at {}: {:?}", location, e);
+            None
+        }
+    }
+}
+
 impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T, U> {
     pub fn new(
         cluster: BallistaCluster,
@@ -112,6 +143,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
                 scheduler_name,
             ),
             session_manager: SessionManager::new(cluster.job_state()),
+            admission_controller: AdmissionController::new(&config),
+            result_cache: ResultCache::default(),
+            job_history: job_history(&config),
             codec,
             config,
         }
@@ -146,6 +180,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
                 dispatcher,
             ),
             session_manager: SessionManager::new(cluster.job_state()),
+            admission_controller: AdmissionController::new(&config),
+            result_cache: ResultCache::default(),
+            job_history: job_history(&config),
             codec,
             config,
         }
@@ -155,13 +192,63 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         self.executor_manager.init().await
     }
 
+    /// Return the resources reserved by the given tasks, as (job id, task id), of an
+    /// executor. The reserved resources are recomputed from the properties of the jobs,
+    /// so that tasks bound by another scheduler or before a restart are fully released.
+    pub(crate) async fn unbind_tasks(
+        &self,
+        executor_id: &str,
+        tasks: Vec<(String, usize)>,
+    ) -> Result<()> {
+        let mut job_resources = HashMap::new();
+        let mut resources = vec![];
+        for (job_id, _) in tasks {
+            if !job_resources.contains_key(&job_id) {
+                let task_resources = self.task_manager.get_task_resources(&job_id).await;
+                job_resources.insert(job_id.clone(), task_resources);
+            }
+            resources.push(job_resources[&job_id]);
+        }
+        self.executor_manager
+            .unbind_tasks(executor_id, resources.into_iter().sum())
+            .await
+    }
+
+    /// Abort the running jobs which are past their timeouts. The timer started when a job
+    /// is planned is lost when the scheduler restarts, so the deadlines of the jobs are
+    /// also checked whenever tasks are bound.
+    pub(crate) async fn time_out_jobs(
+        &self,
+        sender: &EventSender<QueryStageSchedulerEvent>,
+    ) -> Result<()> {
+        let timed_out_jobs = self
+            .task_manager
+            .get_timed_out_jobs(timestamp_millis())
+            .await;
+        for (job_id, timeout_seconds, queued_at) in timed_out_jobs {
+            sender
+                .post_event(QueryStageSchedulerEvent::JobTimedOut {
+                    job_id,
+                    timeout_seconds,
+                    queued_at,
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
     pub(crate) async fn revive_offers(
         &self,
         sender: EventSender<QueryStageSchedulerEvent>,
     ) -> Result<()> {
+        self.time_out_jobs(&sender).await?;
         let schedulable_tasks = self
             .executor_manager
-            .bind_schedulable_tasks(self.task_manager.get_running_job_cache())
+            .bind_schedulable_tasks(
+                self.task_manager
+                    .get_running_job_cache(&self.config.job_scheduling)
+                    .await,
+            )
             .await?;
         if schedulable_tasks.is_empty() {
             warn!("No schedulable tasks found to be launched");
@@ -172,13 +259,9 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         tokio::spawn(async move {
             let mut if_revive = false;
             match state.launch_tasks(schedulable_tasks).await {
-                Ok(unassigned_executor_slots) => {
-                    if !unassigned_executor_slots.is_empty() {
-                        if let Err(e) = state
-                            .executor_manager
-                            .unbind_tasks(unassigned_executor_slots)
-                            .await
-                        {
+                Ok(unassigned_executor_tasks) => {
+                    for (executor_id, tasks) in unassigned_executor_tasks {
+                        if let Err(e) = state.unbind_tasks(&executor_id, tasks).await {
                             error!("Fail to unbind tasks: {}", e);
                         }
                         if_revive = true;
@@ -242,11 +325,12 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
     /// 1. Firstly reorganize according to: executor -> job stage -> tasks;
     /// 2. Then launch the task set vector to each executor one by one.
     ///
-    /// If it fails to launch a task set, the related [`ExecutorSlot`] will be returned.
+    /// If it fails to launch a task set, the executor id and the job id and task id of
+    /// each of its tasks will be returned.
     async fn launch_tasks(
         &self,
         bound_tasks: Vec<BoundTask>,
-    ) -> Result<Vec<ExecutorSlot>> {
+    ) -> Result<Vec<(String, Vec<(String, usize)>)>> {
         // Put tasks to the same executor together
         // And put tasks belonging to the same stage together for creating MultiTaskDefinition
         let mut executor_stage_assignments: HashMap<
@@ -274,8 +358,12 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         let mut join_handles = vec![];
         for (executor_id, tasks) in executor_stage_assignments.into_iter() {
             let tasks: Vec<Vec<TaskDescription>> = tasks.into_values().collect();
-            // Total number of tasks to be launched for one executor
-            let n_tasks: usize = tasks.iter().map(|stage_tasks| stage_tasks.len()).sum();
+            // All the tasks to be launched for one executor
+            let task_ids: Vec<(String, usize)> = tasks
+                .iter()
+                .flatten()
+                .map(|task| (task.partition.job_id.clone(), task.task_id))
+                .collect();
 
             let state = self.clone();
             let join_handle = tokio::spawn(async move {
@@ -308,27 +396,20 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
                     }
                 };
                 if success {
-                    vec![]
+                    None
                 } else {
-                    vec![(executor_id.clone(), n_tasks as u32)]
+                    Some((executor_id, task_ids))
                 }
             });
             join_handles.push(join_handle);
         }
 
-        let unassigned_executor_slots =
-            futures::future::join_all(join_handles)
-                .await
-                .into_iter()
-                .collect::<std::result::Result<
-                    Vec<Vec<ExecutorSlot>>,
-                    tokio::task::JoinError,
-                >>()?;
-
-        Ok(unassigned_executor_slots
+        let unassigned_executor_tasks = futures::future::join_all(join_handles)
+            .await
             .into_iter()
-            .flatten()
-            .collect::<Vec<ExecutorSlot>>())
+            .collect::<std::result::Result<Vec<_>, tokio::task::JoinError>>()?;
+
+        Ok(unassigned_executor_tasks.into_iter().flatten().collect())
     }
 
     pub(crate) async fn update_task_statuses(
@@ -350,6 +431,26 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         &self,
         job_id: &str,
         job_name: &str,
+        mut properties: JobProperties,
+        session_ctx: Arc<SessionContext>,
+        plan: &LogicalPlan,
+        queued_at: u64,
+    ) -> Result<()> {
+        let span = info_span!(parent: None, "job", job_id, job_name);
+        telemetry::set_parent(&span, &properties.trace_context);
+        // the spans of the stages of the job are children of the span of the job
+        properties.trace_context = telemetry::trace_context(&span);
+
+        self.plan_job(job_id, job_name, properties, session_ctx, plan, queued_at)
+            .instrument(span)
+            .await
+    }
+
+    async fn plan_job(
+        &self,
+        job_id: &str,
+        job_name: &str,
+        properties: JobProperties,
         session_ctx: Arc<SessionContext>,
         plan: &LogicalPlan,
         queued_at: u64,
@@ -409,7 +510,14 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         );
 
         self.task_manager
-            .submit_job(job_id, job_name, &session_ctx.session_id(), plan, queued_at)
+            .submit_job(
+                job_id,
+                job_name,
+                properties,
+                &session_ctx.session_id(),
+                plan,
+                queued_at,
+            )
             .await?;
 
         let elapsed = start.elapsed();
@@ -419,8 +527,82 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
         Ok(())
     }
 
+    /// Get the status of a job, with the queue position of a job waiting to run
+    pub(crate) async fn get_job_status(&self, job_id: &str) -> Result<Option<JobStatus>> {
+        let mut status = self.task_manager.get_job_status(job_id).await?;
+        if let Some(JobStatus {
+            status: Some(job_status::Status::Queued(queued)),
+            ..
+        }) = status.as_mut()
+        {
+            queued.position = self
+                .admission_controller
+                .queue_position(job_id)
+                .unwrap_or_default() as u32;
+        }
+        Ok(status)
+    }
+
+    /// Cache the results of a successful job whose plan is watched by the result cache,
+    /// until the data of the job is cleaned up
+    pub(crate) async fn cache_job_result(&self, job_id: &str) -> Result<()> {
+        if !self.result_cache.is_watched(job_id) {
+            return Ok(());
+        }
+        if let Some(JobStatus {
+            status: Some(job_status::Status::Successful(successful)),
+            ..
+        }) = self.task_manager.get_job_status(job_id).await?
+        {
+            let executors: HashSet<String> = successful
+                .partition_location
+                .iter()
+                .filter_map(|location| location.executor_meta.as_ref())
+                .map(|executor| executor.id.clone())
+                .collect();
+            let ttl = [
+                self.config.finished_job_data_clean_up_interval_seconds,
+                self.config.finished_job_state_clean_up_interval_seconds,
+            ]
+            .into_iter()
+            .filter(|seconds| *seconds > 0)
+            .min()
+            .map(Duration::from_secs);
+            self.result_cache.insert(job_id, executors, ttl);
+        }
+        Ok(())
+    }
+
+    /// Spawn a future to archive the execution graph of a finished job to the job history
+    fn archive_job(&self, job_id: &str) {
+        let job_history = match self.job_history.clone() {
+            Some(job_history) => job_history,
+            None => return,
+        };
+        let task_manager = self.task_manager.clone();
+        let codec = self.codec.clone();
+        let job_id = job_id.to_owned();
+        tokio::spawn(async move {
+            let archived = async {
+                if let Some(graph) = task_manager.get_job_execution_graph(&job_id).await?
+                {
+                    let graph = ExecutionGraph::encode_execution_graph(
+                        graph.as_ref().clone(),
+                        &codec,
+                    )?;
+                    job_history.archive_job(graph).await?;
+                }
+                Ok::<_, BallistaError>(())
+            };
+            if let Err(e) = archived.await {
+                error!("Failed to archive job {job_id}: {e:?}");
+            }
+        });
+    }
+
     /// Spawn a delayed future to clean up job data on both Scheduler and Executors
     pub(crate) fn clean_up_successful_job(&self, job_id: String) {
+        self.archive_job(&job_id);
         self.executor_manager.clean_up_job_data_delayed(
             job_id.clone(),
             self.config.finished_job_data_clean_up_interval_seconds,
@@ -433,6 +615,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> SchedulerState<T,
 
     /// Spawn a delayed future to clean up job data on both Scheduler and Executors
     pub(crate) fn clean_up_failed_job(&self, job_id: String) {
+        self.result_cache.remove_job(&job_id);
+        self.archive_job(&job_id);
         self.executor_manager.clean_up_job_data(job_id.clone());
         self.task_manager.clean_up_job_delayed(
             job_id,
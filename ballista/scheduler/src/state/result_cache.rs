@@ -0,0 +1,308 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ballista_core::error::Result;
+use datafusion::common::tree_node::{TreeNode, VisitRecursion};
+use datafusion::datasource::listing::ListingTable;
+use datafusion::datasource::source_as_provider;
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::expr::{ScalarFunction, ScalarUDF};
+use datafusion::logical_expr::{Expr, LogicalPlan, Volatility};
+use datafusion::prelude::SessionContext;
+use futures::TryStreamExt;
+use log::debug;
+use object_store::ObjectMeta;
+use parking_lot::Mutex;
+
+/// The optimized plan of a job and the path, size and modification time in milliseconds
+/// of the files read by the plan. The whole key is compared when looking up results, so
+/// that plans whose keys only hash the same never share results.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResultCacheKey {
+    plan: String,
+    files: Vec<(String, usize, i64)>,
+}
+
+/// The results of a successful job which can be reused by jobs running the same plan
+struct CachedResult {
+    job_id: String,
+    /// Executors holding the output partitions of the job
+    executors: HashSet<String>,
+    /// When the output partitions of the job are cleaned up
+    expires_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct ResultCacheState {
+    results: HashMap<ResultCacheKey, CachedResult>,
+    /// Key of the plan of every running job whose results are to be cached
+    running: HashMap<String, ResultCacheKey>,
+}
+
+/// Cache of the results of successful jobs, keyed on their optimized logical plan and
+/// the metadata of the files they read.
+///
+/// The output partitions of the jobs are not copied, so a result is evicted once the
+/// data of its job is cleaned up or lost.
+///
+/// The cache is kept in the memory of each scheduler and isn't shared through the
+/// cluster state, so a job is only answered from the cache of the scheduler it is
+/// submitted to, and the cache is empty after a restart.
+#[derive(Clone, Default)]
+pub struct ResultCache {
+    state: Arc<Mutex<ResultCacheState>>,
+}
+
+impl ResultCache {
+    /// Get the id of the job whose results answer a plan with the given key
+    pub fn get(&self, key: &ResultCacheKey) -> Option<String> {
+        let mut state = self.state.lock();
+        let expired = match state.results.get(key) {
+            Some(result) => result
+                .expires_at
+                .map(|expires_at| expires_at <= Instant::now())
+                .unwrap_or(false),
+            None => return None,
+        };
+        if expired {
+            state.results.remove(key);
+            return None;
+        }
+        state.results.get(key).map(|result| result.job_id.clone())
+    }
+
+    /// Remember the key of the plan of a submitted job, whose results are cached once
+    /// the job succeeds
+    pub fn watch(&self, job_id: &str, key: ResultCacheKey) {
+        self.state.lock().running.insert(job_id.to_owned(), key);
+    }
+
+    pub fn is_watched(&self, job_id: &str) -> bool {
+        self.state.lock().running.contains_key(job_id)
+    }
+
+    /// Cache the results of a watched job which succeeded. They are evicted after `ttl`,
+    /// when the data of the job is cleaned up.
+    pub fn insert(
+        &self,
+        job_id: &str,
+        executors: HashSet<String>,
+        ttl: Option<Duration>,
+    ) {
+        let mut state = self.state.lock();
+        if let Some(key) = state.running.remove(job_id) {
+            state.results.insert(
+                key,
+                CachedResult {
+                    job_id: job_id.to_owned(),
+                    executors,
+                    expires_at: ttl.map(|ttl| Instant::now() + ttl),
+                },
+            );
+        }
+    }
+
+    /// Forget a job, because it failed or its data has been cleaned up
+    pub fn remove_job(&self, job_id: &str) {
+        let mut state = self.state.lock();
+        state.running.remove(job_id);
+        state.results.retain(|_, result| result.job_id != job_id);
+    }
+
+    /// Evict the results of the jobs whose output partitions were on a lost executor
+    pub fn remove_executor(&self, executor_id: &str) {
+        self.state
+            .lock()
+            .results
+            .retain(|_, result| !result.executors.contains(executor_id));
+    }
+}
+
+/// Compute the result cache key of a plan, made of the optimized plan and the path, size
+/// and modification time of the files read by the plan.
+///
+/// Returns `None` if the results of the plan can't be cached, because it modifies data,
+/// calls volatile functions or reads tables which are not listing tables.
+pub async fn result_cache_key(
+    session_ctx: &SessionContext,
+    plan: &LogicalPlan,
+) -> Result<Option<ResultCacheKey>> {
+    let mut cacheable = true;
+    let mut tables = vec![];
+    plan.apply(&mut |plan| {
+        match plan {
+            LogicalPlan::Dml(_)
+            | LogicalPlan::Ddl(_)
+            | LogicalPlan::Copy(_)
+            | LogicalPlan::Statement(_)
+            | LogicalPlan::Extension(_)
+            | LogicalPlan::Explain(_)
+            | LogicalPlan::Analyze(_) => cacheable = false,
+            LogicalPlan::TableScan(scan) => {
+                let provider = source_as_provider(&scan.source)?;
+                match provider.as_any().downcast_ref::<ListingTable>() {
+                    Some(table) => tables.extend(table.table_paths().iter().cloned()),
+                    None => cacheable = false,
+                }
+            }
+            _ => {}
+        }
+        for expr in plan.expressions() {
+            expr.apply(&mut |expr| {
+                let volatility = match expr {
+                    Expr::ScalarFunction(ScalarFunction { fun, .. }) => fun.volatility(),
+                    Expr::ScalarUDF(ScalarUDF { fun, .. }) => fun.signature.volatility,
+                    _ => return Ok(VisitRecursion::Continue),
+                };
+                if volatility == Volatility::Volatile {
+                    cacheable = false;
+                }
+                Ok(VisitRecursion::Continue)
+            })?;
+        }
+        Ok(if cacheable {
+            VisitRecursion::Continue
+        } else {
+            VisitRecursion::Stop
+        })
+    })?;
+    if !cacheable {
+        debug!("The results of plan {:?} can't be cached", plan);
+        return Ok(None);
+    }
+
+    let optimized_plan = session_ctx.state().optimize(plan)?;
+
+    let mut files: Vec<ObjectMeta> = vec![];
+    for table_path in tables {
+        let store = session_ctx.runtime_env().object_store(&table_path)?;
+        if table_path.as_str().ends_with('/') {
+            let listed = store
+                .list(Some(table_path.prefix()))
+                .await
+                .map_err(DataFusionError::from)?;
+            files.extend(
+                listed
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .map_err(DataFusionError::from)?,
+            );
+        } else {
+            let file = store
+                .head(table_path.prefix())
+                .await
+                .map_err(DataFusionError::from)?;
+            files.push(file);
+        }
+    }
+    files.sort_by(|a, b| a.location.cmp(&b.location));
+
+    Ok(Some(ResultCacheKey {
+        plan: format!("{}", optimized_plan.display_indent_schema()),
+        files: files
+            .into_iter()
+            .map(|file| {
+                (
+                    file.location.to_string(),
+                    file.size,
+                    file.last_modified.timestamp_millis(),
+                )
+            })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::CsvReadOptions;
+    use std::fs;
+    use tempfile::TempDir;
+
+    async fn plan_key(ctx: &SessionContext, sql: &str) -> Result<Option<ResultCacheKey>> {
+        let plan = ctx.sql(sql).await?.into_unoptimized_plan();
+        result_cache_key(ctx, &plan).await
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_key() -> Result<()> {
+        let dir = TempDir::new()?;
+        fs::write(dir.path().join("a.csv"), "a,b\n1,2\n")?;
+        let ctx = SessionContext::new();
+        ctx.register_csv(
+            "t",
+            &format!("{}/", dir.path().to_str().unwrap()),
+            CsvReadOptions::new(),
+        )
+        .await?;
+
+        let key = plan_key(&ctx, "SELECT a FROM t WHERE b > 1").await?;
+        assert!(key.is_some());
+        assert_eq!(key, plan_key(&ctx, "SELECT a FROM t WHERE b > 1").await?);
+        assert_ne!(key, plan_key(&ctx, "SELECT a FROM t WHERE b > 2").await?);
+        assert_eq!(None, plan_key(&ctx, "SELECT a, random() FROM t").await?);
+
+        // adding a file changes the key
+        fs::write(dir.path().join("b.csv"), "a,b\n3,4\n")?;
+        assert_ne!(key, plan_key(&ctx, "SELECT a FROM t WHERE b > 1").await?);
+        Ok(())
+    }
+
+    fn key(plan: &str, files: &[&str]) -> ResultCacheKey {
+        ResultCacheKey {
+            plan: plan.to_owned(),
+            files: files.iter().map(|file| (file.to_string(), 1, 0)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let cache = ResultCache::default();
+        cache.watch("job-1", key("plan-1", &["a.csv"]));
+        cache.watch("job-2", key("plan-2", &["a.csv"]));
+        assert!(cache.is_watched("job-1"));
+        assert_eq!(None, cache.get(&key("plan-1", &["a.csv"])));
+
+        cache.insert("job-1", HashSet::from(["executor-1".to_owned()]), None);
+        cache.insert("job-2", HashSet::from(["executor-2".to_owned()]), None);
+        assert!(!cache.is_watched("job-1"));
+        assert_eq!(
+            Some("job-1".to_owned()),
+            cache.get(&key("plan-1", &["a.csv"]))
+        );
+        assert_eq!(
+            Some("job-2".to_owned()),
+            cache.get(&key("plan-2", &["a.csv"]))
+        );
+        // the same plan over other files misses
+        assert_eq!(None, cache.get(&key("plan-1", &["a.csv", "b.csv"])));
+
+        cache.remove_job("job-1");
+        assert_eq!(None, cache.get(&key("plan-1", &["a.csv"])));
+        cache.remove_executor("executor-2");
+        assert_eq!(None, cache.get(&key("plan-2", &["a.csv"])));
+
+        // results expire when the data of their job is cleaned up
+        cache.watch("job-3", key("plan-3", &[]));
+        cache.insert("job-3", HashSet::new(), Some(Duration::ZERO));
+        assert_eq!(None, cache.get(&key("plan-3", &[])));
+    }
+}
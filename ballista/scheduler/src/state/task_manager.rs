@@ -18,17 +18,20 @@
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 
 use crate::state::execution_graph::{
-    ExecutionGraph, ExecutionStage, RunningTaskInfo, TaskDescription,
+    ExecutionGraph, ExecutionStage, JobProperties, RunningTaskInfo, TaskDescription,
 };
 use crate::state::executor_manager::ExecutorManager;
 
 use ballista_core::error::BallistaError;
 use ballista_core::error::Result;
 
-use crate::cluster::JobState;
+use crate::cluster::{
+    order_active_jobs, ActiveJobs, JobState, JobStateEventStream, TaskResources,
+};
+use crate::config::{JobSchedulingPolicy, SpeculationConfig};
 use ballista_core::serde::protobuf::{
-    job_status, JobStatus, KeyValuePair, MultiTaskDefinition, TaskDefinition, TaskId,
-    TaskStatus,
+    job_status, JobProgress, JobStatus, KeyValuePair, MultiTaskDefinition,
+    TaskDefinition, TaskId, TaskStatus,
 };
 use ballista_core::serde::scheduler::ExecutorMetadata;
 use ballista_core::serde::BallistaCodec;
@@ -47,7 +50,9 @@ use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
-use ballista_core::config::BALLISTA_DATA_CACHE_ENABLED;
+use ballista_core::config::{
+    BALLISTA_DATA_CACHE_ENABLED, BALLISTA_TASK_MEMORY, BALLISTA_TASK_TIMEOUT_SECONDS,
+};
 use tracing::trace;
 
 type ActiveJobCache = Arc<DashMap<String, JobInfoCache>>;
@@ -126,6 +131,11 @@ pub struct JobInfoCache {
     pub execution_graph: Arc<RwLock<ExecutionGraph>>,
     // Cache for job status
     pub status: Option<job_status::Status>,
+    // Cache for the properties deciding the order in which jobs are scheduled
+    pub properties: JobProperties,
+    pub queued_at: u64,
+    // Weight of the job's pool when the jobs are scheduled by the fair policy
+    pub pool_weight: Option<u32>,
     // Cache for encoded execution stage plan to avoid duplicated encoding for multiple tasks
     encoded_stage_plans: HashMap<usize, Vec<u8>>,
 }
@@ -133,9 +143,14 @@ pub struct JobInfoCache {
 impl JobInfoCache {
     pub fn new(graph: ExecutionGraph) -> Self {
         let status = graph.status().status.clone();
+        let properties = graph.properties().clone();
+        let queued_at = graph.queued_at();
         Self {
             execution_graph: Arc::new(RwLock::new(graph)),
             status,
+            properties,
+            queued_at,
+            pool_weight: None,
             encoded_stage_plans: HashMap::new(),
         }
     }
@@ -204,6 +219,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
         &self,
         job_id: &str,
         job_name: &str,
+        properties: JobProperties,
         session_id: &str,
         plan: Arc<dyn ExecutionPlan>,
         queued_at: u64,
@@ -215,6 +231,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
             session_id,
             plan,
             queued_at,
+            properties,
         )?;
         info!("Submitting execution graph: {:?}", graph);
 
@@ -227,8 +244,12 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
         Ok(())
     }
 
-    pub fn get_running_job_cache(&self) -> Arc<HashMap<String, JobInfoCache>> {
-        let ret = self
+    /// Get the running jobs, ordered by the job scheduling policy
+    pub async fn get_running_job_cache(
+        &self,
+        policy: &JobSchedulingPolicy,
+    ) -> ActiveJobs {
+        let jobs = self
             .active_job_cache
             .iter()
             .filter_map(|pair| {
@@ -239,8 +260,46 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                     None
                 }
             })
-            .collect::<HashMap<_, _>>();
-        Arc::new(ret)
+            .collect::<Vec<_>>();
+        Arc::new(order_active_jobs(jobs, policy).await)
+    }
+
+    /// Mark the straggler tasks of the active jobs for speculative execution.
+    /// Returns the number of newly marked tasks
+    pub async fn speculate_tasks(&self, config: &SpeculationConfig) -> usize {
+        let graphs = self
+            .active_job_cache
+            .iter()
+            .map(|job_info| job_info.execution_graph.clone())
+            .collect::<Vec<_>>();
+        let mut speculated = 0;
+        for graph in graphs {
+            speculated += graph.write().await.speculate_tasks(config);
+        }
+        speculated
+    }
+
+    /// Get the active jobs which are still running after their timeouts at the given time
+    /// in milliseconds, as (job id, timeout in seconds, queued at)
+    pub(crate) async fn get_timed_out_jobs(&self, now: u64) -> Vec<(String, u64, u64)> {
+        let graphs = self
+            .active_job_cache
+            .iter()
+            .filter(|job_info| job_info.properties.timeout_seconds > 0)
+            .map(|job_info| job_info.execution_graph.clone())
+            .collect::<Vec<_>>();
+        let mut timed_out = vec![];
+        for graph in graphs {
+            let graph = graph.read().await;
+            if graph.is_timed_out(now) {
+                timed_out.push((
+                    graph.job_id().to_owned(),
+                    graph.properties().timeout_seconds,
+                    graph.queued_at(),
+                ));
+            }
+        }
+        timed_out
     }
 
     /// Get a list of active job ids
@@ -269,12 +328,38 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
         if let Some(graph) = self.get_active_execution_graph(job_id) {
             let guard = graph.read().await;
 
-            Ok(Some(guard.status().clone()))
+            let mut status = guard.status().clone();
+            if let Some(job_status::Status::Running(running)) = status.status.as_mut() {
+                running.progress = Some(guard.progress());
+            }
+            Ok(Some(status))
         } else {
             self.state.get_job_status(job_id).await
         }
     }
 
+    /// Get the resources reserved by each task of a job, from the properties of the job.
+    /// If the job is no longer found, its tasks are assumed to only hold a task slot.
+    pub(crate) async fn get_task_resources(&self, job_id: &str) -> TaskResources {
+        if let Some(job_info) = self.active_job_cache.get(job_id) {
+            return TaskResources::for_task(&job_info.properties);
+        }
+        match self.state.get_execution_graph(job_id).await {
+            Ok(Some(graph)) => return TaskResources::for_task(graph.properties()),
+            Ok(None) => warn!("Job {job_id} not found when getting its task resources"),
+            Err(e) => warn!("Fail to get job {job_id} for its task resources: {e}"),
+        }
+        TaskResources {
+            slots: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Subscribe to the status changes of all jobs in the cluster
+    pub async fn job_state_events(&self) -> Result<JobStateEventStream> {
+        self.state.job_state_events().await
+    }
+
     /// Get the execution graph of of a job. First look in the active cache.
     /// If no one found, then in the Active/Completed jobs.
     pub(crate) async fn get_job_execution_graph(
@@ -400,6 +485,41 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
         Ok((tasks_to_cancel, pending_tasks))
     }
 
+    /// Abort a job which is still running after its timeout and return the running
+    /// tasks to cancel, or `None` if the job has already finished
+    pub(crate) async fn time_out_job(
+        &self,
+        job_id: &str,
+        timeout_seconds: u64,
+    ) -> Result<Option<Vec<RunningTaskInfo>>> {
+        let running = match self.get_active_execution_graph(job_id) {
+            Some(graph) => matches!(
+                graph.read().await.status().status,
+                Some(job_status::Status::Running(_))
+            ),
+            None => false,
+        };
+        if !running {
+            return Ok(None);
+        }
+
+        match self.remove_active_execution_graph(job_id) {
+            Some(graph) => {
+                let mut guard = graph.write().await;
+                let running_tasks = guard.running_tasks();
+                info!(
+                    "Cancelling {} running tasks for timed out job {}",
+                    running_tasks.len(),
+                    job_id
+                );
+                guard.time_out_job(timeout_seconds);
+                self.state.save_job(job_id, &guard).await?;
+                Ok(Some(running_tasks))
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Mark a unscheduled job as failed. This will create a key under the FailedJobs keyspace
     /// and remove the job from ActiveJobs or QueuedJobs
     pub async fn fail_unscheduled_job(
@@ -496,7 +616,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                 plan_buf
             };
 
-            let mut props = vec![];
+            let mut props = task_props(&job_info.properties);
             if task.data_cache {
                 props.push(KeyValuePair {
                     key: BALLISTA_DATA_CACHE_ENABLED.to_string(),
@@ -518,6 +638,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                     .unwrap()
                     .as_millis() as u64,
                 props,
+                trace_context: trace_context_pairs(task.trace_context),
             };
             Ok(task_definition)
         } else {
@@ -562,6 +683,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
             let job_id = task.partition.job_id.clone();
             let stage_id = task.partition.stage_id;
             let stage_attempt_num = task.stage_attempt_num;
+            // the tasks of a stage are children of the span of the stage
+            let trace_context = trace_context_pairs(task.trace_context.clone());
 
             if log::max_level() >= log::Level::Debug {
                 let task_ids: Vec<usize> = tasks
@@ -598,6 +721,7 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
 
                 let (tasks_with_data_cache, tasks_without_data_cache): (Vec<_>, Vec<_>) =
                     tasks.into_iter().partition(|task| task.data_cache);
+                let props = task_props(&job_info.properties);
 
                 let mut multi_tasks = vec![];
                 if !tasks_with_data_cache.is_empty() {
@@ -609,6 +733,11 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                             partition_id: task.partition.partition_id as u32,
                         })
                         .collect();
+                    let mut props = props.clone();
+                    props.push(KeyValuePair {
+                        key: BALLISTA_DATA_CACHE_ENABLED.to_string(),
+                        value: "true".to_string(),
+                    });
                     multi_tasks.push(MultiTaskDefinition {
                         task_ids,
                         job_id: job_id.clone(),
@@ -617,10 +746,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                         plan: plan.clone(),
                         session_id: session_id.clone(),
                         launch_time,
-                        props: vec![KeyValuePair {
-                            key: BALLISTA_DATA_CACHE_ENABLED.to_string(),
-                            value: "true".to_string(),
-                        }],
+                        props,
+                        trace_context: trace_context.clone(),
                     });
                 }
                 if !tasks_without_data_cache.is_empty() {
@@ -640,7 +767,8 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
                         plan,
                         session_id,
                         launch_time,
-                        props: vec![],
+                        props,
+                        trace_context,
                     });
                 }
 
@@ -704,14 +832,41 @@ impl<T: 'static + AsLogicalPlan, U: 'static + AsExecutionPlan> TaskManager<T, U>
     }
 }
 
+/// Properties of the tasks of a job which are needed by the executors to run them
+fn trace_context_pairs(trace_context: HashMap<String, String>) -> Vec<KeyValuePair> {
+    trace_context
+        .into_iter()
+        .map(|(key, value)| KeyValuePair { key, value })
+        .collect()
+}
+
+fn task_props(properties: &JobProperties) -> Vec<KeyValuePair> {
+    let mut props = vec![];
+    if properties.task_memory > 0 {
+        props.push(KeyValuePair {
+            key: BALLISTA_TASK_MEMORY.to_string(),
+            value: properties.task_memory.to_string(),
+        });
+    }
+    if properties.task_timeout_seconds > 0 {
+        props.push(KeyValuePair {
+            key: BALLISTA_TASK_TIMEOUT_SECONDS.to_string(),
+            value: properties.task_timeout_seconds.to_string(),
+        });
+    }
+    props
+}
+
 pub struct JobOverview {
     pub job_id: String,
     pub job_name: String,
+    pub user: Option<String>,
     pub status: JobStatus,
     pub start_time: u64,
     pub end_time: u64,
     pub num_stages: usize,
     pub completed_stages: usize,
+    pub progress: JobProgress,
 }
 
 impl From<&ExecutionGraph> for JobOverview {
@@ -726,11 +881,13 @@ impl From<&ExecutionGraph> for JobOverview {
         Self {
             job_id: value.job_id().to_string(),
             job_name: value.job_name().to_string(),
+            user: value.user().map(str::to_string),
             status: value.status().clone(),
             start_time: value.start_time(),
             end_time: value.end_time(),
             num_stages: value.stage_count(),
             completed_stages,
+            progress: value.progress(),
         }
     }
 }
@@ -33,12 +33,13 @@ use crate::state::task_manager::TaskLauncher;
 
 use ballista_core::config::{BallistaConfig, BALLISTA_DEFAULT_SHUFFLE_PARTITIONS};
 use ballista_core::serde::protobuf::job_status::Status;
+use ballista_core::serde::protobuf::scheduler_grpc_server::SchedulerGrpc;
 use ballista_core::serde::protobuf::{
     task_status, FailedTask, JobStatus, MultiTaskDefinition, ShuffleWritePartition,
-    SuccessfulTask, TaskId, TaskStatus,
+    SuccessfulTask, TaskId, TaskStatus, WatchJobParams,
 };
 use ballista_core::serde::scheduler::{
-    ExecutorData, ExecutorMetadata, ExecutorSpecification,
+    ExecutorData, ExecutorMetadata, ExecutorMetrics, ExecutorSpecification,
 };
 use ballista_core::serde::{protobuf, BallistaCodec};
 use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
@@ -55,11 +56,12 @@ use datafusion::test_util::scan_empty;
 use crate::cluster::BallistaCluster;
 use crate::scheduler_server::event::QueryStageSchedulerEvent;
 
-use crate::state::execution_graph::{ExecutionGraph, TaskDescription};
+use crate::state::execution_graph::{ExecutionGraph, JobProperties, TaskDescription};
 use ballista_core::utils::default_session_builder;
 use datafusion_proto::protobuf::{LogicalPlanNode, PhysicalPlanNode};
 use parking_lot::Mutex;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tonic::Request;
 
 pub const TPCH_TABLES: &[&str] = &[
     "part", "supplier", "partsupp", "customer", "orders", "lineitem", "nation", "region",
@@ -129,6 +131,13 @@ pub fn test_cluster_context() -> BallistaCluster {
 pub async fn datafusion_test_context(path: &str) -> Result<SessionContext> {
     let default_shuffle_partitions = 2;
     let config = SessionConfig::new().with_target_partitions(default_shuffle_partitions);
+    datafusion_test_context_with_config(path, config).await
+}
+
+pub async fn datafusion_test_context_with_config(
+    path: &str,
+    config: SessionConfig,
+) -> Result<SessionContext> {
     let ctx = SessionContext::with_config(config);
     for table in TPCH_TABLES {
         let schema = get_tpch_schema(table);
@@ -277,6 +286,8 @@ pub fn default_task_runner() -> impl TaskRunner {
                 num_batches: 1,
                 num_rows: 1,
                 num_bytes: 1,
+                compression: String::default(),
+                index_path: String::default(),
             })
             .collect();
 
@@ -371,6 +382,9 @@ impl TaskLauncher for VirtualTaskLauncher {
     }
 }
 
+type WatchJobStream =
+    <SchedulerServer<LogicalPlanNode, PhysicalPlanNode> as SchedulerGrpc>::WatchJobStream;
+
 pub struct SchedulerTest {
     scheduler: SchedulerServer<LogicalPlanNode, PhysicalPlanNode>,
     ballista_config: BallistaConfig,
@@ -436,16 +450,14 @@ impl SchedulerTest {
                 host: String::default(),
                 port: 0,
                 grpc_port: 0,
-                specification: ExecutorSpecification {
-                    task_slots: task_slots as u32,
-                },
+                specification: ExecutorSpecification::new(task_slots as u32),
+                labels: Default::default(),
             };
 
-            let executor_data = ExecutorData {
+            let executor_data = ExecutorData::new(
                 executor_id,
-                total_task_slots: task_slots as u32,
-                available_task_slots: task_slots as u32,
-            };
+                ExecutorSpecification::new(task_slots as u32),
+            );
 
             scheduler
                 .state
@@ -492,7 +504,13 @@ impl SchedulerTest {
             .await?;
 
         self.scheduler
-            .submit_job(job_id, job_name, ctx, plan)
+            .submit_job(
+                job_id,
+                job_name,
+                JobProperties::from_config(&self.ballista_config),
+                ctx,
+                plan,
+            )
             .await?;
 
         Ok(())
@@ -527,6 +545,21 @@ impl SchedulerTest {
         Ok(())
     }
 
+    pub async fn job_status(&self, job_id: &str) -> Result<Option<JobStatus>> {
+        self.scheduler
+            .state
+            .task_manager
+            .get_job_status(job_id)
+            .await
+    }
+
+    pub async fn watch_job(&self, job_id: &str) -> Result<WatchJobStream> {
+        let request = Request::new(WatchJobParams {
+            job_id: job_id.to_owned(),
+        });
+        Ok(self.scheduler.watch_job(request).await?.into_inner())
+    }
+
     pub async fn cancel(&self, job_id: &str) -> Result<()> {
         self.scheduler
             .query_stage_event_loop
@@ -617,7 +650,13 @@ impl SchedulerTest {
             .await?;
 
         self.scheduler
-            .submit_job(job_id, job_name, ctx, plan)
+            .submit_job(
+                job_id,
+                job_name,
+                JobProperties::from_config(&self.ballista_config),
+                ctx,
+                plan,
+            )
             .await?;
 
         let mut receiver = self.status_receiver.take().unwrap();
@@ -732,6 +771,10 @@ impl SchedulerMetricsCollector for TestMetricsCollector {
 
     fn set_pending_tasks_queue_size(&self, _value: u64) {}
 
+    fn record_executor_metrics(&self, _executor_id: &str, _metrics: &ExecutorMetrics) {}
+
+    fn remove_executor_metrics(&self, _executor_id: &str) {}
+
     fn gather_metrics(&self) -> Result<Option<(Vec<u8>, String)>> {
         Ok(None)
     }
@@ -818,7 +861,16 @@ pub async fn test_aggregation_plan_with_job_id(
         DisplayableExecutionPlan::new(plan.as_ref()).indent(false)
     );
 
-    ExecutionGraph::new("localhost:50050", job_id, "", "session", plan, 0).unwrap()
+    ExecutionGraph::new(
+        "localhost:50050",
+        job_id,
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap()
 }
 
 pub async fn test_two_aggregations_plan(partition: usize) -> ExecutionGraph {
@@ -853,7 +905,16 @@ pub async fn test_two_aggregations_plan(partition: usize) -> ExecutionGraph {
         DisplayableExecutionPlan::new(plan.as_ref()).indent(false)
     );
 
-    ExecutionGraph::new("localhost:50050", "job", "", "session", plan, 0).unwrap()
+    ExecutionGraph::new(
+        "localhost:50050",
+        "job",
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap()
 }
 
 pub async fn test_coalesce_plan(partition: usize) -> ExecutionGraph {
@@ -880,7 +941,16 @@ pub async fn test_coalesce_plan(partition: usize) -> ExecutionGraph {
         .await
         .unwrap();
 
-    ExecutionGraph::new("localhost:50050", "job", "", "session", plan, 0).unwrap()
+    ExecutionGraph::new(
+        "localhost:50050",
+        "job",
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap()
 }
 
 pub async fn test_join_plan(partition: usize) -> ExecutionGraph {
@@ -928,8 +998,16 @@ pub async fn test_join_plan(partition: usize) -> ExecutionGraph {
         DisplayableExecutionPlan::new(plan.as_ref()).indent(false)
     );
 
-    let graph =
-        ExecutionGraph::new("localhost:50050", "job", "", "session", plan, 0).unwrap();
+    let graph = ExecutionGraph::new(
+        "localhost:50050",
+        "job",
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap();
 
     println!("{graph:?}");
 
@@ -960,8 +1038,16 @@ pub async fn test_union_all_plan(partition: usize) -> ExecutionGraph {
         DisplayableExecutionPlan::new(plan.as_ref()).indent(false)
     );
 
-    let graph =
-        ExecutionGraph::new("localhost:50050", "job", "", "session", plan, 0).unwrap();
+    let graph = ExecutionGraph::new(
+        "localhost:50050",
+        "job",
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap();
 
     println!("{graph:?}");
 
@@ -992,8 +1078,16 @@ pub async fn test_union_plan(partition: usize) -> ExecutionGraph {
         DisplayableExecutionPlan::new(plan.as_ref()).indent(false)
     );
 
-    let graph =
-        ExecutionGraph::new("localhost:50050", "job", "", "session", plan, 0).unwrap();
+    let graph = ExecutionGraph::new(
+        "localhost:50050",
+        "job",
+        "",
+        "session",
+        plan,
+        0,
+        JobProperties::default(),
+    )
+    .unwrap();
 
     println!("{graph:?}");
 
@@ -1006,7 +1100,8 @@ pub fn mock_executor(executor_id: String) -> ExecutorMetadata {
         host: "localhost2".to_string(),
         port: 8080,
         grpc_port: 9090,
-        specification: ExecutorSpecification { task_slots: 1 },
+        specification: ExecutorSpecification::new(1),
+        labels: Default::default(),
     }
 }
 
@@ -1027,6 +1122,8 @@ pub fn mock_completed_task(task: TaskDescription, executor_id: &str) -> TaskStat
             num_batches: 1,
             num_rows: 1,
             num_bytes: 1,
+            compression: String::default(),
+            index_path: String::default(),
         })
     }
 
@@ -1048,6 +1145,21 @@ pub fn mock_completed_task(task: TaskDescription, executor_id: &str) -> TaskStat
     }
 }
 
+/// Same as [`mock_completed_task`], but with the shuffle output written to an object store
+pub fn mock_completed_task_in_object_store(
+    task: TaskDescription,
+    executor_id: &str,
+) -> TaskStatus {
+    let mut task_status = mock_completed_task(task, executor_id);
+    if let Some(task_status::Status::Successful(successful)) = task_status.status.as_mut()
+    {
+        for partition in successful.partitions.iter_mut() {
+            partition.path = format!("memory://{}", partition.path);
+        }
+    }
+    task_status
+}
+
 pub fn mock_failed_task(task: TaskDescription, failed_task: FailedTask) -> TaskStatus {
     let mut partitions: Vec<protobuf::ShuffleWritePartition> = vec![];
 
@@ -1065,6 +1177,8 @@ pub fn mock_failed_task(task: TaskDescription, failed_task: FailedTask) -> TaskS
             num_batches: 1,
             num_rows: 1,
             num_bytes: 1,
+            compression: String::default(),
+            index_path: String::default(),
         })
     }
 
@@ -51,6 +51,7 @@ export enum QueryStatus {
 export interface Query {
   job_id: string;
   job_name: string;
+  user?: string;
   job_status: string;
   num_stages: number;
   percent_complete: number;
@@ -210,6 +211,10 @@ const columns: Column<Query>[] = [
     Header: "Job Name",
     accessor: "job_name",
   },
+  {
+    Header: "User",
+    accessor: "user",
+  },
   {
     Header: "Status",
     accessor: "job_status",